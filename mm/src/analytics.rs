@@ -0,0 +1,258 @@
+use log::{info, warn};
+use phoenix_onchain_mm::PhoenixStrategyState;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+
+/// Quotes of one update, in quote units per base unit
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct SpreadEntry {
+    pub timestamp: i64,
+    pub fair_price_usd: f64,
+    pub bid_price_usd: f64,
+    pub ask_price_usd: f64,
+    /// Spread between the bid and ask in basis points of the fair price
+    pub spread_bps: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpreadSummary {
+    pub count: usize,
+    pub mean_bps: f64,
+    pub median_bps: f64,
+    pub min_bps: f64,
+    pub max_bps: f64,
+    pub std_dev_bps: f64,
+}
+
+/// Keeps the spreads of the most recent quote updates, and optionally appends every update to a
+/// JSON lines file for external analysis.
+#[derive(Debug)]
+pub struct SpreadTracker {
+    history_length: usize,
+    entries: VecDeque<SpreadEntry>,
+    log_file: Option<File>,
+}
+
+impl SpreadTracker {
+    pub fn new(history_length: usize) -> Self {
+        Self {
+            history_length: history_length.max(1),
+            entries: VecDeque::new(),
+            log_file: None,
+        }
+    }
+
+    /// Appends every entry to `path` as a line of JSON. The file is created if it does not exist.
+    pub fn with_log_file(mut self, path: &Path) -> anyhow::Result<Self> {
+        self.log_file = Some(OpenOptions::new().create(true).append(true).open(path)?);
+        Ok(self)
+    }
+
+    /// Records the quotes of a successful update. A failed write to the log file is only logged,
+    /// since the spread log is informational.
+    pub fn update(
+        &mut self,
+        timestamp: i64,
+        fair_price_usd: f64,
+        bid_price_usd: f64,
+        ask_price_usd: f64,
+    ) {
+        let spread_bps = if fair_price_usd > 0.0 {
+            (ask_price_usd - bid_price_usd) / fair_price_usd * 10_000.0
+        } else {
+            0.0
+        };
+        let entry = SpreadEntry {
+            timestamp,
+            fair_price_usd,
+            bid_price_usd,
+            ask_price_usd,
+            spread_bps,
+        };
+        if let Some(file) = self.log_file.as_mut() {
+            let result = serde_json::to_string(&entry)
+                .map_err(anyhow::Error::from)
+                .and_then(|line| writeln!(file, "{}", line).map_err(anyhow::Error::from));
+            if let Err(e) = result {
+                warn!("Failed to write to the spread log: {}", e);
+            }
+        }
+        if self.entries.len() == self.history_length {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry);
+    }
+
+    /// Statistics of the spreads in the history, or None if nothing was quoted.
+    pub fn summary(&self) -> Option<SpreadSummary> {
+        if self.entries.is_empty() {
+            return None;
+        }
+        let mut spreads = self
+            .entries
+            .iter()
+            .map(|entry| entry.spread_bps)
+            .collect::<Vec<_>>();
+        spreads.sort_by(f64::total_cmp);
+        let count = spreads.len();
+        let mean_bps = spreads.iter().sum::<f64>() / count as f64;
+        let median_bps = if count % 2 == 0 {
+            (spreads[count / 2 - 1] + spreads[count / 2]) / 2.0
+        } else {
+            spreads[count / 2]
+        };
+        let variance = spreads
+            .iter()
+            .map(|spread| (spread - mean_bps).powi(2))
+            .sum::<f64>()
+            / count as f64;
+        Some(SpreadSummary {
+            count,
+            mean_bps,
+            median_bps,
+            min_bps: spreads[0],
+            max_bps: spreads[count - 1],
+            std_dev_bps: variance.sqrt(),
+        })
+    }
+
+    /// Logs the spread statistics as part of the session summary.
+    pub fn print_spread_summary(&self) {
+        match self.summary() {
+            Some(summary) => info!(
+                "  Quoted spread:            mean {:.1} bps, median {:.1}, min {:.1}, max {:.1}, std dev {:.1} (last {} updates)",
+                summary.mean_bps,
+                summary.median_bps,
+                summary.min_bps,
+                summary.max_bps,
+                summary.std_dev_bps,
+                summary.count
+            ),
+            None => info!("  Quoted spread:            no updates sent"),
+        }
+    }
+}
+
+/// Returns the change in the strategy's position between two reads of its state as
+/// `(net_base_lot_change, net_quote_atom_change)`. Bids filled in between add base lots and spend
+/// quote atoms at the bid price of `prev_state`, asks do the opposite at its ask price, since the
+/// fills were on the orders resting at the time. A counter that went down was zeroed by
+/// `reset_session`, and everything it counts since then is new.
+pub fn calculate_position_delta(
+    prev_state: &PhoenixStrategyState,
+    new_state: &PhoenixStrategyState,
+    tick_size_in_quote_atoms_per_base_unit: u64,
+    base_lots_per_base_unit: u64,
+) -> (i64, i64) {
+    let fill_since = |prev: u64, new: u64| if new < prev { new } else { new - prev };
+    let bid_fills = fill_since(
+        prev_state.cumulative_bid_fill_in_base_lots,
+        new_state.cumulative_bid_fill_in_base_lots,
+    ) as i128;
+    let ask_fills = fill_since(
+        prev_state.cumulative_ask_fill_in_base_lots,
+        new_state.cumulative_ask_fill_in_base_lots,
+    ) as i128;
+    let base_delta = bid_fills - ask_fills;
+    let quote_delta = if base_lots_per_base_unit == 0 {
+        0
+    } else {
+        (ask_fills * prev_state.ask_price_in_ticks as i128
+            - bid_fills * prev_state.bid_price_in_ticks as i128)
+            * tick_size_in_quote_atoms_per_base_unit as i128
+            / base_lots_per_base_unit as i128
+    };
+    let clamp = |value: i128| value.clamp(i64::MIN as i128, i64::MAX as i128) as i64;
+    (clamp(base_delta), clamp(quote_delta))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytemuck::Zeroable;
+
+    fn state(bid_fill: u64, ask_fill: u64) -> PhoenixStrategyState {
+        let mut state = PhoenixStrategyState::zeroed();
+        state.bid_price_in_ticks = 99;
+        state.ask_price_in_ticks = 101;
+        state.cumulative_bid_fill_in_base_lots = bid_fill;
+        state.cumulative_ask_fill_in_base_lots = ask_fill;
+        state
+    }
+
+    #[test]
+    fn test_position_delta_from_fills() {
+        // 1,000 quote atoms per tick per base unit and 100 base lots per base unit
+        assert_eq!(
+            calculate_position_delta(&state(10, 5), &state(10, 5), 1_000, 100),
+            (0, 0)
+        );
+        // Bought 30 lots at 99 ticks and sold 10 at 101
+        assert_eq!(
+            calculate_position_delta(&state(10, 5), &state(40, 15), 1_000, 100),
+            (20, 10 * 101 * 10 - 30 * 99 * 10)
+        );
+        // Only asks filled
+        assert_eq!(
+            calculate_position_delta(&state(0, 0), &state(0, 50), 1_000, 100),
+            (-50, 50 * 101 * 10)
+        );
+    }
+
+    #[test]
+    fn test_position_delta_after_a_session_reset() {
+        // The counters were zeroed and 5 lots were bought since
+        assert_eq!(
+            calculate_position_delta(&state(40, 15), &state(5, 0), 1_000, 100),
+            (5, -5 * 99 * 10)
+        );
+    }
+
+    #[test]
+    fn test_summary_of_the_spread_history() {
+        let mut tracker = SpreadTracker::new(4);
+        assert_eq!(tracker.summary(), None);
+        // 10, 20, 30 and 40 bps around a fair price of 100
+        for (timestamp, half_spread) in [(1, 0.05), (2, 0.1), (3, 0.15), (4, 0.2)] {
+            tracker.update(timestamp, 100.0, 100.0 - half_spread, 100.0 + half_spread);
+        }
+        let summary = tracker.summary().unwrap();
+        assert_eq!(summary.count, 4);
+        assert!((summary.mean_bps - 25.0).abs() < 1e-6);
+        assert!((summary.median_bps - 25.0).abs() < 1e-6);
+        assert!((summary.min_bps - 10.0).abs() < 1e-6);
+        assert!((summary.max_bps - 40.0).abs() < 1e-6);
+        assert!((summary.std_dev_bps - 125f64.sqrt()).abs() < 1e-6);
+
+        // The oldest entry is dropped once the history is full
+        tracker.update(5, 100.0, 99.75, 100.25);
+        let summary = tracker.summary().unwrap();
+        assert_eq!(summary.count, 4);
+        assert!((summary.min_bps - 20.0).abs() < 1e-6);
+        assert!((summary.median_bps - 35.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_entries_are_logged_as_json_lines() {
+        let path = std::env::temp_dir().join(format!("mm-spreads-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let mut tracker = SpreadTracker::new(10).with_log_file(&path).unwrap();
+        tracker.update(1, 100.0, 99.9, 100.1);
+        tracker.update(2, 0.0, 0.0, 0.0);
+        drop(tracker);
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let lines = contents
+            .lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["timestamp"], 1);
+        assert!((lines[0]["spread_bps"].as_f64().unwrap() - 20.0).abs() < 1e-6);
+        assert_eq!(lines[1]["spread_bps"], 0.0);
+    }
+}
@@ -0,0 +1,76 @@
+use phoenix::state::Side;
+
+/// A quote that external markets could trade against at a profit.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ArbOpportunity {
+    /// Side of our quotes that is mispriced
+    pub side: Side,
+    pub our_price: f64,
+    /// Reference price that our quote crosses
+    pub reference_price: f64,
+    /// Distance between the two prices in basis points of the reference price
+    pub profit_bps: f64,
+}
+
+/// Returns the largest arbitrage against our quotes, if any exceeds `threshold_bps`.
+///
+/// The reference feeds only report a single price, so each one stands in for both the bid and the
+/// ask of its market: our bid is checked against the lowest reference price and our ask against
+/// the highest one.
+pub fn check_for_arb_opportunity(
+    our_bid_usd: f64,
+    our_ask_usd: f64,
+    reference_prices: &[f64],
+    threshold_bps: f64,
+) -> Option<ArbOpportunity> {
+    let best_reference_ask = reference_prices.iter().copied().reduce(f64::min)?;
+    let best_reference_bid = reference_prices.iter().copied().reduce(f64::max)?;
+    let bid = ArbOpportunity {
+        side: Side::Bid,
+        our_price: our_bid_usd,
+        reference_price: best_reference_ask,
+        profit_bps: (our_bid_usd - best_reference_ask) / best_reference_ask * 10_000.0,
+    };
+    let ask = ArbOpportunity {
+        side: Side::Ask,
+        our_price: our_ask_usd,
+        reference_price: best_reference_bid,
+        profit_bps: (best_reference_bid - our_ask_usd) / best_reference_bid * 10_000.0,
+    };
+    [bid, ask]
+        .into_iter()
+        .filter(|opportunity| opportunity.profit_bps > threshold_bps)
+        .max_by(|a, b| a.profit_bps.total_cmp(&b.profit_bps))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quotes_inside_the_references_are_safe() {
+        assert_eq!(check_for_arb_opportunity(99.0, 101.0, &[], 10.0), None);
+        assert_eq!(
+            check_for_arb_opportunity(99.0, 101.0, &[100.0, 100.5], 10.0),
+            None
+        );
+        // Crossed by less than the threshold
+        assert_eq!(
+            check_for_arb_opportunity(100.05, 101.0, &[100.0], 10.0),
+            None
+        );
+    }
+
+    #[test]
+    fn test_crossed_quotes_are_detected() {
+        let opportunity = check_for_arb_opportunity(101.0, 102.0, &[100.0, 100.5], 10.0).unwrap();
+        assert_eq!(opportunity.side, Side::Bid);
+        assert_eq!(opportunity.reference_price, 100.0);
+        assert!((opportunity.profit_bps - 100.0).abs() < 1e-9);
+
+        let opportunity = check_for_arb_opportunity(97.0, 98.0, &[99.5, 100.0], 10.0).unwrap();
+        assert_eq!(opportunity.side, Side::Ask);
+        assert_eq!(opportunity.reference_price, 100.0);
+        assert!((opportunity.profit_bps - 200.0).abs() < 1e-9);
+    }
+}
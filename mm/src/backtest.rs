@@ -0,0 +1,355 @@
+use anyhow::anyhow;
+use phoenix::program::MarketHeader;
+use phoenix::state::Side;
+use phoenix_onchain_mm::{
+    apply_price_improvement, enforce_min_spread, get_ask_price_in_ticks, get_bid_price_in_ticks,
+    get_effective_edge_in_bps, get_volatility_edge_in_bps, price_usd_to_fair_price,
+    quote_atoms_to_usd, tick_to_price_usd, update_rolling_variance, PhoenixStrategyState,
+    PriceImprovementBehavior, PricePrecisionMode,
+};
+use serde::Deserialize;
+
+/// Top of the book and fair price at one point in time, read from a CSV row of
+/// `timestamp,bid,ask,fair_price`.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct Observation {
+    pub timestamp: i64,
+    /// Best bid, 0 if the book had no bids
+    #[serde(rename = "bid")]
+    pub best_bid_ticks: u64,
+    /// Best ask, u64::MAX if the book had no asks
+    #[serde(rename = "ask")]
+    pub best_ask_ticks: u64,
+    /// Fair price in quote units per base unit
+    #[serde(rename = "fair_price")]
+    pub fair_price_usd: f64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct BacktestResult {
+    /// PnL in quote units, with the position marked at the last fair price
+    pub total_pnl: f64,
+    /// Largest drop of the marked PnL from a previous peak, in quote units
+    pub max_drawdown: f64,
+    pub fill_count: u64,
+    /// Number of observations that were quoted
+    pub quote_count: u64,
+    /// Share of fills on resting quotes, between 0 and 1
+    pub maker_fill_ratio: f64,
+}
+
+/// Replays the quoting logic of `update_quotes` against historical observations.
+///
+/// Every quote fills in full. A quote rests until the next observation and fills as a maker if
+/// that observation's book trades through it. A new quote that crosses the book fills immediately
+/// as a taker at the best opposing price, unless it is post-only, in which case it is moved one
+/// tick behind the opposing price like Phoenix does. Sizes are always the fixed quote size, since
+/// the simulation has no deposited balances.
+pub struct SimulatedMarketMaker {
+    state: PhoenixStrategyState,
+    header: MarketHeader,
+    /// Bid and ask resting since the previous observation
+    quotes: Option<(u64, u64)>,
+    position_in_base_units: f64,
+    cash_in_quote_units: f64,
+    peak_pnl: f64,
+    maker_fill_count: u64,
+    result: BacktestResult,
+    /// Set once the first observation has been seen
+    started: bool,
+}
+
+impl SimulatedMarketMaker {
+    /// Quotes with the parameters in `state` on a market with the parameters in `header`.
+    pub fn new(state: PhoenixStrategyState, header: MarketHeader) -> Self {
+        Self {
+            state,
+            header,
+            quotes: None,
+            position_in_base_units: 0.0,
+            cash_in_quote_units: 0.0,
+            peak_pnl: 0.0,
+            maker_fill_count: 0,
+            result: BacktestResult::default(),
+            started: false,
+        }
+    }
+
+    /// Fills the resting quotes that the observed book traded through, then requotes.
+    pub fn observe(&mut self, observation: &Observation) -> anyhow::Result<()> {
+        let Observation {
+            timestamp,
+            best_bid_ticks: best_bid,
+            best_ask_ticks: best_ask,
+            fair_price_usd,
+        } = *observation;
+        // The fill decay starts counting from the first observation, like it does from `initialize`
+        if !self.started {
+            self.state.last_fill_unix_timestamp = timestamp;
+            self.started = true;
+        }
+        if let Some((bid, ask)) = self.quotes.take() {
+            if best_ask <= bid {
+                self.fill(Side::Bid, bid, true, timestamp);
+            }
+            if best_bid != 0 && best_bid >= ask {
+                self.fill(Side::Ask, ask, true, timestamp);
+            }
+        }
+
+        self.quotes = self.get_quotes(timestamp, best_bid, best_ask, fair_price_usd)?;
+        if let Some((bid, ask)) = self.quotes {
+            self.result.quote_count += 1;
+            if !self.is_post_only() {
+                if best_ask != u64::MAX && bid >= best_ask {
+                    self.fill(Side::Bid, best_ask, false, timestamp);
+                    self.quotes = None;
+                } else if best_bid != 0 && ask <= best_bid {
+                    self.fill(Side::Ask, best_bid, false, timestamp);
+                    self.quotes = None;
+                }
+            }
+        }
+
+        self.result.total_pnl =
+            self.cash_in_quote_units + self.position_in_base_units * fair_price_usd;
+        self.peak_pnl = self.peak_pnl.max(self.result.total_pnl);
+        self.result.max_drawdown = self
+            .result
+            .max_drawdown
+            .max(self.peak_pnl - self.result.total_pnl);
+        Ok(())
+    }
+
+    pub fn result(&self) -> BacktestResult {
+        let mut result = self.result;
+        if result.fill_count > 0 {
+            result.maker_fill_ratio = self.maker_fill_count as f64 / result.fill_count as f64;
+        }
+        result
+    }
+
+    /// Orders are posted instead of crossing, as in `update_quotes`, unless they are limit orders
+    /// that join the book.
+    fn is_post_only(&self) -> bool {
+        self.state.post_only
+            || self.state.price_improvement_behavior != PriceImprovementBehavior::Join.to_u8()
+    }
+
+    /// Returns the bid and ask in ticks, or None if the book is crossed.
+    fn get_quotes(
+        &mut self,
+        timestamp: i64,
+        best_bid: u64,
+        best_ask: u64,
+        fair_price_usd: f64,
+    ) -> anyhow::Result<Option<(u64, u64)>> {
+        let header = &self.header;
+        let state = &mut self.state;
+        let fair_price = price_usd_to_fair_price(fair_price_usd, header);
+        (
+            state.rolling_price_mean_x32,
+            state.rolling_price_variance_x64,
+        ) = update_rolling_variance(
+            state.rolling_price_mean_x32,
+            state.rolling_price_variance_x64,
+            state.price_update_count,
+            fair_price,
+        );
+        state.price_update_count = state.price_update_count.saturating_add(1);
+        if best_bid != 0 && best_ask != u64::MAX && best_bid >= best_ask {
+            return Ok(None);
+        }
+
+        let volatility_edge_in_bps = if state.price_update_count >= state.min_warmup_updates {
+            get_volatility_edge_in_bps(
+                state.rolling_price_mean_x32,
+                state.rolling_price_variance_x64,
+                fair_price,
+                state.volatility_multiplier_in_bps,
+            )
+        } else {
+            0
+        };
+        let edge_in_bps = get_effective_edge_in_bps(
+            state
+                .quote_edge_in_bps
+                .saturating_add(volatility_edge_in_bps),
+            timestamp - state.last_fill_unix_timestamp,
+            state.time_since_last_fill_decay_factor_in_bps,
+            state.max_edge_in_bps,
+        );
+        let mode = PricePrecisionMode::try_from_u8(state.price_precision_mode)
+            .map_err(|e| anyhow!("{}", e))?;
+        let (bid, ask) = apply_price_improvement(
+            PriceImprovementBehavior::try_from_u8(state.price_improvement_behavior)
+                .map_err(|e| anyhow!("{}", e))?,
+            get_bid_price_in_ticks(fair_price, header, edge_in_bps, mode)
+                .map_err(|e| anyhow!("{}", e))?,
+            get_ask_price_in_ticks(fair_price, header, edge_in_bps, mode)
+                .map_err(|e| anyhow!("{}", e))?,
+            best_bid,
+            best_ask,
+        );
+        let (mut bid, mut ask) =
+            enforce_min_spread(bid, ask, state.min_spread_in_bps).map_err(|e| anyhow!("{}", e))?;
+        if self.is_post_only() {
+            if best_ask != u64::MAX {
+                bid = bid.min(best_ask - 1);
+            }
+            if best_bid != 0 {
+                ask = ask.max(best_bid + 1);
+            }
+        }
+        Ok(Some((bid, ask)))
+    }
+
+    fn fill(&mut self, side: Side, price_in_ticks: u64, is_maker: bool, timestamp: i64) {
+        let price = tick_to_price_usd(price_in_ticks, &self.header);
+        if price <= 0.0 {
+            return;
+        }
+        let size = quote_atoms_to_usd(self.state.quote_size_in_quote_atoms, &self.header) / price;
+        match side {
+            Side::Bid => {
+                self.position_in_base_units += size;
+                self.cash_in_quote_units -= size * price;
+            }
+            Side::Ask => {
+                self.position_in_base_units -= size;
+                self.cash_in_quote_units += size * price;
+            }
+        }
+        self.result.fill_count += 1;
+        if is_maker {
+            self.maker_fill_count += 1;
+        }
+        self.state.last_fill_unix_timestamp = timestamp;
+    }
+}
+
+/// Runs the strategy in `state` over `observations` and returns the simulated PnL and activity.
+pub fn simulate_pnl(
+    state: PhoenixStrategyState,
+    header: MarketHeader,
+    observations: &[Observation],
+) -> anyhow::Result<BacktestResult> {
+    let mut simulation = SimulatedMarketMaker::new(state, header);
+    for observation in observations {
+        simulation.observe(observation)?;
+    }
+    Ok(simulation.result())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytemuck::Zeroable;
+    use phoenix::program::accounts::{MarketSizeParams, TokenParams};
+    use phoenix::quantities::{
+        BaseAtomsPerBaseLot, QuoteAtomsPerBaseUnitPerTick, QuoteAtomsPerQuoteLot, WrapperU64,
+    };
+    use solana_sdk::pubkey::Pubkey;
+
+    /// SOL/USDC-like market with 0.001 USDC ticks
+    fn header() -> MarketHeader {
+        let mut base_params = TokenParams::zeroed();
+        base_params.decimals = 9;
+        let mut quote_params = TokenParams::zeroed();
+        quote_params.decimals = 6;
+        MarketHeader::new(
+            MarketSizeParams::default(),
+            base_params,
+            BaseAtomsPerBaseLot::new(1_000_000),
+            quote_params,
+            QuoteAtomsPerQuoteLot::new(1),
+            QuoteAtomsPerBaseUnitPerTick::new(1_000),
+            Pubkey::default(),
+            Pubkey::default(),
+            Pubkey::default(),
+            1,
+        )
+    }
+
+    /// Quotes 100 USDC 10 bps away from the fair price
+    fn strategy(behavior: PriceImprovementBehavior, post_only: bool) -> PhoenixStrategyState {
+        let mut state = PhoenixStrategyState::zeroed();
+        state.quote_edge_in_bps = 10;
+        state.quote_size_in_quote_atoms = 100_000_000;
+        state.post_only = post_only;
+        state.price_improvement_behavior = behavior.to_u8();
+        state
+    }
+
+    fn observation(timestamp: i64, bid: u64, ask: u64, fair_price_usd: f64) -> Observation {
+        Observation {
+            timestamp,
+            best_bid_ticks: bid,
+            best_ask_ticks: ask,
+            fair_price_usd,
+        }
+    }
+
+    #[test]
+    fn test_round_trip_earns_the_spread() {
+        // Quotes at 19_980 / 20_020 ticks around a fair price of 20 USDC
+        let result = simulate_pnl(
+            strategy(PriceImprovementBehavior::Ignore, true),
+            header(),
+            &[
+                observation(0, 19_000, 21_000, 20.0),
+                // Sellers trade through our bid
+                observation(1, 18_000, 19_900, 20.0),
+                // Buyers trade through our ask
+                observation(2, 20_100, 22_000, 20.0),
+                observation(3, 19_000, 21_000, 20.0),
+            ],
+        )
+        .unwrap();
+        assert_eq!(result.fill_count, 2);
+        assert_eq!(result.quote_count, 4);
+        assert_eq!(result.maker_fill_ratio, 1.0);
+        // 100 USDC bought at 19.98 and sold at 20.02, with the rest of the position marked at 20
+        assert!((result.total_pnl - 20.0 * (100.0 / 19.98 - 100.0 / 20.02)).abs() < 1e-9);
+        assert_eq!(result.max_drawdown, 0.0);
+    }
+
+    #[test]
+    fn test_crossing_limit_orders_take_liquidity() {
+        // Without bids to join, the bid stays at 19_980 and crosses the ask
+        let result = simulate_pnl(
+            strategy(PriceImprovementBehavior::Join, false),
+            header(),
+            &[observation(0, 0, 19_950, 20.0)],
+        )
+        .unwrap();
+        assert_eq!(result.fill_count, 1);
+        assert_eq!(result.maker_fill_ratio, 0.0);
+
+        // Post-only quotes are moved behind the book instead
+        let result = simulate_pnl(
+            strategy(PriceImprovementBehavior::Join, true),
+            header(),
+            &[observation(0, 0, 19_950, 20.0)],
+        )
+        .unwrap();
+        assert_eq!(result.fill_count, 0);
+    }
+
+    #[test]
+    fn test_losses_are_tracked_as_drawdown() {
+        let result = simulate_pnl(
+            strategy(PriceImprovementBehavior::Ignore, true),
+            header(),
+            &[
+                observation(0, 19_000, 21_000, 20.0),
+                observation(1, 18_000, 19_900, 19.0),
+                observation(2, 17_000, 19_000, 18.0),
+            ],
+        )
+        .unwrap();
+        assert_eq!(result.fill_count, 1);
+        assert!(result.total_pnl < 0.0);
+        assert!((result.max_drawdown + result.total_pnl).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,163 @@
+use crate::market::get_market_header;
+use crate::rpc::RateLimitedRpcClient;
+use anyhow::anyhow;
+use phoenix::program::MarketHeader;
+use solana_sdk::hash::Hash;
+use solana_sdk::pubkey::Pubkey;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+/// Last fetched header of a market. The header only changes when the market's parameters or
+/// status change, so it does not need to be fetched with every quote update.
+pub struct MarketHeaderCache {
+    market: Pubkey,
+    header: Option<MarketHeader>,
+    fetched_slot: u64,
+}
+
+impl MarketHeaderCache {
+    pub fn new(market: Pubkey) -> Self {
+        Self {
+            market,
+            header: None,
+            fetched_slot: 0,
+        }
+    }
+
+    /// Forces the next refresh to fetch the header.
+    pub fn invalidate(&mut self) {
+        self.header = None;
+    }
+
+    fn is_stale(&self, max_age_slots: u64, current_slot: u64) -> bool {
+        self.header.is_none() || current_slot.saturating_sub(self.fetched_slot) > max_age_slots
+    }
+
+    /// Returns the cached header, fetching it first if it is missing or was fetched more than
+    /// `max_age_slots` before `current_slot`.
+    pub async fn refresh_if_stale(
+        &mut self,
+        max_age_slots: u64,
+        current_slot: u64,
+        client: &RateLimitedRpcClient,
+    ) -> anyhow::Result<&MarketHeader> {
+        if self.is_stale(max_age_slots, current_slot) {
+            let header = get_market_header(client, &self.market).await?;
+            self.store(header, current_slot)?;
+        }
+        self.header
+            .as_ref()
+            .ok_or_else(|| anyhow!("Market header cache is empty"))
+    }
+
+    fn store(&mut self, header: MarketHeader, slot: u64) -> anyhow::Result<()> {
+        if let Some(cached) = self.header {
+            if cached.discriminant != header.discriminant {
+                self.invalidate();
+                return Err(anyhow!(
+                    "Market {} discriminant changed from {} to {}",
+                    self.market,
+                    cached.discriminant,
+                    header.discriminant
+                ));
+            }
+        }
+        self.header = Some(header);
+        self.fetched_slot = slot;
+        Ok(())
+    }
+}
+
+/// Latest blockhash and when it was fetched. A blockhash stays valid for roughly a minute, so
+/// transactions sent in quick succession can share one instead of fetching it every time.
+pub struct BlockhashCache {
+    ttl: Duration,
+    blockhash: Mutex<Option<(Hash, Instant)>>,
+}
+
+impl BlockhashCache {
+    /// A TTL of zero fetches the blockhash for every transaction.
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            blockhash: Mutex::new(None),
+        }
+    }
+
+    /// Returns the cached blockhash if it was fetched less than `ttl` before `now`.
+    fn get_fresh(&self, cached: &Option<(Hash, Instant)>, now: Instant) -> Option<Hash> {
+        cached
+            .filter(|(_, fetched_at)| now.saturating_duration_since(*fetched_at) < self.ttl)
+            .map(|(blockhash, _)| blockhash)
+    }
+
+    /// Returns the cached blockhash, fetching a new one once the cached one has expired.
+    pub async fn get_latest_blockhash(
+        &self,
+        client: &RateLimitedRpcClient,
+    ) -> anyhow::Result<Hash> {
+        let mut cached = self.blockhash.lock().await;
+        if let Some(blockhash) = self.get_fresh(&cached, Instant::now()) {
+            return Ok(blockhash);
+        }
+        let blockhash = client.get_latest_blockhash().await?;
+        *cached = Some((blockhash, Instant::now()));
+        Ok(blockhash)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytemuck::Zeroable;
+
+    #[test]
+    fn test_staleness() {
+        let mut cache = MarketHeaderCache::new(Pubkey::new_unique());
+        assert!(cache.is_stale(10, 100));
+
+        cache.store(MarketHeader::zeroed(), 100).unwrap();
+        assert!(!cache.is_stale(10, 100));
+        assert!(!cache.is_stale(10, 110));
+        assert!(cache.is_stale(10, 111));
+
+        cache.invalidate();
+        assert!(cache.is_stale(10, 100));
+    }
+
+    #[test]
+    fn test_discriminant_change_invalidates() {
+        let mut cache = MarketHeaderCache::new(Pubkey::new_unique());
+        let mut header = MarketHeader::zeroed();
+        cache.store(header, 100).unwrap();
+
+        header.discriminant = 1;
+        assert!(cache.store(header, 200).is_err());
+        assert!(cache.is_stale(1_000, 100));
+    }
+    #[test]
+    fn test_blockhash_is_reused_until_it_expires() {
+        let cache = BlockhashCache::new(Duration::from_millis(30_000));
+        let start = Instant::now();
+        assert_eq!(cache.get_fresh(&None, start), None);
+
+        let blockhash = Hash::new_unique();
+        let cached = Some((blockhash, start));
+        assert_eq!(cache.get_fresh(&cached, start), Some(blockhash));
+        assert_eq!(
+            cache.get_fresh(&cached, start + Duration::from_millis(29_999)),
+            Some(blockhash)
+        );
+        assert_eq!(
+            cache.get_fresh(&cached, start + Duration::from_millis(30_000)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_zero_ttl_never_reuses() {
+        let cache = BlockhashCache::new(Duration::ZERO);
+        let now = Instant::now();
+        assert_eq!(cache.get_fresh(&Some((Hash::new_unique(), now)), now), None);
+    }
+}
@@ -0,0 +1,102 @@
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::signer::keypair::Keypair;
+use solana_sdk::signer::Signer;
+use solana_sdk::transaction::Transaction;
+use std::sync::mpsc::{channel, RecvTimeoutError, Sender};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+/// Cancels the strategy's orders when the quoting loop stops sending heartbeats, e.g. because it
+/// deadlocked, panicked or returned an error. It cannot help if the process is killed outright.
+///
+/// The watchdog runs on its own OS thread with a blocking RPC client, so that it keeps running
+/// while the async runtime is stuck or shutting down. The cancel instruction is built up front
+/// and only signed with a fresh blockhash when it fires, because a transaction signed at startup
+/// would expire after about a minute.
+///
+/// The signer must be allowed to cancel the strategy's orders. `cancel_all_orders` only accepts
+/// the trader that owns the strategy, so the guard holds a copy of the trader keypair. Running the
+/// guard with a separate keypair limited to cancelling requires a cancel delegate in the program,
+/// which it does not support yet.
+pub struct CancelGuard {
+    heartbeat: Option<Sender<()>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl CancelGuard {
+    pub fn spawn(
+        rpc_url: String,
+        commitment: CommitmentConfig,
+        signer: Keypair,
+        cancel_instruction: Instruction,
+        timeout: Duration,
+    ) -> Self {
+        let (heartbeat, heartbeats) = channel();
+        let handle = std::thread::spawn(move || {
+            let client = RpcClient::new_with_commitment(rpc_url, commitment);
+            // Only cancel once per silence, the loop requotes when it recovers
+            let mut armed = true;
+            loop {
+                match heartbeats.recv_timeout(timeout) {
+                    Ok(()) => armed = true,
+                    Err(RecvTimeoutError::Timeout) => {
+                        if armed {
+                            println!(
+                                "No heartbeat from the quoting loop for {} ms, cancelling orders",
+                                timeout.as_millis()
+                            );
+                            cancel(&client, &signer, &cancel_instruction);
+                            armed = false;
+                        }
+                    }
+                    Err(RecvTimeoutError::Disconnected) => {
+                        if armed {
+                            println!("Quoting loop stopped, cancelling orders");
+                            cancel(&client, &signer, &cancel_instruction);
+                        }
+                        return;
+                    }
+                }
+            }
+        });
+        Self {
+            heartbeat: Some(heartbeat),
+            handle: Some(handle),
+        }
+    }
+
+    /// Signals that the quoting loop is still running.
+    pub fn heartbeat(&self) {
+        if let Some(heartbeat) = &self.heartbeat {
+            heartbeat.send(()).ok();
+        }
+    }
+}
+
+impl Drop for CancelGuard {
+    /// Cancels the orders and waits for the cancellation, including while unwinding from a panic,
+    /// so that the process does not exit before it is sent.
+    fn drop(&mut self) {
+        self.heartbeat.take();
+        if let Some(handle) = self.handle.take() {
+            handle.join().ok();
+        }
+    }
+}
+
+fn cancel(client: &RpcClient, signer: &Keypair, cancel_instruction: &Instruction) {
+    let result = client.get_latest_blockhash().and_then(|blockhash| {
+        client.send_and_confirm_transaction(&Transaction::new_signed_with_payer(
+            std::slice::from_ref(cancel_instruction),
+            Some(&signer.pubkey()),
+            &[signer],
+            blockhash,
+        ))
+    });
+    match result {
+        Ok(sig) => println!("Cancelled orders: {}", sig),
+        Err(e) => println!("Failed to cancel orders: {}", e),
+    }
+}
@@ -0,0 +1,520 @@
+use crate::cache::BlockhashCache;
+use crate::market::get_market_header;
+use crate::nonce::DurableNonce;
+use crate::rpc::RateLimitedRpcClient;
+use crate::state::read_strategy_state;
+use anchor_lang::InstructionData;
+use anchor_lang::ToAccountMetas;
+use anyhow::anyhow;
+use phoenix::program::{create_withdraw_funds_instruction, get_seat_address, get_vault_address};
+use phoenix::state::Side;
+use phoenix_onchain_mm::{
+    get_strategy_address_with_id, get_strategy_registry_address, OrderParams, PhoenixStrategyState,
+    StrategyParams,
+};
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_sdk::signer::keypair::Keypair;
+use solana_sdk::signer::Signer;
+use solana_sdk::transaction::Transaction;
+use spl_associated_token_account::get_associated_token_address_with_program_id;
+use spl_token_2022::extension::StateWithExtensions;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Builds and sends the market maker's instructions for one strategy of the payer on a market.
+pub struct PhoenixOnchainMM<'a> {
+    payer: &'a Keypair,
+    market: Pubkey,
+    strategy_id: u8,
+    rpc_client: Arc<RateLimitedRpcClient>,
+    blockhash_cache: BlockhashCache,
+    durable_nonce: Option<DurableNonce<'a>>,
+}
+
+impl<'a> PhoenixOnchainMM<'a> {
+    /// Uses strategy ID 0. See `with_strategy_id` to manage other strategies on the same market.
+    /// Fetches a new blockhash for every transaction unless `with_blockhash_cache_ttl` is set.
+    pub fn new(payer: &'a Keypair, market: Pubkey, rpc_client: Arc<RateLimitedRpcClient>) -> Self {
+        Self {
+            payer,
+            market,
+            strategy_id: 0,
+            rpc_client,
+            blockhash_cache: BlockhashCache::new(Duration::ZERO),
+            durable_nonce: None,
+        }
+    }
+
+    pub fn with_strategy_id(self, strategy_id: u8) -> Self {
+        Self {
+            strategy_id,
+            ..self
+        }
+    }
+
+    /// Reuses a fetched blockhash for transactions signed within `ttl` of fetching it.
+    pub fn with_blockhash_cache_ttl(self, ttl: Duration) -> Self {
+        Self {
+            blockhash_cache: BlockhashCache::new(ttl),
+            ..self
+        }
+    }
+
+    /// Signs transactions with a durable nonce instead of a recent blockhash. Only one transaction
+    /// per nonce value can land, so transactions must not be sent concurrently.
+    pub fn with_durable_nonce(self, durable_nonce: DurableNonce<'a>) -> Self {
+        Self {
+            durable_nonce: Some(durable_nonce),
+            ..self
+        }
+    }
+
+    pub fn payer(&self) -> &Keypair {
+        self.payer
+    }
+
+    pub fn market(&self) -> Pubkey {
+        self.market
+    }
+
+    pub fn strategy_id(&self) -> u8 {
+        self.strategy_id
+    }
+
+    pub fn strategy_key(&self) -> Pubkey {
+        get_strategy_address_with_id(&self.payer.pubkey(), &self.market, self.strategy_id).0
+    }
+
+    pub fn rpc_client(&self) -> &RateLimitedRpcClient {
+        &self.rpc_client
+    }
+
+    /// Returns a transaction containing `instructions`, signed by the payer with a recent blockhash,
+    /// or with the durable nonce and its authority if one is set.
+    pub async fn sign_transaction(
+        &self,
+        instructions: &[Instruction],
+    ) -> anyhow::Result<Transaction> {
+        if let Some(durable_nonce) = &self.durable_nonce {
+            let mut signers = vec![self.payer];
+            if durable_nonce.authority.pubkey() != self.payer.pubkey() {
+                signers.push(durable_nonce.authority);
+            }
+            return Ok(Transaction::new_signed_with_payer(
+                &durable_nonce.with_advance_instruction(instructions),
+                Some(&self.payer.pubkey()),
+                &signers,
+                durable_nonce.get_blockhash(&self.rpc_client).await?,
+            ));
+        }
+        Ok(Transaction::new_signed_with_payer(
+            instructions,
+            Some(&self.payer.pubkey()),
+            &[self.payer],
+            self.blockhash_cache
+                .get_latest_blockhash(&self.rpc_client)
+                .await?,
+        ))
+    }
+
+    async fn send_instructions(&self, instructions: &[Instruction]) -> anyhow::Result<Signature> {
+        let transaction = self.sign_transaction(instructions).await?;
+        Ok(self
+            .rpc_client
+            .send_and_confirm_transaction(&transaction)
+            .await?)
+    }
+
+    /// Returns the `initialize` instruction for `params`, failing if they are incomplete.
+    pub fn initialize_instruction(&self, params: StrategyParams) -> anyhow::Result<Instruction> {
+        params
+            .validate(true)
+            .map_err(|e| anyhow!("Invalid strategy params: {}", e))?;
+        let accounts = phoenix_onchain_mm::accounts::Initialize {
+            phoenix_strategy: self.strategy_key(),
+            strategy_registry: get_strategy_registry_address(&self.payer.pubkey()).0,
+            market: self.market,
+            user: self.payer.pubkey(),
+            system_program: solana_sdk::system_program::id(),
+        };
+        let data = phoenix_onchain_mm::instruction::Initialize {
+            params,
+            strategy_id: self.strategy_id,
+        };
+        Ok(Instruction {
+            program_id: phoenix_onchain_mm::id(),
+            accounts: accounts.to_account_metas(None),
+            data: data.data(),
+        })
+    }
+
+    /// Creates the strategy account and records it in the payer's strategy registry. The trader
+    /// must already have a seat on the market.
+    pub async fn initialize(&self, params: StrategyParams) -> anyhow::Result<Signature> {
+        self.send_instructions(&[self.initialize_instruction(params)?])
+            .await
+    }
+
+    /// Updates the strategy parameters and records the market's current tick size and raw base
+    /// units per base unit, e.g. after a market migration.
+    pub async fn reinitialize(&self, params: StrategyParams) -> anyhow::Result<Signature> {
+        params
+            .validate(false)
+            .map_err(|e| anyhow!("Invalid strategy params: {}", e))?;
+        let accounts = phoenix_onchain_mm::accounts::Reinitialize {
+            phoenix_strategy: self.strategy_key(),
+            user: self.payer.pubkey(),
+            market: self.market,
+        };
+        let data = phoenix_onchain_mm::instruction::Reinitialize {
+            params,
+            strategy_id: self.strategy_id,
+        };
+        self.send_instructions(&[Instruction {
+            program_id: phoenix_onchain_mm::id(),
+            accounts: accounts.to_account_metas(None),
+            data: data.data(),
+        }])
+        .await
+    }
+
+    /// Returns the accounts for `update_quotes`, funding orders from the given token accounts or
+    /// the payer's associated token accounts by default. The volatility oracle defaults to the one
+    /// configured in the strategy, if it exists.
+    pub async fn get_update_quotes_accounts(
+        &self,
+        base_token_account: Option<Pubkey>,
+        quote_token_account: Option<Pubkey>,
+        volatility_oracle: Option<Pubkey>,
+    ) -> anyhow::Result<phoenix_onchain_mm::accounts::UpdateQuotes> {
+        let volatility_oracle = match volatility_oracle {
+            Some(volatility_oracle) => volatility_oracle,
+            None => self
+                .get_state()
+                .await
+                .map(|state| state.volatility_oracle_pubkey)
+                .unwrap_or_default(),
+        };
+        let header = get_market_header(&self.rpc_client, &self.market).await?;
+        let quote_token_program = self
+            .get_token_program(&header.quote_params.mint_key)
+            .await?;
+        let base_token_program = self.get_token_program(&header.base_params.mint_key).await?;
+        if quote_token_program != base_token_program {
+            return Err(anyhow!(
+                "Base and quote mints are owned by different token programs ({} and {})",
+                base_token_program,
+                quote_token_program
+            ));
+        }
+        let quote_account = self
+            .get_token_account(
+                quote_token_account,
+                &header.quote_params.mint_key,
+                &quote_token_program,
+            )
+            .await?;
+        let base_account = self
+            .get_token_account(
+                base_token_account,
+                &header.base_params.mint_key,
+                &base_token_program,
+            )
+            .await?;
+        Ok(phoenix_onchain_mm::accounts::UpdateQuotes {
+            phoenix_strategy: self.strategy_key(),
+            market: self.market,
+            user: self.payer.pubkey(),
+            phoenix_program: phoenix::id(),
+            log_authority: phoenix::phoenix_log_authority::id(),
+            seat: get_seat_address(&self.market, &self.payer.pubkey()).0,
+            quote_account,
+            base_account,
+            quote_vault: get_vault_address(&self.market, &header.quote_params.mint_key).0,
+            base_vault: get_vault_address(&self.market, &header.base_params.mint_key).0,
+            token_program: quote_token_program,
+            volatility_oracle: (volatility_oracle != Pubkey::default())
+                .then_some(volatility_oracle),
+        })
+    }
+
+    /// Returns the `update_quotes` instruction for `params` using precomputed accounts.
+    pub fn update_quotes_instruction(
+        &self,
+        accounts: &phoenix_onchain_mm::accounts::UpdateQuotes,
+        params: OrderParams,
+    ) -> Instruction {
+        let data = phoenix_onchain_mm::instruction::UpdateQuotes {
+            params,
+            strategy_id: self.strategy_id,
+        };
+        Instruction {
+            program_id: phoenix_onchain_mm::id(),
+            accounts: accounts.to_account_metas(None),
+            data: data.data(),
+        }
+    }
+
+    /// Requotes around the fair price in `params`, funding orders from the payer's associated
+    /// token accounts.
+    #[allow(dead_code)]
+    pub async fn update_quotes(&self, params: OrderParams) -> anyhow::Result<Signature> {
+        let accounts = self.get_update_quotes_accounts(None, None, None).await?;
+        self.send_instructions(&[self.update_quotes_instruction(&accounts, params)])
+            .await
+    }
+
+    /// Returns the `cancel_all_orders` instruction for the strategy.
+    pub fn cancel_all_instruction(&self) -> Instruction {
+        let accounts = phoenix_onchain_mm::accounts::CancelAllOrders {
+            phoenix_strategy: self.strategy_key(),
+            user: self.payer.pubkey(),
+            phoenix_program: phoenix::id(),
+            log_authority: phoenix::phoenix_log_authority::id(),
+            market: self.market,
+        };
+        let data = phoenix_onchain_mm::instruction::CancelAllOrders {
+            strategy_id: self.strategy_id,
+        };
+        Instruction {
+            program_id: phoenix_onchain_mm::id(),
+            accounts: accounts.to_account_metas(None),
+            data: data.data(),
+        }
+    }
+
+    /// Cancels the strategy's resting orders.
+    pub async fn cancel_all(&self) -> anyhow::Result<Signature> {
+        self.send_instructions(&[self.cancel_all_instruction()])
+            .await
+    }
+
+    /// Cancels the strategy's resting order on one side of the book, leaving the other side.
+    pub async fn cancel_side(&self, side: Side) -> anyhow::Result<Signature> {
+        let accounts = phoenix_onchain_mm::accounts::CancelAllOrders {
+            phoenix_strategy: self.strategy_key(),
+            user: self.payer.pubkey(),
+            phoenix_program: phoenix::id(),
+            log_authority: phoenix::phoenix_log_authority::id(),
+            market: self.market,
+        };
+        let data = phoenix_onchain_mm::instruction::CancelSide {
+            strategy_id: self.strategy_id,
+            side: match side {
+                Side::Bid => 0,
+                Side::Ask => 1,
+            },
+        };
+        self.send_instructions(&[Instruction {
+            program_id: phoenix_onchain_mm::id(),
+            accounts: accounts.to_account_metas(None),
+            data: data.data(),
+        }])
+        .await
+    }
+
+    /// Cancels the strategy's resting orders and withdraws all of the trader's funds from the
+    /// market in a single transaction.
+    pub async fn close(&self) -> anyhow::Result<Signature> {
+        let header = get_market_header(&self.rpc_client, &self.market).await?;
+        let withdraw = create_withdraw_funds_instruction(
+            &self.market,
+            &self.payer.pubkey(),
+            &header.base_params.mint_key,
+            &header.quote_params.mint_key,
+        );
+        self.send_instructions(&[self.cancel_all_instruction(), withdraw])
+            .await
+    }
+
+    /// Returns the accounts for withdrawing free funds to the payer's associated token accounts.
+    async fn get_withdraw_accounts(
+        &self,
+    ) -> anyhow::Result<phoenix_onchain_mm::accounts::Withdraw> {
+        let header = get_market_header(&self.rpc_client, &self.market).await?;
+        let token_program = self
+            .get_token_program(&header.quote_params.mint_key)
+            .await?;
+        Ok(phoenix_onchain_mm::accounts::Withdraw {
+            phoenix_strategy: self.strategy_key(),
+            user: self.payer.pubkey(),
+            phoenix_program: phoenix::id(),
+            log_authority: phoenix::phoenix_log_authority::id(),
+            market: self.market,
+            base_account: get_associated_token_address_with_program_id(
+                &self.payer.pubkey(),
+                &header.base_params.mint_key,
+                &token_program,
+            ),
+            quote_account: get_associated_token_address_with_program_id(
+                &self.payer.pubkey(),
+                &header.quote_params.mint_key,
+                &token_program,
+            ),
+            base_vault: get_vault_address(&self.market, &header.base_params.mint_key).0,
+            quote_vault: get_vault_address(&self.market, &header.quote_params.mint_key).0,
+            token_program,
+        })
+    }
+
+    /// Withdraws all of the payer's free funds in the market to their associated token accounts,
+    /// leaving open orders in place.
+    pub async fn withdraw_to_wallet(&self) -> anyhow::Result<Signature> {
+        let accounts = self.get_withdraw_accounts().await?;
+        let data = phoenix_onchain_mm::instruction::WithdrawToWallet {
+            strategy_id: self.strategy_id,
+        };
+        self.send_instructions(&[Instruction {
+            program_id: phoenix_onchain_mm::id(),
+            accounts: accounts.to_account_metas(None),
+            data: data.data(),
+        }])
+        .await
+    }
+
+    /// Withdraws the given amounts of the payer's free funds in the market to their associated
+    /// token accounts, leaving open orders in place.
+    pub async fn partial_withdraw(
+        &self,
+        base_lots_to_withdraw: u64,
+        quote_lots_to_withdraw: u64,
+    ) -> anyhow::Result<Signature> {
+        let accounts = self.get_withdraw_accounts().await?;
+        let data = phoenix_onchain_mm::instruction::PartialWithdraw {
+            strategy_id: self.strategy_id,
+            base_lots_to_withdraw,
+            quote_lots_to_withdraw,
+        };
+        self.send_instructions(&[Instruction {
+            program_id: phoenix_onchain_mm::id(),
+            accounts: accounts.to_account_metas(None),
+            data: data.data(),
+        }])
+        .await
+    }
+
+    /// Migrates the strategy account to layout version `state_version`, which must be the version
+    /// after its current one.
+    pub async fn migrate_state(&self, state_version: u8) -> anyhow::Result<Signature> {
+        let accounts = phoenix_onchain_mm::accounts::MigrateState {
+            phoenix_strategy: self.strategy_key(),
+            user: self.payer.pubkey(),
+            market: self.market,
+            system_program: solana_sdk::system_program::id(),
+        };
+        let data = phoenix_onchain_mm::instruction::MigrateState {
+            state_version,
+            strategy_id: self.strategy_id,
+        };
+        self.send_instructions(&[Instruction {
+            program_id: phoenix_onchain_mm::id(),
+            accounts: accounts.to_account_metas(None),
+            data: data.data(),
+        }])
+        .await
+    }
+
+    /// Zeros the strategy's cumulative fill counters, keeping its params and resting orders.
+    pub async fn reset_session(&self) -> anyhow::Result<Signature> {
+        let accounts = phoenix_onchain_mm::accounts::ResetSession {
+            phoenix_strategy: self.strategy_key(),
+            user: self.payer.pubkey(),
+            market: self.market,
+        };
+        let data = phoenix_onchain_mm::instruction::ResetSession {
+            strategy_id: self.strategy_id,
+        };
+        self.send_instructions(&[Instruction {
+            program_id: phoenix_onchain_mm::id(),
+            accounts: accounts.to_account_metas(None),
+            data: data.data(),
+        }])
+        .await
+    }
+
+    /// Emits the strategy's state as a `StrategySnapshot` event in the transaction logs.
+    pub async fn snapshot(&self) -> anyhow::Result<Signature> {
+        let accounts = phoenix_onchain_mm::accounts::Snapshot {
+            phoenix_strategy: self.strategy_key(),
+        };
+        self.send_instructions(&[Instruction {
+            program_id: phoenix_onchain_mm::id(),
+            accounts: accounts.to_account_metas(None),
+            data: phoenix_onchain_mm::instruction::Snapshot {}.data(),
+        }])
+        .await
+    }
+
+    pub async fn get_state(&self) -> anyhow::Result<PhoenixStrategyState> {
+        read_strategy_state(
+            &self
+                .rpc_client
+                .get_account_data(&self.strategy_key())
+                .await?,
+        )
+    }
+
+    /// Returns the program that owns `mint`, either SPL Token or Token-2022.
+    async fn get_token_program(&self, mint: &Pubkey) -> anyhow::Result<Pubkey> {
+        let account = self
+            .rpc_client
+            .get_account(mint)
+            .await
+            .map_err(|e| anyhow!("Failed to fetch mint {}: {}", mint, e))?;
+        if account.owner != spl_token::id() && account.owner != spl_token_2022::id() {
+            return Err(anyhow!(
+                "{} is not a mint, it is owned by {}",
+                mint,
+                account.owner
+            ));
+        }
+        Ok(account.owner)
+    }
+
+    /// Returns the token account override if it is a token account for `mint`, otherwise the
+    /// payer's associated token account.
+    async fn get_token_account(
+        &self,
+        token_account: Option<Pubkey>,
+        mint: &Pubkey,
+        token_program: &Pubkey,
+    ) -> anyhow::Result<Pubkey> {
+        let token_account = match token_account {
+            Some(token_account) => token_account,
+            None => {
+                return Ok(get_associated_token_address_with_program_id(
+                    &self.payer.pubkey(),
+                    mint,
+                    token_program,
+                ))
+            }
+        };
+        let account = self
+            .rpc_client
+            .get_account(&token_account)
+            .await
+            .map_err(|e| anyhow!("Failed to fetch token account {}: {}", token_account, e))?;
+        if account.owner != *token_program {
+            return Err(anyhow!(
+                "{} is not a token account, it is owned by {}",
+                token_account,
+                account.owner
+            ));
+        }
+        // Token-2022 accounts may carry extensions after the base account data
+        let state = StateWithExtensions::<spl_token_2022::state::Account>::unpack(&account.data)
+            .map_err(|e| anyhow!("Failed to parse token account {}: {}", token_account, e))?
+            .base;
+        if state.mint != *mint {
+            return Err(anyhow!(
+                "Token account {} has mint {}, expected {}",
+                token_account,
+                state.mint,
+                mint
+            ));
+        }
+        Ok(token_account)
+    }
+}
@@ -0,0 +1,42 @@
+use crate::backtest::{simulate_pnl, Observation};
+use crate::market::get_market_header;
+use crate::rpc::RateLimitedRpcClient;
+use anyhow::anyhow;
+use bytemuck::Zeroable;
+use phoenix_onchain_mm::{PhoenixStrategyState, StrategyParams};
+use solana_sdk::pubkey::Pubkey;
+
+/// Reads observations from a CSV file with a `timestamp,bid,ask,fair_price` header row.
+pub fn read_observations(input_file: &str) -> anyhow::Result<Vec<Observation>> {
+    csv::Reader::from_path(input_file)?
+        .deserialize()
+        .collect::<Result<Vec<Observation>, _>>()
+        .map_err(|e| anyhow!("Failed to read {}: {}", input_file, e))
+}
+
+/// Simulates the strategy with `params` on the market's current tick and lot sizes over the
+/// observations in `input_file` and prints the result.
+pub async fn backtest(
+    client: &RateLimitedRpcClient,
+    market: &Pubkey,
+    params: StrategyParams,
+    input_file: &str,
+) -> anyhow::Result<()> {
+    let observations = read_observations(input_file)?;
+    let header = get_market_header(client, market).await?;
+    let mut state = PhoenixStrategyState::zeroed();
+    state
+        .apply_params(&params)
+        .map_err(|e| anyhow!("Invalid strategy params: {}", e))?;
+    let result = simulate_pnl(state, header, &observations)?;
+    println!("Observations:     {}", observations.len());
+    println!("Quotes:           {}", result.quote_count);
+    println!(
+        "Fills:            {} ({:.1}% maker)",
+        result.fill_count,
+        result.maker_fill_ratio * 100.0
+    );
+    println!("Total PnL:        {:.4}", result.total_pnl);
+    println!("Max drawdown:     {:.4}", result.max_drawdown);
+    Ok(())
+}
@@ -0,0 +1,14 @@
+use crate::client::PhoenixOnchainMM;
+use phoenix::state::Side;
+
+pub async fn cancel(mm: &PhoenixOnchainMM<'_>) -> anyhow::Result<()> {
+    let txid = mm.cancel_all().await?;
+    println!("Cancelling orders: {}", txid);
+    Ok(())
+}
+
+pub async fn cancel_side(mm: &PhoenixOnchainMM<'_>, side: Side) -> anyhow::Result<()> {
+    let txid = mm.cancel_side(side).await?;
+    println!("Cancelling {:?} order: {}", side, txid);
+    Ok(())
+}
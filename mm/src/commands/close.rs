@@ -0,0 +1,8 @@
+use crate::client::PhoenixOnchainMM;
+
+/// Cancels the strategy's orders and withdraws all of the trader's funds from the market.
+pub async fn close(mm: &PhoenixOnchainMM<'_>) -> anyhow::Result<()> {
+    let txid = mm.close().await?;
+    println!("Cancelling orders and withdrawing funds: {}", txid);
+    Ok(())
+}
@@ -0,0 +1,13 @@
+use crate::keypair::encrypt_keypair_to_file;
+use anyhow::anyhow;
+use solana_sdk::signer::keypair::Keypair;
+
+pub fn encrypt_keypair(payer: &Keypair, output_path: &str) -> anyhow::Result<()> {
+    let password = rpassword::prompt_password("New keypair password: ")?;
+    if password != rpassword::prompt_password("Confirm password: ")? {
+        return Err(anyhow!("Passwords do not match"));
+    }
+    encrypt_keypair_to_file(payer, output_path, &password)?;
+    println!("Wrote encrypted keypair to {}", output_path);
+    Ok(())
+}
@@ -0,0 +1,92 @@
+use crate::commands::pnl::get_all_signatures;
+use crate::log_parser::{parse_phoenix_logs, MarketEventType};
+use crate::rpc::RateLimitedRpcClient;
+use clap::ValueEnum;
+use phoenix::state::Side;
+use serde::Serialize;
+use solana_client::rpc_config::RpcTransactionConfig;
+use solana_sdk::pubkey::Pubkey;
+use solana_transaction_status::option_serializer::OptionSerializer;
+use solana_transaction_status::UiTransactionEncoding;
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportRecord {
+    pub slot: u64,
+    pub timestamp: Option<i64>,
+    pub side: &'static str,
+    pub price_in_ticks: u64,
+    pub size_in_base_lots: Option<u64>,
+    #[serde(rename = "type")]
+    pub event_type: MarketEventType,
+    pub txid: String,
+}
+
+pub async fn get_export_records(
+    client: &RateLimitedRpcClient,
+    strategy_key: &Pubkey,
+    from_slot: Option<u64>,
+    to_slot: Option<u64>,
+) -> anyhow::Result<Vec<ExportRecord>> {
+    let mut records = vec![];
+    for signature in get_all_signatures(client, strategy_key, from_slot).await? {
+        let tx = client
+            .get_transaction_with_config(
+                &signature,
+                RpcTransactionConfig {
+                    encoding: Some(UiTransactionEncoding::Json),
+                    commitment: Some(client.commitment()),
+                    max_supported_transaction_version: Some(0),
+                },
+            )
+            .await?;
+        if to_slot.is_some_and(|to_slot| tx.slot > to_slot) {
+            break;
+        }
+        let logs = match tx.transaction.meta.map(|meta| meta.log_messages) {
+            Some(OptionSerializer::Some(logs)) => logs,
+            _ => continue,
+        };
+        for event in parse_phoenix_logs(&logs) {
+            records.push(ExportRecord {
+                slot: tx.slot,
+                timestamp: tx.block_time,
+                side: match event.side {
+                    Side::Bid => "bid",
+                    Side::Ask => "ask",
+                },
+                price_in_ticks: event.price_in_ticks,
+                size_in_base_lots: event.size_in_base_lots,
+                event_type: event.event_type,
+                txid: signature.to_string(),
+            });
+        }
+    }
+    Ok(records)
+}
+
+pub fn write_export_records(
+    records: &[ExportRecord],
+    output_file: &str,
+    format: ExportFormat,
+) -> anyhow::Result<()> {
+    match format {
+        ExportFormat::Csv => {
+            let mut writer = csv::Writer::from_path(output_file)?;
+            for record in records {
+                writer.serialize(record)?;
+            }
+            writer.flush()?;
+        }
+        ExportFormat::Json => {
+            let file = std::fs::File::create(output_file)?;
+            serde_json::to_writer_pretty(file, records)?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,341 @@
+use crate::client::PhoenixOnchainMM;
+use crate::market::load_market;
+use crate::rpc::RateLimitedRpcClient;
+use clap::Args;
+use log::warn;
+use phoenix::program::status::SeatApprovalStatus;
+use phoenix::program::{get_seat_address, Seat};
+use phoenix_onchain_mm::compute_minimum_profitable_edge_in_bps;
+use phoenix_onchain_mm::ClientOrderIdMode;
+use phoenix_onchain_mm::PriceImprovementBehavior;
+use phoenix_onchain_mm::PricePrecisionMode;
+use phoenix_onchain_mm::SizeAdjustmentCurve;
+use phoenix_onchain_mm::SizingMode;
+use phoenix_onchain_mm::StrategyParams;
+use phoenix_sdk::sdk_client::SDKClient;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signer::Signer;
+use std::time::Duration;
+
+/// Number of times the maker setup transaction is sent before giving up
+const MAKER_SETUP_ATTEMPTS: u32 = 3;
+const MAKER_SETUP_RETRY_DELAY: Duration = Duration::from_secs(2);
+
+#[derive(Args, Debug)]
+pub struct StrategyArgs {
+    #[clap(long, default_value = "3")]
+    pub quote_edge_in_bps: u64,
+    /// Minimum spread between the bid and ask in basis points of the mid price
+    #[clap(long, default_value = "0")]
+    pub min_spread_in_bps: u64,
+    /// Basis points added to the edge for every hour without a fill
+    #[clap(long, default_value = "0")]
+    pub time_since_last_fill_decay_factor_in_bps: u64,
+    /// Upper bound on the edge after widening. 0 means no limit.
+    #[clap(long, default_value = "0")]
+    pub max_edge_in_bps: u64,
+    /// Basis points of edge added per standard deviation of the fair price, relative to the fair price
+    #[clap(long, default_value = "0")]
+    pub volatility_multiplier_in_bps: u64,
+    /// Number of fair prices to observe before the volatility edge is applied
+    #[clap(long, default_value = "0")]
+    pub min_warmup_updates: u64,
+    /// Quote atoms a crossing order may take through the book before it is posted instead. 0 means no limit.
+    #[clap(long, default_value = "0")]
+    pub max_taker_exposure_in_quote_atoms: u64,
+    /// Reject quote updates that land more than this many slots after their fair price was observed. 0 disables the check.
+    #[clap(long, default_value = "0")]
+    pub max_slot_age_for_price: u64,
+    /// Warn when the free quote atoms deposited in the market fall below this. 0 disables the warning.
+    #[clap(long, default_value = "0")]
+    pub min_deposited_quote_atoms: u64,
+    /// Warn when the free base lots deposited in the market fall below this. 0 disables the warning.
+    #[clap(long, default_value = "0")]
+    pub min_deposited_base_lots: u64,
+    /// Account holding a volatility oracle whose annualized volatility widens the edge
+    #[clap(long)]
+    pub volatility_oracle: Option<Pubkey>,
+    /// Reject quote updates when the volatility oracle is older than this many slots. 0 disables the check.
+    #[clap(long, default_value = "0")]
+    pub max_oracle_staleness_in_slots: u64,
+    /// Expected seconds between quote updates, used to scale the oracle's annualized volatility
+    #[clap(long, default_value = "2")]
+    pub quote_refresh_interval_in_seconds: u64,
+    #[clap(long, default_value = "100000000")]
+    pub quote_size: u64,
+    #[clap(long, default_value = "ignore")]
+    pub price_improvement_behavior: String,
+    #[clap(long, default_value = "true")]
+    pub post_only: bool,
+    /// Only fund orders from tokens already deposited in the market, failing if they are insufficient
+    #[clap(long)]
+    pub use_only_deposited_funds: bool,
+    /// How to derive the client order ID: "pubkey" for the same ID on every order, or "counter" for a unique ID per update
+    #[clap(long, default_value = "pubkey")]
+    pub client_order_id_mode: String,
+    /// How to interpret the quote size: "fixed" for quote atoms, or "percent" for basis points of the deposited quote balance
+    #[clap(long, default_value = "fixed")]
+    pub sizing_mode: String,
+    /// How level sizes scale with distance from the fair price: "flat", "linear" or "inverse"
+    #[clap(long, default_value = "flat")]
+    pub size_curve: String,
+    /// Basis points of size added per tick for "linear", or the size multiplier at one tick for "inverse"
+    #[clap(long, default_value = "0")]
+    pub size_curve_parameter: u64,
+    /// How quote prices between two ticks are rounded: "conservative" away from the fair price,
+    /// "aggressive" toward it, or "exact" to reject prices that are not on a tick
+    #[clap(long, default_value = "conservative")]
+    pub price_precision_mode: String,
+    /// Replace resting orders after this many seconds even if their price is unchanged. 0 disables the refresh.
+    #[clap(long, default_value = "0")]
+    pub order_refresh_interval_in_seconds: u64,
+    /// Taker fee of the trader's fee tier. Defaults to the market's taker fee.
+    #[clap(long)]
+    pub taker_fee_in_bps: Option<u64>,
+    /// Maker rebate of the trader's fee tier
+    #[clap(long, default_value = "0")]
+    pub maker_rebate_in_bps: u64,
+    /// Profit per round trip on top of the fees, used to suggest a quote edge at startup
+    #[clap(long, default_value = "0")]
+    pub target_profit_bps: u64,
+    /// Only quote a side of the book when other traders have at least this many orders on it. 0 always quotes.
+    #[clap(long, default_value = "0")]
+    pub min_external_orders_per_side: u64,
+    /// Label stored in the strategy account to tell strategies apart, up to 32 bytes of UTF-8
+    #[clap(long, value_parser = parse_strategy_label)]
+    pub strategy_label: Option<[u8; 32]>,
+}
+
+/// Encodes a label into the strategy's client metadata, padded with zero bytes.
+pub fn parse_strategy_label(label: &str) -> Result<[u8; 32], String> {
+    let bytes = label.as_bytes();
+    if bytes.len() > 32 {
+        return Err(format!(
+            "Label is {} bytes, at most 32 are allowed",
+            bytes.len()
+        ));
+    }
+    let mut client_metadata = [0; 32];
+    client_metadata[..bytes.len()].copy_from_slice(bytes);
+    Ok(client_metadata)
+}
+
+impl StrategyArgs {
+    pub fn to_strategy_params(&self) -> StrategyParams {
+        let price_improvement = match self.price_improvement_behavior.as_str() {
+            "Join" | "join" => PriceImprovementBehavior::Join,
+            "Dime" | "dime" => PriceImprovementBehavior::Dime,
+            "Ignore" | "ignore" => PriceImprovementBehavior::Ignore,
+            _ => PriceImprovementBehavior::Join,
+        };
+        StrategyParams {
+            quote_edge_in_bps: Some(self.quote_edge_in_bps),
+            quote_size_in_quote_atoms: Some(self.quote_size),
+            price_improvement_behavior: Some(price_improvement),
+            post_only: Some(self.post_only),
+            use_only_deposited_funds: Some(self.use_only_deposited_funds),
+            size_decay_factor_in_bps: None,
+            min_spread_in_bps: Some(self.min_spread_in_bps),
+            time_since_last_fill_decay_factor_in_bps: Some(
+                self.time_since_last_fill_decay_factor_in_bps,
+            ),
+            max_edge_in_bps: Some(self.max_edge_in_bps),
+            volatility_multiplier_in_bps: Some(self.volatility_multiplier_in_bps),
+            min_warmup_updates: Some(self.min_warmup_updates),
+            max_taker_exposure_in_quote_atoms: Some(self.max_taker_exposure_in_quote_atoms),
+            max_slot_age_for_price: Some(self.max_slot_age_for_price),
+            min_deposited_quote_atoms: Some(self.min_deposited_quote_atoms),
+            min_deposited_base_lots: Some(self.min_deposited_base_lots),
+            volatility_oracle_pubkey: self.volatility_oracle,
+            max_oracle_staleness_in_slots: Some(self.max_oracle_staleness_in_slots),
+            quote_refresh_interval_in_seconds: Some(self.quote_refresh_interval_in_seconds),
+            order_refresh_interval_in_seconds: Some(self.order_refresh_interval_in_seconds),
+            taker_fee_in_bps: self.taker_fee_in_bps,
+            maker_rebate_in_bps: Some(self.maker_rebate_in_bps),
+            min_external_orders_per_side: Some(self.min_external_orders_per_side),
+            client_metadata: self.strategy_label,
+            client_order_id_mode: Some(match self.client_order_id_mode.as_str() {
+                "Counter" | "counter" => ClientOrderIdMode::Counter,
+                _ => ClientOrderIdMode::Pubkey,
+            }),
+            sizing_mode: Some(match self.sizing_mode.as_str() {
+                "Percent" | "percent" => SizingMode::PercentOfDepositedFunds,
+                _ => SizingMode::Fixed,
+            }),
+            price_precision_mode: Some(match self.price_precision_mode.as_str() {
+                "Aggressive" | "aggressive" => PricePrecisionMode::Aggressive,
+                "Exact" | "exact" => PricePrecisionMode::Exact,
+                _ => PricePrecisionMode::Conservative,
+            }),
+            size_adjustment_curve: Some(match self.size_curve.as_str() {
+                "Linear" | "linear" => SizeAdjustmentCurve::Linear(self.size_curve_parameter),
+                "Inverse" | "inverse" => SizeAdjustmentCurve::Inverse(self.size_curve_parameter),
+                _ => SizeAdjustmentCurve::Flat,
+            }),
+        }
+    }
+
+    /// Returns the strategy params with the taker fee defaulting to the market's, and prints the
+    /// edge needed to reach the target profit. An edge below it is only a warning, since quoting
+    /// at a loss can be intentional.
+    pub async fn resolve_strategy_params(
+        &self,
+        client: &RateLimitedRpcClient,
+        market: &Pubkey,
+    ) -> anyhow::Result<StrategyParams> {
+        let mut params = self.to_strategy_params();
+        let taker_fee_in_bps = match self.taker_fee_in_bps {
+            Some(taker_fee_in_bps) => taker_fee_in_bps,
+            None => {
+                let data = client.get_account_data(market).await?;
+                load_market(&data)?.1.get_taker_fee_bps()
+            }
+        };
+        params.taker_fee_in_bps = Some(taker_fee_in_bps);
+        let suggested_edge_in_bps = compute_minimum_profitable_edge_in_bps(
+            taker_fee_in_bps,
+            self.maker_rebate_in_bps,
+            self.target_profit_bps,
+        );
+        println!(
+            "Suggested quote edge: {} bps ({} bps taker fee, {} bps maker rebate, {} bps target profit)",
+            suggested_edge_in_bps,
+            taker_fee_in_bps,
+            self.maker_rebate_in_bps,
+            self.target_profit_bps
+        );
+        if self.quote_edge_in_bps < suggested_edge_in_bps {
+            warn!(
+                "Quote edge of {} bps is below the suggested edge of {} bps",
+                self.quote_edge_in_bps, suggested_edge_in_bps
+            );
+        }
+        Ok(params)
+    }
+}
+
+/// Returns whether seat account data belongs to an approved seat.
+fn is_approved_seat(data: &[u8]) -> bool {
+    data.get(..std::mem::size_of::<Seat>())
+        .and_then(|data| bytemuck::try_pod_read_unaligned::<Seat>(data).ok())
+        .map(|seat| SeatApprovalStatus::from(seat.approval_status) == SeatApprovalStatus::Approved)
+        .unwrap_or(false)
+}
+
+/// Requests a seat on the market for the payer, unless it already has an approved one.
+pub async fn setup_maker(sdk: &SDKClient, mm: &PhoenixOnchainMM<'_>) -> anyhow::Result<()> {
+    let seat = get_seat_address(&mm.market(), &mm.payer().pubkey()).0;
+    if let Ok(account) = mm.rpc_client().get_account(&seat).await {
+        if is_approved_seat(&account.data) {
+            println!("Seat {} is already approved, skipping maker setup", seat);
+            return Ok(());
+        }
+    }
+    println!("Seat {} is not approved yet, running maker setup", seat);
+
+    let mut attempt = 1;
+    loop {
+        let result = match sdk
+            .get_maker_setup_instructions_for_market(&mm.market())
+            .await
+        {
+            Ok(instructions) => sdk
+                .client
+                .sign_send_instructions(instructions, vec![])
+                .await
+                .map_err(anyhow::Error::from),
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(txid) => {
+                println!("Maker setup succeeded: {}", txid);
+                return Ok(());
+            }
+            Err(e) if attempt < MAKER_SETUP_ATTEMPTS => {
+                println!(
+                    "Maker setup failed (attempt {}/{}): {}, retrying in {}s",
+                    attempt,
+                    MAKER_SETUP_ATTEMPTS,
+                    e,
+                    MAKER_SETUP_RETRY_DELAY.as_secs()
+                );
+                tokio::time::sleep(MAKER_SETUP_RETRY_DELAY).await;
+                attempt += 1;
+            }
+            Err(e) => {
+                return Err(anyhow::anyhow!(
+                    "Maker setup failed after {} attempts: {}. Check that the payer is funded and \
+                     that the market's seat manager is accepting requests, or pass \
+                     --skip-maker-setup if the seat was set up separately.",
+                    MAKER_SETUP_ATTEMPTS,
+                    e
+                ))
+            }
+        }
+    }
+}
+
+/// Requests a seat on the market and initializes the strategy. The program creates the strategy
+/// account if it does not exist yet, and refuses to overwrite a strategy with resting orders.
+pub async fn init(
+    sdk: &SDKClient,
+    mm: &PhoenixOnchainMM<'_>,
+    params: StrategyParams,
+    skip_maker_setup: bool,
+) -> anyhow::Result<()> {
+    if skip_maker_setup {
+        println!("Skipping maker setup");
+    } else {
+        setup_maker(sdk, mm).await?;
+    }
+
+    let txid = mm.initialize(params).await?;
+    println!("Initializing strategy account: {}", txid);
+    Ok(())
+}
+
+/// Like `init`, but leaves an existing strategy as it is. Restarting `run` must not reset the
+/// strategy's fill history or fail on its resting orders.
+pub async fn init_if_missing(
+    sdk: &SDKClient,
+    mm: &PhoenixOnchainMM<'_>,
+    params: StrategyParams,
+    skip_maker_setup: bool,
+) -> anyhow::Result<()> {
+    match mm.rpc_client().get_account(&mm.strategy_key()).await {
+        Ok(account) if !account.data.is_empty() => {
+            if !skip_maker_setup {
+                setup_maker(sdk, mm).await?;
+            }
+            Ok(())
+        }
+        _ => init(sdk, mm, params, skip_maker_setup).await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::display::client_metadata_to_label;
+    use bytemuck::Zeroable;
+
+    #[test]
+    fn test_strategy_label_round_trip() {
+        let client_metadata = parse_strategy_label("SOL/USDC tight").unwrap();
+        assert_eq!(client_metadata_to_label(&client_metadata), "SOL/USDC tight");
+        assert_eq!(client_metadata_to_label(&[0; 32]), "");
+        assert!(parse_strategy_label(&"a".repeat(32)).is_ok());
+        assert!(parse_strategy_label(&"a".repeat(33)).is_err());
+    }
+
+    #[test]
+    fn test_only_approved_seats_skip_setup() {
+        let mut seat = Seat::zeroed();
+        assert!(!is_approved_seat(bytemuck::bytes_of(&seat)));
+        seat.approval_status = SeatApprovalStatus::Approved as u64;
+        assert!(is_approved_seat(bytemuck::bytes_of(&seat)));
+        seat.approval_status = SeatApprovalStatus::Retired as u64;
+        assert!(!is_approved_seat(bytemuck::bytes_of(&seat)));
+        assert!(!is_approved_seat(&[]));
+    }
+}
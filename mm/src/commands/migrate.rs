@@ -0,0 +1,23 @@
+use crate::client::PhoenixOnchainMM;
+use anyhow::anyhow;
+use phoenix_onchain_mm::{get_state_version, STRATEGY_STATE_VERSION};
+
+/// Migrates the strategy account from its current layout version to the latest one, applying
+/// every migration in between in order.
+pub async fn migrate(mm: &PhoenixOnchainMM<'_>) -> anyhow::Result<()> {
+    let data = mm.rpc_client().get_account_data(&mm.strategy_key()).await?;
+    let current_version =
+        get_state_version(&data).map_err(|e| anyhow!("Failed to read strategy version: {}", e))?;
+    if current_version >= STRATEGY_STATE_VERSION {
+        println!(
+            "Strategy is already at the latest version {}",
+            current_version
+        );
+        return Ok(());
+    }
+    for state_version in current_version + 1..=STRATEGY_STATE_VERSION {
+        let txid = mm.migrate_state(state_version).await?;
+        println!("Migrating strategy to version {}: {}", state_version, txid);
+    }
+    Ok(())
+}
@@ -0,0 +1,16 @@
+pub mod backtest;
+pub mod cancel;
+pub mod close;
+pub mod encrypt_keypair;
+pub mod export;
+pub mod init;
+pub mod migrate;
+pub mod pnl;
+pub mod price_ladder;
+pub mod rebalance;
+pub mod reset_session;
+pub mod run;
+pub mod snapshot;
+pub mod status;
+pub mod watch;
+pub mod withdraw;
@@ -0,0 +1,135 @@
+use crate::rpc::RateLimitedRpcClient;
+use phoenix::state::Side;
+use phoenix_sdk::sdk_client::{MarketEventDetails, MarketMetadata, SDKClient};
+use solana_client::rpc_client::GetConfirmedSignaturesForAddress2Config;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use std::str::FromStr;
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct PnLReport {
+    /// Net quote atoms received from fills (ask proceeds minus bid costs)
+    pub total_realized: i64,
+    /// Value of the net base position at the current market price, in quote atoms
+    pub total_unrealized: i64,
+    pub total_bid_volume: u64,
+    pub total_ask_volume: u64,
+    pub fill_count: u64,
+}
+
+/// Returns all successful transaction signatures for the address, oldest first.
+/// Pagination stops once signatures older than `from_slot` are reached.
+pub async fn get_all_signatures(
+    client: &RateLimitedRpcClient,
+    address: &Pubkey,
+    from_slot: Option<u64>,
+) -> anyhow::Result<Vec<Signature>> {
+    let from_slot = from_slot.unwrap_or(0);
+    let mut signatures = vec![];
+    let mut before = None;
+    loop {
+        let page = client
+            .get_signatures_for_address_with_config(
+                address,
+                GetConfirmedSignaturesForAddress2Config {
+                    before,
+                    until: None,
+                    limit: None,
+                    commitment: Some(client.commitment()),
+                },
+            )
+            .await?;
+        let Some(last) = page.last() else {
+            break;
+        };
+        before = Some(Signature::from_str(&last.signature)?);
+        let reached_from_slot = last.slot < from_slot;
+        for sig in page.iter() {
+            if sig.err.is_none() && sig.slot >= from_slot {
+                signatures.push(Signature::from_str(&sig.signature)?);
+            }
+        }
+        if reached_from_slot {
+            break;
+        }
+    }
+    signatures.reverse();
+    Ok(signatures)
+}
+
+pub async fn compute_pnl(
+    sdk: &SDKClient,
+    client: &RateLimitedRpcClient,
+    meta: &MarketMetadata,
+    strategy_key: &Pubkey,
+    market: &Pubkey,
+    trader: &Pubkey,
+    from_slot: Option<u64>,
+) -> anyhow::Result<PnLReport> {
+    let mut report = PnLReport::default();
+
+    for signature in get_all_signatures(client, strategy_key, from_slot).await? {
+        for event in sdk.parse_fills(&signature).await {
+            if event.market != *market {
+                continue;
+            }
+            let MarketEventDetails::Fill(fill) = event.details else {
+                continue;
+            };
+            // The side of the fill is the side of the maker's order
+            let side = if fill.maker == *trader {
+                fill.side_filled
+            } else if fill.taker == *trader {
+                fill.side_filled.opposite()
+            } else {
+                continue;
+            };
+            let quote_atoms = meta
+                .base_lots_and_price_to_quote_atoms(fill.base_lots_filled, fill.price_in_ticks)
+                as i64;
+            match side {
+                Side::Bid => {
+                    report.total_realized -= quote_atoms;
+                    report.total_bid_volume += fill.base_lots_filled;
+                }
+                Side::Ask => {
+                    report.total_realized += quote_atoms;
+                    report.total_ask_volume += fill.base_lots_filled;
+                }
+            }
+            report.fill_count += 1;
+        }
+    }
+
+    let ladder = sdk.get_market_ladder(market, 1).await?;
+    if let (Some(best_bid), Some(best_ask)) = (ladder.bids.first(), ladder.asks.first()) {
+        let mid_price_in_ticks = (best_bid.price_in_ticks + best_ask.price_in_ticks) / 2;
+        let net_base_lots = report.total_bid_volume as i64 - report.total_ask_volume as i64;
+        let position_value = meta
+            .base_lots_and_price_to_quote_atoms(net_base_lots.unsigned_abs(), mid_price_in_ticks)
+            as i64;
+        report.total_unrealized = position_value * net_base_lots.signum();
+    }
+
+    Ok(report)
+}
+
+pub fn print_pnl_report(report: &PnLReport, meta: &MarketMetadata) {
+    let to_quote_units = |atoms: i64| {
+        atoms.signum() as f64 * meta.quote_atoms_to_quote_units_as_float(atoms.unsigned_abs())
+    };
+    let to_base_units =
+        |lots: u64| meta.base_atoms_to_raw_base_units_as_float(meta.base_lots_to_base_atoms(lots));
+    println!("Fills: {}", report.fill_count);
+    println!("Bid volume: {}", to_base_units(report.total_bid_volume));
+    println!("Ask volume: {}", to_base_units(report.total_ask_volume));
+    println!("Realized PnL: {:.6}", to_quote_units(report.total_realized));
+    println!(
+        "Unrealized PnL: {:.6}",
+        to_quote_units(report.total_unrealized)
+    );
+    println!(
+        "Total PnL: {:.6}",
+        to_quote_units(report.total_realized + report.total_unrealized)
+    );
+}
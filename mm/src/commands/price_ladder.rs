@@ -0,0 +1,242 @@
+use crate::market::get_market_header;
+use crate::rpc::RateLimitedRpcClient;
+use anyhow::anyhow;
+use bytemuck::Zeroable;
+use comfy_table::Table;
+use phoenix::program::MarketHeader;
+use phoenix_onchain_mm::{
+    apply_price_improvement, enforce_min_spread, get_ask_price_in_ticks, get_bid_price_in_ticks,
+    get_effective_edge_in_bps, get_spread_in_bps, price_usd_to_fair_price, price_usd_to_ticks,
+    tick_to_price_usd, PhoenixStrategyState, PriceImprovementBehavior, PricePrecisionMode,
+    StrategyParams,
+};
+use solana_sdk::pubkey::Pubkey;
+
+/// Upper bound on the number of fair prices, to catch a step that is too small for the range
+const MAX_LADDER_ROWS: usize = 10_000;
+
+/// Quotes the strategy would place at one fair price
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PriceLadderRow {
+    pub fair_price_usd: f64,
+    pub bid_price_in_ticks: u64,
+    pub ask_price_in_ticks: u64,
+}
+
+/// Returns the fair prices from `from_price` to `to_price`, inclusive, `price_step` apart.
+pub fn get_ladder_prices(
+    from_price: f64,
+    to_price: f64,
+    price_step: f64,
+) -> anyhow::Result<Vec<f64>> {
+    if !price_step.is_finite() || price_step <= 0.0 {
+        return Err(anyhow!("--price-step must be positive"));
+    }
+    if !(from_price > 0.0 && to_price >= from_price) {
+        return Err(anyhow!(
+            "--from-price must be positive and not above --to-price"
+        ));
+    }
+    // Tolerate the rounding error of the step, so that --to-price itself is included
+    let steps = ((to_price - from_price) / price_step + 1e-9).floor();
+    if steps >= MAX_LADDER_ROWS as f64 {
+        return Err(anyhow!(
+            "The price range has more than {} steps, use a larger --price-step",
+            MAX_LADDER_ROWS
+        ));
+    }
+    Ok((0..=steps as usize)
+        .map(|i| from_price + i as f64 * price_step)
+        .collect())
+}
+
+/// Computes the quotes the way `update_quotes` does against a book whose best prices from other
+/// traders are `best_bid` and `best_ask`, with the usual 0 and u64::MAX for an empty side. The
+/// edge is the configured one, without widening for volatility or time since the last fill,
+/// which depend on the strategy's history.
+pub fn compute_price_ladder(
+    state: &PhoenixStrategyState,
+    header: &MarketHeader,
+    fair_prices_usd: &[f64],
+    best_bid: u64,
+    best_ask: u64,
+) -> anyhow::Result<Vec<PriceLadderRow>> {
+    let edge_in_bps =
+        get_effective_edge_in_bps(state.quote_edge_in_bps, 0, 0, state.max_edge_in_bps);
+    let mode = PricePrecisionMode::try_from_u8(state.price_precision_mode)
+        .map_err(|e| anyhow!("{}", e))?;
+    let behavior = PriceImprovementBehavior::try_from_u8(state.price_improvement_behavior)
+        .map_err(|e| anyhow!("{}", e))?;
+    fair_prices_usd
+        .iter()
+        .map(|&fair_price_usd| {
+            let fair_price = price_usd_to_fair_price(fair_price_usd, header);
+            let (bid, ask) = apply_price_improvement(
+                behavior,
+                get_bid_price_in_ticks(fair_price, header, edge_in_bps, mode)
+                    .map_err(|e| anyhow!("{}", e))?,
+                get_ask_price_in_ticks(fair_price, header, edge_in_bps, mode)
+                    .map_err(|e| anyhow!("{}", e))?,
+                best_bid,
+                best_ask,
+            );
+            let (bid_price_in_ticks, ask_price_in_ticks) =
+                enforce_min_spread(bid, ask, state.min_spread_in_bps)
+                    .map_err(|e| anyhow!("{}", e))?;
+            Ok(PriceLadderRow {
+                fair_price_usd,
+                bid_price_in_ticks,
+                ask_price_in_ticks,
+            })
+        })
+        .collect()
+}
+
+/// Prints the quotes that `params` would produce at every fair price in the range, on the
+/// market's current tick size and against a sample best bid and ask in quote units.
+#[allow(clippy::too_many_arguments)]
+pub async fn price_ladder(
+    client: &RateLimitedRpcClient,
+    market: &Pubkey,
+    params: StrategyParams,
+    from_price: f64,
+    to_price: f64,
+    price_step: f64,
+    best_bid: Option<f64>,
+    best_ask: Option<f64>,
+) -> anyhow::Result<()> {
+    let fair_prices = get_ladder_prices(from_price, to_price, price_step)?;
+    let header = get_market_header(client, market).await?;
+    let mut state = PhoenixStrategyState::zeroed();
+    state
+        .apply_params(&params)
+        .map_err(|e| anyhow!("Invalid strategy params: {}", e))?;
+    let best_bid = best_bid.map_or(0, |price| price_usd_to_ticks(price, &header));
+    let best_ask = best_ask.map_or(u64::MAX, |price| price_usd_to_ticks(price, &header));
+    if best_bid != 0 && best_ask != u64::MAX && best_bid >= best_ask {
+        return Err(anyhow!("--best-bid must be below --best-ask"));
+    }
+    let rows = compute_price_ladder(&state, &header, &fair_prices, best_bid, best_ask)?;
+
+    let mut table = Table::new();
+    table.set_header(vec![
+        "Fair Price",
+        "Bid Ticks",
+        "Bid USD",
+        "Ask Ticks",
+        "Ask USD",
+        "Spread Ticks",
+        "Spread Bps",
+    ]);
+    for row in rows {
+        let fair_price_in_ticks = price_usd_to_ticks(row.fair_price_usd, &header);
+        table.add_row(vec![
+            format!("{:.4}", row.fair_price_usd),
+            row.bid_price_in_ticks.to_string(),
+            format!("{:.4}", tick_to_price_usd(row.bid_price_in_ticks, &header)),
+            row.ask_price_in_ticks.to_string(),
+            format!("{:.4}", tick_to_price_usd(row.ask_price_in_ticks, &header)),
+            row.ask_price_in_ticks
+                .saturating_sub(row.bid_price_in_ticks)
+                .to_string(),
+            get_spread_in_bps(
+                row.bid_price_in_ticks,
+                row.ask_price_in_ticks,
+                fair_price_in_ticks,
+            )
+            .to_string(),
+        ]);
+    }
+    println!("{}", table);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use phoenix::program::accounts::{MarketSizeParams, TokenParams};
+    use phoenix::quantities::{
+        BaseAtomsPerBaseLot, QuoteAtomsPerBaseUnitPerTick, QuoteAtomsPerQuoteLot, WrapperU64,
+    };
+
+    /// SOL/USDC-like market with 0.001 USDC ticks
+    fn header() -> MarketHeader {
+        let mut base_params = TokenParams::zeroed();
+        base_params.decimals = 9;
+        let mut quote_params = TokenParams::zeroed();
+        quote_params.decimals = 6;
+        MarketHeader::new(
+            MarketSizeParams::default(),
+            base_params,
+            BaseAtomsPerBaseLot::new(1_000_000),
+            quote_params,
+            QuoteAtomsPerQuoteLot::new(1),
+            QuoteAtomsPerBaseUnitPerTick::new(1_000),
+            Pubkey::default(),
+            Pubkey::default(),
+            Pubkey::default(),
+            1,
+        )
+    }
+
+    /// Quotes 10 bps away from the fair price
+    fn strategy(behavior: PriceImprovementBehavior) -> PhoenixStrategyState {
+        let mut state = PhoenixStrategyState::zeroed();
+        state.quote_edge_in_bps = 10;
+        state.price_improvement_behavior = behavior.to_u8();
+        state
+    }
+
+    #[test]
+    fn test_ladder_prices_include_both_ends() {
+        assert_eq!(get_ladder_prices(20.0, 20.3, 0.1).unwrap().len(), 4);
+        assert_eq!(get_ladder_prices(20.0, 20.0, 1.0).unwrap(), vec![20.0]);
+        assert!(get_ladder_prices(20.0, 21.0, 0.0).is_err());
+        assert!(get_ladder_prices(21.0, 20.0, 0.1).is_err());
+        assert!(get_ladder_prices(1.0, 1_000_000.0, 0.001).is_err());
+    }
+
+    #[test]
+    fn test_ladder_quotes_the_edge_on_an_empty_book() {
+        let rows = compute_price_ladder(
+            &strategy(PriceImprovementBehavior::Join),
+            &header(),
+            &[20.0, 30.0],
+            0,
+            u64::MAX,
+        )
+        .unwrap();
+        // 10 bps around 20.00 and 30.00 in 0.001 ticks
+        assert_eq!(
+            rows.iter()
+                .map(|row| (row.bid_price_in_ticks, row.ask_price_in_ticks))
+                .collect::<Vec<_>>(),
+            vec![(19_980, 20_020), (29_970, 30_030)]
+        );
+    }
+
+    #[test]
+    fn test_ladder_applies_price_improvement_and_min_spread() {
+        let header = header();
+        // Improve a 19.970 @ 20.030 book by at most one tick
+        let rows = compute_price_ladder(
+            &strategy(PriceImprovementBehavior::Dime),
+            &header,
+            &[20.0],
+            19_970,
+            20_030,
+        )
+        .unwrap();
+        assert_eq!(
+            (rows[0].bid_price_in_ticks, rows[0].ask_price_in_ticks),
+            (19_971, 20_029)
+        );
+
+        // A 50 bps floor widens the 20 bps spread around the mid
+        let mut state = strategy(PriceImprovementBehavior::Join);
+        state.min_spread_in_bps = 50;
+        let rows = compute_price_ladder(&state, &header, &[20.0], 0, u64::MAX).unwrap();
+        let spread = rows[0].ask_price_in_ticks - rows[0].bid_price_in_ticks;
+        assert!(spread * 10_000 >= 50 * 20_000);
+    }
+}
@@ -0,0 +1,34 @@
+use crate::client::PhoenixOnchainMM;
+use anchor_lang::InstructionData;
+use anchor_lang::ToAccountMetas;
+use phoenix::program::get_seat_address;
+use phoenix_onchain_mm::RebalanceParams;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::signer::Signer;
+
+pub async fn rebalance(mm: &PhoenixOnchainMM<'_>, params: RebalanceParams) -> anyhow::Result<()> {
+    let args = phoenix_onchain_mm::instruction::Rebalance {
+        params,
+        strategy_id: mm.strategy_id(),
+    };
+    let accounts = phoenix_onchain_mm::accounts::Rebalance {
+        phoenix_strategy: mm.strategy_key(),
+        user: mm.payer().pubkey(),
+        phoenix_program: phoenix::id(),
+        log_authority: phoenix::phoenix_log_authority::id(),
+        market: mm.market(),
+        seat: get_seat_address(&mm.market(), &mm.payer().pubkey()).0,
+    };
+    let ix = Instruction {
+        program_id: phoenix_onchain_mm::id(),
+        accounts: accounts.to_account_metas(None),
+        data: args.data(),
+    };
+    let transaction = mm.sign_transaction(&[ix]).await?;
+    let txid = mm
+        .rpc_client()
+        .send_and_confirm_transaction(&transaction)
+        .await?;
+    println!("Rebalancing inventory: {}", txid);
+    Ok(())
+}
@@ -0,0 +1,7 @@
+use crate::client::PhoenixOnchainMM;
+
+pub async fn reset_session(mm: &PhoenixOnchainMM<'_>) -> anyhow::Result<()> {
+    let txid = mm.reset_session().await?;
+    println!("Resetting session fill tracking: {}", txid);
+    Ok(())
+}
@@ -0,0 +1,1258 @@
+use crate::analytics::SpreadTracker;
+use crate::arb_detection::check_for_arb_opportunity;
+use crate::cache::MarketHeaderCache;
+use crate::cancel_guard::CancelGuard;
+use crate::client::PhoenixOnchainMM;
+use crate::commands::init::{init_if_missing, StrategyArgs};
+use crate::commands::snapshot::parse_events;
+use crate::ema::Ema;
+use crate::fill_monitor::FillMonitor;
+use crate::market::{get_market_header, get_mint_decimals, load_market};
+use crate::monitor::PriceFeedMonitor;
+use crate::params_file::ParamsFileWatcher;
+use crate::price_feed::{
+    BinanceFeed, CoinbaseFeed, FixedPriceFeed, KrakenFeed, MedianPriceFeed, PriceFeed,
+    PythHermesFeed,
+};
+use crate::risk::{
+    check_for_arbitrage, is_within_tolerance, FrontrunDetector, ADVERSE_SELECTION_WINDOW,
+};
+use crate::rpc::RateLimitedRpcClient;
+use crate::session::{format_net_inventory, SessionStats};
+use crate::tui::Dashboard;
+use anchor_lang::InstructionData;
+use anchor_lang::ToAccountMetas;
+use anyhow::anyhow;
+use clap::Args;
+use clap::ValueEnum;
+use futures::StreamExt;
+use log::{info, warn};
+use phoenix::program::MarketHeader;
+use phoenix::state::markets::FIFOOrderId;
+use phoenix::state::Side;
+use phoenix_onchain_mm::base_lots_to_display;
+use phoenix_onchain_mm::check_market_status;
+use phoenix_onchain_mm::get_ask_price_in_ticks;
+use phoenix_onchain_mm::get_bid_price_in_ticks;
+use phoenix_onchain_mm::quote_atoms_to_usd;
+use phoenix_onchain_mm::tick_to_price_usd;
+use phoenix_onchain_mm::LowBalanceWarning;
+use phoenix_onchain_mm::OrderParams;
+use phoenix_onchain_mm::PricePrecisionMode;
+use phoenix_onchain_mm::StrategyParams;
+use phoenix_sdk::sdk_client::SDKClient;
+use solana_account_decoder::UiAccountEncoding;
+use solana_cli_config::Config;
+use solana_client::nonblocking::pubsub_client::PubsubClient;
+use solana_client::rpc_config::{
+    RpcAccountInfoConfig, RpcSendTransactionConfig, RpcSimulateTransactionConfig,
+};
+use solana_client::rpc_response::SlotInfo;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_sdk::signer::keypair::Keypair;
+use solana_sdk::signer::Signer;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc::{self, error::TrySendError};
+
+/// Slot notifications buffered while an update is in flight
+const SLOT_CHANNEL_CAPACITY: usize = 16;
+
+#[derive(Args, Debug)]
+pub struct RunArgs {
+    // The ticker is used to pull the price from the Coinbase API, and therefore should conform to the Coinbase ticker format.
+    /// Note that for all USDC quoted markets, the price feed should use "USD" instead of "USDC".
+    #[clap(short, long, default_value = "SOL-USD")]
+    pub ticker: String,
+    /// Polling interval, or the minimum time between updates when subscribed to the market
+    #[clap(long, default_value = "2000")]
+    pub quote_refresh_frequency_in_ms: u64,
+    /// Websocket endpoint used to subscribe to market changes. Defaults to the websocket equivalent of the RPC URL.
+    #[clap(long)]
+    pub rpc_url_ws: Option<String>,
+    /// Poll on a fixed interval instead of subscribing to market changes
+    #[clap(long)]
+    pub no_ws: bool,
+    /// Update the quotes as new slots are produced instead of when the market changes
+    #[clap(long, conflicts_with = "no_ws")]
+    pub trigger_on_slot: bool,
+    /// With --trigger-on-slot, number of slots between quote updates
+    #[clap(long, default_value = "1", value_parser = clap::value_parser!(u64).range(1..))]
+    pub update_every_n_slots: u64,
+    #[clap(flatten)]
+    pub strategy: StrategyArgs,
+    /// Do not check for or request a seat on startup, e.g. because it was set up separately
+    #[clap(long)]
+    pub skip_maker_setup: bool,
+    /// Base token account to trade from. Defaults to the payer's associated token account.
+    #[clap(long)]
+    pub base_token_account: Option<Pubkey>,
+    /// Quote token account to trade from. Defaults to the payer's associated token account.
+    #[clap(long)]
+    pub quote_token_account: Option<Pubkey>,
+    /// Hex-encoded Pyth price feed ID. If set, prices are pulled from the Pyth Hermes API instead of Coinbase.
+    #[clap(long)]
+    pub pyth_price_feed_id: Option<String>,
+    /// Exchanges to take the median price across, e.g. "coinbase,binance,kraken". Each exchange's
+    /// symbol is derived from the ticker.
+    #[clap(long, value_enum, value_delimiter = ',')]
+    pub price_feeds: Vec<PriceFeedSource>,
+    /// Minimum number of exchanges in --price-feeds that must return a price
+    #[clap(long, default_value = "1")]
+    pub min_price_feeds: usize,
+    /// Use this fair price in quote atoms per raw base unit instead of any price feed. Intended for testing.
+    #[clap(long)]
+    pub fair_price_override: Option<u64>,
+    /// Maximum age of a Pyth price before it is rejected
+    #[clap(long, default_value = "10")]
+    pub max_price_staleness_in_seconds: u64,
+    /// Maximum ratio of the Pyth confidence interval to the price before it is rejected
+    #[clap(long, default_value = "50")]
+    pub max_price_confidence_ratio_bps: u64,
+    /// Weight of the newest price in the exponential moving average of the fair price. 1.0 disables smoothing.
+    #[clap(long, default_value = "1.0")]
+    pub ema_alpha: f64,
+    /// Number of prices to observe before placing orders, while the moving average warms up
+    #[clap(long, default_value = "0")]
+    pub ema_warmup_iterations: u64,
+    /// Seconds to wait before checking again when the market is not accepting orders
+    #[clap(long, default_value = "30")]
+    pub health_check_retry_secs: u64,
+    /// Skip updates where the quotes would cross the fair price or a --arb-reference-feeds price by more than
+    /// this many basis points
+    #[clap(long, default_value = "10")]
+    pub arbitrage_threshold_bps: f64,
+    /// Exchanges to check the quotes against before every update, e.g. "binance,kraken". Updates that other
+    /// markets could arbitrage are skipped.
+    #[clap(long, value_enum, value_delimiter = ',')]
+    pub arb_reference_feeds: Vec<PriceFeedSource>,
+    /// Only log fills on the strategy's orders, without initializing it or sending any transactions
+    #[clap(long)]
+    pub monitor_fills_only: bool,
+    /// Send quote updates without simulating them first. Low balance warnings are read from the
+    /// simulation logs, so this also disables them.
+    #[clap(long)]
+    pub skip_preflight: bool,
+    /// Commitment used to simulate quote updates. Defaults to the confirmation commitment.
+    #[clap(long)]
+    pub preflight_commitment: Option<CommitmentConfig>,
+    /// Refetch the market header once it is this many slots old
+    #[clap(long, default_value = "150")]
+    pub market_header_max_age_slots: u64,
+    /// Cancel the strategy's orders from a background thread if the quoting loop stalls for this long or exits.
+    /// The guard signs with the trader keypair, since only the trader can cancel the strategy's orders.
+    #[clap(long)]
+    pub cancel_on_disconnect_timeout_ms: Option<u64>,
+    /// Poll the price feed in the background and stop quoting if it returns no price for this long
+    #[clap(long)]
+    pub price_feed_timeout_ms: Option<u64>,
+    /// How often the background price feed monitor polls the feed
+    #[clap(long, default_value = "500")]
+    pub price_feed_poll_interval_ms: u64,
+    /// Mark the price feed unhealthy when a price is this many basis points away from its moving average
+    #[clap(long, default_value = "500")]
+    pub max_price_jump_bps: f64,
+    /// Quote atoms per unit of the quote currency used to convert exchange prices. Defaults to 10^decimals of the quote mint.
+    #[clap(long)]
+    pub price_scale_override: Option<f64>,
+    /// On startup, keep the strategy's resting orders instead of re-quoting them if both are within this many basis
+    /// points of the prices they would be quoted at now
+    #[clap(long)]
+    pub restart_tolerance_bps: Option<u64>,
+    /// Send a single quote update and exit, failing if it was not sent. Intended for external schedulers such as cron.
+    #[clap(long)]
+    pub send_once: bool,
+    /// Simulate quote updates instead of sending them. The strategy is not created if it does not exist.
+    #[clap(long)]
+    pub dry_run: bool,
+    /// Warn when the fair price moves away from the strategy's fill prices by more than this many basis points
+    /// on average over the recent fills, a sign that the quotes are picked off and the edge is too tight
+    #[clap(long)]
+    pub adverse_selection_threshold_bps: Option<f64>,
+    /// JSON or TOML file of strategy params that override the command line ones. The file is watched while
+    /// running, and the quotes are updated with the new params whenever it changes.
+    #[clap(long)]
+    pub params_file: Option<PathBuf>,
+    /// Number of recent quote updates whose spread is summarized on exit
+    #[clap(long, default_value = "100")]
+    pub spread_history_length: usize,
+    /// Append the fair price, quotes and spread of every quote update to this file as JSON lines
+    #[clap(long)]
+    pub spread_log_file: Option<PathBuf>,
+    /// Simulate the initialize and update_quotes transactions, report the compute units they consume and exit
+    /// without sending anything
+    #[clap(long)]
+    pub estimate_compute: bool,
+    /// Compute unit limit that the estimates are compared against
+    #[clap(long, default_value = "1400000")]
+    pub compute_limit: u64,
+    /// Show a terminal dashboard of the params, the book, the PnL and the fills instead of the log lines
+    #[clap(long)]
+    pub tui: bool,
+    /// How often the dashboard is redrawn, independently of the quote refresh
+    #[clap(long, default_value = "500")]
+    pub tui_refresh_ms: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum PriceFeedSource {
+    Coinbase,
+    Binance,
+    Kraken,
+}
+
+impl PriceFeedSource {
+    fn to_price_feed(self, ticker: &str, price_scale: f64) -> Box<dyn PriceFeed> {
+        match self {
+            PriceFeedSource::Coinbase => {
+                Box::new(CoinbaseFeed::new(ticker.to_string()).with_price_scale(price_scale))
+            }
+            PriceFeedSource::Binance => {
+                Box::new(BinanceFeed::from_coinbase_ticker(ticker).with_price_scale(price_scale))
+            }
+            PriceFeedSource::Kraken => {
+                Box::new(KrakenFeed::from_coinbase_ticker(ticker).with_price_scale(price_scale))
+            }
+        }
+    }
+}
+
+/// Settings that apply to every quote update
+#[derive(Debug, Clone, Copy)]
+struct QuoteConfig {
+    params: StrategyParams,
+    health_check_retry_secs: u64,
+    arbitrage_threshold_bps: f64,
+    skip_preflight: bool,
+    preflight_commitment: CommitmentConfig,
+    market_header_max_age_slots: u64,
+    dry_run: bool,
+}
+
+/// Outcome of a single quote update
+#[derive(Debug)]
+enum QuoteUpdate {
+    Sent(Signature),
+    /// Simulated without being sent, in a dry run
+    Simulated,
+    /// Nothing was sent, e.g. while the moving average warms up
+    Skipped,
+    /// The simulation or the transaction failed
+    Failed,
+}
+
+/// State carried from one quote update to the next
+struct QuoteState {
+    ema: Ema,
+    header_cache: MarketHeaderCache,
+    session: SessionStats,
+    price_feed_monitor: Option<PriceFeedMonitor>,
+    /// Set once the orders have been cancelled because the price feed is unhealthy
+    paused_for_price_feed: bool,
+    /// Set on startup when the resting orders are kept, so that the first update leaves them alone
+    skip_next_update: bool,
+    /// Secondary feeds that the quotes are checked against for arbitrage
+    reference_feeds: Vec<Box<dyn PriceFeed>>,
+    frontrun_detector: FrontrunDetector,
+    /// Fair price in quote units per base unit at the previous update, which the fills since then
+    /// were quoted around
+    last_fair_price: Option<f64>,
+    spread_tracker: SpreadTracker,
+    /// Shown with `--tui`
+    dashboard: Option<Dashboard>,
+}
+
+pub async fn run(sdk: &SDKClient, mm: &PhoenixOnchainMM<'_>, args: RunArgs) -> anyhow::Result<()> {
+    let RunArgs {
+        ticker,
+        quote_refresh_frequency_in_ms,
+        rpc_url_ws,
+        no_ws,
+        trigger_on_slot,
+        update_every_n_slots,
+        strategy,
+        skip_maker_setup,
+        base_token_account,
+        quote_token_account,
+        pyth_price_feed_id,
+        price_feeds,
+        min_price_feeds,
+        fair_price_override,
+        max_price_staleness_in_seconds,
+        max_price_confidence_ratio_bps,
+        ema_alpha,
+        ema_warmup_iterations,
+        health_check_retry_secs,
+        arbitrage_threshold_bps,
+        arb_reference_feeds,
+        monitor_fills_only,
+        skip_preflight,
+        preflight_commitment,
+        market_header_max_age_slots,
+        cancel_on_disconnect_timeout_ms,
+        price_feed_timeout_ms,
+        price_feed_poll_interval_ms,
+        max_price_jump_bps,
+        price_scale_override,
+        restart_tolerance_bps,
+        send_once,
+        dry_run,
+        params_file,
+        adverse_selection_threshold_bps,
+        spread_history_length,
+        spread_log_file,
+        estimate_compute,
+        compute_limit,
+        tui,
+        tui_refresh_ms,
+    } = args;
+    let (client, payer, market, strategy_key) =
+        (mm.rpc_client(), mm.payer(), mm.market(), mm.strategy_key());
+    if monitor_fills_only {
+        return monitor_fills(
+            mm,
+            std::time::Duration::from_millis(quote_refresh_frequency_in_ms),
+        )
+        .await;
+    }
+    let ema = Ema::new(ema_alpha, ema_warmup_iterations)?;
+
+    let params = strategy.resolve_strategy_params(client, &market).await?;
+    let mut params_file = params_file.map(|path| ParamsFileWatcher::new(path, params));
+    let params = match &mut params_file {
+        Some(watcher) => watcher
+            .poll()
+            .map_err(|e| {
+                anyhow!(
+                    "Failed to load params from {}: {}",
+                    watcher.path().display(),
+                    e
+                )
+            })?
+            .unwrap_or(params),
+        None => params,
+    };
+    params
+        .validate(false)
+        .map_err(|e| anyhow!("Invalid strategy params: {}", e))?;
+    if !dry_run && !estimate_compute {
+        init_if_missing(sdk, mm, params, skip_maker_setup).await?;
+    }
+
+    if !estimate_compute {
+        health_check(client, payer, market, strategy_key).await?;
+    }
+
+    let accounts = mm
+        .get_update_quotes_accounts(
+            base_token_account,
+            quote_token_account,
+            params.volatility_oracle_pubkey,
+        )
+        .await?;
+
+    let price_scale = match price_scale_override {
+        Some(price_scale) => {
+            println!("Converting prices with a scale of {}", price_scale);
+            price_scale
+        }
+        None => {
+            let header = get_market_header(client, &market).await?;
+            let decimals = get_mint_decimals(client, &header.quote_params.mint_key).await?;
+            println!(
+                "Quote mint {} has {} decimals",
+                header.quote_params.mint_key, decimals
+            );
+            10_f64.powi(decimals as i32)
+        }
+    };
+    let reference_feeds = arb_reference_feeds
+        .iter()
+        .map(|source| source.to_price_feed(&ticker, price_scale))
+        .collect();
+    let price_feed: Arc<dyn PriceFeed> = match (fair_price_override, pyth_price_feed_id) {
+        (Some(fair_price), _) => Arc::new(FixedPriceFeed::new(fair_price)),
+        (None, Some(price_feed_id)) => Arc::new(
+            PythHermesFeed::new(
+                price_feed_id,
+                max_price_staleness_in_seconds,
+                max_price_confidence_ratio_bps,
+            )
+            .with_price_scale(price_scale),
+        ),
+        (None, None) if !price_feeds.is_empty() => Arc::new(MedianPriceFeed::new(
+            price_feeds
+                .iter()
+                .map(|source| source.to_price_feed(&ticker, price_scale))
+                .collect(),
+            min_price_feeds,
+        )),
+        (None, None) => Arc::new(CoinbaseFeed::new(ticker).with_price_scale(price_scale)),
+    };
+
+    if estimate_compute {
+        return estimate_compute_units(mm, &*price_feed, &accounts, params, compute_limit).await;
+    }
+
+    println!("Quote Params: {:#?}", params);
+
+    let refresh_interval = std::time::Duration::from_millis(quote_refresh_frequency_in_ms);
+    let mut config = QuoteConfig {
+        params,
+        health_check_retry_secs,
+        arbitrage_threshold_bps,
+        skip_preflight,
+        preflight_commitment: preflight_commitment.unwrap_or_else(|| client.commitment()),
+        market_header_max_age_slots,
+        dry_run,
+    };
+
+    let price_feed_monitor = match price_feed_timeout_ms {
+        Some(timeout_ms) => Some(PriceFeedMonitor::spawn(
+            price_feed.clone(),
+            std::time::Duration::from_millis(price_feed_poll_interval_ms),
+            std::time::Duration::from_millis(timeout_ms),
+            max_price_jump_bps,
+        )?),
+        None => None,
+    };
+    let resume_orders = match restart_tolerance_bps {
+        Some(tolerance_bps) => can_resume_orders(
+            mm,
+            &*price_feed,
+            params.quote_edge_in_bps.unwrap_or_default(),
+            tolerance_bps,
+        )
+        .await
+        .unwrap_or_else(|e| {
+            println!("Failed to check the resting orders, re-quoting: {}", e);
+            false
+        }),
+        None => false,
+    };
+    if resume_orders {
+        println!("Resuming existing orders, skipping initial re-quote");
+    }
+    let spread_tracker = SpreadTracker::new(spread_history_length);
+    let spread_tracker = match &spread_log_file {
+        Some(path) => spread_tracker
+            .with_log_file(path)
+            .map_err(|e| anyhow!("Failed to open spread log file {}: {}", path.display(), e))?,
+        None => spread_tracker,
+    };
+    let mut state = QuoteState {
+        ema,
+        header_cache: MarketHeaderCache::new(market),
+        session: SessionStats::new(),
+        price_feed_monitor,
+        paused_for_price_feed: false,
+        skip_next_update: resume_orders,
+        reference_feeds,
+        frontrun_detector: FrontrunDetector::new(
+            adverse_selection_threshold_bps,
+            ADVERSE_SELECTION_WINDOW,
+        ),
+        last_fair_price: None,
+        spread_tracker,
+        dashboard: None,
+    };
+    // Exits before the cancel guard starts, which would cancel the new orders on exit
+    if send_once {
+        return match update_quotes(mm, &*price_feed, &mut state, &accounts, &config).await? {
+            QuoteUpdate::Sent(sig) => {
+                // Printed on its own line so that scripts can pick it up
+                println!("{}", sig);
+                Ok(())
+            }
+            QuoteUpdate::Simulated => Ok(()),
+            QuoteUpdate::Skipped => Err(anyhow!("No quote update was sent")),
+            QuoteUpdate::Failed => Err(anyhow!("Quote update failed")),
+        };
+    }
+    if tui {
+        state.dashboard = Some(Dashboard::start(std::time::Duration::from_millis(
+            tui_refresh_ms,
+        ))?);
+    }
+    let guard = match cancel_on_disconnect_timeout_ms.filter(|_| !dry_run) {
+        Some(timeout_ms) => Some(CancelGuard::spawn(
+            client.url(),
+            client.commitment(),
+            Keypair::from_bytes(&payer.to_bytes())?,
+            mm.cancel_all_instruction(),
+            std::time::Duration::from_millis(timeout_ms),
+        )),
+        None => None,
+    };
+    let heartbeat = || {
+        if let Some(guard) = &guard {
+            guard.heartbeat();
+        }
+    };
+    let quoting = async {
+        if no_ws {
+            loop {
+                reload_params(&mut params_file, &mut config);
+                update_quotes(mm, &*price_feed, &mut state, &accounts, &config).await?;
+                draw_dashboard(&mut state.dashboard)?;
+                heartbeat();
+                sleep_with_dashboard(refresh_interval, &mut state.dashboard).await?;
+            }
+        }
+
+        let ws_url = rpc_url_ws.unwrap_or_else(|| Config::compute_websocket_url(&client.url()));
+        if trigger_on_slot {
+            let mut slots = spawn_slot_subscription(ws_url, refresh_interval);
+            let mut next_update_slot = 0;
+            loop {
+                // Slots queued up during the previous update are skipped until the next update is due
+                loop {
+                    tokio::select! {
+                        slot_info = slots.recv() => {
+                            let Some(slot_info) = slot_info else {
+                                return Err(anyhow!("Slot subscription stopped"));
+                            };
+                            heartbeat();
+                            if slot_info.slot >= next_update_slot {
+                                next_update_slot = slot_info.slot + update_every_n_slots;
+                                break;
+                            }
+                        }
+                        result = refresh_dashboard(&mut state.dashboard) => result?,
+                    }
+                }
+                reload_params(&mut params_file, &mut config);
+                update_quotes(mm, &*price_feed, &mut state, &accounts, &config).await?;
+                draw_dashboard(&mut state.dashboard)?;
+            }
+        }
+
+        let subscribe_config = RpcAccountInfoConfig {
+            encoding: Some(UiAccountEncoding::Base64),
+            commitment: Some(client.commitment()),
+            ..RpcAccountInfoConfig::default()
+        };
+        loop {
+            let pubsub = match PubsubClient::new(&ws_url).await {
+                Ok(pubsub) => pubsub,
+                Err(e) => {
+                    println!("Failed to connect to {}: {}", ws_url, e);
+                    tokio::time::sleep(refresh_interval).await;
+                    continue;
+                }
+            };
+            match pubsub
+                .account_subscribe(&market, Some(subscribe_config.clone()))
+                .await
+            {
+                Ok((mut notifications, unsubscribe)) => {
+                    println!("Subscribed to market updates on {}", ws_url);
+                    // Quote once immediately, then whenever the market changes
+                    let mut last_update: Option<std::time::Instant> = None;
+                    loop {
+                        if reload_params(&mut params_file, &mut config) {
+                            last_update = None;
+                        }
+                        if last_update.is_none_or(|t| t.elapsed() >= refresh_interval) {
+                            update_quotes(mm, &*price_feed, &mut state, &accounts, &config).await?;
+                            draw_dashboard(&mut state.dashboard)?;
+                            last_update = Some(std::time::Instant::now());
+                        }
+                        heartbeat();
+                        // Keep the heartbeat going and the params file watched while the market is quiet
+                        let idle = tokio::time::sleep(refresh_interval);
+                        tokio::pin!(idle);
+                        let notification = loop {
+                            tokio::select! {
+                                notification = notifications.next() => break notification.map(|_| ()),
+                                _ = &mut idle => {
+                                    idle.as_mut().reset(tokio::time::Instant::now() + refresh_interval);
+                                    heartbeat();
+                                    if reload_params(&mut params_file, &mut config) {
+                                        last_update = None;
+                                        break Some(());
+                                    }
+                                }
+                                result = refresh_dashboard(&mut state.dashboard) => result?,
+                            }
+                        };
+                        if notification.is_none() {
+                            break;
+                        }
+                    }
+                    unsubscribe().await;
+                }
+                Err(e) => println!("Failed to subscribe to market updates: {}", e),
+            }
+            pubsub.shutdown().await.ok();
+            println!("Market subscription closed, reconnecting");
+            tokio::time::sleep(refresh_interval).await;
+        }
+    };
+
+    let result = tokio::select! {
+        result = quoting => result,
+        _ = tokio::signal::ctrl_c() => {
+            println!("Received Ctrl-C, shutting down");
+            Ok(())
+        }
+    };
+    // Restores the terminal before the summary is logged
+    state.dashboard = None;
+    state.session.summarize();
+    if let Some(average_bps) = state.frontrun_detector.average_adverse_move_bps() {
+        info!("  Avg adverse selection:    {:.1} bps", average_bps);
+    }
+    state.spread_tracker.print_spread_summary();
+    // Cancels the orders before returning
+    drop(guard);
+    result
+}
+
+/// Returns true if both of the strategy's orders are still resting on the book and each is within
+/// `tolerance_bps` of the price it would be quoted at for the current fair price.
+async fn can_resume_orders(
+    mm: &PhoenixOnchainMM<'_>,
+    price_feed: &dyn PriceFeed,
+    edge_in_bps: u64,
+    tolerance_bps: u64,
+) -> anyhow::Result<bool> {
+    let state = mm.get_state().await?;
+    if !state.has_live_bid() || !state.has_live_ask() {
+        return Ok(false);
+    }
+    let data = mm.rpc_client().get_account_data(&mm.market()).await?;
+    let (header, market) = load_market(&data)?;
+    let is_resting = |side: Side, price_in_ticks: u64, sequence_number: u64| {
+        market
+            .get_book(side)
+            .get(&FIFOOrderId::new_from_untyped(
+                price_in_ticks,
+                sequence_number,
+            ))
+            .is_some()
+    };
+    if !is_resting(
+        Side::Bid,
+        state.bid_price_in_ticks,
+        state.bid_order_sequence_number,
+    ) || !is_resting(
+        Side::Ask,
+        state.ask_price_in_ticks,
+        state.ask_order_sequence_number,
+    ) {
+        return Ok(false);
+    }
+    let fair_price = price_feed.get_fair_price_in_quote_atoms().await?;
+    let mode = PricePrecisionMode::try_from_u8(state.price_precision_mode)
+        .map_err(|e| anyhow!("{}", e))?;
+    // A price that cannot be computed in `Exact` mode is left to the program to reject
+    let (Ok(bid), Ok(ask)) = (
+        get_bid_price_in_ticks(fair_price, header, edge_in_bps, mode),
+        get_ask_price_in_ticks(fair_price, header, edge_in_bps, mode),
+    ) else {
+        return Ok(false);
+    };
+    Ok(
+        is_within_tolerance(state.bid_price_in_ticks, bid, tolerance_bps)
+            && is_within_tolerance(state.ask_price_in_ticks, ask, tolerance_bps),
+    )
+}
+
+/// Records the fills and quotes resting since the previous update in the session stats. Returns
+/// the price in quote units of each side that was filled.
+async fn update_session_stats(
+    mm: &PhoenixOnchainMM<'_>,
+    session: &mut SessionStats,
+    dashboard: &mut Option<Dashboard>,
+) -> anyhow::Result<Vec<f64>> {
+    let state = mm.get_state().await?;
+    let data = mm.rpc_client().get_account_data(&mm.market()).await?;
+    let (header, market) = load_market(&data)?;
+    let (bid_fill, ask_fill) = session.update(&state, header, market);
+    if let Some(dashboard) = dashboard {
+        dashboard.view.update(
+            &state,
+            header,
+            market,
+            session.estimated_pnl_in_quote_atoms(),
+            (bid_fill, ask_fill),
+            SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64,
+        );
+    }
+    let (base_delta, quote_delta) = session.last_position_delta();
+    if base_delta != 0 || quote_delta != 0 {
+        info!(
+            "Position change: {:+} base lots / {:+} quote atoms",
+            base_delta, quote_delta
+        );
+        println!(
+            "Current net inventory: {}",
+            format_net_inventory(session.net_inventory())
+        );
+    }
+    Ok([
+        (bid_fill, state.bid_price_in_ticks),
+        (ask_fill, state.ask_price_in_ticks),
+    ]
+    .into_iter()
+    .filter(|(fill, _)| *fill > 0)
+    .map(|(_, price_in_ticks)| tick_to_price_usd(price_in_ticks, header))
+    .collect())
+}
+
+/// Forwards slot notifications from `ws_url` to the returned channel, reconnecting after
+/// `retry_interval` when the subscription drops. Notifications are dropped while the channel is
+/// full, since only the latest slot matters. Stops once the receiver is dropped.
+fn spawn_slot_subscription(
+    ws_url: String,
+    retry_interval: std::time::Duration,
+) -> mpsc::Receiver<SlotInfo> {
+    let (sender, receiver) = mpsc::channel(SLOT_CHANNEL_CAPACITY);
+    tokio::spawn(async move {
+        while !sender.is_closed() {
+            match PubsubClient::new(&ws_url).await {
+                Ok(pubsub) => {
+                    match pubsub.slot_subscribe().await {
+                        Ok((mut notifications, unsubscribe)) => {
+                            println!("Subscribed to slot updates on {}", ws_url);
+                            while let Some(slot_info) = notifications.next().await {
+                                if let Err(TrySendError::Closed(_)) = sender.try_send(slot_info) {
+                                    break;
+                                }
+                            }
+                            unsubscribe().await;
+                        }
+                        Err(e) => println!("Failed to subscribe to slot updates: {}", e),
+                    }
+                    pubsub.shutdown().await.ok();
+                }
+                Err(e) => println!("Failed to connect to {}: {}", ws_url, e),
+            }
+            if !sender.is_closed() {
+                println!("Slot subscription closed, reconnecting");
+                tokio::time::sleep(retry_interval).await;
+            }
+        }
+    });
+    receiver
+}
+
+fn draw_dashboard(dashboard: &mut Option<Dashboard>) -> anyhow::Result<()> {
+    match dashboard {
+        Some(dashboard) => dashboard.draw(),
+        None => Ok(()),
+    }
+}
+
+/// Redraws the dashboard at its refresh rate. Never completes without a dashboard, so that it can
+/// be raced against the quoting loop's other events.
+async fn refresh_dashboard(dashboard: &mut Option<Dashboard>) -> anyhow::Result<()> {
+    match dashboard {
+        Some(dashboard) => dashboard.refresh().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Sleeps for `duration`, keeping the dashboard up to date in the meantime.
+async fn sleep_with_dashboard(
+    duration: std::time::Duration,
+    dashboard: &mut Option<Dashboard>,
+) -> anyhow::Result<()> {
+    let sleep = tokio::time::sleep(duration);
+    tokio::pin!(sleep);
+    loop {
+        tokio::select! {
+            _ = &mut sleep => return Ok(()),
+            result = refresh_dashboard(dashboard) => result?,
+        }
+    }
+}
+
+/// Polls the strategy and the market, printing fills as they happen. Read-only.
+async fn monitor_fills(
+    mm: &PhoenixOnchainMM<'_>,
+    refresh_interval: std::time::Duration,
+) -> anyhow::Result<()> {
+    println!("Monitoring fills for strategy {}", mm.strategy_key());
+    let mut monitor = FillMonitor::default();
+    loop {
+        let state = mm.get_state().await?;
+        let data = mm.rpc_client().get_account_data(&mm.market()).await?;
+        let (header, market) = load_market(&data)?;
+
+        let (bid_fill, ask_fill) = monitor.observe(&state, market);
+        let (bid_total, ask_total) = monitor.strategy_totals_in_base_lots();
+        println!(
+            "Fills since last check: bought {:.4}, sold {:.4} | cumulative: bought {:.4}, sold {:.4} | estimated PnL: {:.4}",
+            base_lots_to_display(bid_fill, header),
+            base_lots_to_display(ask_fill, header),
+            base_lots_to_display(bid_total, header),
+            base_lots_to_display(ask_total, header),
+            monitor.estimated_pnl_in_quote_atoms(header, market) as f64
+                / 10f64.powi(header.quote_params.decimals as i32),
+        );
+        tokio::time::sleep(refresh_interval).await;
+    }
+}
+
+/// Simulates the `health_check` instruction and fails if the strategy is in an inconsistent state.
+async fn health_check(
+    client: &RateLimitedRpcClient,
+    payer: &Keypair,
+    market: Pubkey,
+    strategy_key: Pubkey,
+) -> anyhow::Result<()> {
+    let accounts = phoenix_onchain_mm::accounts::HealthCheck {
+        phoenix_strategy: strategy_key,
+        market,
+    };
+    let ix = Instruction {
+        program_id: phoenix_onchain_mm::id(),
+        accounts: accounts.to_account_metas(None),
+        data: phoenix_onchain_mm::instruction::HealthCheck {}.data(),
+    };
+    let transaction = solana_sdk::transaction::Transaction::new_signed_with_payer(
+        &[ix],
+        Some(&payer.pubkey()),
+        &[payer],
+        client.get_latest_blockhash().await?,
+    );
+    let result = client.simulate_transaction(&transaction).await?.value;
+    if let Some(err) = result.err {
+        for log in result.logs.unwrap_or_default() {
+            println!("{}", log);
+        }
+        return Err(anyhow!("Strategy health check failed: {}", err));
+    }
+    println!("Strategy health check passed");
+    Ok(())
+}
+
+/// Simulates the `initialize` and `update_quotes` transactions at the current fair price and
+/// prints the compute units each consumes against `compute_limit`. Nothing is sent.
+async fn estimate_compute_units(
+    mm: &PhoenixOnchainMM<'_>,
+    price_feed: &dyn PriceFeed,
+    accounts: &phoenix_onchain_mm::accounts::UpdateQuotes,
+    params: StrategyParams,
+    compute_limit: u64,
+) -> anyhow::Result<()> {
+    let client = mm.rpc_client();
+    let order_params = OrderParams {
+        fair_price_in_quote_atoms_per_raw_base_unit: price_feed
+            .get_fair_price_in_quote_atoms()
+            .await?,
+        strategy_params: params,
+        submitted_slot: client.get_slot().await?,
+    };
+    let estimates = [
+        ("initialize", mm.initialize_instruction(params)),
+        (
+            "update_quotes",
+            Ok(mm.update_quotes_instruction(accounts, order_params)),
+        ),
+    ];
+    for (name, ix) in estimates {
+        let ix = match ix {
+            Ok(ix) => ix,
+            Err(e) => {
+                println!("Cannot estimate compute units for {}: {}", name, e);
+                continue;
+            }
+        };
+        let transaction = mm.sign_transaction(&[ix]).await?;
+        let result = client
+            .simulate_transaction_with_config(
+                &transaction,
+                RpcSimulateTransactionConfig {
+                    sig_verify: false,
+                    commitment: None,
+                    ..RpcSimulateTransactionConfig::default()
+                },
+            )
+            .await?
+            .value;
+        if let Some(err) = result.err {
+            // The estimate is still printed below, but only covers the instructions up to the error
+            println!("Simulation of {} failed: {}", name, err);
+            for log in result.logs.unwrap_or_default() {
+                println!("  {}", log);
+            }
+        }
+        let Some(units_consumed) = result.units_consumed else {
+            println!("The RPC node did not report compute units for {}", name);
+            continue;
+        };
+        println!(
+            "Estimated compute units for {}: {} / {}",
+            name,
+            format_compute_units(units_consumed),
+            format_compute_units(compute_limit)
+        );
+        if units_consumed as f64 > 0.9 * compute_limit as f64 {
+            warn!(
+                "{} uses more than 90% of the compute limit and may fail on congested validators",
+                name
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Formats compute units with thousands separators, e.g. 1,400,000
+fn format_compute_units(units: u64) -> String {
+    let digits = units.to_string();
+    let mut formatted = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            formatted.push(',');
+        }
+        formatted.push(digit);
+    }
+    formatted
+}
+
+/// Fetches the reference feeds concurrently and returns their prices in quote units per base
+/// unit, leaving out the feeds that failed.
+async fn get_reference_prices(feeds: &[Box<dyn PriceFeed>], header: &MarketHeader) -> Vec<f64> {
+    futures::future::join_all(
+        feeds
+            .iter()
+            .map(|feed| feed.get_fair_price_in_quote_atoms()),
+    )
+    .await
+    .into_iter()
+    .filter_map(|result| {
+        result
+            .map_err(|e| println!("Reference price feed failed: {}", e))
+            .ok()
+    })
+    .map(|price| {
+        quote_atoms_to_usd(
+            price.saturating_mul(header.raw_base_units_per_base_unit as u64),
+            header,
+        )
+    })
+    .collect()
+}
+
+/// Replaces the params in `config` if the params file changed. Returns true if they were replaced,
+/// so that the quotes are updated right away. A file that fails to load leaves the params as they were.
+fn reload_params(params_file: &mut Option<ParamsFileWatcher>, config: &mut QuoteConfig) -> bool {
+    let Some(watcher) = params_file else {
+        return false;
+    };
+    match watcher.poll() {
+        Ok(Some(params)) => {
+            println!("Reloaded params from file: {:#?}", params);
+            config.params = params;
+            true
+        }
+        Ok(None) => false,
+        Err(e) => {
+            warn!(
+                "Failed to reload params from {}, keeping the current params: {}",
+                watcher.path().display(),
+                e
+            );
+            false
+        }
+    }
+}
+
+/// Reads the next fair price from the feed and folds it into the moving average. Returns the
+/// raw price and the smoothed price to quote around, which is None while the average warms up.
+async fn next_fair_price(
+    price_feed: &dyn PriceFeed,
+    ema: &mut Ema,
+) -> anyhow::Result<(u64, Option<u64>)> {
+    let raw_fair_price = price_feed.get_fair_price_in_quote_atoms().await?;
+    ema.update(raw_fair_price as f64);
+    Ok((raw_fair_price, ema.is_warm().then(|| smoothed_price(ema))))
+}
+
+fn smoothed_price(ema: &Ema) -> u64 {
+    ema.value().unwrap_or_default().round() as u64
+}
+
+async fn update_quotes(
+    mm: &PhoenixOnchainMM<'_>,
+    price_feed: &dyn PriceFeed,
+    state: &mut QuoteState,
+    accounts: &phoenix_onchain_mm::accounts::UpdateQuotes,
+    config: &QuoteConfig,
+) -> anyhow::Result<QuoteUpdate> {
+    let QuoteConfig {
+        params,
+        health_check_retry_secs,
+        arbitrage_threshold_bps,
+        skip_preflight,
+        preflight_commitment,
+        market_header_max_age_slots,
+        dry_run,
+    } = *config;
+    let client = mm.rpc_client();
+    let QuoteState {
+        ema,
+        header_cache,
+        session,
+        price_feed_monitor,
+        paused_for_price_feed,
+        skip_next_update,
+        reference_feeds,
+        frontrun_detector,
+        last_fair_price,
+        spread_tracker,
+        dashboard,
+    } = state;
+    if let Some(dashboard) = dashboard {
+        dashboard.view.set_params(&params);
+    }
+
+    // Pull the quotes once and stop quoting until the price feed recovers
+    if price_feed_monitor
+        .as_ref()
+        .is_some_and(|monitor| !monitor.is_healthy())
+    {
+        if !*paused_for_price_feed {
+            if !dry_run {
+                println!("Price feed is unhealthy, cancelling orders");
+                mm.cancel_all().await?;
+            }
+            *paused_for_price_feed = true;
+        }
+        println!("Price feed is unhealthy, not quoting");
+        return Ok(QuoteUpdate::Skipped);
+    }
+    *paused_for_price_feed = false;
+
+    // Pause quoting while the market is halted instead of sending transactions that will fail
+    let header = loop {
+        let current_slot = client.get_slot().await?;
+        let header = *header_cache
+            .refresh_if_stale(market_header_max_age_slots, current_slot, client)
+            .await?;
+        if check_market_status(&header).is_ok() {
+            break header;
+        }
+        // Fetch the status again on the next attempt
+        header_cache.invalidate();
+        println!(
+            "Market is not accepting orders (status {}), retrying in {}s",
+            header.status, health_check_retry_secs
+        );
+        tokio::time::sleep(std::time::Duration::from_secs(health_check_retry_secs)).await;
+    };
+
+    // Session stats are informational, so a failed fetch does not stop quoting
+    match update_session_stats(mm, session, dashboard).await {
+        Ok(fill_prices) => {
+            if let Some(fair_price) = *last_fair_price {
+                let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
+                for fill_price in fill_prices {
+                    frontrun_detector.record_fill(now, fill_price, fair_price);
+                }
+            }
+        }
+        Err(e) => println!("Failed to update session stats: {}", e),
+    }
+    if *skip_next_update {
+        *skip_next_update = false;
+        return Ok(QuoteUpdate::Skipped);
+    }
+
+    let (raw_fair_price, fair_price) = next_fair_price(price_feed, ema).await?;
+
+    println!(
+        "Fair price: {} (EMA: {})",
+        quote_atoms_to_usd(raw_fair_price, &header),
+        quote_atoms_to_usd(smoothed_price(ema), &header)
+    );
+    let Some(fair_price) = fair_price else {
+        println!("Warming up the moving average, not placing orders");
+        return Ok(QuoteUpdate::Skipped);
+    };
+
+    let edge_in_bps = params.quote_edge_in_bps.unwrap_or_default();
+    let mode = params
+        .price_precision_mode
+        .unwrap_or(PricePrecisionMode::Conservative);
+    // Quote units per base unit, to compare against the tick prices
+    let reference_price = quote_atoms_to_usd(
+        fair_price.saturating_mul(header.raw_base_units_per_base_unit as u64),
+        &header,
+    );
+    *last_fair_price = Some(reference_price);
+    if !frontrun_detector
+        .observe_fair_price(reference_price)
+        .is_empty()
+        && frontrun_detector.is_adverse_selection_high()
+    {
+        warn!(
+            "Fills are followed by fair price moves of {:.1} bps on average, consider widening the edge",
+            frontrun_detector
+                .average_adverse_move_bps()
+                .unwrap_or_default()
+        );
+    }
+    let bid_price = tick_to_price_usd(
+        get_bid_price_in_ticks(fair_price, &header, edge_in_bps, mode)
+            .map_err(|e| anyhow!("{}", e))?,
+        &header,
+    );
+    let ask_price = tick_to_price_usd(
+        get_ask_price_in_ticks(fair_price, &header, edge_in_bps, mode)
+            .map_err(|e| anyhow!("{}", e))?,
+        &header,
+    );
+    if check_for_arbitrage(
+        bid_price,
+        ask_price,
+        reference_price,
+        arbitrage_threshold_bps,
+    ) {
+        println!(
+            "Warning: quotes {:.4}/{:.4} are crossed against the fair price {:.4}, skipping update",
+            bid_price, ask_price, reference_price
+        );
+        return Ok(QuoteUpdate::Skipped);
+    }
+    if !reference_feeds.is_empty() {
+        let reference_prices = get_reference_prices(reference_feeds, &header).await;
+        if let Some(opportunity) = check_for_arb_opportunity(
+            bid_price,
+            ask_price,
+            &reference_prices,
+            arbitrage_threshold_bps,
+        ) {
+            println!(
+                "Warning: {:?} {:.4} can be arbitraged against the reference price {:.4} ({:.1} bps), skipping update",
+                opportunity.side,
+                opportunity.our_price,
+                opportunity.reference_price,
+                opportunity.profit_bps
+            );
+            return Ok(QuoteUpdate::Skipped);
+        }
+    }
+
+    // Lets the program reject the update if it lands too long after the price was observed
+    let submitted_slot = client.get_slot().await?;
+    let order_params = OrderParams {
+        fair_price_in_quote_atoms_per_raw_base_unit: fair_price,
+        strategy_params: params,
+        submitted_slot,
+    };
+    if let Err(e) = order_params.validate() {
+        println!("Invalid order params, skipping update: {}", e);
+        return Ok(QuoteUpdate::Skipped);
+    }
+    let ix = mm.update_quotes_instruction(accounts, order_params);
+    let transaction = mm.sign_transaction(&[ix]).await?;
+    if !skip_preflight || dry_run {
+        let result = client
+            .simulate_transaction_with_config(
+                &transaction,
+                RpcSimulateTransactionConfig {
+                    sig_verify: false,
+                    commitment: Some(preflight_commitment),
+                    ..RpcSimulateTransactionConfig::default()
+                },
+            )
+            .await?
+            .value;
+        if let Some(err) = result.err {
+            println!("Simulation failed, not sending the update: {}", err);
+            for log in result.logs.unwrap_or_default() {
+                println!("  {}", log);
+            }
+            return Ok(QuoteUpdate::Failed);
+        }
+        if let Some(units_consumed) = result.units_consumed {
+            println!("Simulation consumed {} compute units", units_consumed);
+        }
+        for warning in parse_events::<LowBalanceWarning>(result.logs.as_deref().unwrap_or_default())
+        {
+            let unit = if warning.side == "quote" {
+                "quote atoms"
+            } else {
+                "base lots"
+            };
+            warn!(
+                "Low deposited {} balance: {} {} (minimum {})",
+                warning.side, warning.balance, unit, warning.threshold
+            );
+        }
+        if dry_run {
+            println!("Dry run, not sending the update");
+            return Ok(QuoteUpdate::Simulated);
+        }
+    }
+
+    // The transaction was either simulated above or the user opted out of preflight checks
+    let send_config = RpcSendTransactionConfig {
+        skip_preflight: true,
+        ..RpcSendTransactionConfig::default()
+    };
+    let result = match client
+        .send_transaction_with_config(&transaction, send_config)
+        .await
+    {
+        Ok(sig) => client.poll_for_signature(&sig).await.map(|_| sig),
+        Err(e) => Err(e),
+    };
+    session.record_transaction(result.is_ok());
+    match result {
+        Ok(sig) => {
+            println!("Updating quotes: {}", sig);
+            let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
+            spread_tracker.update(now, reference_price, bid_price, ask_price);
+            Ok(QuoteUpdate::Sent(sig))
+        }
+        Err(e) => {
+            println!("Failed to update quotes: {}", e);
+            Ok(QuoteUpdate::Failed)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::price_feed::MockPriceFeed;
+
+    #[tokio::test]
+    async fn test_no_quotes_while_the_ema_warms_up() {
+        let feed = MockPriceFeed::sequence(vec![100_000, 110_000, 120_000]);
+        let mut ema = Ema::new(0.5, 2).unwrap();
+        assert_eq!(
+            next_fair_price(&feed, &mut ema).await.unwrap(),
+            (100_000, None)
+        );
+        assert_eq!(
+            next_fair_price(&feed, &mut ema).await.unwrap(),
+            (110_000, None)
+        );
+        assert_eq!(
+            next_fair_price(&feed, &mut ema).await.unwrap(),
+            (120_000, Some(112_500))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_failed_price_fetch_does_not_advance_the_ema() {
+        let feed = MockPriceFeed::new(vec![Err(anyhow!("timeout")), Ok(100_000)]);
+        let mut ema = Ema::new(0.5, 0).unwrap();
+        assert!(next_fair_price(&feed, &mut ema).await.is_err());
+        assert_eq!(ema.value(), None);
+        assert_eq!(
+            next_fair_price(&feed, &mut ema).await.unwrap(),
+            (100_000, Some(100_000))
+        );
+    }
+}
@@ -0,0 +1,81 @@
+use crate::client::PhoenixOnchainMM;
+use anchor_lang::__private::base64;
+use anchor_lang::{AnchorDeserialize, Discriminator};
+use anyhow::anyhow;
+use phoenix_onchain_mm::StrategySnapshot;
+use solana_client::rpc_config::RpcTransactionConfig;
+use solana_transaction_status::option_serializer::OptionSerializer;
+use solana_transaction_status::UiTransactionEncoding;
+
+/// Returns the events of type `E` emitted in a transaction's logs.
+pub fn parse_events<E: AnchorDeserialize + Discriminator>(logs: &[String]) -> Vec<E> {
+    logs.iter()
+        .filter_map(|log| log.strip_prefix("Program data: "))
+        .filter_map(|data| base64::decode(data).ok())
+        .filter_map(|data| {
+            let payload = data.strip_prefix(&E::discriminator())?;
+            E::try_from_slice(payload).ok()
+        })
+        .collect()
+}
+
+/// Returns the `StrategySnapshot` events emitted in a transaction's logs.
+pub fn parse_strategy_snapshots(logs: &[String]) -> Vec<StrategySnapshot> {
+    parse_events(logs)
+}
+
+pub async fn snapshot(mm: &PhoenixOnchainMM<'_>) -> anyhow::Result<()> {
+    let txid = mm.snapshot().await?;
+    println!("Snapshot transaction: {}", txid);
+    let client = mm.rpc_client();
+    let tx = client
+        .get_transaction_with_config(
+            &txid,
+            RpcTransactionConfig {
+                encoding: Some(UiTransactionEncoding::Json),
+                commitment: Some(client.commitment()),
+                max_supported_transaction_version: Some(0),
+            },
+        )
+        .await?;
+    let logs = match tx.transaction.meta.map(|meta| meta.log_messages) {
+        Some(OptionSerializer::Some(logs)) => logs,
+        _ => return Err(anyhow!("Transaction {} has no logs", txid)),
+    };
+    let snapshot = parse_strategy_snapshots(&logs)
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow!("No strategy snapshot in transaction {}", txid))?;
+    println!("{:#?}", snapshot);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::Event;
+    use bytemuck::Zeroable;
+    use phoenix_onchain_mm::PhoenixStrategyState;
+    use solana_sdk::pubkey::Pubkey;
+
+    #[test]
+    fn test_parse_strategy_snapshots() {
+        let mut state = PhoenixStrategyState::zeroed();
+        state.bid_price_in_ticks = 1_234;
+        state.strategy_id = 3;
+        let strategy = Pubkey::new_unique();
+        let logs = vec![
+            "Program log: Instruction: Snapshot".to_string(),
+            "Program data: bm90IGFuIGV2ZW50".to_string(),
+            format!(
+                "Program data: {}",
+                base64::encode(StrategySnapshot::new(strategy, &state).data())
+            ),
+        ];
+        let snapshots = parse_strategy_snapshots(&logs);
+        assert_eq!(snapshots.len(), 1);
+        assert_eq!(snapshots[0].strategy, strategy);
+        assert_eq!(snapshots[0].bid_price_in_ticks, 1_234);
+        assert_eq!(snapshots[0].strategy_id, 3);
+    }
+}
@@ -0,0 +1,271 @@
+use crate::display::{quote_lots_to_size, StrategyStateDisplay};
+use crate::market::{load_market, PhoenixMarket};
+use crate::rpc::RateLimitedRpcClient;
+use crate::state::{get_strategies_for_user, read_strategy_state};
+use comfy_table::Table;
+use phoenix::program::MarketHeader;
+use phoenix::quantities::WrapperU64;
+use phoenix::state::Side;
+use phoenix_onchain_mm::{
+    base_lots_to_display, compute_break_even_spread_in_bps, get_inventory_balance,
+    get_spread_in_bps, market_order_book_stats, tick_to_price_usd, PhoenixStrategyState,
+    MARKET_STATS_DEPTH_LEVELS,
+};
+use solana_sdk::pubkey::Pubkey;
+
+const BOOK_DEPTH: usize = 5;
+
+fn book_table(
+    market: &PhoenixMarket<'_>,
+    header: &MarketHeader,
+    side: Side,
+    our_order_sequence_number: Option<u64>,
+) -> Table {
+    let mut levels = market
+        .get_book(side)
+        .iter()
+        .take(BOOK_DEPTH)
+        .map(|(order_id, order)| {
+            vec![
+                format!(
+                    "{:.4}",
+                    tick_to_price_usd(order_id.price_in_ticks.as_u64(), header)
+                ),
+                format!(
+                    "{:.4}",
+                    base_lots_to_display(order.num_base_lots.as_u64(), header)
+                ),
+                if Some(order_id.order_sequence_number) == our_order_sequence_number {
+                    "*".to_string()
+                } else {
+                    "".to_string()
+                },
+            ]
+        })
+        .collect::<Vec<_>>();
+    // Show the asks with the best price at the bottom, closest to the bids
+    if side == Side::Ask {
+        levels.reverse();
+    }
+    let mut table = Table::new();
+    table.set_header(vec!["Price", "Size", "Is Ours"]);
+    for level in levels {
+        table.add_row(level);
+    }
+    table
+}
+
+fn describe_position(
+    header: &MarketHeader,
+    side: Side,
+    our_price_in_ticks: u64,
+    best_price_in_ticks: Option<u64>,
+) -> String {
+    let price = tick_to_price_usd(our_price_in_ticks, header);
+    let name = match side {
+        Side::Bid => "bid",
+        Side::Ask => "ask",
+    };
+    match best_price_in_ticks {
+        Some(best) if best == our_price_in_ticks => {
+            format!("{:.4} (joining the best {})", price, name)
+        }
+        Some(best) => {
+            let is_inside = match side {
+                Side::Bid => our_price_in_ticks > best,
+                Side::Ask => our_price_in_ticks < best,
+            };
+            format!(
+                "{:.4} ({} ticks {} the best other {})",
+                price,
+                best.abs_diff(our_price_in_ticks),
+                if is_inside { "inside" } else { "behind" },
+                name
+            )
+        }
+        None => format!("{:.4} (top of book)", price),
+    }
+}
+
+/// Lists the strategies recorded in the user's registry, on every market.
+pub async fn list_strategies(
+    client: &RateLimitedRpcClient,
+    user: &Pubkey,
+    json: bool,
+) -> anyhow::Result<()> {
+    let mut strategies = vec![];
+    for strategy_key in get_strategies_for_user(client, user).await? {
+        let state = read_strategy_state(&client.get_account_data(&strategy_key).await?)?;
+        strategies.push((strategy_key, state));
+    }
+
+    if json {
+        let strategies_json = strategies
+            .iter()
+            .map(|(strategy_key, state)| {
+                serde_json::json!({
+                    "strategy": strategy_key.to_string(),
+                    "market": state.market.to_string(),
+                    "strategy_id": state.strategy_id,
+                    "has_live_bid": state.has_live_bid(),
+                    "has_live_ask": state.has_live_ask(),
+                })
+            })
+            .collect::<Vec<_>>();
+        println!("{}", serde_json::to_string_pretty(&strategies_json)?);
+        return Ok(());
+    }
+
+    if strategies.is_empty() {
+        println!("No strategies registered for {}", user);
+        return Ok(());
+    }
+    let mut table = Table::new();
+    table.set_header(vec!["Strategy", "Market", "ID", "Live Bid", "Live Ask"]);
+    for (strategy_key, state) in &strategies {
+        table.add_row(vec![
+            strategy_key.to_string(),
+            state.market.to_string(),
+            state.strategy_id.to_string(),
+            state.has_live_bid().to_string(),
+            state.has_live_ask().to_string(),
+        ]);
+    }
+    println!("{}", table);
+    Ok(())
+}
+
+pub async fn status(
+    client: &RateLimitedRpcClient,
+    market_key: &Pubkey,
+    strategy_key: &Pubkey,
+    json: bool,
+) -> anyhow::Result<()> {
+    let strategy: Option<PhoenixStrategyState> = match client.get_account_data(strategy_key).await {
+        Ok(data) => Some(read_strategy_state(&data)?),
+        Err(_) => None,
+    };
+
+    let data = client.get_account_data(market_key).await?;
+    let (header, market) = load_market(&data)?;
+
+    if json {
+        let strategy_json = match &strategy {
+            Some(strategy) => strategy.to_json(header),
+            None => serde_json::Value::Null,
+        };
+        println!("{}", serde_json::to_string_pretty(&strategy_json)?);
+        return Ok(());
+    }
+
+    // Orders from other traders, so our own quotes can be compared against them
+    let trader_index = strategy
+        .and_then(|s| market.get_trader_index(&s.trader))
+        .unwrap_or(u32::MAX) as u64;
+    let stats = market_order_book_stats(market, trader_index);
+    let best_bid = (stats.best_bid_price_ticks != 0).then_some(stats.best_bid_price_ticks);
+    let best_ask = (stats.best_ask_price_ticks != u64::MAX).then_some(stats.best_ask_price_ticks);
+    let live = |sequence_number: u64| (sequence_number != 0).then_some(sequence_number);
+
+    println!("Strategy: {}", strategy_key);
+    match &strategy {
+        Some(strategy) => {
+            println!("{}", strategy.display_with_header(header));
+            for (side, name, sequence_number, price_in_ticks, best) in [
+                (
+                    Side::Bid,
+                    "bid",
+                    strategy.bid_order_sequence_number,
+                    strategy.bid_price_in_ticks,
+                    best_bid,
+                ),
+                (
+                    Side::Ask,
+                    "ask",
+                    strategy.ask_order_sequence_number,
+                    strategy.ask_price_in_ticks,
+                    best_ask,
+                ),
+            ] {
+                if sequence_number != 0 {
+                    println!(
+                        "Our {}: {}",
+                        name,
+                        describe_position(header, side, price_in_ticks, best)
+                    );
+                } else {
+                    println!("Our {}: none", name);
+                }
+            }
+            if strategy.has_live_bid() && strategy.has_live_ask() {
+                // The quotes are placed symmetrically around the fair price, so their midpoint
+                // stands in for it
+                let fair_price_in_ticks =
+                    (strategy.bid_price_in_ticks + strategy.ask_price_in_ticks) / 2;
+                let spread_in_bps = get_spread_in_bps(
+                    strategy.bid_price_in_ticks,
+                    strategy.ask_price_in_ticks,
+                    fair_price_in_ticks,
+                );
+                // Phoenix does not pay makers a rebate
+                let break_even_in_bps =
+                    compute_break_even_spread_in_bps(market.get_taker_fee_bps(), 0);
+                println!(
+                    "Your current spread: {} bps, Break-even: {} bps, Profitability: {} bps",
+                    spread_in_bps,
+                    break_even_in_bps,
+                    spread_in_bps as i64 - break_even_in_bps as i64
+                );
+            }
+            let (free_base_lots, free_quote_lots) = get_inventory_balance(market, &strategy.trader);
+            println!(
+                "Free inventory: {:.4} base, {:.4} quote",
+                base_lots_to_display(free_base_lots, header),
+                quote_lots_to_size(header, free_quote_lots)
+            );
+        }
+        None => println!("Strategy account not found"),
+    }
+
+    match (best_bid, best_ask) {
+        (Some(bid), Some(ask)) => {
+            let spread = tick_to_price_usd(stats.spread_in_ticks, header);
+            let mid = (tick_to_price_usd(bid, header) + tick_to_price_usd(ask, header)) / 2.0;
+            println!(
+                "Spread: {:.4} ({} ticks, {:.2} bps)",
+                spread,
+                stats.spread_in_ticks,
+                spread / mid * 10_000.0
+            );
+        }
+        _ => println!("Spread: book is one-sided"),
+    }
+    println!(
+        "Depth ({} levels): {:.4} bid, {:.4} ask",
+        MARKET_STATS_DEPTH_LEVELS,
+        base_lots_to_display(stats.bid_depth_5_levels, header),
+        base_lots_to_display(stats.ask_depth_5_levels, header)
+    );
+
+    println!("\nAsks");
+    println!(
+        "{}",
+        book_table(
+            market,
+            header,
+            Side::Ask,
+            strategy.and_then(|s| live(s.ask_order_sequence_number))
+        )
+    );
+    println!("Bids");
+    println!(
+        "{}",
+        book_table(
+            market,
+            header,
+            Side::Bid,
+            strategy.and_then(|s| live(s.bid_order_sequence_number))
+        )
+    );
+    Ok(())
+}
@@ -0,0 +1,313 @@
+use crate::display::client_metadata_to_label;
+use crate::rpc::RateLimitedRpcClient;
+use crate::state::read_strategy_state;
+use crossterm::{
+    cursor::MoveTo,
+    execute,
+    terminal::{Clear, ClearType},
+};
+use phoenix_onchain_mm::PhoenixStrategyState;
+use solana_client::rpc_client::GetConfirmedSignaturesForAddress2Config;
+use solana_client::rpc_response::RpcConfirmedTransactionStatusWithSignature;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use std::collections::VecDeque;
+use std::str::FromStr;
+
+const MAX_TUI_SIGNATURES: usize = 10;
+
+pub fn strategy_state_fields(state: &PhoenixStrategyState) -> Vec<(&'static str, String)> {
+    vec![
+        ("trader", state.trader.to_string()),
+        ("market", state.market.to_string()),
+        (
+            "volatility_oracle_pubkey",
+            state.volatility_oracle_pubkey.to_string(),
+        ),
+        ("label", client_metadata_to_label(&state.client_metadata)),
+        ("version", state.version.to_string()),
+        (
+            "bid_order_sequence_number",
+            state.bid_order_sequence_number.to_string(),
+        ),
+        ("bid_price_in_ticks", state.bid_price_in_ticks.to_string()),
+        (
+            "initial_bid_size_in_base_lots",
+            state.initial_bid_size_in_base_lots.to_string(),
+        ),
+        (
+            "ask_order_sequence_number",
+            state.ask_order_sequence_number.to_string(),
+        ),
+        ("ask_price_in_ticks", state.ask_price_in_ticks.to_string()),
+        (
+            "initial_ask_size_in_base_lots",
+            state.initial_ask_size_in_base_lots.to_string(),
+        ),
+        ("last_update_slot", state.last_update_slot.to_string()),
+        (
+            "last_update_unix_timestamp",
+            state.last_update_unix_timestamp.to_string(),
+        ),
+        ("quote_edge_in_bps", state.quote_edge_in_bps.to_string()),
+        (
+            "quote_size_in_quote_atoms",
+            state.quote_size_in_quote_atoms.to_string(),
+        ),
+        (
+            "last_fair_price_in_quote_atoms_per_raw_base_unit",
+            state
+                .last_fair_price_in_quote_atoms_per_raw_base_unit
+                .to_string(),
+        ),
+        ("min_spread_in_bps", state.min_spread_in_bps.to_string()),
+        (
+            "time_since_last_fill_decay_factor_in_bps",
+            state.time_since_last_fill_decay_factor_in_bps.to_string(),
+        ),
+        ("max_edge_in_bps", state.max_edge_in_bps.to_string()),
+        (
+            "last_fill_unix_timestamp",
+            state.last_fill_unix_timestamp.to_string(),
+        ),
+        (
+            "cumulative_bid_fill_in_base_lots",
+            state.cumulative_bid_fill_in_base_lots.to_string(),
+        ),
+        (
+            "cumulative_ask_fill_in_base_lots",
+            state.cumulative_ask_fill_in_base_lots.to_string(),
+        ),
+        (
+            "cumulative_maker_bid_volume_in_base_lots",
+            state.cumulative_maker_bid_volume_in_base_lots.to_string(),
+        ),
+        (
+            "cumulative_maker_ask_volume_in_base_lots",
+            state.cumulative_maker_ask_volume_in_base_lots.to_string(),
+        ),
+        (
+            "cumulative_taker_bid_volume_in_base_lots",
+            state.cumulative_taker_bid_volume_in_base_lots.to_string(),
+        ),
+        (
+            "cumulative_taker_ask_volume_in_base_lots",
+            state.cumulative_taker_ask_volume_in_base_lots.to_string(),
+        ),
+        (
+            "rolling_price_mean_x32",
+            state.rolling_price_mean_x32.to_string(),
+        ),
+        (
+            "rolling_price_variance_x64",
+            state.rolling_price_variance_x64.to_string(),
+        ),
+        ("price_update_count", state.price_update_count.to_string()),
+        (
+            "volatility_multiplier_in_bps",
+            state.volatility_multiplier_in_bps.to_string(),
+        ),
+        ("min_warmup_updates", state.min_warmup_updates.to_string()),
+        (
+            "max_taker_exposure_in_quote_atoms",
+            state.max_taker_exposure_in_quote_atoms.to_string(),
+        ),
+        (
+            "max_slot_age_for_price",
+            state.max_slot_age_for_price.to_string(),
+        ),
+        (
+            "min_deposited_quote_atoms",
+            state.min_deposited_quote_atoms.to_string(),
+        ),
+        (
+            "min_deposited_base_lots",
+            state.min_deposited_base_lots.to_string(),
+        ),
+        (
+            "max_oracle_staleness_in_slots",
+            state.max_oracle_staleness_in_slots.to_string(),
+        ),
+        (
+            "quote_refresh_interval_in_seconds",
+            state.quote_refresh_interval_in_seconds.to_string(),
+        ),
+        (
+            "order_refresh_interval_in_seconds",
+            state.order_refresh_interval_in_seconds.to_string(),
+        ),
+        (
+            "last_order_refresh_unix_timestamp",
+            state.last_order_refresh_unix_timestamp.to_string(),
+        ),
+        ("taker_fee_in_bps", state.taker_fee_in_bps.to_string()),
+        ("maker_rebate_in_bps", state.maker_rebate_in_bps.to_string()),
+        (
+            "min_external_orders_per_side",
+            state.min_external_orders_per_side.to_string(),
+        ),
+        (
+            "cached_tick_size_in_quote_atoms",
+            state.cached_tick_size_in_quote_atoms.to_string(),
+        ),
+        (
+            "cached_base_lot_size",
+            state.cached_base_lot_size.to_string(),
+        ),
+        (
+            "cached_quote_lot_size",
+            state.cached_quote_lot_size.to_string(),
+        ),
+        (
+            "cached_raw_base_units_per_base_unit",
+            state.cached_raw_base_units_per_base_unit.to_string(),
+        ),
+        (
+            "size_decay_factor_in_bps",
+            state.size_decay_factor_in_bps.to_string(),
+        ),
+        (
+            "client_order_id_counter",
+            state.client_order_id_counter.to_string(),
+        ),
+        (
+            "client_order_id_mode",
+            state.client_order_id_mode.to_string(),
+        ),
+        ("sizing_mode", state.sizing_mode.to_string()),
+        (
+            "price_precision_mode",
+            state.price_precision_mode.to_string(),
+        ),
+        (
+            "size_adjustment_curve",
+            state.size_adjustment_curve.to_string(),
+        ),
+        (
+            "size_curve_slope_in_bps",
+            state.size_curve_slope_in_bps.to_string(),
+        ),
+        (
+            "size_curve_scaling_factor",
+            state.size_curve_scaling_factor.to_string(),
+        ),
+        ("post_only", state.post_only.to_string()),
+        (
+            "price_improvement_behavior",
+            state.price_improvement_behavior.to_string(),
+        ),
+        (
+            "use_only_deposited_funds",
+            state.use_only_deposited_funds.to_string(),
+        ),
+    ]
+}
+
+async fn fetch_new_signatures(
+    client: &RateLimitedRpcClient,
+    strategy_key: &Pubkey,
+    until: Option<Signature>,
+) -> anyhow::Result<Vec<RpcConfirmedTransactionStatusWithSignature>> {
+    let mut signatures = client
+        .get_signatures_for_address_with_config(
+            strategy_key,
+            GetConfirmedSignaturesForAddress2Config {
+                before: None,
+                until,
+                limit: None,
+                commitment: Some(client.commitment()),
+            },
+        )
+        .await?;
+    // Signatures are returned newest first
+    signatures.reverse();
+    Ok(signatures)
+}
+
+fn format_signature(sig: &RpcConfirmedTransactionStatusWithSignature) -> String {
+    let status = match &sig.err {
+        Some(e) => format!("Failed ({})", e),
+        None => "Success".to_string(),
+    };
+    format!("{} slot={} status={}", sig.signature, sig.slot, status)
+}
+
+fn draw_tui(
+    strategy_key: &Pubkey,
+    state: &PhoenixStrategyState,
+    signatures: &VecDeque<String>,
+) -> anyhow::Result<()> {
+    let mut stdout = std::io::stdout();
+    execute!(stdout, Clear(ClearType::All), MoveTo(0, 0))?;
+    println!("Strategy: {}\n", strategy_key);
+    for (name, value) in strategy_state_fields(state) {
+        println!("{:<32}{}", name, value);
+    }
+    println!("\nRecent transactions:");
+    for sig in signatures.iter() {
+        println!("  {}", sig);
+    }
+    Ok(())
+}
+
+pub async fn watch(
+    client: &RateLimitedRpcClient,
+    strategy_key: &Pubkey,
+    watch_interval_ms: u64,
+    tui: bool,
+) -> anyhow::Result<()> {
+    let mut previous_state: Option<PhoenixStrategyState> = None;
+    let mut last_signature = fetch_new_signatures(client, strategy_key, None)
+        .await?
+        .last()
+        .map(|s| Signature::from_str(&s.signature))
+        .transpose()?;
+    let mut recent_signatures = VecDeque::with_capacity(MAX_TUI_SIGNATURES);
+
+    if !tui {
+        println!("Watching strategy account: {}", strategy_key);
+    }
+
+    loop {
+        let state = read_strategy_state(&client.get_account_data(strategy_key).await?)?;
+
+        let new_signatures = fetch_new_signatures(client, strategy_key, last_signature).await?;
+        if let Some(sig) = new_signatures.last() {
+            last_signature = Some(Signature::from_str(&sig.signature)?);
+        }
+
+        if tui {
+            for sig in new_signatures.iter() {
+                if recent_signatures.len() == MAX_TUI_SIGNATURES {
+                    recent_signatures.pop_front();
+                }
+                recent_signatures.push_back(format_signature(sig));
+            }
+            draw_tui(strategy_key, &state, &recent_signatures)?;
+        } else {
+            match &previous_state {
+                Some(previous) => {
+                    for ((name, old), (_, new)) in strategy_state_fields(previous)
+                        .into_iter()
+                        .zip(strategy_state_fields(&state))
+                    {
+                        if old != new {
+                            println!("{}: Changed({}, {})", name, old, new);
+                        }
+                    }
+                }
+                None => {
+                    for (name, value) in strategy_state_fields(&state) {
+                        println!("{}: {}", name, value);
+                    }
+                }
+            }
+            for sig in new_signatures.iter() {
+                println!("Transaction: {}", format_signature(sig));
+            }
+        }
+        previous_state = Some(state);
+
+        tokio::time::sleep(std::time::Duration::from_millis(watch_interval_ms)).await;
+    }
+}
@@ -0,0 +1,25 @@
+use crate::client::PhoenixOnchainMM;
+
+/// Withdraws the trader's free funds from the market. Funds locked in open orders are not
+/// withdrawn and the orders keep resting.
+pub async fn withdraw(mm: &PhoenixOnchainMM<'_>) -> anyhow::Result<()> {
+    let txid = mm.withdraw_to_wallet().await?;
+    println!("Withdrawing free funds: {}", txid);
+    Ok(())
+}
+
+/// Withdraws the given amounts of the trader's free funds from the market.
+pub async fn partial_withdraw(
+    mm: &PhoenixOnchainMM<'_>,
+    base_lots_to_withdraw: u64,
+    quote_lots_to_withdraw: u64,
+) -> anyhow::Result<()> {
+    let txid = mm
+        .partial_withdraw(base_lots_to_withdraw, quote_lots_to_withdraw)
+        .await?;
+    println!(
+        "Withdrawing {} base lots and {} quote lots: {}",
+        base_lots_to_withdraw, quote_lots_to_withdraw, txid
+    );
+    Ok(())
+}
@@ -0,0 +1,372 @@
+use phoenix::program::MarketHeader;
+use phoenix::quantities::WrapperU64;
+use phoenix_onchain_mm::{
+    base_lots_to_display, quote_atoms_to_usd, tick_to_price_usd, ClientOrderIdMode,
+    PhoenixStrategyState, PriceImprovementBehavior, PricePrecisionMode, SizeAdjustmentCurve,
+    SizingMode,
+};
+use serde_json::json;
+use solana_sdk::pubkey::Pubkey;
+use std::fmt;
+
+pub fn quote_lots_to_size(header: &MarketHeader, size_in_quote_lots: u64) -> f64 {
+    quote_atoms_to_usd(
+        size_in_quote_lots * header.get_quote_lot_size().as_u64(),
+        header,
+    )
+}
+
+fn price_improvement_name(byte: u8) -> String {
+    match PriceImprovementBehavior::try_from_u8(byte) {
+        Ok(behavior) => format!("{:?}", behavior),
+        Err(_) => format!("Unknown ({})", byte),
+    }
+}
+
+fn client_order_id_mode_name(byte: u8) -> String {
+    match ClientOrderIdMode::try_from_u8(byte) {
+        Ok(mode) => format!("{:?}", mode),
+        Err(_) => format!("Unknown ({})", byte),
+    }
+}
+
+fn sizing_mode_name(byte: u8) -> String {
+    match SizingMode::try_from_u8(byte) {
+        Ok(mode) => format!("{:?}", mode),
+        Err(_) => format!("Unknown ({})", byte),
+    }
+}
+
+fn price_precision_mode_name(byte: u8) -> String {
+    match PricePrecisionMode::try_from_u8(byte) {
+        Ok(mode) => format!("{:?}", mode),
+        Err(_) => format!("Unknown ({})", byte),
+    }
+}
+
+fn size_adjustment_curve_name(state: &PhoenixStrategyState) -> String {
+    match state.size_adjustment_curve() {
+        Ok(SizeAdjustmentCurve::Flat) => "Flat".to_string(),
+        Ok(SizeAdjustmentCurve::Linear(slope_in_bps)) => {
+            format!("Linear (+{} bps per tick)", slope_in_bps)
+        }
+        Ok(SizeAdjustmentCurve::Inverse(scaling_factor)) => {
+            format!("Inverse ({}x at one tick)", scaling_factor)
+        }
+        Err(_) => format!("Unknown ({})", state.size_adjustment_curve),
+    }
+}
+
+/// Decodes the client metadata as a UTF-8 label, ignoring trailing zero bytes and replacing
+/// invalid sequences.
+pub fn client_metadata_to_label(client_metadata: &[u8; 32]) -> String {
+    let len = client_metadata
+        .iter()
+        .rposition(|byte| *byte != 0)
+        .map_or(0, |i| i + 1);
+    String::from_utf8_lossy(&client_metadata[..len]).into_owned()
+}
+
+/// Human-readable view of a strategy, with prices and sizes converted using the market header.
+pub struct PhoenixStrategyStateDisplay {
+    state: PhoenixStrategyState,
+    header: MarketHeader,
+}
+
+pub trait StrategyStateDisplay {
+    fn display_with_header(&self, header: &MarketHeader) -> PhoenixStrategyStateDisplay;
+    fn to_json(&self, header: &MarketHeader) -> serde_json::Value;
+}
+
+impl StrategyStateDisplay for PhoenixStrategyState {
+    fn display_with_header(&self, header: &MarketHeader) -> PhoenixStrategyStateDisplay {
+        PhoenixStrategyStateDisplay {
+            state: *self,
+            header: *header,
+        }
+    }
+
+    fn to_json(&self, header: &MarketHeader) -> serde_json::Value {
+        json!({
+            "trader": self.trader.to_string(),
+            "market": self.market.to_string(),
+            "volatility_oracle_pubkey": self.volatility_oracle_pubkey.to_string(),
+            "strategy_id": self.strategy_id,
+            "label": client_metadata_to_label(&self.client_metadata),
+            "version": self.version.max(1),
+            "bid": {
+                "order_sequence_number": self.bid_order_sequence_number,
+                "price_in_ticks": self.bid_price_in_ticks,
+                "price": tick_to_price_usd(self.bid_price_in_ticks, header),
+                "initial_size_in_base_lots": self.initial_bid_size_in_base_lots,
+                "initial_size": base_lots_to_display(self.initial_bid_size_in_base_lots, header),
+            },
+            "ask": {
+                "order_sequence_number": self.ask_order_sequence_number,
+                "price_in_ticks": self.ask_price_in_ticks,
+                "price": tick_to_price_usd(self.ask_price_in_ticks, header),
+                "initial_size_in_base_lots": self.initial_ask_size_in_base_lots,
+                "initial_size": base_lots_to_display(self.initial_ask_size_in_base_lots, header),
+            },
+            "last_update_slot": self.last_update_slot,
+            "last_update_unix_timestamp": self.last_update_unix_timestamp,
+            "last_fair_price_in_quote_atoms_per_raw_base_unit":
+                self.last_fair_price_in_quote_atoms_per_raw_base_unit,
+            "last_fair_price": quote_atoms_to_usd(self.last_fair_price_in_quote_atoms_per_raw_base_unit, header),
+            "quote_edge_in_bps": self.quote_edge_in_bps,
+            "quote_size_in_quote_atoms": self.quote_size_in_quote_atoms,
+            "quote_size": quote_atoms_to_usd(self.quote_size_in_quote_atoms, header),
+            "min_spread_in_bps": self.min_spread_in_bps,
+            "time_since_last_fill_decay_factor_in_bps": self.time_since_last_fill_decay_factor_in_bps,
+            "max_edge_in_bps": self.max_edge_in_bps,
+            "last_fill_unix_timestamp": self.last_fill_unix_timestamp,
+            "cumulative_bid_fill_in_base_lots": self.cumulative_bid_fill_in_base_lots,
+            "cumulative_bid_fill": base_lots_to_display(self.cumulative_bid_fill_in_base_lots, header),
+            "cumulative_ask_fill_in_base_lots": self.cumulative_ask_fill_in_base_lots,
+            "cumulative_ask_fill": base_lots_to_display(self.cumulative_ask_fill_in_base_lots, header),
+            "cumulative_maker_bid_volume_in_base_lots": self.cumulative_maker_bid_volume_in_base_lots,
+            "cumulative_maker_ask_volume_in_base_lots": self.cumulative_maker_ask_volume_in_base_lots,
+            "cumulative_taker_bid_volume_in_base_lots": self.cumulative_taker_bid_volume_in_base_lots,
+            "cumulative_taker_ask_volume_in_base_lots": self.cumulative_taker_ask_volume_in_base_lots,
+            "rolling_price_mean_x32": self.rolling_price_mean_x32,
+            "rolling_price_variance_x64": self.rolling_price_variance_x64,
+            "price_update_count": self.price_update_count,
+            "volatility_multiplier_in_bps": self.volatility_multiplier_in_bps,
+            "min_warmup_updates": self.min_warmup_updates,
+            "max_taker_exposure_in_quote_atoms": self.max_taker_exposure_in_quote_atoms,
+            "max_slot_age_for_price": self.max_slot_age_for_price,
+            "min_deposited_quote_atoms": self.min_deposited_quote_atoms,
+            "min_deposited_base_lots": self.min_deposited_base_lots,
+            "max_oracle_staleness_in_slots": self.max_oracle_staleness_in_slots,
+            "quote_refresh_interval_in_seconds": self.quote_refresh_interval_in_seconds,
+            "order_refresh_interval_in_seconds": self.order_refresh_interval_in_seconds,
+            "taker_fee_in_bps": self.taker_fee_in_bps,
+            "maker_rebate_in_bps": self.maker_rebate_in_bps,
+            "min_external_orders_per_side": self.min_external_orders_per_side,
+            "last_order_refresh_unix_timestamp": self.last_order_refresh_unix_timestamp,
+            "cached_tick_size_in_quote_atoms": self.cached_tick_size_in_quote_atoms,
+            "cached_base_lot_size": self.cached_base_lot_size,
+            "cached_quote_lot_size": self.cached_quote_lot_size,
+            "cached_raw_base_units_per_base_unit": self.cached_raw_base_units_per_base_unit,
+            "size_decay_factor_in_bps": self.size_decay_factor_in_bps,
+            "size_adjustment_curve": size_adjustment_curve_name(self),
+            "size_curve_slope_in_bps": self.size_curve_slope_in_bps,
+            "size_curve_scaling_factor": self.size_curve_scaling_factor,
+            "post_only": self.post_only,
+            "use_only_deposited_funds": self.use_only_deposited_funds,
+            "price_improvement_behavior": price_improvement_name(self.price_improvement_behavior),
+            "client_order_id_mode": client_order_id_mode_name(self.client_order_id_mode),
+            "sizing_mode": sizing_mode_name(self.sizing_mode),
+            "price_precision_mode": price_precision_mode_name(self.price_precision_mode),
+            "client_order_id_counter": self.client_order_id_counter,
+        })
+    }
+}
+
+impl fmt::Display for PhoenixStrategyStateDisplay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (state, header) = (&self.state, &self.header);
+        writeln!(f, "Trader:                     {}", state.trader)?;
+        writeln!(f, "Market:                     {}", state.market)?;
+        writeln!(f, "Strategy ID:                {}", state.strategy_id)?;
+        writeln!(f, "Layout version:             {}", state.version.max(1))?;
+        writeln!(
+            f,
+            "Label:                      {}",
+            client_metadata_to_label(&state.client_metadata)
+        )?;
+        for (name, sequence_number, price_in_ticks, size_in_base_lots) in [
+            (
+                "Bid",
+                state.bid_order_sequence_number,
+                state.bid_price_in_ticks,
+                state.initial_bid_size_in_base_lots,
+            ),
+            (
+                "Ask",
+                state.ask_order_sequence_number,
+                state.ask_price_in_ticks,
+                state.initial_ask_size_in_base_lots,
+            ),
+        ] {
+            if sequence_number == 0 {
+                writeln!(f, "{}:                        none", name)?;
+            } else {
+                writeln!(
+                    f,
+                    "{}:                        {:.4} x {:.4} ({} ticks, {} lots, seq {})",
+                    name,
+                    tick_to_price_usd(price_in_ticks, header),
+                    base_lots_to_display(size_in_base_lots, header),
+                    price_in_ticks,
+                    size_in_base_lots,
+                    sequence_number
+                )?;
+            }
+        }
+        writeln!(
+            f,
+            "Last update:                slot {} (unix {})",
+            state.last_update_slot, state.last_update_unix_timestamp
+        )?;
+        writeln!(
+            f,
+            "Last fair price:            {:.4}",
+            quote_atoms_to_usd(
+                state.last_fair_price_in_quote_atoms_per_raw_base_unit,
+                header
+            )
+        )?;
+        writeln!(
+            f,
+            "Quote edge:                 {} bps",
+            state.quote_edge_in_bps
+        )?;
+        if state.sizing_mode == SizingMode::PercentOfDepositedFunds.to_u8() {
+            writeln!(
+                f,
+                "Quote size:                 {} bps of the deposited quote balance",
+                state.quote_size_in_quote_atoms
+            )?;
+        } else {
+            writeln!(
+                f,
+                "Quote size:                 {:.4} ({} quote atoms)",
+                quote_atoms_to_usd(state.quote_size_in_quote_atoms, header),
+                state.quote_size_in_quote_atoms
+            )?;
+        }
+        writeln!(
+            f,
+            "Sizing mode:                {}",
+            sizing_mode_name(state.sizing_mode)
+        )?;
+        writeln!(
+            f,
+            "Price precision mode:       {}",
+            price_precision_mode_name(state.price_precision_mode)
+        )?;
+        writeln!(
+            f,
+            "Min spread:                 {} bps",
+            state.min_spread_in_bps
+        )?;
+        writeln!(
+            f,
+            "Edge widening:              {} bps per hour without a fill (max edge {} bps)",
+            state.time_since_last_fill_decay_factor_in_bps, state.max_edge_in_bps
+        )?;
+        writeln!(
+            f,
+            "Last fill:                  unix {}",
+            state.last_fill_unix_timestamp
+        )?;
+        writeln!(
+            f,
+            "Cumulative fills:           bought {:.4}, sold {:.4}",
+            base_lots_to_display(state.cumulative_bid_fill_in_base_lots, header),
+            base_lots_to_display(state.cumulative_ask_fill_in_base_lots, header)
+        )?;
+        writeln!(
+            f,
+            "Maker/taker volume:         bought {:.4}/{:.4}, sold {:.4}/{:.4}",
+            base_lots_to_display(state.cumulative_maker_bid_volume_in_base_lots, header),
+            base_lots_to_display(state.cumulative_taker_bid_volume_in_base_lots, header),
+            base_lots_to_display(state.cumulative_maker_ask_volume_in_base_lots, header),
+            base_lots_to_display(state.cumulative_taker_ask_volume_in_base_lots, header)
+        )?;
+        writeln!(
+            f,
+            "Volatility edge:            {} bps per std dev after {} updates ({} seen)",
+            state.volatility_multiplier_in_bps, state.min_warmup_updates, state.price_update_count
+        )?;
+        writeln!(
+            f,
+            "Max taker exposure:         {:.4} ({} quote atoms)",
+            quote_atoms_to_usd(state.max_taker_exposure_in_quote_atoms, header),
+            state.max_taker_exposure_in_quote_atoms
+        )?;
+        writeln!(
+            f,
+            "Max price age:              {} slots",
+            state.max_slot_age_for_price
+        )?;
+        writeln!(
+            f,
+            "Low balance warning:        below {:.4} quote ({} atoms) or {:.4} base ({} lots)",
+            quote_atoms_to_usd(state.min_deposited_quote_atoms, header),
+            state.min_deposited_quote_atoms,
+            base_lots_to_display(state.min_deposited_base_lots, header),
+            state.min_deposited_base_lots
+        )?;
+        if state.volatility_oracle_pubkey == Pubkey::default() {
+            writeln!(f, "Volatility oracle:          none")?;
+        } else {
+            writeln!(
+                f,
+                "Volatility oracle:          {} (max age {} slots, refresh interval {}s)",
+                state.volatility_oracle_pubkey,
+                state.max_oracle_staleness_in_slots,
+                state.quote_refresh_interval_in_seconds
+            )?;
+        }
+        writeln!(
+            f,
+            "Fees:                       {} bps taker, {} bps maker rebate",
+            state.taker_fee_in_bps, state.maker_rebate_in_bps
+        )?;
+        if state.min_external_orders_per_side == 0 {
+            writeln!(f, "Liquidity check:            disabled")?;
+        } else {
+            writeln!(
+                f,
+                "Liquidity check:            at least {} external orders per side",
+                state.min_external_orders_per_side
+            )?;
+        }
+        if state.order_refresh_interval_in_seconds == 0 {
+            writeln!(f, "Order refresh:              disabled")?;
+        } else {
+            writeln!(
+                f,
+                "Order refresh:              every {}s, last at {}",
+                state.order_refresh_interval_in_seconds, state.last_order_refresh_unix_timestamp
+            )?;
+        }
+        writeln!(
+            f,
+            "Market params at init:      tick size {} quote atoms, lot sizes {} base atoms and {} quote atoms, {} raw base units per base unit",
+            state.cached_tick_size_in_quote_atoms,
+            state.cached_base_lot_size,
+            state.cached_quote_lot_size,
+            state.cached_raw_base_units_per_base_unit
+        )?;
+        writeln!(
+            f,
+            "Size decay factor:          {} bps",
+            state.size_decay_factor_in_bps
+        )?;
+        writeln!(
+            f,
+            "Size curve:                 {}",
+            size_adjustment_curve_name(state)
+        )?;
+        writeln!(f, "Post only:                  {}", state.post_only)?;
+        writeln!(
+            f,
+            "Use only deposited funds:   {}",
+            state.use_only_deposited_funds
+        )?;
+        writeln!(
+            f,
+            "Price improvement:          {}",
+            price_improvement_name(state.price_improvement_behavior)
+        )?;
+        write!(
+            f,
+            "Client order ID mode:       {} (counter {})",
+            client_order_id_mode_name(state.client_order_id_mode),
+            state.client_order_id_counter
+        )
+    }
+}
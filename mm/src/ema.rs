@@ -0,0 +1,44 @@
+/// Exponential moving average of the fair price, seeded with the first observed price.
+#[derive(Debug, Clone, Copy)]
+pub struct Ema {
+    alpha: f64,
+    value: Option<f64>,
+    iterations: u64,
+    warmup_iterations: u64,
+}
+
+impl Ema {
+    /// `alpha` is the weight of the newest price, so 1.0 disables smoothing.
+    pub fn new(alpha: f64, warmup_iterations: u64) -> anyhow::Result<Self> {
+        if !(alpha > 0.0 && alpha <= 1.0) {
+            return Err(anyhow::anyhow!("EMA alpha must be in (0.0, 1.0]"));
+        }
+        Ok(Self {
+            alpha,
+            value: None,
+            iterations: 0,
+            warmup_iterations,
+        })
+    }
+
+    /// Adds a price observation and returns the smoothed price.
+    pub fn update(&mut self, price: f64) -> f64 {
+        let value = match self.value {
+            Some(ema) => self.alpha * price + (1.0 - self.alpha) * ema,
+            None => price,
+        };
+        self.value = Some(value);
+        self.iterations += 1;
+        value
+    }
+
+    /// Returns the current average, or None before the first price.
+    pub fn value(&self) -> Option<f64> {
+        self.value
+    }
+
+    /// Returns true once more than `warmup_iterations` prices have been observed.
+    pub fn is_warm(&self) -> bool {
+        self.iterations > self.warmup_iterations
+    }
+}
@@ -0,0 +1,99 @@
+use crate::market::PhoenixMarket;
+use phoenix::program::MarketHeader;
+use phoenix::quantities::WrapperU64;
+use phoenix::state::markets::FIFOOrderId;
+use phoenix::state::Side;
+use phoenix_onchain_mm::{
+    calculate_theoretical_pnl, get_new_fill_in_base_lots, PhoenixStrategyState,
+};
+
+/// Fills seen on the strategy's orders, accumulated across iterations without sending transactions.
+#[derive(Debug, Default)]
+pub struct FillMonitor {
+    last_bid_total_in_base_lots: Option<u64>,
+    last_ask_total_in_base_lots: Option<u64>,
+    pub bid_fill_in_base_lots: u64,
+    pub ask_fill_in_base_lots: u64,
+    bid_notional_in_ticks: u128,
+    ask_notional_in_ticks: u128,
+}
+
+impl FillMonitor {
+    /// Returns the base lots filled on each side since the previous call. The first call only
+    /// records a baseline.
+    pub fn observe(&mut self, state: &PhoenixStrategyState, market: &PhoenixMarket) -> (u64, u64) {
+        // Fills on the resting orders that the strategy has not recorded yet
+        let pending = |side: Side, price_in_ticks: u64, sequence_number: u64, initial, tracked| {
+            if sequence_number == 0 {
+                return 0;
+            }
+            let order_id = FIFOOrderId::new_from_untyped(price_in_ticks, sequence_number);
+            let remaining = market
+                .get_book(side)
+                .get(&order_id)
+                .map(|order| order.num_base_lots.as_u64());
+            get_new_fill_in_base_lots(initial, remaining, tracked)
+        };
+        let bid_total = state.cumulative_bid_fill_in_base_lots
+            + pending(
+                Side::Bid,
+                state.bid_price_in_ticks,
+                state.bid_order_sequence_number,
+                state.initial_bid_size_in_base_lots,
+                state.tracked_bid_fill_in_base_lots,
+            );
+        let ask_total = state.cumulative_ask_fill_in_base_lots
+            + pending(
+                Side::Ask,
+                state.ask_price_in_ticks,
+                state.ask_order_sequence_number,
+                state.initial_ask_size_in_base_lots,
+                state.tracked_ask_fill_in_base_lots,
+            );
+
+        let bid_fill =
+            bid_total.saturating_sub(self.last_bid_total_in_base_lots.unwrap_or(bid_total));
+        let ask_fill =
+            ask_total.saturating_sub(self.last_ask_total_in_base_lots.unwrap_or(ask_total));
+        self.last_bid_total_in_base_lots = Some(bid_total);
+        self.last_ask_total_in_base_lots = Some(ask_total);
+
+        // Fills are attributed to the price of the current quote on each side
+        self.bid_fill_in_base_lots += bid_fill;
+        self.ask_fill_in_base_lots += ask_fill;
+        self.bid_notional_in_ticks += bid_fill as u128 * state.bid_price_in_ticks as u128;
+        self.ask_notional_in_ticks += ask_fill as u128 * state.ask_price_in_ticks as u128;
+        (bid_fill, ask_fill)
+    }
+
+    /// Total base lots filled on each side according to the strategy account
+    pub fn strategy_totals_in_base_lots(&self) -> (u64, u64) {
+        (
+            self.last_bid_total_in_base_lots.unwrap_or(0),
+            self.last_ask_total_in_base_lots.unwrap_or(0),
+        )
+    }
+
+    /// PnL in quote atoms of the fills seen so far, at their average prices
+    pub fn estimated_pnl_in_quote_atoms(
+        &self,
+        header: &MarketHeader,
+        market: &PhoenixMarket,
+    ) -> i64 {
+        let average_price = |notional: u128, size: u64| {
+            if size == 0 {
+                0
+            } else {
+                (notional / size as u128) as u64
+            }
+        };
+        calculate_theoretical_pnl(
+            self.bid_fill_in_base_lots,
+            self.ask_fill_in_base_lots,
+            average_price(self.bid_notional_in_ticks, self.bid_fill_in_base_lots),
+            average_price(self.ask_notional_in_ticks, self.ask_fill_in_base_lots),
+            header.get_tick_size_in_quote_atoms_per_base_unit().as_u64(),
+            market.get_base_lots_per_base_unit().as_u64(),
+        )
+    }
+}
@@ -0,0 +1,50 @@
+use age::{Decryptor, Encryptor};
+use anyhow::anyhow;
+use secrecy::Secret;
+use solana_sdk::signature::{read_keypair, write_keypair};
+use solana_sdk::signer::keypair::Keypair;
+use std::io::{Read, Write};
+
+/// Encrypts the keypair with a passphrase and writes it to `path` in the age format.
+/// The plaintext is the same JSON byte array used by `solana-keygen`.
+pub fn encrypt_keypair_to_file(
+    keypair: &Keypair,
+    path: &str,
+    password: &str,
+) -> anyhow::Result<()> {
+    let mut plaintext = vec![];
+    write_keypair(keypair, &mut plaintext).map_err(|e| anyhow!(e.to_string()))?;
+
+    let mut encrypted = vec![];
+    let mut writer = Encryptor::with_user_passphrase(Secret::new(password.to_owned()))
+        .wrap_output(&mut encrypted)?;
+    writer.write_all(&plaintext)?;
+    writer.finish()?;
+
+    std::fs::write(&*shellexpand::tilde(path), encrypted)?;
+    Ok(())
+}
+
+pub fn load_keypair_from_encrypted_file(path: &str, password: &str) -> anyhow::Result<Keypair> {
+    let encrypted = std::fs::read(&*shellexpand::tilde(path))?;
+    let decryptor = match Decryptor::new(&encrypted[..])? {
+        Decryptor::Passphrase(d) => d,
+        Decryptor::Recipients(_) => {
+            return Err(anyhow!("Keypair file is not encrypted with a passphrase"))
+        }
+    };
+
+    let mut plaintext = vec![];
+    decryptor
+        .decrypt(&Secret::new(password.to_owned()), None)?
+        .read_to_end(&mut plaintext)?;
+    read_keypair(&mut &plaintext[..]).map_err(|e| anyhow!(e.to_string()))
+}
+
+/// Returns the password passed on the command line, or prompts for it if none was given.
+pub fn get_keypair_password(password: Option<String>) -> anyhow::Result<String> {
+    match password {
+        Some(password) => Ok(password),
+        None => Ok(rpassword::prompt_password("Keypair password: ")?),
+    }
+}
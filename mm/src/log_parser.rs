@@ -0,0 +1,85 @@
+use phoenix::state::Side;
+use regex::Regex;
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MarketEventType {
+    Placed,
+    Cancelled,
+    Filled,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MarketEvent {
+    pub side: Side,
+    pub price_in_ticks: u64,
+    /// Not known for cancelled or filled orders
+    pub size_in_base_lots: Option<u64>,
+    pub event_type: MarketEventType,
+}
+
+const ORDER_ID_PATTERN: &str =
+    r"FIFOOrderId \{ price_in_ticks: Ticks \{ inner: (\d+) \}, order_sequence_number: (\d+) \}";
+
+/// Extracts order events from the `msg!` output of `update_quotes`.
+/// Placed order sizes are taken from the preceding "Our market" line.
+pub fn parse_phoenix_logs(logs: &[String]) -> Vec<MarketEvent> {
+    let our_market = Regex::new(r"Our market: (\d+) (\d+) @ (\d+) (\d+)").unwrap();
+    let placed = Regex::new(&format!(r"Placed (Bid|Ask) Order: {}", ORDER_ID_PATTERN)).unwrap();
+    let cancelled = Regex::new(&format!(
+        r"Found partially filled resting order: {}",
+        ORDER_ID_PATTERN
+    ))
+    .unwrap();
+    let filled = Regex::new(&format!(
+        r"Failed to find resting order: {}",
+        ORDER_ID_PATTERN
+    ))
+    .unwrap();
+
+    let mut bid_size_in_base_lots = None;
+    let mut ask_size_in_base_lots = None;
+    let mut events = vec![];
+    for log in logs {
+        if let Some(c) = our_market.captures(log) {
+            bid_size_in_base_lots = c[1].parse().ok();
+            ask_size_in_base_lots = c[4].parse().ok();
+        } else if let Some(c) = placed.captures(log) {
+            let side = if &c[1] == "Bid" { Side::Bid } else { Side::Ask };
+            let Ok(price_in_ticks) = c[2].parse() else {
+                continue;
+            };
+            events.push(MarketEvent {
+                side,
+                price_in_ticks,
+                size_in_base_lots: match side {
+                    Side::Bid => bid_size_in_base_lots,
+                    Side::Ask => ask_size_in_base_lots,
+                },
+                event_type: MarketEventType::Placed,
+            });
+        } else if let Some((c, event_type)) = cancelled
+            .captures(log)
+            .map(|c| (c, MarketEventType::Cancelled))
+            .or_else(|| filled.captures(log).map(|c| (c, MarketEventType::Filled)))
+        {
+            let (Ok(price_in_ticks), Ok(order_sequence_number)) =
+                (c[1].parse::<u64>(), c[2].parse::<u64>())
+            else {
+                continue;
+            };
+            // A zero sequence number means there was no resting order
+            if order_sequence_number == 0 {
+                continue;
+            }
+            events.push(MarketEvent {
+                side: Side::from_order_sequence_number(order_sequence_number),
+                price_in_ticks,
+                size_in_base_lots: None,
+                event_type,
+            });
+        }
+    }
+    events
+}
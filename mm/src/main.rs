@@ -1,36 +1,46 @@
-use anchor_lang::InstructionData;
-use anchor_lang::ToAccountMetas;
+// The strategy state is rendered with a single json! call, which outgrows the default limit
+#![recursion_limit = "256"]
+
+mod analytics;
+mod arb_detection;
+mod backtest;
+mod cache;
+mod cancel_guard;
+mod client;
+mod commands;
+mod display;
+mod ema;
+mod fill_monitor;
+mod keypair;
+mod log_parser;
+mod market;
+mod monitor;
+mod nonce;
+mod params_file;
+mod price_feed;
+mod risk;
+mod rpc;
+mod session;
+mod state;
+mod tui;
+mod utils;
+
 use anyhow::anyhow;
-use clap::Parser;
-use phoenix::program::get_seat_address;
-use phoenix::program::get_vault_address;
-use phoenix::program::MarketHeader;
-use phoenix_onchain_mm::OrderParams;
-use phoenix_onchain_mm::PriceImprovementBehavior;
-use phoenix_onchain_mm::StrategyParams;
+use clap::{Parser, Subcommand};
+use client::PhoenixOnchainMM;
+use commands::export::ExportFormat;
+use commands::init::StrategyArgs;
+use commands::run::RunArgs;
+use phoenix::state::Side;
+use phoenix_onchain_mm::{get_strategy_address_with_id, RebalanceParams};
+use rpc::RateLimitedRpcClient;
 use solana_cli_config::{Config, ConfigInput, CONFIG_FILE};
 use solana_client::nonblocking::rpc_client::RpcClient;
-use solana_client::rpc_config::RpcSendTransactionConfig;
-use solana_sdk::instruction::Instruction;
 use solana_sdk::pubkey::Pubkey;
-use solana_sdk::signature::read_keypair_file;
-use solana_sdk::signer::keypair::Keypair;
 use solana_sdk::signer::Signer;
-use spl_associated_token_account::get_associated_token_address;
-use std::str::FromStr;
-
-pub fn get_network(network_str: &str) -> &str {
-    match network_str {
-        "devnet" | "dev" | "d" => "https://api.devnet.solana.com",
-        "mainnet" | "main" | "m" | "mainnet-beta" => "https://api.mainnet-beta.solana.com",
-        "localnet" | "localhost" | "l" | "local" => "http://localhost:8899",
-        _ => network_str,
-    }
-}
-
-pub fn get_payer_keypair_from_path(path: &str) -> anyhow::Result<Keypair> {
-    read_keypair_file(&*shellexpand::tilde(path)).map_err(|e| anyhow!(e.to_string()))
-}
+use std::sync::Arc;
+use std::time::Duration;
+use utils::{get_network, get_payer_keypair_from_path};
 
 #[derive(Parser, Debug)]
 #[clap(version, about)]
@@ -38,28 +48,172 @@ struct Arguments {
     /// Optionally include your keypair path. Defaults to your Solana CLI config file.
     #[clap(global = true, short, long)]
     keypair_path: Option<String>,
+    /// Decrypt the keypair file with this password. If passed without a value, the password is read from stdin.
+    #[clap(global = true, long, num_args = 0..=1, require_equals = true)]
+    keypair_password: Option<Option<String>>,
     /// Optionally include your RPC endpoint. Use "local", "dev", "main" for default endpoints. Defaults to your Solana CLI config file.
     #[clap(global = true, short, long)]
     url: Option<String>,
     /// Optionally include a commitment level. Defaults to your Solana CLI config file.
     #[clap(global = true, short, long)]
     commitment: Option<String>,
-    /// Market pubkey to provide on
-    market: Pubkey,
-    // The ticker is used to pull the price from the Coinbase API, and therefore should conform to the Coinbase ticker format.
-    /// Note that for all USDC quoted markets, the price feed should use "USD" instead of "USDC".
-    #[clap(short, long, default_value = "SOL-USD")]
-    ticker: String,
-    #[clap(long, default_value = "2000")]
-    quote_refresh_frequency_in_ms: u64,
-    #[clap(long, default_value = "3")]
-    quote_edge_in_bps: u64,
-    #[clap(long, default_value = "100000000")]
-    quote_size: u64,
-    #[clap(long, default_value = "ignore")]
-    price_improvement_behavior: String,
-    #[clap(long, default_value = "true")]
-    post_only: bool,
+    /// Identifies which of the user's strategies on this market to use
+    #[clap(global = true, long, default_value = "0")]
+    strategy_id: u8,
+    /// Maximum number of RPC requests per second. Unlimited by default.
+    #[clap(global = true, long)]
+    max_rpc_requests_per_second: Option<usize>,
+    /// Maximum number of transactions sent per minute. Unlimited by default.
+    #[clap(global = true, long)]
+    max_tx_per_minute: Option<usize>,
+    /// Reuse a fetched blockhash for this many milliseconds. Blockhashes expire after about a minute.
+    #[clap(global = true, long, default_value = "30000")]
+    blockhash_cache_ttl_ms: u64,
+    /// Sign transactions with the durable nonce stored in this account instead of a recent
+    /// blockhash, so that they stay valid until they land. Defaults to the account created by
+    /// --auto-create-nonce-account.
+    #[clap(global = true, long, requires = "nonce_authority")]
+    nonce_account: Option<Pubkey>,
+    /// Keypair path of the nonce account's authority
+    #[clap(global = true, long)]
+    nonce_authority: Option<String>,
+    /// Create and fund a nonce account for the payer if it does not exist yet
+    #[clap(global = true, long, requires = "nonce_authority")]
+    auto_create_nonce_account: bool,
+    /// Market pubkey to provide on. Required by every command except `status --all` and
+    /// `encrypt-keypair`.
+    market: Option<Pubkey>,
+    #[clap(subcommand)]
+    command: Commands,
+}
+
+#[derive(Subcommand, Debug)]
+enum Commands {
+    #[clap(flatten)]
+    Market(Box<MarketCommands>),
+    /// Show the strategy's quotes alongside the top of the order book
+    Status {
+        /// Print the strategy state as JSON instead of tables
+        #[clap(long)]
+        json: bool,
+        /// List every strategy in the payer's strategy registry instead of the one on the market
+        #[clap(long)]
+        all: bool,
+    },
+    /// Write an encrypted copy of the keypair that can be loaded with --keypair-password
+    EncryptKeypair {
+        /// Path of the encrypted keypair file to create
+        output_path: String,
+    },
+}
+
+// Commands that act on the payer's strategy on the market
+#[derive(Subcommand, Debug)]
+enum MarketCommands {
+    /// Request a seat on the market and create the strategy account. An existing strategy is
+    /// overwritten with the new parameters if it has no resting orders.
+    Init {
+        #[clap(flatten)]
+        strategy: StrategyArgs,
+        /// Do not check for or request a seat, e.g. because it was set up separately
+        #[clap(long)]
+        skip_maker_setup: bool,
+    },
+    /// Update the strategy parameters and adopt the market's current tick size, e.g. after a market migration
+    Reinitialize(StrategyArgs),
+    /// Create the strategy if needed and continuously update its quotes
+    Run(Box<RunArgs>),
+    /// Monitor the strategy account and print changes without sending any transactions
+    Watch {
+        #[clap(long, default_value = "1000")]
+        watch_interval_ms: u64,
+        /// Render the strategy state in a full-screen terminal view instead of printing diffs
+        #[clap(long)]
+        tui: bool,
+    },
+    /// Reconstruct the strategy's PnL from its transaction history
+    Pnl {
+        /// Only include transactions at or after this slot
+        #[clap(long)]
+        from_slot: Option<u64>,
+    },
+    /// Export order placements, cancellations and fills parsed from the strategy's transaction logs
+    Export {
+        #[clap(long, default_value = "fills.csv")]
+        output_file: String,
+        /// Only include transactions at or after this slot
+        #[clap(long)]
+        from_slot: Option<u64>,
+        /// Only include transactions at or before this slot
+        #[clap(long)]
+        to_slot: Option<u64>,
+        #[clap(long, value_enum, default_value = "csv")]
+        format: ExportFormat,
+    },
+    /// Cancel the strategy's resting orders. Does not require token accounts.
+    Cancel,
+    /// Cancel only the strategy's resting bid. The next quote update places it again.
+    CancelBid,
+    /// Cancel only the strategy's resting ask. The next quote update places it again.
+    CancelAsk,
+    /// Send a transaction that emits the full strategy state as an event and print it
+    Snapshot,
+    /// Zero the strategy's cumulative fills, e.g. at the start of a trading day. The params and resting orders
+    /// are kept.
+    ResetSession,
+    /// Buy or sell base tokens in the seat with a taker order until it holds the target amount
+    Rebalance {
+        /// Desired base lots held in the seat
+        #[clap(long, allow_hyphen_values = true)]
+        target_base_lots: i64,
+        /// How many ticks past the best price the order may fill
+        #[clap(long, default_value = "10")]
+        max_slippage_in_ticks: u64,
+    },
+    /// Cancel the strategy's resting orders and withdraw all funds from the market
+    Close,
+    /// Withdraw all free funds from the market to the wallet's token accounts. Open orders are not
+    /// affected and the funds locked in them stay in the market.
+    Withdraw,
+    /// Withdraw part of the free funds from the market to the wallet's token accounts. Open orders
+    /// are not affected.
+    PartialWithdraw {
+        #[clap(long, default_value = "0")]
+        base_lots: u64,
+        #[clap(long, default_value = "0")]
+        quote_lots: u64,
+    },
+    /// Migrate the strategy account to the latest layout version
+    Migrate,
+    /// Simulate the strategy on historical prices from a CSV file with `timestamp,bid,ask,fair_price`
+    /// columns, where bid and ask are the best prices in ticks. Does not send any transactions.
+    Backtest {
+        input_file: String,
+        #[clap(flatten)]
+        strategy: StrategyArgs,
+    },
+    /// Show the quotes the strategy would place over a range of fair prices on the market's current
+    /// tick size. Does not send any transactions.
+    PriceLadder {
+        /// Lowest fair price, in quote units per base unit
+        #[clap(long)]
+        from_price: f64,
+        /// Highest fair price, in quote units per base unit
+        #[clap(long)]
+        to_price: f64,
+        #[clap(long)]
+        price_step: f64,
+        /// Sample best bid from other traders to apply the price improvement behavior against.
+        /// Defaults to no bids.
+        #[clap(long)]
+        best_bid: Option<f64>,
+        /// Sample best ask from other traders to apply the price improvement behavior against.
+        /// Defaults to no asks.
+        #[clap(long)]
+        best_ask: Option<f64>,
+        #[clap(flatten)]
+        strategy: StrategyArgs,
+    },
 }
 
 #[derive(Debug, Clone, Copy, Default)]
@@ -72,6 +226,7 @@ pub struct FaucetMetadata {
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
     let cli = Arguments::parse();
     let config = match CONFIG_FILE.as_ref() {
         Some(config_file) => Config::load(config_file).unwrap_or_else(|_| {
@@ -82,160 +237,193 @@ async fn main() -> anyhow::Result<()> {
     };
     let commitment =
         ConfigInput::compute_commitment_config("", &cli.commitment.unwrap_or(config.commitment)).1;
-    let payer = get_payer_keypair_from_path(&cli.keypair_path.unwrap_or(config.keypair_path))?;
+    let keypair_path = cli.keypair_path.unwrap_or(config.keypair_path);
+    let payer = match cli.keypair_password {
+        Some(password) => keypair::load_keypair_from_encrypted_file(
+            &keypair_path,
+            &keypair::get_keypair_password(password)?,
+        )?,
+        None => get_payer_keypair_from_path(&keypair_path)?,
+    };
     let network_url = &get_network(&cli.url.unwrap_or(config.json_rpc_url)).to_string();
-    let client = RpcClient::new_with_commitment(network_url.to_string(), commitment);
-
-    let sdk = phoenix_sdk::sdk_client::SDKClient::new(&payer, network_url).await?;
+    let client = Arc::new(RateLimitedRpcClient::new(
+        RpcClient::new_with_commitment(network_url.to_string(), commitment),
+        cli.max_rpc_requests_per_second,
+        cli.max_tx_per_minute,
+    ));
 
     let Arguments {
         market,
-        ticker,
-        quote_edge_in_bps,
-        quote_size,
-        quote_refresh_frequency_in_ms,
-        price_improvement_behavior,
-        post_only,
+        strategy_id,
+        blockhash_cache_ttl_ms,
+        nonce_account,
+        nonce_authority,
+        auto_create_nonce_account,
+        command,
         ..
     } = cli;
 
-    let maker_setup_instructions = sdk.get_maker_setup_instructions_for_market(&market).await?;
-    sdk.client
-        .sign_send_instructions(maker_setup_instructions, vec![])
-        .await
-        .unwrap();
-
-    let strategy_key = Pubkey::find_program_address(
-        &[b"phoenix", payer.pubkey().as_ref(), market.as_ref()],
-        &phoenix_onchain_mm::id(),
-    )
-    .0;
-
-    let mut create = false;
-    match client.get_account(&strategy_key).await {
-        Ok(acc) => {
-            if acc.data.is_empty() {
-                create = true;
-            }
+    let require_market =
+        || market.ok_or_else(|| anyhow!("The market pubkey is required for this command"));
+    let command = match command {
+        Commands::Market(command) => *command,
+        Commands::Status { json, all: true } => {
+            return commands::status::list_strategies(&client, &payer.pubkey(), json).await;
         }
-        Err(_) => {
-            create = true;
+        Commands::Status { json, all: false } => {
+            let market = require_market()?;
+            let strategy_key =
+                get_strategy_address_with_id(&payer.pubkey(), &market, strategy_id).0;
+            return commands::status::status(&client, &market, &strategy_key, json).await;
+        }
+        Commands::EncryptKeypair { output_path } => {
+            return commands::encrypt_keypair::encrypt_keypair(&payer, &output_path);
         }
-    }
-
-    let price_improvement = match price_improvement_behavior.as_str() {
-        "Join" | "join" => PriceImprovementBehavior::Join,
-        "Dime" | "dime" => PriceImprovementBehavior::Dime,
-        "Ignore" | "ignore" => PriceImprovementBehavior::Ignore,
-        _ => PriceImprovementBehavior::Join,
     };
+    let market = require_market()?;
 
-    let params = StrategyParams {
-        quote_edge_in_bps: Some(quote_edge_in_bps),
-        quote_size_in_quote_atoms: Some(quote_size),
-        price_improvement_behavior: Some(price_improvement),
-        post_only: Some(post_only),
+    let nonce_authority = nonce_authority
+        .map(|path| get_payer_keypair_from_path(&path))
+        .transpose()?;
+    let durable_nonce = match &nonce_authority {
+        Some(authority) => {
+            if auto_create_nonce_account {
+                if let Some(txid) =
+                    nonce::create_nonce_account_if_missing(&client, &payer, &authority.pubkey())
+                        .await?
+                {
+                    println!("Created nonce account: {}", txid);
+                }
+            }
+            nonce_account
+                .or(auto_create_nonce_account
+                    .then(|| nonce::get_nonce_account_address(&payer.pubkey())))
+                .map(|nonce_account| nonce::DurableNonce {
+                    nonce_account,
+                    authority,
+                })
+        }
+        None => None,
     };
-    if create {
-        let initialize_data = phoenix_onchain_mm::instruction::Initialize { params };
-        let initialize_accounts = phoenix_onchain_mm::accounts::Initialize {
-            phoenix_strategy: strategy_key,
-            market,
-            user: payer.pubkey(),
-            system_program: solana_sdk::system_program::id(),
-        };
 
-        let ix = Instruction {
-            program_id: phoenix_onchain_mm::id(),
-            accounts: initialize_accounts.to_account_metas(None),
-            data: initialize_data.data(),
-        };
-
-        let transaction = solana_sdk::transaction::Transaction::new_signed_with_payer(
-            &[ix],
-            Some(&payer.pubkey()),
-            &[&payer],
-            client.get_latest_blockhash().await?,
-        );
-        let txid = client.send_and_confirm_transaction(&transaction).await?;
-        println!("Creating strategy account: {}", txid);
+    let mut mm = PhoenixOnchainMM::new(&payer, market, client.clone())
+        .with_strategy_id(strategy_id)
+        .with_blockhash_cache_ttl(Duration::from_millis(blockhash_cache_ttl_ms));
+    if let Some(durable_nonce) = durable_nonce {
+        mm = mm.with_durable_nonce(durable_nonce);
     }
+    let strategy_key = mm.strategy_key();
 
-    let data = client.get_account_data(&market).await?;
-    let header =
-        bytemuck::try_from_bytes::<MarketHeader>(&data[..std::mem::size_of::<MarketHeader>()])
-            .map_err(|_| anyhow::Error::msg("Failed to parse Phoenix market header"))?;
-
-    println!("Quote Params: {:#?}", params);
-
-    loop {
-        let fair_price = {
-            let response = reqwest::get(format!(
-                "https://api.coinbase.com/v2/prices/{}/spot",
-                ticker
-            ))
-            .await?
-            .json::<serde_json::Value>()
-            .await?;
-
-            f64::from_str(response["data"]["amount"].as_str().unwrap())?
-        };
-
-        println!("Fair price: {}", fair_price);
-
-        let args = phoenix_onchain_mm::instruction::UpdateQuotes {
-            params: OrderParams {
-                fair_price_in_quote_atoms_per_raw_base_unit: (fair_price * 1e6) as u64,
-                strategy_params: params,
-            },
-        };
-
-        let accounts = phoenix_onchain_mm::accounts::UpdateQuotes {
-            phoenix_strategy: strategy_key,
-            market,
-            user: payer.pubkey(),
-            phoenix_program: phoenix::id(),
-            log_authority: phoenix::phoenix_log_authority::id(),
-            seat: get_seat_address(&market, &payer.pubkey()).0,
-            quote_account: get_associated_token_address(
-                &payer.pubkey(),
-                &header.quote_params.mint_key,
-            ),
-            base_account: get_associated_token_address(
-                &payer.pubkey(),
-                &header.base_params.mint_key,
-            ),
-            quote_vault: get_vault_address(&market, &header.quote_params.mint_key).0,
-            base_vault: get_vault_address(&market, &header.base_params.mint_key).0,
-            token_program: spl_token::id(),
-        };
-
-        let ix = Instruction {
-            program_id: phoenix_onchain_mm::id(),
-            accounts: accounts.to_account_metas(None),
-            data: args.data(),
-        };
-
-        let transaction = solana_sdk::transaction::Transaction::new_signed_with_payer(
-            &[ix],
-            Some(&payer.pubkey()),
-            &[&payer],
-            client.get_latest_blockhash().await?,
-        );
-        match client
-            .send_and_confirm_transaction(&transaction)
+    match command {
+        MarketCommands::Init {
+            strategy,
+            skip_maker_setup,
+        } => {
+            let sdk = phoenix_sdk::sdk_client::SDKClient::new(&payer, network_url).await?;
+            let params = strategy.resolve_strategy_params(&client, &market).await?;
+            commands::init::init(&sdk, &mm, params, skip_maker_setup).await
+        }
+        MarketCommands::Reinitialize(strategy_args) => {
+            let params = strategy_args
+                .resolve_strategy_params(&client, &market)
+                .await?;
+            let txid = mm.reinitialize(params).await?;
+            println!("Reinitializing strategy: {}", txid);
+            Ok(())
+        }
+        MarketCommands::Run(run_args) => {
+            let sdk = phoenix_sdk::sdk_client::SDKClient::new(&payer, network_url).await?;
+            commands::run::run(&sdk, &mm, *run_args).await
+        }
+        MarketCommands::Watch {
+            watch_interval_ms,
+            tui,
+        } => commands::watch::watch(&client, &strategy_key, watch_interval_ms, tui).await,
+        MarketCommands::Export {
+            output_file,
+            from_slot,
+            to_slot,
+            format,
+        } => {
+            let records =
+                commands::export::get_export_records(&client, &strategy_key, from_slot, to_slot)
+                    .await?;
+            commands::export::write_export_records(&records, &output_file, format)?;
+            println!("Exported {} events to {}", records.len(), output_file);
+            Ok(())
+        }
+        MarketCommands::Cancel => commands::cancel::cancel(&mm).await,
+        MarketCommands::CancelBid => commands::cancel::cancel_side(&mm, Side::Bid).await,
+        MarketCommands::CancelAsk => commands::cancel::cancel_side(&mm, Side::Ask).await,
+        MarketCommands::Snapshot => commands::snapshot::snapshot(&mm).await,
+        MarketCommands::ResetSession => commands::reset_session::reset_session(&mm).await,
+        MarketCommands::Rebalance {
+            target_base_lots,
+            max_slippage_in_ticks,
+        } => {
+            commands::rebalance::rebalance(
+                &mm,
+                RebalanceParams {
+                    target_base_lots,
+                    max_slippage_in_ticks,
+                },
+            )
             .await
-            .and_then(|sig| {
-                println!("Updating quotes: {}", sig);
-                Ok(())
-            }) {
-            Ok(_) => {}
-            Err(e) => println!("Failed to update quotes: {}", e),
         }
-
-        tokio::time::sleep(std::time::Duration::from_millis(
-            quote_refresh_frequency_in_ms,
-        ))
-        .await;
+        MarketCommands::Close => commands::close::close(&mm).await,
+        MarketCommands::Withdraw => commands::withdraw::withdraw(&mm).await,
+        MarketCommands::PartialWithdraw {
+            base_lots,
+            quote_lots,
+        } => commands::withdraw::partial_withdraw(&mm, base_lots, quote_lots).await,
+        MarketCommands::Migrate => commands::migrate::migrate(&mm).await,
+        MarketCommands::Backtest {
+            input_file,
+            strategy,
+        } => {
+            commands::backtest::backtest(
+                &client,
+                &market,
+                strategy.to_strategy_params(),
+                &input_file,
+            )
+            .await
+        }
+        MarketCommands::PriceLadder {
+            from_price,
+            to_price,
+            price_step,
+            best_bid,
+            best_ask,
+            strategy,
+        } => {
+            commands::price_ladder::price_ladder(
+                &client,
+                &market,
+                strategy.to_strategy_params(),
+                from_price,
+                to_price,
+                price_step,
+                best_bid,
+                best_ask,
+            )
+            .await
+        }
+        MarketCommands::Pnl { from_slot } => {
+            let sdk = phoenix_sdk::sdk_client::SDKClient::new(&payer, network_url).await?;
+            let meta = sdk.get_market_metadata(&market).await?;
+            let report = commands::pnl::compute_pnl(
+                &sdk,
+                &client,
+                &meta,
+                &strategy_key,
+                &market,
+                &payer.pubkey(),
+                from_slot,
+            )
+            .await?;
+            commands::pnl::print_pnl_report(&report, &meta);
+            Ok(())
+        }
     }
 }
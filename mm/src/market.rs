@@ -0,0 +1,44 @@
+use crate::rpc::RateLimitedRpcClient;
+use anyhow::anyhow;
+use phoenix::program::{load_with_dispatch, MarketHeader};
+use phoenix::state::markets::{FIFOOrderId, FIFORestingOrder, Market};
+use phoenix::state::OrderPacket;
+use solana_sdk::pubkey::Pubkey;
+use spl_token_2022::extension::StateWithExtensions;
+
+pub type PhoenixMarket<'a> = dyn Market<Pubkey, FIFOOrderId, FIFORestingOrder, OrderPacket> + 'a;
+
+pub fn parse_market_header(data: &[u8]) -> anyhow::Result<&MarketHeader> {
+    if data.len() < std::mem::size_of::<MarketHeader>() {
+        return Err(anyhow!("Phoenix market account data is too small"));
+    }
+    bytemuck::try_from_bytes::<MarketHeader>(&data[..std::mem::size_of::<MarketHeader>()])
+        .map_err(|_| anyhow!("Failed to parse Phoenix market header"))
+}
+
+/// Parses the header and the order book from raw market account data.
+pub fn load_market(data: &[u8]) -> anyhow::Result<(&MarketHeader, &PhoenixMarket<'_>)> {
+    let header = parse_market_header(data)?;
+    let market_bytes = &data[std::mem::size_of::<MarketHeader>()..];
+    let market = load_with_dispatch(&header.market_size_params, market_bytes)
+        .map_err(|_| anyhow!("Failed to deserialize Phoenix market"))?
+        .inner;
+    Ok((header, market))
+}
+
+pub async fn get_market_header(
+    client: &RateLimitedRpcClient,
+    market: &Pubkey,
+) -> anyhow::Result<MarketHeader> {
+    let data = client.get_account_data(market).await?;
+    parse_market_header(&data).copied()
+}
+
+/// Returns the decimals of an SPL Token or Token-2022 mint.
+pub async fn get_mint_decimals(client: &RateLimitedRpcClient, mint: &Pubkey) -> anyhow::Result<u8> {
+    let data = client.get_account_data(mint).await?;
+    // Token-2022 mints may carry extensions after the base mint data
+    let mint_state = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&data)
+        .map_err(|e| anyhow!("Failed to parse mint {}: {}", mint, e))?;
+    Ok(mint_state.base.decimals)
+}
@@ -0,0 +1,171 @@
+use crate::ema::Ema;
+use crate::price_feed::PriceFeed;
+use log::{debug, warn};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+
+/// Weight of the newest price in the average that price jumps are measured against
+const PRICE_JUMP_EMA_ALPHA: f64 = 0.1;
+
+/// Health of a price feed, updated from the result of every poll.
+#[derive(Debug)]
+struct FeedHealth {
+    timeout: Duration,
+    max_jump_in_bps: f64,
+    average: Ema,
+    last_price_at: Instant,
+    last_price_suspicious: bool,
+    healthy: bool,
+}
+
+impl FeedHealth {
+    /// The feed starts out healthy and has `timeout` from `start` to return its first price.
+    fn new(timeout: Duration, max_jump_in_bps: f64, start: Instant) -> anyhow::Result<Self> {
+        Ok(Self {
+            timeout,
+            max_jump_in_bps,
+            average: Ema::new(PRICE_JUMP_EMA_ALPHA, 0)?,
+            last_price_at: start,
+            last_price_suspicious: false,
+            healthy: true,
+        })
+    }
+
+    /// Records the price returned by a poll at `now`, or None if the poll failed, and returns
+    /// whether the feed is healthy.
+    fn observe(&mut self, price: Option<u64>, now: Instant) -> bool {
+        if let Some(price) = price {
+            let price = price as f64;
+            let jump_in_bps = match self.average.value() {
+                Some(average) if average > 0.0 => (price - average).abs() / average * 10_000.0,
+                _ => 0.0,
+            };
+            self.last_price_suspicious = jump_in_bps > self.max_jump_in_bps;
+            if self.last_price_suspicious {
+                warn!(
+                    "Price {} is {:.0} bps away from its moving average",
+                    price, jump_in_bps
+                );
+            }
+            // A lasting move is eventually absorbed by the average and the feed recovers
+            self.average.update(price);
+            self.last_price_at = now;
+        }
+        let stale = now.saturating_duration_since(self.last_price_at) > self.timeout;
+        let healthy = !stale && !self.last_price_suspicious;
+        if healthy != self.healthy {
+            if healthy {
+                warn!("Price feed is healthy again");
+            } else if stale {
+                warn!(
+                    "Price feed is unhealthy: no price for {} ms",
+                    now.saturating_duration_since(self.last_price_at)
+                        .as_millis()
+                );
+            } else {
+                warn!("Price feed is unhealthy: suspicious price jump");
+            }
+        }
+        self.healthy = healthy;
+        healthy
+    }
+}
+
+/// Polls a price feed in the background, faster than the quoting loop, and flags it as unhealthy
+/// when it stops returning prices or its price jumps away from its moving average.
+pub struct PriceFeedMonitor {
+    is_healthy: Arc<AtomicBool>,
+    handle: JoinHandle<()>,
+}
+
+impl PriceFeedMonitor {
+    pub fn spawn(
+        price_feed: Arc<dyn PriceFeed>,
+        poll_interval: Duration,
+        timeout: Duration,
+        max_jump_in_bps: f64,
+    ) -> anyhow::Result<Self> {
+        let mut health = FeedHealth::new(timeout, max_jump_in_bps, Instant::now())?;
+        let is_healthy = Arc::new(AtomicBool::new(true));
+        let flag = is_healthy.clone();
+        let handle = tokio::spawn(async move {
+            loop {
+                // A hanging request counts as a missing price
+                let price =
+                    match tokio::time::timeout(timeout, price_feed.get_fair_price_in_quote_atoms())
+                        .await
+                    {
+                        Ok(Ok(price)) => Some(price),
+                        Ok(Err(e)) => {
+                            debug!("Price feed poll failed: {}", e);
+                            None
+                        }
+                        Err(_) => {
+                            debug!("Price feed poll timed out");
+                            None
+                        }
+                    };
+                flag.store(health.observe(price, Instant::now()), Ordering::Relaxed);
+                tokio::time::sleep(poll_interval).await;
+            }
+        });
+        Ok(Self { is_healthy, handle })
+    }
+
+    pub fn is_healthy(&self) -> bool {
+        self.is_healthy.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for PriceFeedMonitor {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::price_feed::MockPriceFeed;
+
+    #[test]
+    fn test_feed_is_unhealthy_without_prices() {
+        let start = Instant::now();
+        let mut health = FeedHealth::new(Duration::from_millis(1_000), 500.0, start).unwrap();
+        assert!(health.observe(None, start + Duration::from_millis(1_000)));
+        assert!(!health.observe(None, start + Duration::from_millis(1_001)));
+        assert!(health.observe(Some(100_000), start + Duration::from_millis(1_500)));
+    }
+
+    #[test]
+    fn test_price_jumps_are_suspicious() {
+        let start = Instant::now();
+        let mut health = FeedHealth::new(Duration::from_millis(1_000), 500.0, start).unwrap();
+        assert!(health.observe(Some(100_000), start));
+        assert!(health.observe(Some(104_000), start));
+        assert!(!health.observe(Some(120_000), start));
+        // Failed polls keep the feed unhealthy until a plausible price arrives
+        assert!(!health.observe(None, start));
+        assert!(health.observe(Some(104_000), start));
+    }
+
+    #[tokio::test]
+    async fn test_monitor_flags_a_failing_feed() {
+        let spawn = |feed: MockPriceFeed| {
+            PriceFeedMonitor::spawn(
+                Arc::new(feed),
+                Duration::from_millis(1),
+                Duration::from_millis(20),
+                500.0,
+            )
+            .unwrap()
+        };
+        let working = spawn(MockPriceFeed::always(100_000));
+        let failing = spawn(MockPriceFeed::failing("unavailable"));
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(working.is_healthy());
+        assert!(!failing.is_healthy());
+    }
+}
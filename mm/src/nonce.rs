@@ -0,0 +1,113 @@
+use crate::rpc::RateLimitedRpcClient;
+use anyhow::anyhow;
+use solana_client::nonblocking::nonce_utils::data_from_account;
+use solana_sdk::hash::Hash;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::nonce::State;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_sdk::signer::keypair::Keypair;
+use solana_sdk::signer::Signer;
+use solana_sdk::system_instruction;
+use solana_sdk::system_program;
+use solana_sdk::transaction::Transaction;
+
+/// Seed of the nonce account created for the payer by `--auto-create-nonce-account`
+pub const NONCE_ACCOUNT_SEED: &str = "phoenix-mm-nonce";
+
+/// A durable nonce that replaces the recent blockhash of every transaction. The nonce is
+/// advanced by each transaction that lands, so transactions signed with it do not expire.
+pub struct DurableNonce<'a> {
+    pub nonce_account: Pubkey,
+    pub authority: &'a Keypair,
+}
+
+impl<'a> DurableNonce<'a> {
+    /// Returns the blockhash currently stored in the nonce account.
+    pub async fn get_blockhash(&self, client: &RateLimitedRpcClient) -> anyhow::Result<Hash> {
+        let account = client.get_account(&self.nonce_account).await?;
+        let data = data_from_account(&account)
+            .map_err(|e| anyhow!("Invalid nonce account {}: {}", self.nonce_account, e))?;
+        if data.authority != self.authority.pubkey() {
+            return Err(anyhow!(
+                "Nonce account {} is controlled by {}, not {}",
+                self.nonce_account,
+                data.authority,
+                self.authority.pubkey()
+            ));
+        }
+        Ok(data.blockhash())
+    }
+
+    /// Returns `instructions` preceded by the instruction that advances the nonce, which must be
+    /// the first instruction of a durable nonce transaction.
+    pub fn with_advance_instruction(&self, instructions: &[Instruction]) -> Vec<Instruction> {
+        std::iter::once(system_instruction::advance_nonce_account(
+            &self.nonce_account,
+            &self.authority.pubkey(),
+        ))
+        .chain(instructions.iter().cloned())
+        .collect()
+    }
+}
+
+/// Returns the address of the nonce account created for `payer` by `create_nonce_account_if_missing`.
+pub fn get_nonce_account_address(payer: &Pubkey) -> Pubkey {
+    Pubkey::create_with_seed(payer, NONCE_ACCOUNT_SEED, &system_program::id())
+        .expect("Nonce account seed is valid")
+}
+
+/// Creates the payer's nonce account, funded with the rent exemption and controlled by
+/// `authority`, unless it already exists.
+pub async fn create_nonce_account_if_missing(
+    client: &RateLimitedRpcClient,
+    payer: &Keypair,
+    authority: &Pubkey,
+) -> anyhow::Result<Option<Signature>> {
+    let nonce_account = get_nonce_account_address(&payer.pubkey());
+    if client.get_account(&nonce_account).await.is_ok() {
+        return Ok(None);
+    }
+    let lamports = client
+        .get_minimum_balance_for_rent_exemption(State::size())
+        .await?;
+    let instructions = system_instruction::create_nonce_account_with_seed(
+        &payer.pubkey(),
+        &nonce_account,
+        &payer.pubkey(),
+        NONCE_ACCOUNT_SEED,
+        authority,
+        lamports,
+    );
+    let transaction = Transaction::new_signed_with_payer(
+        &instructions,
+        Some(&payer.pubkey()),
+        &[payer],
+        client.get_latest_blockhash().await?,
+    );
+    Ok(Some(
+        client.send_and_confirm_transaction(&transaction).await?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_advance_instruction_comes_first() {
+        let authority = Keypair::new();
+        let nonce = DurableNonce {
+            nonce_account: Pubkey::new_unique(),
+            authority: &authority,
+        };
+        let instruction = Instruction::new_with_bytes(Pubkey::new_unique(), &[1], vec![]);
+        let instructions = nonce.with_advance_instruction(std::slice::from_ref(&instruction));
+        assert_eq!(instructions.len(), 2);
+        assert_eq!(
+            instructions[0],
+            system_instruction::advance_nonce_account(&nonce.nonce_account, &authority.pubkey())
+        );
+        assert_eq!(instructions[1], instruction);
+    }
+}
@@ -0,0 +1,136 @@
+use anyhow::anyhow;
+use phoenix_onchain_mm::StrategyParams;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Reloads strategy parameters from a JSON or TOML file whenever its modification time changes.
+/// Parameters set in the file override the ones from the command line, and keys that are missing
+/// or null keep their command line value.
+pub struct ParamsFileWatcher {
+    path: PathBuf,
+    base_params: StrategyParams,
+    modified: Option<SystemTime>,
+}
+
+impl ParamsFileWatcher {
+    pub fn new(path: PathBuf, base_params: StrategyParams) -> Self {
+        Self {
+            path,
+            base_params,
+            modified: None,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the parameters from the file if it changed since the last call. A file that fails
+    /// to load is not read again until it changes.
+    pub fn poll(&mut self) -> anyhow::Result<Option<StrategyParams>> {
+        let modified = std::fs::metadata(&self.path)?.modified()?;
+        if self.modified == Some(modified) {
+            return Ok(None);
+        }
+        self.modified = Some(modified);
+        let contents = std::fs::read_to_string(&self.path)?;
+        let is_toml = self.path.extension().is_some_and(|ext| ext == "toml");
+        parse_params(&contents, is_toml, &self.base_params).map(Some)
+    }
+}
+
+/// Parses a params file and applies the parameters it sets over `base_params`. The result is
+/// validated the same way as `update_quotes` would validate it on chain.
+pub fn parse_params(
+    contents: &str,
+    is_toml: bool,
+    base_params: &StrategyParams,
+) -> anyhow::Result<StrategyParams> {
+    let overrides: serde_json::Value = if is_toml {
+        toml::from_str(contents)?
+    } else {
+        serde_json::from_str(contents)?
+    };
+    let serde_json::Value::Object(overrides) = overrides else {
+        return Err(anyhow!("Params file must contain a table of parameters"));
+    };
+    let mut params = serde_json::to_value(base_params)?;
+    for (key, value) in overrides {
+        if !value.is_null() {
+            params[key] = value;
+        }
+    }
+    let params: StrategyParams = serde_json::from_value(params)?;
+    params
+        .validate(false)
+        .map_err(|e| anyhow!("Invalid params: {}", e))?;
+    Ok(params)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use phoenix_onchain_mm::PriceImprovementBehavior;
+
+    fn base_params() -> StrategyParams {
+        StrategyParams {
+            quote_edge_in_bps: Some(10),
+            quote_size_in_quote_atoms: Some(1_000_000),
+            price_improvement_behavior: Some(PriceImprovementBehavior::Join),
+            ..StrategyParams::default()
+        }
+    }
+
+    #[test]
+    fn test_json_overrides_base_params() {
+        let params = parse_params(
+            r#"{"quote_edge_in_bps": 25, "price_improvement_behavior": "Dime", "post_only": null}"#,
+            false,
+            &base_params(),
+        )
+        .unwrap();
+        assert_eq!(params.quote_edge_in_bps, Some(25));
+        assert_eq!(params.quote_size_in_quote_atoms, Some(1_000_000));
+        assert!(matches!(
+            params.price_improvement_behavior,
+            Some(PriceImprovementBehavior::Dime)
+        ));
+        assert_eq!(params.post_only, None);
+    }
+
+    #[test]
+    fn test_toml_overrides_base_params() {
+        let params = parse_params(
+            "quote_size_in_quote_atoms = 5000\npost_only = true\n",
+            true,
+            &base_params(),
+        )
+        .unwrap();
+        assert_eq!(params.quote_edge_in_bps, Some(10));
+        assert_eq!(params.quote_size_in_quote_atoms, Some(5000));
+        assert_eq!(params.post_only, Some(true));
+    }
+
+    #[test]
+    fn test_invalid_params_are_rejected() {
+        // Fails validation
+        assert!(parse_params(r#"{"quote_edge_in_bps": 0}"#, false, &base_params()).is_err());
+        // Misspelled parameter
+        assert!(parse_params(r#"{"quote_edge_bps": 20}"#, false, &base_params()).is_err());
+        // Not a table
+        assert!(parse_params("[1, 2]", false, &base_params()).is_err());
+        assert!(parse_params("quote_edge_in_bps = ", true, &base_params()).is_err());
+    }
+
+    #[test]
+    fn test_watcher_reloads_only_when_modified() {
+        let path = std::env::temp_dir().join(format!("mm-params-{}.json", std::process::id()));
+        std::fs::write(&path, r#"{"quote_edge_in_bps": 30}"#).unwrap();
+        let mut watcher = ParamsFileWatcher::new(path.clone(), base_params());
+        let params = watcher.poll().unwrap().unwrap();
+        assert_eq!(params.quote_edge_in_bps, Some(30));
+        assert!(watcher.poll().unwrap().is_none());
+        std::fs::remove_file(&path).unwrap();
+        assert!(watcher.poll().is_err());
+    }
+}
@@ -0,0 +1,53 @@
+use super::{PriceFeed, DEFAULT_PRICE_SCALE};
+use std::str::FromStr;
+
+pub struct BinanceFeed {
+    /// Binance symbol, e.g. "SOLUSDT"
+    pub symbol: String,
+    /// Quote atoms per unit of the quote currency
+    pub price_scale: f64,
+}
+
+impl BinanceFeed {
+    pub fn new(symbol: String) -> Self {
+        Self {
+            symbol,
+            price_scale: DEFAULT_PRICE_SCALE,
+        }
+    }
+
+    pub fn with_price_scale(self, price_scale: f64) -> Self {
+        Self {
+            price_scale,
+            ..self
+        }
+    }
+
+    /// Converts a Coinbase ticker such as "SOL-USD" into the Binance symbol "SOLUSDT". Binance
+    /// has no USD spot pairs, so USD is priced against USDT.
+    pub fn from_coinbase_ticker(ticker: &str) -> Self {
+        let (base, quote) = ticker.split_once('-').unwrap_or((ticker, ""));
+        let quote = if quote == "USD" { "USDT" } else { quote };
+        Self::new(format!("{}{}", base, quote))
+    }
+}
+
+#[async_trait::async_trait]
+impl PriceFeed for BinanceFeed {
+    async fn get_fair_price_in_quote_atoms(&self) -> anyhow::Result<u64> {
+        let response = reqwest::get(format!(
+            "https://api.binance.com/api/v3/ticker/price?symbol={}",
+            self.symbol
+        ))
+        .await?
+        .json::<serde_json::Value>()
+        .await?;
+
+        let fair_price = f64::from_str(
+            response["price"]
+                .as_str()
+                .ok_or_else(|| anyhow::anyhow!("Missing price in Binance response"))?,
+        )?;
+        Ok((fair_price * self.price_scale) as u64)
+    }
+}
@@ -0,0 +1,45 @@
+use super::{PriceFeed, DEFAULT_PRICE_SCALE};
+use std::str::FromStr;
+
+pub struct CoinbaseFeed {
+    /// Coinbase ticker, e.g. "SOL-USD"
+    pub ticker: String,
+    /// Quote atoms per unit of the quote currency
+    pub price_scale: f64,
+}
+
+impl CoinbaseFeed {
+    pub fn new(ticker: String) -> Self {
+        Self {
+            ticker,
+            price_scale: DEFAULT_PRICE_SCALE,
+        }
+    }
+
+    pub fn with_price_scale(self, price_scale: f64) -> Self {
+        Self {
+            price_scale,
+            ..self
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl PriceFeed for CoinbaseFeed {
+    async fn get_fair_price_in_quote_atoms(&self) -> anyhow::Result<u64> {
+        let response = reqwest::get(format!(
+            "https://api.coinbase.com/v2/prices/{}/spot",
+            self.ticker
+        ))
+        .await?
+        .json::<serde_json::Value>()
+        .await?;
+
+        let fair_price = f64::from_str(
+            response["data"]["amount"]
+                .as_str()
+                .ok_or_else(|| anyhow::anyhow!("Missing price in Coinbase response"))?,
+        )?;
+        Ok((fair_price * self.price_scale) as u64)
+    }
+}
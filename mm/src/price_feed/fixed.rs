@@ -0,0 +1,26 @@
+use super::PriceFeed;
+
+/// Always returns the same price. Used to test specific price scenarios without a live feed.
+pub struct FixedPriceFeed {
+    /// Fair price in quote atoms per raw base unit
+    pub fair_price_in_quote_atoms: u64,
+}
+
+impl FixedPriceFeed {
+    pub fn new(fair_price_in_quote_atoms: u64) -> Self {
+        Self {
+            fair_price_in_quote_atoms,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl PriceFeed for FixedPriceFeed {
+    async fn get_fair_price_in_quote_atoms(&self) -> anyhow::Result<u64> {
+        println!(
+            "WARNING: fair price is overridden to {} quote atoms per raw base unit",
+            self.fair_price_in_quote_atoms
+        );
+        Ok(self.fair_price_in_quote_atoms)
+    }
+}
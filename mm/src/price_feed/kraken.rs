@@ -0,0 +1,55 @@
+use super::{PriceFeed, DEFAULT_PRICE_SCALE};
+use std::str::FromStr;
+
+pub struct KrakenFeed {
+    /// Kraken pair, e.g. "SOLUSD"
+    pub pair: String,
+    /// Quote atoms per unit of the quote currency
+    pub price_scale: f64,
+}
+
+impl KrakenFeed {
+    pub fn new(pair: String) -> Self {
+        Self {
+            pair,
+            price_scale: DEFAULT_PRICE_SCALE,
+        }
+    }
+
+    pub fn with_price_scale(self, price_scale: f64) -> Self {
+        Self {
+            price_scale,
+            ..self
+        }
+    }
+
+    /// Converts a Coinbase ticker such as "SOL-USD" into the Kraken pair "SOLUSD".
+    pub fn from_coinbase_ticker(ticker: &str) -> Self {
+        Self::new(ticker.replace('-', ""))
+    }
+}
+
+#[async_trait::async_trait]
+impl PriceFeed for KrakenFeed {
+    async fn get_fair_price_in_quote_atoms(&self) -> anyhow::Result<u64> {
+        let response = reqwest::get(format!(
+            "https://api.kraken.com/0/public/Ticker?pair={}",
+            self.pair
+        ))
+        .await?
+        .json::<serde_json::Value>()
+        .await?;
+
+        if let Some(error) = response["error"].as_array().and_then(|e| e.first()) {
+            return Err(anyhow::anyhow!("Kraken returned an error: {}", error));
+        }
+        // Results are keyed by Kraken's canonical pair name, which may differ from the request
+        let last_trade_price = response["result"]
+            .as_object()
+            .and_then(|result| result.values().next())
+            .and_then(|ticker| ticker["c"][0].as_str())
+            .ok_or_else(|| anyhow::anyhow!("Missing price in Kraken response"))?;
+        let fair_price = f64::from_str(last_trade_price)?;
+        Ok((fair_price * self.price_scale) as u64)
+    }
+}
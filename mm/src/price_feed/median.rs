@@ -0,0 +1,94 @@
+use super::PriceFeed;
+use futures::future::join_all;
+
+/// Queries several feeds concurrently and returns the median of the prices that were fetched.
+pub struct MedianPriceFeed {
+    pub feeds: Vec<Box<dyn PriceFeed>>,
+    /// Minimum number of feeds that must return a price
+    pub min_feeds: usize,
+}
+
+impl MedianPriceFeed {
+    pub fn new(feeds: Vec<Box<dyn PriceFeed>>, min_feeds: usize) -> Self {
+        Self { feeds, min_feeds }
+    }
+}
+
+/// Returns the median of `prices`, averaging the two middle prices when there is an even number.
+fn median(prices: &mut [u64]) -> Option<u64> {
+    if prices.is_empty() {
+        return None;
+    }
+    prices.sort_unstable();
+    let mid = prices.len() / 2;
+    if prices.len() % 2 == 1 {
+        Some(prices[mid])
+    } else {
+        Some(((prices[mid - 1] as u128 + prices[mid] as u128) / 2) as u64)
+    }
+}
+
+#[async_trait::async_trait]
+impl PriceFeed for MedianPriceFeed {
+    async fn get_fair_price_in_quote_atoms(&self) -> anyhow::Result<u64> {
+        let results = join_all(
+            self.feeds
+                .iter()
+                .map(|feed| feed.get_fair_price_in_quote_atoms()),
+        )
+        .await;
+        let mut prices = vec![];
+        for (i, result) in results.into_iter().enumerate() {
+            match result {
+                Ok(price) => prices.push(price),
+                Err(e) => println!("Price feed {} failed: {}", i, e),
+            }
+        }
+        if prices.len() < self.min_feeds.max(1) {
+            return Err(anyhow::anyhow!(
+                "Only {} of {} price feeds responded, at least {} are required",
+                prices.len(),
+                self.feeds.len(),
+                self.min_feeds
+            ));
+        }
+        median(&mut prices).ok_or_else(|| anyhow::anyhow!("No price feeds responded"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::price_feed::MockPriceFeed;
+
+    #[test]
+    fn test_median() {
+        assert_eq!(median(&mut []), None);
+        assert_eq!(median(&mut [5]), Some(5));
+        assert_eq!(median(&mut [30, 10, 20]), Some(20));
+        assert_eq!(median(&mut [40, 10, 30, 20]), Some(25));
+        assert_eq!(median(&mut [u64::MAX, u64::MAX]), Some(u64::MAX));
+    }
+
+    #[tokio::test]
+    async fn test_failed_feeds_are_ignored() {
+        let feed = MedianPriceFeed::new(
+            vec![
+                Box::new(MockPriceFeed::always(100)),
+                Box::new(MockPriceFeed::failing("unavailable")),
+                Box::new(MockPriceFeed::always(300)),
+            ],
+            2,
+        );
+        assert_eq!(feed.get_fair_price_in_quote_atoms().await.unwrap(), 200);
+
+        let feed = MedianPriceFeed::new(
+            vec![
+                Box::new(MockPriceFeed::always(100)),
+                Box::new(MockPriceFeed::failing("unavailable")),
+            ],
+            2,
+        );
+        assert!(feed.get_fair_price_in_quote_atoms().await.is_err());
+    }
+}
@@ -0,0 +1,96 @@
+use super::PriceFeed;
+use anyhow::anyhow;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+/// Returns scripted prices and errors in order, then repeats the last one. Used to test the
+/// client's handling of prices without a live feed.
+pub struct MockPriceFeed {
+    /// Errors are kept as messages, since the last response may have to be returned repeatedly
+    responses: Mutex<VecDeque<Result<u64, String>>>,
+}
+
+impl MockPriceFeed {
+    pub fn new(responses: Vec<anyhow::Result<u64>>) -> Self {
+        Self {
+            responses: Mutex::new(
+                responses
+                    .into_iter()
+                    .map(|response| response.map_err(|e| format!("{:#}", e)))
+                    .collect(),
+            ),
+        }
+    }
+
+    pub fn always(price: u64) -> Self {
+        Self::new(vec![Ok(price)])
+    }
+
+    pub fn sequence(prices: Vec<u64>) -> Self {
+        Self::new(prices.into_iter().map(Ok).collect())
+    }
+
+    pub fn failing(error: &str) -> Self {
+        Self::new(vec![Err(anyhow!("{}", error))])
+    }
+}
+
+#[async_trait::async_trait]
+impl PriceFeed for MockPriceFeed {
+    async fn get_fair_price_in_quote_atoms(&self) -> anyhow::Result<u64> {
+        let mut responses = self.responses.lock().unwrap();
+        let response = if responses.len() > 1 {
+            responses.pop_front()
+        } else {
+            responses.front().cloned()
+        };
+        match response {
+            Some(response) => response.map_err(|e| anyhow!(e)),
+            None => Err(anyhow!("Mock price feed has no prices")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ema::Ema;
+
+    #[tokio::test]
+    async fn test_responses_are_returned_in_order_then_repeated() {
+        let feed = MockPriceFeed::new(vec![Ok(100), Err(anyhow!("timeout")), Ok(300)]);
+        assert_eq!(feed.get_fair_price_in_quote_atoms().await.unwrap(), 100);
+        assert_eq!(
+            feed.get_fair_price_in_quote_atoms()
+                .await
+                .unwrap_err()
+                .to_string(),
+            "timeout"
+        );
+        for _ in 0..3 {
+            assert_eq!(feed.get_fair_price_in_quote_atoms().await.unwrap(), 300);
+        }
+
+        let feed = MockPriceFeed::failing("rate limited");
+        for _ in 0..2 {
+            assert!(feed.get_fair_price_in_quote_atoms().await.is_err());
+        }
+        assert!(MockPriceFeed::new(vec![])
+            .get_fair_price_in_quote_atoms()
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_ema_smooths_a_price_jump() {
+        // The quoting loop smooths every fair price before quoting around it
+        let feed = MockPriceFeed::sequence(vec![100_000, 100_000, 110_000]);
+        let mut ema = Ema::new(0.5, 0).unwrap();
+        let mut smoothed = vec![];
+        for _ in 0..4 {
+            let price = feed.get_fair_price_in_quote_atoms().await.unwrap();
+            smoothed.push(ema.update(price as f64).round() as u64);
+        }
+        assert_eq!(smoothed, vec![100_000, 100_000, 105_000, 107_500]);
+    }
+}
@@ -0,0 +1,27 @@
+pub mod binance;
+pub mod coinbase;
+pub mod fixed;
+pub mod kraken;
+pub mod median;
+#[cfg(test)]
+pub mod mock;
+pub mod pyth_hermes;
+
+pub use binance::BinanceFeed;
+pub use coinbase::CoinbaseFeed;
+pub use fixed::FixedPriceFeed;
+pub use kraken::KrakenFeed;
+pub use median::MedianPriceFeed;
+#[cfg(test)]
+pub use mock::MockPriceFeed;
+pub use pyth_hermes::PythHermesFeed;
+
+/// Quote atoms per unit of the quote currency for USDC and other 6 decimal quote mints
+pub const DEFAULT_PRICE_SCALE: f64 = 1e6;
+
+/// A source of fair prices for the market maker.
+#[async_trait::async_trait]
+pub trait PriceFeed: Send + Sync {
+    /// Returns the fair price in quote atoms per raw base unit.
+    async fn get_fair_price_in_quote_atoms(&self) -> anyhow::Result<u64>;
+}
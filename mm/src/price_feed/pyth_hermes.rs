@@ -0,0 +1,131 @@
+use super::{PriceFeed, DEFAULT_PRICE_SCALE};
+use serde::Deserialize;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const HERMES_URL: &str = "https://hermes.pyth.network/api/latest_price_feeds";
+
+#[derive(Debug, Deserialize)]
+struct HermesPriceFeed {
+    price: HermesPrice,
+}
+
+#[derive(Debug, Deserialize)]
+struct HermesPrice {
+    #[serde(deserialize_with = "deserialize_i64_from_string")]
+    price: i64,
+    #[serde(deserialize_with = "deserialize_i64_from_string")]
+    conf: i64,
+    expo: i32,
+    publish_time: i64,
+}
+
+fn deserialize_i64_from_string<'de, D>(deserializer: D) -> Result<i64, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    // Hermes encodes 64-bit integers as strings
+    match serde_json::Value::deserialize(deserializer)? {
+        serde_json::Value::String(s) => s.parse().map_err(serde::de::Error::custom),
+        serde_json::Value::Number(n) => n
+            .as_i64()
+            .ok_or_else(|| serde::de::Error::custom("Invalid integer")),
+        _ => Err(serde::de::Error::custom("Expected a string or a number")),
+    }
+}
+
+pub struct PythHermesFeed {
+    /// Hex-encoded Pyth price feed ID
+    pub price_feed_id: String,
+    /// Maximum age of the published price before it is rejected
+    pub max_staleness_in_seconds: u64,
+    /// Maximum allowed ratio of the confidence interval to the price, in basis points
+    pub max_confidence_ratio_in_bps: u64,
+    /// Quote atoms per unit of the quote currency
+    pub price_scale: f64,
+}
+
+impl PythHermesFeed {
+    pub fn new(
+        price_feed_id: String,
+        max_staleness_in_seconds: u64,
+        max_confidence_ratio_in_bps: u64,
+    ) -> Self {
+        Self {
+            price_feed_id: price_feed_id.trim_start_matches("0x").to_string(),
+            max_staleness_in_seconds,
+            max_confidence_ratio_in_bps,
+            price_scale: DEFAULT_PRICE_SCALE,
+        }
+    }
+
+    pub fn with_price_scale(self, price_scale: f64) -> Self {
+        Self {
+            price_scale,
+            ..self
+        }
+    }
+}
+
+/// Converts a Pyth price with the given exponent into quote atoms, with `price_scale` quote atoms
+/// per unit of the quote currency
+fn to_quote_atoms(price: i64, expo: i32, price_scale: f64) -> u64 {
+    let price = price.unsigned_abs() as f64;
+    // Dividing by a power of ten is exact where multiplying by its inverse is not
+    let price = if expo >= 0 {
+        price * 10f64.powi(expo)
+    } else {
+        price / 10f64.powi(-expo)
+    };
+    (price * price_scale).round() as u64
+}
+
+#[async_trait::async_trait]
+impl PriceFeed for PythHermesFeed {
+    async fn get_fair_price_in_quote_atoms(&self) -> anyhow::Result<u64> {
+        let feeds = reqwest::get(format!("{}?ids[]={}", HERMES_URL, self.price_feed_id))
+            .await?
+            .json::<Vec<HermesPriceFeed>>()
+            .await?;
+        let HermesPrice {
+            price,
+            conf,
+            expo,
+            publish_time,
+        } = feeds
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("Pyth price feed {} not found", self.price_feed_id))?
+            .price;
+
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
+        if now - publish_time > self.max_staleness_in_seconds as i64 {
+            return Err(anyhow::anyhow!(
+                "Pyth price is stale: published {} seconds ago",
+                now - publish_time
+            ));
+        }
+        if price == 0 {
+            return Err(anyhow::anyhow!("Pyth price is zero"));
+        }
+        let confidence_ratio_in_bps = (conf as i128 * 10_000 / price as i128).unsigned_abs();
+        if confidence_ratio_in_bps > self.max_confidence_ratio_in_bps as u128 {
+            return Err(anyhow::anyhow!(
+                "Pyth confidence interval is too wide: {} bps",
+                confidence_ratio_in_bps
+            ));
+        }
+        Ok(to_quote_atoms(price, expo, self.price_scale))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_quote_atoms() {
+        assert_eq!(to_quote_atoms(2_012_345_678, -8, 1e6), 20_123_457);
+        assert_eq!(to_quote_atoms(2_012_345_678, -8, 1e9), 20_123_456_780);
+        assert_eq!(to_quote_atoms(20, 0, 1e6), 20_000_000);
+    }
+}
@@ -0,0 +1,158 @@
+use std::collections::VecDeque;
+
+/// Returns true if the quotes are crossed against the reference price by more than
+/// `edge_threshold_bps`, i.e. the bid is above or the ask is below the reference price.
+/// Quotes like this would be arbitraged immediately and usually point to a bad price conversion.
+pub fn check_for_arbitrage(
+    bid_price_usd: f64,
+    ask_price_usd: f64,
+    reference_price_usd: f64,
+    edge_threshold_bps: f64,
+) -> bool {
+    let threshold = reference_price_usd * edge_threshold_bps / 10_000.0;
+    bid_price_usd > reference_price_usd + threshold
+        || ask_price_usd < reference_price_usd - threshold
+}
+
+/// Returns true if `price_in_ticks` is within `tolerance_bps` of `target_price_in_ticks`.
+pub fn is_within_tolerance(
+    price_in_ticks: u64,
+    target_price_in_ticks: u64,
+    tolerance_bps: u64,
+) -> bool {
+    price_in_ticks.abs_diff(target_price_in_ticks) as u128 * 10_000
+        <= target_price_in_ticks as u128 * tolerance_bps as u128
+}
+
+/// Number of fills averaged by `FrontrunDetector`
+pub const ADVERSE_SELECTION_WINDOW: usize = 20;
+
+/// A fill on one of the strategy's orders, with the fair price before and after it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FillEvent {
+    pub fill_timestamp: i64,
+    pub fill_price: f64,
+    pub fair_price_at_fill_time: f64,
+    pub next_fair_price_after_fill: f64,
+}
+
+impl FillEvent {
+    /// Distance of the next fair price from the fill price, in basis points of the fill price.
+    pub fn adverse_move_bps(&self) -> f64 {
+        if self.fill_price <= 0.0 {
+            return 0.0;
+        }
+        (self.next_fair_price_after_fill - self.fill_price).abs() / self.fill_price * 10_000.0
+    }
+}
+
+/// Flags fills that are consistently followed by large price moves, a sign that the quotes are
+/// being picked off just before the market moves against them.
+///
+/// A fill is recorded with the fair price of the update that placed the filled order, and is
+/// completed by the next fair price that the market maker observes.
+#[derive(Debug)]
+pub struct FrontrunDetector {
+    threshold_bps: Option<f64>,
+    window: usize,
+    /// Fills waiting for the next fair price
+    pending: Vec<FillEvent>,
+    adverse_moves_bps: VecDeque<f64>,
+}
+
+impl FrontrunDetector {
+    /// `threshold_bps` is the average adverse move above which `is_adverse_selection_high`
+    /// returns true. None only tracks the average.
+    pub fn new(threshold_bps: Option<f64>, window: usize) -> Self {
+        Self {
+            threshold_bps,
+            window: window.max(1),
+            pending: vec![],
+            adverse_moves_bps: VecDeque::new(),
+        }
+    }
+
+    pub fn record_fill(&mut self, fill_timestamp: i64, fill_price: f64, fair_price: f64) {
+        self.pending.push(FillEvent {
+            fill_timestamp,
+            fill_price,
+            fair_price_at_fill_time: fair_price,
+            next_fair_price_after_fill: fair_price,
+        });
+    }
+
+    /// Completes the pending fills with `fair_price` and returns them.
+    pub fn observe_fair_price(&mut self, fair_price: f64) -> Vec<FillEvent> {
+        let fills = std::mem::take(&mut self.pending)
+            .into_iter()
+            .map(|fill| FillEvent {
+                next_fair_price_after_fill: fair_price,
+                ..fill
+            })
+            .collect::<Vec<_>>();
+        for fill in fills.iter() {
+            if self.adverse_moves_bps.len() == self.window {
+                self.adverse_moves_bps.pop_front();
+            }
+            self.adverse_moves_bps.push_back(fill.adverse_move_bps());
+        }
+        fills
+    }
+
+    /// Average adverse move over the last fills, or None before the first fill is completed.
+    pub fn average_adverse_move_bps(&self) -> Option<f64> {
+        if self.adverse_moves_bps.is_empty() {
+            return None;
+        }
+        Some(self.adverse_moves_bps.iter().sum::<f64>() / self.adverse_moves_bps.len() as f64)
+    }
+
+    pub fn is_adverse_selection_high(&self) -> bool {
+        match (self.threshold_bps, self.average_adverse_move_bps()) {
+            (Some(threshold_bps), Some(average_bps)) => average_bps > threshold_bps,
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_within_tolerance() {
+        assert!(is_within_tolerance(10_000, 10_000, 0));
+        assert!(is_within_tolerance(10_010, 10_000, 10));
+        assert!(is_within_tolerance(9_990, 10_000, 10));
+        assert!(!is_within_tolerance(10_011, 10_000, 10));
+        assert!(!is_within_tolerance(1, 0, 10_000));
+    }
+
+    #[test]
+    fn test_adverse_move_is_measured_from_the_fill_price() {
+        let mut detector = FrontrunDetector::new(Some(15.0), 2);
+        assert_eq!(detector.average_adverse_move_bps(), None);
+        detector.record_fill(1, 100.0, 100.05);
+        let fills = detector.observe_fair_price(99.9);
+        assert!((fills[0].adverse_move_bps() - 10.0).abs() < 1e-9);
+        assert!(!detector.is_adverse_selection_high());
+
+        // Fills are only completed once
+        assert!(detector.observe_fair_price(50.0).is_empty());
+        detector.record_fill(2, 100.0, 100.0);
+        detector.record_fill(2, 100.0, 100.0);
+        detector.observe_fair_price(100.3);
+        // The window keeps the last two fills, both 30 bps
+        assert!((detector.average_adverse_move_bps().unwrap() - 30.0).abs() < 1e-9);
+        assert!(detector.is_adverse_selection_high());
+    }
+
+    #[test]
+    fn test_adverse_selection_is_never_high_without_a_threshold() {
+        let mut detector = FrontrunDetector::new(None, ADVERSE_SELECTION_WINDOW);
+        detector.record_fill(1, 100.0, 100.0);
+        detector.observe_fair_price(110.0);
+        assert!(detector.average_adverse_move_bps().unwrap() > 0.0);
+        assert!(!detector.is_adverse_selection_high());
+    }
+}
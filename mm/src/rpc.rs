@@ -0,0 +1,244 @@
+use log::debug;
+use solana_client::client_error::Result as ClientResult;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_client::GetConfirmedSignaturesForAddress2Config;
+use solana_client::rpc_config::{
+    RpcSendTransactionConfig, RpcSimulateTransactionConfig, RpcTransactionConfig,
+};
+use solana_client::rpc_response::{
+    Response, RpcConfirmedTransactionStatusWithSignature, RpcSimulateTransactionResult,
+};
+use solana_sdk::account::Account;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::hash::Hash;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_sdk::transaction::Transaction;
+use solana_transaction_status::EncodedConfirmedTransactionWithStatusMeta;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+/// Sliding window limit on the number of events within `window`.
+#[derive(Debug)]
+pub struct RateLimiter {
+    name: &'static str,
+    max_events: Option<usize>,
+    window: Duration,
+    timestamps: Mutex<VecDeque<Instant>>,
+}
+
+impl RateLimiter {
+    /// A limit of None never waits.
+    pub fn new(name: &'static str, max_events: Option<usize>, window: Duration) -> Self {
+        Self {
+            name,
+            max_events,
+            window,
+            timestamps: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Returns how long to wait at `now` before another event fits in the window, dropping
+    /// timestamps that have left the window.
+    fn get_delay(&self, timestamps: &mut VecDeque<Instant>, now: Instant) -> Option<Duration> {
+        let max_events = self.max_events?.max(1);
+        while timestamps
+            .front()
+            .is_some_and(|t| now.duration_since(*t) >= self.window)
+        {
+            timestamps.pop_front();
+        }
+        if timestamps.len() < max_events {
+            return None;
+        }
+        timestamps
+            .get(timestamps.len() - max_events)
+            .map(|oldest| self.window - now.duration_since(*oldest))
+    }
+
+    /// Sleeps until another event is allowed, then records it.
+    pub async fn acquire(&self) {
+        if self.max_events.is_none() {
+            return;
+        }
+        let mut timestamps = self.timestamps.lock().await;
+        if let Some(delay) = self.get_delay(&mut timestamps, Instant::now()) {
+            debug!("Rate limiting {} for {} ms", self.name, delay.as_millis());
+            tokio::time::sleep(delay).await;
+        }
+        timestamps.push_back(Instant::now());
+    }
+}
+
+/// Wraps an `RpcClient` so that every request respects the configured request and transaction
+/// rates. Transactions count against both limits.
+pub struct RateLimitedRpcClient {
+    inner: RpcClient,
+    request_limiter: RateLimiter,
+    transaction_limiter: RateLimiter,
+}
+
+impl RateLimitedRpcClient {
+    pub fn new(
+        inner: RpcClient,
+        max_rpc_requests_per_second: Option<usize>,
+        max_tx_per_minute: Option<usize>,
+    ) -> Self {
+        Self {
+            inner,
+            request_limiter: RateLimiter::new(
+                "RPC requests",
+                max_rpc_requests_per_second,
+                Duration::from_secs(1),
+            ),
+            transaction_limiter: RateLimiter::new(
+                "transactions",
+                max_tx_per_minute,
+                Duration::from_secs(60),
+            ),
+        }
+    }
+
+    async fn throttle_transaction(&self) {
+        self.transaction_limiter.acquire().await;
+        self.request_limiter.acquire().await;
+    }
+
+    pub fn commitment(&self) -> CommitmentConfig {
+        self.inner.commitment()
+    }
+
+    pub fn url(&self) -> String {
+        self.inner.url()
+    }
+
+    pub async fn get_account(&self, pubkey: &Pubkey) -> ClientResult<Account> {
+        self.request_limiter.acquire().await;
+        self.inner.get_account(pubkey).await
+    }
+
+    pub async fn get_account_data(&self, pubkey: &Pubkey) -> ClientResult<Vec<u8>> {
+        self.request_limiter.acquire().await;
+        self.inner.get_account_data(pubkey).await
+    }
+
+    pub async fn get_latest_blockhash(&self) -> ClientResult<Hash> {
+        self.request_limiter.acquire().await;
+        self.inner.get_latest_blockhash().await
+    }
+
+    pub async fn get_minimum_balance_for_rent_exemption(
+        &self,
+        data_len: usize,
+    ) -> ClientResult<u64> {
+        self.request_limiter.acquire().await;
+        self.inner
+            .get_minimum_balance_for_rent_exemption(data_len)
+            .await
+    }
+
+    pub async fn get_slot(&self) -> ClientResult<u64> {
+        self.request_limiter.acquire().await;
+        self.inner.get_slot().await
+    }
+
+    pub async fn get_signatures_for_address_with_config(
+        &self,
+        address: &Pubkey,
+        config: GetConfirmedSignaturesForAddress2Config,
+    ) -> ClientResult<Vec<RpcConfirmedTransactionStatusWithSignature>> {
+        self.request_limiter.acquire().await;
+        self.inner
+            .get_signatures_for_address_with_config(address, config)
+            .await
+    }
+
+    pub async fn get_transaction_with_config(
+        &self,
+        signature: &Signature,
+        config: RpcTransactionConfig,
+    ) -> ClientResult<EncodedConfirmedTransactionWithStatusMeta> {
+        self.request_limiter.acquire().await;
+        self.inner
+            .get_transaction_with_config(signature, config)
+            .await
+    }
+
+    pub async fn poll_for_signature(&self, signature: &Signature) -> ClientResult<()> {
+        self.request_limiter.acquire().await;
+        self.inner.poll_for_signature(signature).await
+    }
+
+    pub async fn simulate_transaction(
+        &self,
+        transaction: &Transaction,
+    ) -> ClientResult<Response<RpcSimulateTransactionResult>> {
+        self.request_limiter.acquire().await;
+        self.inner.simulate_transaction(transaction).await
+    }
+
+    pub async fn simulate_transaction_with_config(
+        &self,
+        transaction: &Transaction,
+        config: RpcSimulateTransactionConfig,
+    ) -> ClientResult<Response<RpcSimulateTransactionResult>> {
+        self.request_limiter.acquire().await;
+        self.inner
+            .simulate_transaction_with_config(transaction, config)
+            .await
+    }
+
+    pub async fn send_and_confirm_transaction(
+        &self,
+        transaction: &Transaction,
+    ) -> ClientResult<Signature> {
+        self.throttle_transaction().await;
+        self.inner.send_and_confirm_transaction(transaction).await
+    }
+
+    pub async fn send_transaction_with_config(
+        &self,
+        transaction: &Transaction,
+        config: RpcSendTransactionConfig,
+    ) -> ClientResult<Signature> {
+        self.throttle_transaction().await;
+        self.inner
+            .send_transaction_with_config(transaction, config)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unlimited_never_waits() {
+        let limiter = RateLimiter::new("test", None, Duration::from_secs(1));
+        let now = Instant::now();
+        let mut timestamps = VecDeque::from(vec![now; 100]);
+        assert_eq!(limiter.get_delay(&mut timestamps, now), None);
+    }
+
+    #[test]
+    fn test_waits_for_the_oldest_event_to_leave_the_window() {
+        let limiter = RateLimiter::new("test", Some(2), Duration::from_secs(1));
+        let start = Instant::now();
+        let mut timestamps = VecDeque::from(vec![start]);
+        let now = start + Duration::from_millis(100);
+        assert_eq!(limiter.get_delay(&mut timestamps, now), None);
+
+        timestamps.push_back(now);
+        let now = start + Duration::from_millis(300);
+        assert_eq!(
+            limiter.get_delay(&mut timestamps, now),
+            Some(Duration::from_millis(700))
+        );
+
+        // Events older than the window are dropped
+        let now = start + Duration::from_millis(1_000);
+        assert_eq!(limiter.get_delay(&mut timestamps, now), None);
+        assert_eq!(timestamps.len(), 1);
+    }
+}
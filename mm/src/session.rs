@@ -0,0 +1,291 @@
+use crate::analytics::calculate_position_delta;
+use crate::fill_monitor::FillMonitor;
+use crate::market::PhoenixMarket;
+use log::info;
+use phoenix::program::MarketHeader;
+use phoenix::quantities::WrapperU64;
+use phoenix_onchain_mm::PhoenixStrategyState;
+use phoenix_onchain_mm::{base_lots_to_display, quote_atoms_to_usd, tick_to_price_usd};
+use std::time::{Duration, Instant};
+
+/// Metrics accumulated over one run of the market maker, summarized when it exits.
+pub struct SessionStats {
+    start: Instant,
+    transactions_sent: u64,
+    transactions_failed: u64,
+    fills: FillMonitor,
+    estimated_pnl_in_quote_atoms: i64,
+    spread_sum_in_ticks: u128,
+    spread_count: u64,
+    /// Largest absolute net position from the session's fills, in base lots
+    max_inventory_in_base_lots: u64,
+    /// Net base lots bought and quote atoms received through fills since the session started
+    net_inventory: (i64, i64),
+    /// Position change between the last two updates, see `calculate_position_delta`
+    last_position_delta: (i64, i64),
+    /// Strategy state seen on the last update, which the next one is compared against
+    last_state: Option<PhoenixStrategyState>,
+    /// Header seen on the last update, used to convert the summary into display units
+    header: Option<MarketHeader>,
+}
+
+impl Default for SessionStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SessionStats {
+    pub fn new() -> Self {
+        Self {
+            start: Instant::now(),
+            transactions_sent: 0,
+            transactions_failed: 0,
+            fills: FillMonitor::default(),
+            estimated_pnl_in_quote_atoms: 0,
+            spread_sum_in_ticks: 0,
+            spread_count: 0,
+            max_inventory_in_base_lots: 0,
+            net_inventory: (0, 0),
+            last_position_delta: (0, 0),
+            last_state: None,
+            header: None,
+        }
+    }
+
+    /// Records the outcome of a quote update transaction.
+    pub fn record_transaction(&mut self, success: bool) {
+        self.transactions_sent += 1;
+        if !success {
+            self.transactions_failed += 1;
+        }
+    }
+
+    /// Records fills, the quoted spread and the inventory after an iteration of the run loop.
+    /// Returns the base lots filled on each side since the previous iteration.
+    pub fn update(
+        &mut self,
+        state: &PhoenixStrategyState,
+        header: &MarketHeader,
+        market: &PhoenixMarket,
+    ) -> (u64, u64) {
+        let fills = self.fills.observe(state, market);
+        self.estimated_pnl_in_quote_atoms = self.fills.estimated_pnl_in_quote_atoms(header, market);
+        self.record_quotes(state);
+        self.record_position(
+            state,
+            header.get_tick_size_in_quote_atoms_per_base_unit().as_u64(),
+            market.get_base_lots_per_base_unit().as_u64(),
+        );
+        self.header = Some(*header);
+        fills
+    }
+
+    fn record_position(
+        &mut self,
+        state: &PhoenixStrategyState,
+        tick_size_in_quote_atoms_per_base_unit: u64,
+        base_lots_per_base_unit: u64,
+    ) {
+        self.last_position_delta = match &self.last_state {
+            Some(prev_state) => calculate_position_delta(
+                prev_state,
+                state,
+                tick_size_in_quote_atoms_per_base_unit,
+                base_lots_per_base_unit,
+            ),
+            None => (0, 0),
+        };
+        self.net_inventory = (
+            self.net_inventory
+                .0
+                .saturating_add(self.last_position_delta.0),
+            self.net_inventory
+                .1
+                .saturating_add(self.last_position_delta.1),
+        );
+        self.last_state = Some(*state);
+    }
+
+    pub fn estimated_pnl_in_quote_atoms(&self) -> i64 {
+        self.estimated_pnl_in_quote_atoms
+    }
+
+    /// Position change `(base lots, quote atoms)` between the last two updates
+    pub fn last_position_delta(&self) -> (i64, i64) {
+        self.last_position_delta
+    }
+
+    /// Net position `(base lots, quote atoms)` accumulated from the session's fills
+    pub fn net_inventory(&self) -> (i64, i64) {
+        self.net_inventory
+    }
+
+    fn record_quotes(&mut self, state: &PhoenixStrategyState) {
+        if state.has_live_bid() && state.has_live_ask() {
+            self.spread_sum_in_ticks += state
+                .ask_price_in_ticks
+                .saturating_sub(state.bid_price_in_ticks)
+                as u128;
+            self.spread_count += 1;
+        }
+        let inventory = self
+            .fills
+            .bid_fill_in_base_lots
+            .abs_diff(self.fills.ask_fill_in_base_lots);
+        self.max_inventory_in_base_lots = self.max_inventory_in_base_lots.max(inventory);
+    }
+
+    /// Fraction of sent transactions that succeeded, or None if nothing was sent.
+    fn success_rate(&self) -> Option<f64> {
+        if self.transactions_sent == 0 {
+            return None;
+        }
+        Some(
+            (self.transactions_sent - self.transactions_failed) as f64
+                / self.transactions_sent as f64,
+        )
+    }
+
+    fn average_spread_in_ticks(&self) -> Option<u64> {
+        if self.spread_count == 0 {
+            return None;
+        }
+        Some((self.spread_sum_in_ticks / self.spread_count as u128) as u64)
+    }
+
+    pub fn uptime(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    /// Logs the session summary.
+    pub fn summarize(&self) {
+        let uptime = self.uptime().as_secs();
+        info!("Session summary");
+        info!(
+            "  Uptime:                   {}h {}m {}s",
+            uptime / 3600,
+            uptime / 60 % 60,
+            uptime % 60
+        );
+        info!(
+            "  Transactions:             {} sent, {} succeeded, {} failed ({})",
+            self.transactions_sent,
+            self.transactions_sent - self.transactions_failed,
+            self.transactions_failed,
+            self.success_rate()
+                .map(|rate| format!("{:.1}% success", rate * 100.0))
+                .unwrap_or_else(|| "no transactions".to_string())
+        );
+        let header = match self.header.as_ref() {
+            Some(header) => header,
+            None => {
+                info!("  No market data was observed");
+                return;
+            }
+        };
+        info!(
+            "  Estimated PnL:            {:.4} ({} quote atoms)",
+            quote_atoms_to_usd(self.estimated_pnl_in_quote_atoms.unsigned_abs(), header)
+                * self.estimated_pnl_in_quote_atoms.signum() as f64,
+            self.estimated_pnl_in_quote_atoms
+        );
+        info!(
+            "  Volume:                   bought {:.4}, sold {:.4}",
+            base_lots_to_display(self.fills.bid_fill_in_base_lots, header),
+            base_lots_to_display(self.fills.ask_fill_in_base_lots, header)
+        );
+        match self.average_spread_in_ticks() {
+            Some(spread) => info!(
+                "  Average spread:           {:.4} ({} ticks)",
+                tick_to_price_usd(spread, header),
+                spread
+            ),
+            None => info!("  Average spread:           no two-sided quotes"),
+        }
+        info!(
+            "  Max inventory:            {:.4}",
+            base_lots_to_display(self.max_inventory_in_base_lots, header)
+        );
+        info!(
+            "  Net inventory:            {}",
+            format_net_inventory(self.net_inventory)
+        );
+    }
+}
+
+/// Formats a position as e.g. "+20 base lots / -1960 quote atoms".
+pub fn format_net_inventory((base_lots, quote_atoms): (i64, i64)) -> String {
+    format!("{:+} base lots / {:+} quote atoms", base_lots, quote_atoms)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytemuck::Zeroable;
+
+    #[test]
+    fn test_success_rate() {
+        let mut stats = SessionStats::new();
+        assert_eq!(stats.success_rate(), None);
+        stats.record_transaction(true);
+        stats.record_transaction(true);
+        stats.record_transaction(false);
+        stats.record_transaction(true);
+        assert_eq!(stats.success_rate(), Some(0.75));
+    }
+
+    #[test]
+    fn test_average_spread_only_counts_two_sided_quotes() {
+        let mut stats = SessionStats::new();
+        let mut state = PhoenixStrategyState::zeroed();
+        state.bid_order_sequence_number = 1;
+        state.bid_price_in_ticks = 100;
+        state.ask_price_in_ticks = 110;
+        stats.record_quotes(&state);
+        assert_eq!(stats.average_spread_in_ticks(), None);
+
+        state.ask_order_sequence_number = 2;
+        stats.record_quotes(&state);
+        state.ask_price_in_ticks = 130;
+        stats.record_quotes(&state);
+        assert_eq!(stats.average_spread_in_ticks(), Some(20));
+    }
+
+    #[test]
+    fn test_max_inventory_tracks_the_net_position() {
+        let mut stats = SessionStats::new();
+        let state = PhoenixStrategyState::zeroed();
+        stats.fills.bid_fill_in_base_lots = 50;
+        stats.record_quotes(&state);
+        stats.fills.ask_fill_in_base_lots = 120;
+        stats.record_quotes(&state);
+        stats.fills.bid_fill_in_base_lots = 100;
+        stats.record_quotes(&state);
+        assert_eq!(stats.max_inventory_in_base_lots, 70);
+    }
+
+    #[test]
+    fn test_net_inventory_accumulates_position_deltas() {
+        let mut stats = SessionStats::new();
+        let mut state = PhoenixStrategyState::zeroed();
+        state.bid_price_in_ticks = 99;
+        state.ask_price_in_ticks = 101;
+        state.cumulative_bid_fill_in_base_lots = 100;
+        // The first update has nothing to compare against
+        stats.record_position(&state, 1_000, 100);
+        assert_eq!(stats.net_inventory(), (0, 0));
+
+        state.cumulative_bid_fill_in_base_lots = 130;
+        stats.record_position(&state, 1_000, 100);
+        assert_eq!(stats.last_position_delta(), (30, -29_700));
+        state.cumulative_ask_fill_in_base_lots = 10;
+        stats.record_position(&state, 1_000, 100);
+        assert_eq!(stats.last_position_delta(), (-10, 10_100));
+        assert_eq!(stats.net_inventory(), (20, -19_600));
+        assert_eq!(
+            format_net_inventory(stats.net_inventory()),
+            "+20 base lots / -19600 quote atoms"
+        );
+    }
+}
@@ -0,0 +1,121 @@
+use crate::rpc::RateLimitedRpcClient;
+use anchor_lang::error;
+use anyhow::anyhow;
+use phoenix_onchain_mm::{
+    get_strategy_registry_address, PhoenixStrategyState, StrategyError, StrategyRegistry,
+    PHOENIX_STRATEGY_DISCRIMINATOR, STRATEGY_REGISTRY_DISCRIMINATOR,
+};
+use solana_sdk::pubkey::Pubkey;
+
+const DISCRIMINATOR_LEN: usize = 8;
+
+/// Reads a strategy from raw account data, checking the Anchor account discriminator.
+pub fn read_strategy_state(data: &[u8]) -> anyhow::Result<PhoenixStrategyState> {
+    let size = std::mem::size_of::<PhoenixStrategyState>();
+    if data.len() < DISCRIMINATOR_LEN + size {
+        return Err(anyhow!("Strategy account data is too small"));
+    }
+    if data[..DISCRIMINATOR_LEN] != PHOENIX_STRATEGY_DISCRIMINATOR {
+        return Err(error!(StrategyError::InvalidAccountDiscriminator).into());
+    }
+    bytemuck::try_from_bytes::<PhoenixStrategyState>(
+        &data[DISCRIMINATOR_LEN..DISCRIMINATOR_LEN + size],
+    )
+    .copied()
+    .map_err(|_| anyhow!("Failed to parse strategy state"))
+}
+
+/// Reads a strategy registry from raw account data, checking the Anchor account discriminator.
+pub fn read_strategy_registry(data: &[u8]) -> anyhow::Result<StrategyRegistry> {
+    let size = std::mem::size_of::<StrategyRegistry>();
+    if data.len() < DISCRIMINATOR_LEN + size {
+        return Err(anyhow!("Strategy registry account data is too small"));
+    }
+    if data[..DISCRIMINATOR_LEN] != STRATEGY_REGISTRY_DISCRIMINATOR {
+        return Err(error!(StrategyError::InvalidAccountDiscriminator).into());
+    }
+    bytemuck::try_from_bytes::<StrategyRegistry>(&data[DISCRIMINATOR_LEN..DISCRIMINATOR_LEN + size])
+        .copied()
+        .map_err(|_| anyhow!("Failed to parse strategy registry"))
+}
+
+/// Returns the addresses of the strategies recorded in the user's registry.
+pub async fn get_strategies_for_user(
+    client: &RateLimitedRpcClient,
+    user: &Pubkey,
+) -> anyhow::Result<Vec<Pubkey>> {
+    let registry_key = get_strategy_registry_address(user).0;
+    let data = client.get_account_data(&registry_key).await.map_err(|e| {
+        anyhow!(
+            "Failed to fetch the strategy registry {}: {}",
+            registry_key,
+            e
+        )
+    })?;
+    Ok(read_strategy_registry(&data)?.get_strategy_addresses(user))
+}
+
+/// Serializes a strategy into account data, prefixed with the Anchor account discriminator.
+#[allow(dead_code)]
+pub fn write_strategy_state(state: &PhoenixStrategyState) -> Vec<u8> {
+    let mut data = PHOENIX_STRATEGY_DISCRIMINATOR.to_vec();
+    data.extend_from_slice(bytemuck::bytes_of(state));
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strategy_state_round_trip() {
+        let mut state: PhoenixStrategyState = bytemuck::Zeroable::zeroed();
+        state.trader = Pubkey::new_unique();
+        state.market = Pubkey::new_unique();
+        state.bid_price_in_ticks = 1_000;
+        state.ask_price_in_ticks = 1_010;
+        state.quote_edge_in_bps = 5;
+        state.post_only = true;
+        state.strategy_id = 3;
+
+        let data = write_strategy_state(&state);
+        assert_eq!(
+            bytemuck::bytes_of(&read_strategy_state(&data).unwrap()),
+            bytemuck::bytes_of(&state)
+        );
+    }
+
+    #[test]
+    fn test_strategy_registry_round_trip() {
+        let mut registry: StrategyRegistry = bytemuck::Zeroable::zeroed();
+        let market = Pubkey::new_unique();
+        registry.register(market, 2).unwrap();
+
+        let mut data = STRATEGY_REGISTRY_DISCRIMINATOR.to_vec();
+        data.extend_from_slice(bytemuck::bytes_of(&registry));
+        let read = read_strategy_registry(&data).unwrap();
+        assert_eq!(read.entries(), registry.entries());
+        // A strategy account is not mistaken for a registry
+        let mut data = write_strategy_state(&bytemuck::Zeroable::zeroed());
+        data.resize(
+            DISCRIMINATOR_LEN + std::mem::size_of::<StrategyRegistry>(),
+            0,
+        );
+        assert!(read_strategy_registry(&data).is_err());
+    }
+
+    #[test]
+    fn test_rejects_wrong_discriminator() {
+        let state: PhoenixStrategyState = bytemuck::Zeroable::zeroed();
+        let mut data = write_strategy_state(&state);
+        data[0] ^= 1;
+        let err = match read_strategy_state(&data) {
+            Ok(_) => panic!("expected an error"),
+            Err(err) => err,
+        };
+        assert_eq!(
+            err.downcast::<anchor_lang::error::Error>().unwrap(),
+            error!(StrategyError::InvalidAccountDiscriminator)
+        );
+    }
+}
@@ -0,0 +1,360 @@
+use crate::market::PhoenixMarket;
+use crossterm::{
+    cursor::{Hide, MoveTo, Show},
+    event::{poll, read},
+    execute, queue,
+    style::Print,
+    terminal::{size, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use log::LevelFilter;
+use phoenix::program::MarketHeader;
+use phoenix::quantities::WrapperU64;
+use phoenix::state::Side;
+use phoenix_onchain_mm::{
+    base_lots_to_display, quote_atoms_to_usd, tick_to_price_usd, PhoenixStrategyState,
+    StrategyParams,
+};
+use std::collections::VecDeque;
+use std::io::Write;
+use std::time::Duration;
+
+/// Orders shown on each side of the book
+const BOOK_DEPTH: usize = 5;
+const MAX_FILL_EVENTS: usize = 20;
+/// Updates kept for the PnL chart, more than a terminal is wide
+const MAX_PNL_POINTS: usize = 500;
+
+/// One resting order in the book panel
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BookLevel {
+    pub price: f64,
+    pub size: f64,
+    pub is_ours: bool,
+}
+
+/// Contents of the dashboard, rendered into lines of text independently of the terminal.
+#[derive(Debug, Default)]
+pub struct DashboardView {
+    params: Vec<(String, String)>,
+    bids: Vec<BookLevel>,
+    asks: Vec<BookLevel>,
+    pnl_history: VecDeque<f64>,
+    fills: VecDeque<String>,
+}
+
+impl DashboardView {
+    /// Lists the params that are set, by their field names.
+    pub fn set_params(&mut self, params: &StrategyParams) {
+        self.params = match serde_json::to_value(params) {
+            Ok(serde_json::Value::Object(fields)) => fields
+                .into_iter()
+                .filter(|(_, value)| !value.is_null())
+                .map(|(name, value)| (name, value.to_string()))
+                .collect(),
+            _ => vec![],
+        };
+    }
+
+    /// Records the book, the session PnL and the fills since the previous update.
+    pub fn update(
+        &mut self,
+        state: &PhoenixStrategyState,
+        header: &MarketHeader,
+        market: &PhoenixMarket,
+        pnl_in_quote_atoms: i64,
+        (bid_fill, ask_fill): (u64, u64),
+        unix_timestamp: i64,
+    ) {
+        self.bids = get_book_levels(market, header, Side::Bid, state.bid_order_sequence_number);
+        self.asks = get_book_levels(market, header, Side::Ask, state.ask_order_sequence_number);
+        self.record_pnl(
+            quote_atoms_to_usd(pnl_in_quote_atoms.unsigned_abs(), header)
+                * pnl_in_quote_atoms.signum() as f64,
+        );
+        for (fill, action, price_in_ticks) in [
+            (bid_fill, "Bought", state.bid_price_in_ticks),
+            (ask_fill, "Sold", state.ask_price_in_ticks),
+        ] {
+            if fill > 0 {
+                self.record_fill(format!(
+                    "{} {} {:.4} @ {:.4}",
+                    format_time_of_day(unix_timestamp),
+                    action,
+                    base_lots_to_display(fill, header),
+                    tick_to_price_usd(price_in_ticks, header)
+                ));
+            }
+        }
+    }
+
+    fn record_pnl(&mut self, pnl: f64) {
+        if self.pnl_history.len() == MAX_PNL_POINTS {
+            self.pnl_history.pop_front();
+        }
+        self.pnl_history.push_back(pnl);
+    }
+
+    fn record_fill(&mut self, fill: String) {
+        if self.fills.len() == MAX_FILL_EVENTS {
+            self.fills.pop_front();
+        }
+        self.fills.push_back(fill);
+    }
+
+    /// Lays out the four panels in a grid filling `width` by `height` characters: the params and
+    /// the book on the left, the PnL chart and the fills on the right.
+    pub fn render(&self, width: usize, height: usize) -> Vec<String> {
+        let left_width = width / 2;
+        let right_width = width.saturating_sub(left_width + 1);
+        let top_height = height / 2;
+        let bottom_height = height - top_height;
+
+        let params = self
+            .params
+            .iter()
+            .map(|(name, value)| format!("{}: {}", name, value))
+            .collect::<Vec<_>>();
+        let mut book = self
+            .asks
+            .iter()
+            .rev()
+            .map(format_book_level)
+            .collect::<Vec<_>>();
+        book.push("-".repeat(left_width.min(28)));
+        book.extend(self.bids.iter().map(format_book_level));
+        // Newest fills first, so that they stay visible in a short panel
+        let fills = self.fills.iter().rev().cloned().collect::<Vec<_>>();
+
+        let left = [
+            panel("Strategy params", &params, left_width, top_height),
+            panel("Order book (* ours)", &book, left_width, bottom_height),
+        ]
+        .concat();
+        let right = [
+            panel(
+                "PnL",
+                &render_pnl_chart(&self.pnl_history, right_width, top_height.saturating_sub(1)),
+                right_width,
+                top_height,
+            ),
+            panel("Recent fills", &fills, right_width, bottom_height),
+        ]
+        .concat();
+        left.into_iter()
+            .zip(right)
+            .map(|(left, right)| format!("{}│{}", left, right))
+            .collect()
+    }
+}
+
+/// Terminal UI shown by `run --tui` in place of the log lines. The terminal is restored and
+/// logging resumed when it is dropped.
+pub struct Dashboard {
+    pub view: DashboardView,
+    ticks: tokio::time::Interval,
+    previous_log_level: LevelFilter,
+}
+
+impl Dashboard {
+    /// Switches the terminal to the dashboard, redrawing it at least every `refresh_interval`.
+    pub fn start(refresh_interval: Duration) -> anyhow::Result<Self> {
+        execute!(std::io::stdout(), EnterAlternateScreen, Hide)?;
+        let previous_log_level = log::max_level();
+        log::set_max_level(LevelFilter::Off);
+        let mut ticks = tokio::time::interval(refresh_interval);
+        ticks.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        Ok(Self {
+            view: DashboardView::default(),
+            ticks,
+            previous_log_level,
+        })
+    }
+
+    /// Waits for the next refresh and redraws.
+    pub async fn refresh(&mut self) -> anyhow::Result<()> {
+        self.ticks.tick().await;
+        self.draw()
+    }
+
+    /// Redraws the dashboard at the current size of the terminal.
+    pub fn draw(&mut self) -> anyhow::Result<()> {
+        // The size is read on every draw, so resize and other events only need to be drained
+        while poll(Duration::ZERO).unwrap_or(false) {
+            if read().is_err() {
+                break;
+            }
+        }
+        let (width, height) = size()?;
+        let mut stdout = std::io::stdout();
+        queue!(stdout, Clear(ClearType::All))?;
+        for (row, line) in self
+            .view
+            .render(width as usize, height as usize)
+            .into_iter()
+            .enumerate()
+        {
+            queue!(stdout, MoveTo(0, row as u16), Print(line))?;
+        }
+        stdout.flush()?;
+        Ok(())
+    }
+}
+
+impl Drop for Dashboard {
+    fn drop(&mut self) {
+        execute!(std::io::stdout(), Show, LeaveAlternateScreen).ok();
+        log::set_max_level(self.previous_log_level);
+    }
+}
+
+fn get_book_levels(
+    market: &PhoenixMarket,
+    header: &MarketHeader,
+    side: Side,
+    our_order_sequence_number: u64,
+) -> Vec<BookLevel> {
+    market
+        .get_book(side)
+        .iter()
+        .take(BOOK_DEPTH)
+        .map(|(order_id, order)| BookLevel {
+            price: tick_to_price_usd(order_id.price_in_ticks.as_u64(), header),
+            size: base_lots_to_display(order.num_base_lots.as_u64(), header),
+            is_ours: order_id.order_sequence_number == our_order_sequence_number,
+        })
+        .collect()
+}
+
+fn format_book_level(level: &BookLevel) -> String {
+    format!(
+        "{:>12.4} {:>12.4} {}",
+        level.price,
+        level.size,
+        if level.is_ours { "*" } else { "" }
+    )
+}
+
+/// UTC time of day, e.g. 13:04:05
+fn format_time_of_day(unix_timestamp: i64) -> String {
+    let seconds = unix_timestamp.rem_euclid(86_400);
+    format!(
+        "{:02}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+/// A title row followed by `lines`, each cut or padded to exactly `width` characters, and blank
+/// rows up to `height`.
+fn panel(title: &str, lines: &[String], width: usize, height: usize) -> Vec<String> {
+    let fit = |line: &str| {
+        let line = line.chars().take(width).collect::<String>();
+        let padding = width - line.chars().count();
+        line + &" ".repeat(padding)
+    };
+    let title = format!("─ {} {}", title, "─".repeat(width));
+    std::iter::once(fit(&title))
+        .chain(lines.iter().map(|line| fit(line)))
+        .chain(std::iter::repeat(fit("")))
+        .take(height)
+        .collect()
+}
+
+/// Bar chart of the most recent PnL values that fit in `width`, with a line of labels on top. The
+/// bars rise from the lowest value or zero, whichever is lower.
+fn render_pnl_chart(history: &VecDeque<f64>, width: usize, height: usize) -> Vec<String> {
+    let Some(&last) = history.back() else {
+        return vec!["Waiting for the first update".to_string()];
+    };
+    let points = history
+        .iter()
+        .skip(history.len().saturating_sub(width))
+        .copied()
+        .collect::<Vec<_>>();
+    let low = points.iter().copied().fold(0.0, f64::min);
+    let high = points.iter().copied().fold(0.0, f64::max);
+    let mut lines = vec![format!("Last {:.4}  min {:.4}  max {:.4}", last, low, high)];
+    let rows = height.saturating_sub(1);
+    let bar_heights = points
+        .iter()
+        .map(|point| {
+            if high > low {
+                ((point - low) / (high - low) * rows as f64).round() as usize
+            } else {
+                0
+            }
+        })
+        .collect::<Vec<_>>();
+    for row in 0..rows {
+        lines.push(
+            bar_heights
+                .iter()
+                .map(|&bar| if bar >= rows - row { '█' } else { ' ' })
+                .collect(),
+        );
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_fills_the_terminal() {
+        let mut view = DashboardView::default();
+        view.set_params(&StrategyParams {
+            quote_edge_in_bps: Some(25),
+            ..StrategyParams::default()
+        });
+        view.record_fill("12:00:00 Bought 1.0000 @ 20.0000".to_string());
+        let lines = view.render(80, 24);
+        assert_eq!(lines.len(), 24);
+        assert!(lines.iter().all(|line| line.chars().count() == 80));
+        assert!(lines[0].starts_with("─ Strategy params"));
+        assert!(lines[1].starts_with("quote_edge_in_bps: 25"));
+        // Unset params are left out
+        assert!(!lines[2].contains("null"));
+        assert!(lines[12].contains("─ Recent fills"));
+        assert!(lines[13].contains("Bought 1.0000 @ 20.0000"));
+    }
+
+    #[test]
+    fn test_book_panel_marks_our_orders() {
+        let view = DashboardView {
+            asks: vec![
+                BookLevel {
+                    price: 20.02,
+                    size: 1.0,
+                    is_ours: true,
+                },
+                BookLevel {
+                    price: 20.05,
+                    size: 3.0,
+                    is_ours: false,
+                },
+            ],
+            ..DashboardView::default()
+        };
+        let lines = view.render(80, 12);
+        // The best ask is at the bottom, next to the bids
+        assert!(lines[7].contains("20.0500") && !lines[7].contains('*'));
+        assert!(lines[8].contains("20.0200") && lines[8].contains('*'));
+    }
+
+    #[test]
+    fn test_pnl_chart_scales_to_the_range() {
+        let history = VecDeque::from(vec![0.0, 1.0, 2.0, -2.0]);
+        let chart = render_pnl_chart(&history, 10, 5);
+        assert_eq!(chart[0], "Last -2.0000  min -2.0000  max 2.0000");
+        assert_eq!(&chart[1..], &["  █ ", " ██ ", "███ ", "███ "]);
+        // Only the most recent points that fit are shown
+        assert_eq!(render_pnl_chart(&history, 2, 3)[1..], ["█ ", "█ "]);
+    }
+
+    #[test]
+    fn test_time_of_day_is_utc() {
+        assert_eq!(format_time_of_day(1_700_000_000), "22:13:20");
+    }
+}
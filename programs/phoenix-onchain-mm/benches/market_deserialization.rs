@@ -0,0 +1,104 @@
+use anchor_lang::{__private::bytemuck, prelude::Pubkey};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use phoenix::program::{load_with_dispatch, MarketSizeParams};
+use phoenix::quantities::{BaseLots, WrapperU64};
+use phoenix::state::markets::{FIFOMarket, FIFOOrderId, FIFORestingOrder};
+use phoenix::state::TraderState;
+use phoenix_onchain_mm::get_best_bid_and_ask;
+use sokoban::NodeAllocatorMap;
+
+const BOOK_SIZE: usize = 4096;
+const NUM_SEATS: usize = 8193;
+const NUM_TRADERS: usize = 64;
+const MID_PRICE_IN_TICKS: u64 = 100_000;
+
+type Dex = FIFOMarket<Pubkey, BOOK_SIZE, BOOK_SIZE, NUM_SEATS>;
+
+const MARKET_SIZE_PARAMS: MarketSizeParams = MarketSizeParams {
+    bids_size: BOOK_SIZE as u64,
+    asks_size: BOOK_SIZE as u64,
+    num_seats: NUM_SEATS as u64,
+};
+
+/// Builds the raw bytes of a market with `orders_per_side` resting orders on each side,
+/// spread across `NUM_TRADERS` traders. The first trader owns the top of both books.
+fn build_market(orders_per_side: usize) -> (Vec<u128>, Vec<Pubkey>) {
+    // Back the buffer with u128s so the market is correctly aligned
+    let mut data = vec![0u128; std::mem::size_of::<Dex>() / 16 + 1];
+    let bytes: &mut [u8] = bytemuck::cast_slice_mut(&mut data);
+    let market: &mut Dex = bytemuck::from_bytes_mut(&mut bytes[..std::mem::size_of::<Dex>()]);
+    market.bids.initialize();
+    market.asks.initialize();
+    market.traders.initialize();
+
+    let traders = (0..NUM_TRADERS)
+        .map(|_| Pubkey::new_unique())
+        .collect::<Vec<_>>();
+    for trader in traders.iter() {
+        market.traders.insert(*trader, TraderState::default());
+    }
+
+    for i in 0..orders_per_side {
+        let trader_index = market.traders.get_addr(&traders[i % NUM_TRADERS]) as u64;
+        let sequence_number = 2 * i as u64 + 1;
+        market.bids.insert(
+            FIFOOrderId::new_from_untyped(MID_PRICE_IN_TICKS - 1 - i as u64, !sequence_number),
+            FIFORestingOrder::new_default(trader_index, BaseLots::new(10)),
+        );
+        market.asks.insert(
+            FIFOOrderId::new_from_untyped(MID_PRICE_IN_TICKS + 1 + i as u64, sequence_number + 1),
+            FIFORestingOrder::new_default(trader_index, BaseLots::new(10)),
+        );
+    }
+    (data, traders)
+}
+
+fn market_bytes(data: &[u128]) -> &[u8] {
+    &bytemuck::cast_slice(data)[..std::mem::size_of::<Dex>()]
+}
+
+fn benchmark_market_deserialization(c: &mut Criterion) {
+    let mut group = c.benchmark_group("load_with_dispatch");
+    for orders_per_side in [16, 256, 1024, BOOK_SIZE] {
+        let (data, _) = build_market(orders_per_side);
+        let bytes = market_bytes(&data);
+        group.throughput(Throughput::Bytes(bytes.len() as u64));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(orders_per_side),
+            &bytes,
+            |b, bytes| {
+                b.iter(|| {
+                    let market = load_with_dispatch(&MARKET_SIZE_PARAMS, black_box(bytes))
+                        .unwrap()
+                        .inner;
+                    black_box(market.get_sequence_number())
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+fn benchmark_get_best_bid_and_ask(c: &mut Criterion) {
+    let mut group = c.benchmark_group("get_best_bid_and_ask");
+    for orders_per_side in [16, 256, 1024, BOOK_SIZE] {
+        let (data, traders) = build_market(orders_per_side);
+        let market = load_with_dispatch(&MARKET_SIZE_PARAMS, market_bytes(&data))
+            .unwrap()
+            .inner;
+        let trader_index = market.get_trader_index(&traders[0]).unwrap() as u64;
+        group.bench_with_input(
+            BenchmarkId::from_parameter(orders_per_side),
+            &trader_index,
+            |b, &trader_index| b.iter(|| get_best_bid_and_ask(market, black_box(trader_index))),
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    benchmark_market_deserialization,
+    benchmark_get_best_bid_and_ask
+);
+criterion_main!(benches);
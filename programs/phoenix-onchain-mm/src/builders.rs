@@ -0,0 +1,207 @@
+use crate::{
+    ClientOrderIdMode, OrderParams, PriceImprovementBehavior, PricePrecisionMode,
+    SizeAdjustmentCurve, SizingMode, StrategyError, StrategyParams,
+};
+use anchor_lang::prelude::*;
+
+/// Fluent builder for `StrategyParams`.
+/// The edge, size and price improvement behavior are required, matching what `initialize` expects.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct StrategyParamsBuilder {
+    params: StrategyParams,
+}
+
+impl StrategyParamsBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn edge_in_bps(mut self, quote_edge_in_bps: u64) -> Self {
+        self.params.quote_edge_in_bps = Some(quote_edge_in_bps);
+        self
+    }
+
+    pub fn size_in_quote_atoms(mut self, quote_size_in_quote_atoms: u64) -> Self {
+        self.params.quote_size_in_quote_atoms = Some(quote_size_in_quote_atoms);
+        self
+    }
+
+    pub fn price_improvement(mut self, behavior: PriceImprovementBehavior) -> Self {
+        self.params.price_improvement_behavior = Some(behavior);
+        self
+    }
+
+    pub fn post_only(mut self, post_only: bool) -> Self {
+        self.params.post_only = Some(post_only);
+        self
+    }
+
+    pub fn use_only_deposited_funds(mut self, use_only_deposited_funds: bool) -> Self {
+        self.params.use_only_deposited_funds = Some(use_only_deposited_funds);
+        self
+    }
+
+    pub fn size_decay_factor_in_bps(mut self, size_decay_factor_in_bps: u64) -> Self {
+        self.params.size_decay_factor_in_bps = Some(size_decay_factor_in_bps);
+        self
+    }
+
+    pub fn min_spread_in_bps(mut self, min_spread_in_bps: u64) -> Self {
+        self.params.min_spread_in_bps = Some(min_spread_in_bps);
+        self
+    }
+
+    pub fn time_since_last_fill_decay_factor_in_bps(mut self, decay_factor_in_bps: u64) -> Self {
+        self.params.time_since_last_fill_decay_factor_in_bps = Some(decay_factor_in_bps);
+        self
+    }
+
+    pub fn max_edge_in_bps(mut self, max_edge_in_bps: u64) -> Self {
+        self.params.max_edge_in_bps = Some(max_edge_in_bps);
+        self
+    }
+
+    pub fn volatility_multiplier_in_bps(mut self, volatility_multiplier_in_bps: u64) -> Self {
+        self.params.volatility_multiplier_in_bps = Some(volatility_multiplier_in_bps);
+        self
+    }
+
+    pub fn min_warmup_updates(mut self, min_warmup_updates: u64) -> Self {
+        self.params.min_warmup_updates = Some(min_warmup_updates);
+        self
+    }
+
+    pub fn max_taker_exposure_in_quote_atoms(mut self, max_taker_exposure: u64) -> Self {
+        self.params.max_taker_exposure_in_quote_atoms = Some(max_taker_exposure);
+        self
+    }
+
+    pub fn max_slot_age_for_price(mut self, max_slot_age_for_price: u64) -> Self {
+        self.params.max_slot_age_for_price = Some(max_slot_age_for_price);
+        self
+    }
+
+    pub fn client_metadata(mut self, client_metadata: [u8; 32]) -> Self {
+        self.params.client_metadata = Some(client_metadata);
+        self
+    }
+
+    pub fn client_order_id_mode(mut self, mode: ClientOrderIdMode) -> Self {
+        self.params.client_order_id_mode = Some(mode);
+        self
+    }
+
+    pub fn sizing_mode(mut self, sizing_mode: SizingMode) -> Self {
+        self.params.sizing_mode = Some(sizing_mode);
+        self
+    }
+
+    pub fn price_precision_mode(mut self, price_precision_mode: PricePrecisionMode) -> Self {
+        self.params.price_precision_mode = Some(price_precision_mode);
+        self
+    }
+
+    pub fn order_refresh_interval_in_seconds(mut self, interval_in_seconds: u64) -> Self {
+        self.params.order_refresh_interval_in_seconds = Some(interval_in_seconds);
+        self
+    }
+
+    pub fn taker_fee_in_bps(mut self, taker_fee_in_bps: u64) -> Self {
+        self.params.taker_fee_in_bps = Some(taker_fee_in_bps);
+        self
+    }
+
+    pub fn maker_rebate_in_bps(mut self, maker_rebate_in_bps: u64) -> Self {
+        self.params.maker_rebate_in_bps = Some(maker_rebate_in_bps);
+        self
+    }
+
+    pub fn min_external_orders_per_side(mut self, min_external_orders_per_side: u64) -> Self {
+        self.params.min_external_orders_per_side = Some(min_external_orders_per_side);
+        self
+    }
+
+    pub fn min_deposited_quote_atoms(mut self, min_deposited_quote_atoms: u64) -> Self {
+        self.params.min_deposited_quote_atoms = Some(min_deposited_quote_atoms);
+        self
+    }
+
+    pub fn min_deposited_base_lots(mut self, min_deposited_base_lots: u64) -> Self {
+        self.params.min_deposited_base_lots = Some(min_deposited_base_lots);
+        self
+    }
+
+    pub fn volatility_oracle_pubkey(mut self, volatility_oracle_pubkey: Pubkey) -> Self {
+        self.params.volatility_oracle_pubkey = Some(volatility_oracle_pubkey);
+        self
+    }
+
+    pub fn max_oracle_staleness_in_slots(mut self, max_oracle_staleness_in_slots: u64) -> Self {
+        self.params.max_oracle_staleness_in_slots = Some(max_oracle_staleness_in_slots);
+        self
+    }
+
+    pub fn quote_refresh_interval_in_seconds(
+        mut self,
+        quote_refresh_interval_in_seconds: u64,
+    ) -> Self {
+        self.params.quote_refresh_interval_in_seconds = Some(quote_refresh_interval_in_seconds);
+        self
+    }
+
+    pub fn size_adjustment_curve(mut self, curve: SizeAdjustmentCurve) -> Self {
+        self.params.size_adjustment_curve = Some(curve);
+        self
+    }
+
+    pub fn build(self) -> Result<StrategyParams> {
+        self.params.validate(true)?;
+        Ok(self.params)
+    }
+}
+
+/// Fluent builder for `OrderParams`. Both the fair price and the strategy params are required.
+/// The submitted slot defaults to 0, which skips the on-chain freshness check.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct OrderParamsBuilder {
+    fair_price_in_quote_atoms_per_raw_base_unit: Option<u64>,
+    strategy_params: Option<StrategyParams>,
+    submitted_slot: u64,
+}
+
+impl OrderParamsBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn fair_price_in_quote_atoms_per_raw_base_unit(mut self, fair_price: u64) -> Self {
+        self.fair_price_in_quote_atoms_per_raw_base_unit = Some(fair_price);
+        self
+    }
+
+    pub fn strategy_params(mut self, strategy_params: StrategyParams) -> Self {
+        self.strategy_params = Some(strategy_params);
+        self
+    }
+
+    pub fn submitted_slot(mut self, submitted_slot: u64) -> Self {
+        self.submitted_slot = submitted_slot;
+        self
+    }
+
+    pub fn build(self) -> Result<OrderParams> {
+        match (
+            self.fair_price_in_quote_atoms_per_raw_base_unit,
+            self.strategy_params,
+        ) {
+            (Some(fair_price_in_quote_atoms_per_raw_base_unit), Some(strategy_params)) => {
+                Ok(OrderParams {
+                    fair_price_in_quote_atoms_per_raw_base_unit,
+                    strategy_params,
+                    submitted_slot: self.submitted_slot,
+                })
+            }
+            _ => err!(StrategyError::InvalidStrategyParams),
+        }
+    }
+}
@@ -0,0 +1,66 @@
+use phoenix::program::MarketHeader;
+use phoenix::quantities::WrapperU64;
+
+fn quote_atoms_per_unit(header: &MarketHeader) -> f64 {
+    10f64.powi(header.quote_params.decimals as i32)
+}
+
+/// Converts a price in ticks to quote units per base unit, e.g. USD per SOL.
+pub fn tick_to_price_usd(ticks: u64, header: &MarketHeader) -> f64 {
+    ticks as f64 * header.get_tick_size_in_quote_atoms_per_base_unit().as_u64() as f64
+        / header.raw_base_units_per_base_unit as f64
+        / quote_atoms_per_unit(header)
+}
+
+/// Converts a price in quote units per base unit to the nearest tick.
+pub fn price_usd_to_ticks(price_usd: f64, header: &MarketHeader) -> u64 {
+    (price_usd * quote_atoms_per_unit(header) * header.raw_base_units_per_base_unit as f64
+        / header.get_tick_size_in_quote_atoms_per_base_unit().as_u64() as f64)
+        .round() as u64
+}
+
+/// Converts a price in quote units per base unit to the fair price submitted to `update_quotes`,
+/// in quote atoms per raw base unit.
+pub fn price_usd_to_fair_price(price_usd: f64, header: &MarketHeader) -> u64 {
+    (price_usd * quote_atoms_per_unit(header) / header.raw_base_units_per_base_unit.max(1) as f64)
+        .round() as u64
+}
+
+/// Converts quote atoms to quote units, e.g. USDC atoms to USD.
+pub fn quote_atoms_to_usd(atoms: u64, header: &MarketHeader) -> f64 {
+    atoms as f64 / quote_atoms_per_unit(header)
+}
+
+/// Converts base lots to base units, e.g. for logging. Not intended for on-chain arithmetic.
+pub fn base_lots_to_display(lots: u64, header: &MarketHeader) -> f64 {
+    (lots as f64 * header.get_base_lot_size().as_u64() as f64)
+        / 10f64.powi(header.base_params.decimals as i32)
+}
+
+/// Returns the smallest total spread, in basis points, that covers the fees of a round trip: the
+/// taker fee net of the maker rebate, paid once per side.
+pub fn compute_break_even_spread_in_bps(taker_fee_bps: u64, maker_rebate_bps: u64) -> u64 {
+    taker_fee_bps
+        .saturating_sub(maker_rebate_bps)
+        .saturating_mul(2)
+}
+
+/// Returns the spread between a bid and an ask in basis points of the fair price, rounded down.
+/// A fair price of 0 has no meaningful spread and returns 0.
+pub fn get_spread_in_bps(bid_price_ticks: u64, ask_price_ticks: u64, fair_price_ticks: u64) -> u64 {
+    (ask_price_ticks.saturating_sub(bid_price_ticks) as u128 * 10_000)
+        .checked_div(fair_price_ticks as u128)
+        .map_or(0, |spread| spread.min(u64::MAX as u128) as u64)
+}
+
+/// Returns true if the spread between the quotes is wider than the break-even spread for the
+/// taker fee. Phoenix pays no maker rebate.
+pub fn is_spread_profitable(
+    bid_price_ticks: u64,
+    ask_price_ticks: u64,
+    fair_price_ticks: u64,
+    taker_fee_bps: u64,
+) -> bool {
+    get_spread_in_bps(bid_price_ticks, ask_price_ticks, fair_price_ticks)
+        > compute_break_even_spread_in_bps(taker_fee_bps, 0)
+}
@@ -5,16 +5,26 @@ use anchor_lang::{
 };
 use phoenix::program::{
     new_order::{CondensedOrder, MultipleOrderPacket},
+    status::MarketStatus,
     CancelMultipleOrdersByIdParams, CancelOrderParams, MarketHeader,
 };
 use phoenix::{
     quantities::WrapperU64,
     state::{
         markets::{FIFOOrderId, FIFORestingOrder, Market},
-        OrderPacket, Side,
+        OrderPacket, SelfTradeBehavior, Side,
     },
 };
 
+pub mod builders;
+pub use builders::*;
+pub mod conversions;
+pub use conversions::*;
+pub mod migration;
+pub use migration::*;
+pub mod pnl;
+pub use pnl::*;
+
 declare_id!("MM1BW8uAmQ1zXP8mi8izfGQfjB1ASZhh93Tteo9LUfW");
 
 #[derive(Clone)]
@@ -27,6 +37,54 @@ impl anchor_lang::Id for PhoenixV1 {
 }
 pub const PHOENIX_MARKET_DISCRIMINANT: u64 = 8167313896524341111;
 
+/// Anchor account discriminator of `PhoenixStrategyState`, the first 8 bytes of
+/// `sha256("account:PhoenixStrategyState")`.
+pub const PHOENIX_STRATEGY_DISCRIMINATOR: [u8; 8] = [130, 177, 15, 192, 245, 30, 66, 251];
+
+/// Anchor account discriminator of `StrategyRegistry`, the first 8 bytes of
+/// `sha256("account:StrategyRegistry")`.
+pub const STRATEGY_REGISTRY_DISCRIMINATOR: [u8; 8] = [69, 55, 73, 162, 246, 33, 0, 85];
+
+/// Maximum number of strategies recorded in a user's `StrategyRegistry`
+pub const MAX_REGISTRY_ENTRIES: usize = 32;
+
+/// Returns the seed used to distinguish multiple strategies for the same user and market.
+/// Strategy 0 uses an empty seed so that it matches the addresses derived before strategy IDs existed.
+pub fn get_strategy_id_seed(strategy_id: &u8) -> &[u8] {
+    if *strategy_id == 0 {
+        &[]
+    } else {
+        std::slice::from_ref(strategy_id)
+    }
+}
+
+/// Derives the address of the strategy account for the given user and market.
+pub fn get_strategy_address(user: &Pubkey, market: &Pubkey) -> (Pubkey, u8) {
+    get_strategy_address_with_id(user, market, 0)
+}
+
+/// Derives the address of the strategy account for the given user, market and strategy ID.
+pub fn get_strategy_address_with_id(
+    user: &Pubkey,
+    market: &Pubkey,
+    strategy_id: u8,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            b"phoenix".as_ref(),
+            user.as_ref(),
+            market.as_ref(),
+            get_strategy_id_seed(&strategy_id),
+        ],
+        &crate::id(),
+    )
+}
+
+/// Derives the address of the registry listing the user's strategies.
+pub fn get_strategy_registry_address(user: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"registry".as_ref(), user.as_ref()], &crate::id())
+}
+
 #[derive(AnchorDeserialize, AnchorSerialize, Clone, Copy)]
 struct DeserializedFIFOOrderId {
     pub price_in_ticks: u64,
@@ -53,26 +111,253 @@ fn parse_order_ids_from_return_data(order_ids: &mut Vec<FIFOOrderId>) -> Result<
     Ok(())
 }
 
+/// Returns the trader's free `(base_lots, quote_lots)` in the market, or zeros if the trader has no seat.
+pub fn get_inventory_balance(
+    market: &dyn Market<Pubkey, FIFOOrderId, FIFORestingOrder, OrderPacket>,
+    trader: &Pubkey,
+) -> (u64, u64) {
+    market
+        .get_trader_state(trader)
+        .map(|state| {
+            (
+                state.base_lots_free.as_u64(),
+                state.quote_lots_free.as_u64(),
+            )
+        })
+        .unwrap_or((0, 0))
+}
+
+/// Returns the immediate-or-cancel order that moves a position of `current_base_lots` to
+/// `target_base_lots` as `(side, limit_price_in_ticks, size_in_base_lots)`, or None if the
+/// position is already on target. The limit price is the best opposing price, worsened by at
+/// most `max_slippage_in_ticks`.
+pub fn get_rebalance_order(
+    current_base_lots: u64,
+    target_base_lots: i64,
+    best_bid: u64,
+    best_ask: u64,
+    max_slippage_in_ticks: u64,
+) -> Result<Option<(Side, u64, u64)>> {
+    let excess = current_base_lots as i128 - target_base_lots as i128;
+    if excess == 0 {
+        return Ok(None);
+    }
+    let size_in_base_lots = excess.unsigned_abs().min(u64::MAX as u128) as u64;
+    if excess > 0 {
+        require!(best_bid > 0, MarketError::NoLiquidity);
+        Ok(Some((
+            Side::Ask,
+            best_bid.saturating_sub(max_slippage_in_ticks).max(1),
+            size_in_base_lots,
+        )))
+    } else {
+        require!(best_ask < u64::MAX, MarketError::NoLiquidity);
+        Ok(Some((
+            Side::Bid,
+            best_ask.saturating_add(max_slippage_in_ticks),
+            size_in_base_lots,
+        )))
+    }
+}
+
+/// Returns true if the trader's free funds in the market, plus the funds released by cancelling
+/// `orders_to_cancel`, cover a bid of `bid_size_in_base_lots` and an ask of `ask_size_in_base_lots`.
+fn check_balances(
+    market: &dyn Market<Pubkey, FIFOOrderId, FIFORestingOrder, OrderPacket>,
+    trader: &Pubkey,
+    orders_to_cancel: &[FIFOOrderId],
+    bid_size_in_base_lots: u64,
+    bid_price_in_ticks: u64,
+    ask_size_in_base_lots: u64,
+) -> bool {
+    let quote_lots_for = |price_in_ticks: u64, size_in_base_lots: u64| {
+        price_in_ticks * market.get_tick_size().as_u64() * size_in_base_lots
+            / market.get_base_lots_per_base_unit().as_u64()
+    };
+    let (mut free_base_lots, mut free_quote_lots) = get_inventory_balance(market, trader);
+    for order_id in orders_to_cancel {
+        let side = Side::from_order_sequence_number(order_id.order_sequence_number);
+        if let Some(resting_order) = market.get_book(side).get(order_id) {
+            match side {
+                Side::Bid => {
+                    free_quote_lots += quote_lots_for(
+                        order_id.price_in_ticks.as_u64(),
+                        resting_order.num_base_lots.as_u64(),
+                    )
+                }
+                Side::Ask => free_base_lots += resting_order.num_base_lots.as_u64(),
+            }
+        }
+    }
+    free_quote_lots >= quote_lots_for(bid_price_in_ticks, bid_size_in_base_lots)
+        && free_base_lots >= ask_size_in_base_lots
+}
+
+fn cancel_orders_by_id<'info>(
+    phoenix_program: &AccountInfo<'info>,
+    log_authority: &AccountInfo<'info>,
+    user: &AccountInfo<'info>,
+    market: &AccountInfo<'info>,
+    order_ids: &[FIFOOrderId],
+) -> Result<()> {
+    invoke(
+        &phoenix::program::create_cancel_multiple_orders_by_id_with_free_funds_instruction(
+            market.key,
+            user.key,
+            &CancelMultipleOrdersByIdParams {
+                orders: order_ids
+                    .iter()
+                    .map(|o_id| CancelOrderParams {
+                        order_sequence_number: o_id.order_sequence_number,
+                        price_in_ticks: o_id.price_in_ticks.as_u64(),
+                        side: Side::from_order_sequence_number(o_id.order_sequence_number),
+                    })
+                    .collect::<Vec<_>>(),
+            },
+        ),
+        &[
+            phoenix_program.clone(),
+            log_authority.clone(),
+            user.clone(),
+            market.clone(),
+        ],
+    )?;
+    Ok(())
+}
+
+/// Cancels the strategy's live orders and forgets them. Orders that have already been filled are
+/// skipped by Phoenix.
+fn cancel_strategy_orders<'info>(
+    phoenix_strategy: &mut PhoenixStrategyState,
+    phoenix_program: &AccountInfo<'info>,
+    log_authority: &AccountInfo<'info>,
+    user: &AccountInfo<'info>,
+    market: &AccountInfo<'info>,
+) -> Result<()> {
+    let mut orders_to_cancel = vec![];
+    if phoenix_strategy.has_live_bid() {
+        orders_to_cancel.push(FIFOOrderId::new_from_untyped(
+            phoenix_strategy.bid_price_in_ticks,
+            phoenix_strategy.bid_order_sequence_number,
+        ));
+    }
+    if phoenix_strategy.has_live_ask() {
+        orders_to_cancel.push(FIFOOrderId::new_from_untyped(
+            phoenix_strategy.ask_price_in_ticks,
+            phoenix_strategy.ask_order_sequence_number,
+        ));
+    }
+    if orders_to_cancel.is_empty() {
+        msg!("No orders to cancel");
+        return Ok(());
+    }
+
+    cancel_orders_by_id(
+        phoenix_program,
+        log_authority,
+        user,
+        market,
+        &orders_to_cancel,
+    )?;
+
+    phoenix_strategy.forget_order(Side::Bid);
+    phoenix_strategy.forget_order(Side::Ask);
+    // Force the next update to requote
+    phoenix_strategy.last_fair_price_in_quote_atoms_per_raw_base_unit = 0;
+    Ok(())
+}
+
+/// Withdraws the trader's free funds from the market to their token accounts, all of them for
+/// amounts that are None. Funds locked in open orders stay in the market.
+fn withdraw_free_funds(
+    accounts: &Withdraw,
+    strategy_id: u8,
+    base_lots_to_withdraw: Option<u64>,
+    quote_lots_to_withdraw: Option<u64>,
+) -> Result<()> {
+    let Withdraw {
+        phoenix_strategy,
+        user,
+        phoenix_program,
+        log_authority,
+        market,
+        base_account,
+        quote_account,
+        base_vault,
+        quote_vault,
+        token_program,
+    } = accounts;
+    {
+        let phoenix_strategy = phoenix_strategy.load()?;
+        require!(
+            phoenix_strategy.strategy_id == strategy_id && phoenix_strategy.trader == user.key(),
+            StrategyError::InvalidStrategyParams
+        );
+    }
+    let header = load_header(market)?;
+    invoke(
+        &phoenix::program::create_withdraw_funds_with_custom_amounts_instruction_with_custom_token_accounts(
+            &market.key(),
+            &user.key(),
+            &base_account.key(),
+            &quote_account.key(),
+            &header.base_params.mint_key,
+            &header.quote_params.mint_key,
+            &phoenix::program::withdraw::WithdrawParams {
+                quote_lots_to_withdraw,
+                base_lots_to_withdraw,
+            },
+        ),
+        &[
+            phoenix_program.to_account_info(),
+            log_authority.to_account_info(),
+            market.to_account_info(),
+            user.to_account_info(),
+            base_account.to_account_info(),
+            quote_account.to_account_info(),
+            base_vault.to_account_info(),
+            quote_vault.to_account_info(),
+            token_program.to_account_info(),
+        ],
+    )?;
+    Ok(())
+}
+
 fn load_header(info: &AccountInfo) -> Result<MarketHeader> {
     require!(
         info.owner == &phoenix::id(),
-        StrategyError::InvalidPhoenixProgram
+        MarketError::InvalidPhoenixProgram
     );
     let data = info.data.borrow();
     let header =
         bytemuck::try_from_bytes::<MarketHeader>(&data[..std::mem::size_of::<MarketHeader>()])
             .map_err(|_| {
                 msg!("Failed to parse Phoenix market header");
-                StrategyError::FailedToDeserializePhoenixMarket
+                MarketError::FailedToDeserializePhoenixMarket
             })?;
     require!(
         header.discriminant == PHOENIX_MARKET_DISCRIMINANT,
-        StrategyError::InvalidPhoenixProgram,
+        MarketError::InvalidPhoenixProgram,
     );
     Ok(*header)
 }
 
-fn get_best_bid_and_ask(
+/// Returns an error unless the market accepts new orders, i.e. it is Active or PostOnly.
+/// Paused, Closed and Tombstoned markets only accept reductions and withdrawals.
+pub fn check_market_status(header: &MarketHeader) -> Result<()> {
+    if header.status == MarketStatus::Active as u64
+        || header.status == MarketStatus::PostOnly as u64
+    {
+        Ok(())
+    } else {
+        msg!("Market is not accepting orders, status: {}", header.status);
+        err!(MarketError::MarketNotActive)
+    }
+}
+
+/// Returns the best bid and ask prices that are not placed by the trader.
+/// If a side of the book has no external orders, the bid falls back to 0 and the ask falls back to u64::MAX.
+pub fn get_best_bid_and_ask(
     market: &dyn Market<Pubkey, FIFOOrderId, FIFORestingOrder, OrderPacket>,
     trader_index: u64,
 ) -> (u64, u64) {
@@ -81,7 +366,7 @@ fn get_best_bid_and_ask(
         .iter()
         .find(|(_, o)| o.trader_index != trader_index)
         .map(|(o, _)| o.price_in_ticks.as_u64())
-        .unwrap_or_else(|| 1);
+        .unwrap_or_else(|| 0);
     let best_ask = market
         .get_book(Side::Ask)
         .iter()
@@ -91,31 +376,537 @@ fn get_best_bid_and_ask(
     (best_bid, best_ask)
 }
 
-fn get_bid_price_in_ticks(
+/// Returns the order that was placed on `side` from `placed_order_ids` as it rests in the book.
+/// An order that was rejected, e.g. a post-only order that would have crossed, or that was filled
+/// in full when it was placed is not in the book and returns `StrategyError::OrderNotPlaced`.
+pub fn validate_order_was_placed(
+    market: &dyn Market<Pubkey, FIFOOrderId, FIFORestingOrder, OrderPacket>,
+    side: Side,
+    placed_order_ids: &[FIFOOrderId],
+) -> Result<(FIFOOrderId, FIFORestingOrder)> {
+    placed_order_ids
+        .iter()
+        .filter(|order_id| Side::from_order_sequence_number(order_id.order_sequence_number) == side)
+        .find_map(|order_id| {
+            market
+                .get_book(side)
+                .get(order_id)
+                .map(|order| (*order_id, *order))
+        })
+        .ok_or_else(|| error!(StrategyError::OrderNotPlaced))
+}
+
+/// Returns the number of resting bids and asks that are not placed by the trader.
+pub fn count_external_orders(
+    market: &dyn Market<Pubkey, FIFOOrderId, FIFORestingOrder, OrderPacket>,
+    trader_index: u64,
+) -> (u64, u64) {
+    let count = |side: Side| {
+        market
+            .get_book(side)
+            .iter()
+            .filter(|(_, o)| o.trader_index != trader_index)
+            .count() as u64
+    };
+    (count(Side::Bid), count(Side::Ask))
+}
+
+/// Number of price levels summed into the depth of each side in `MarketStats`.
+pub const MARKET_STATS_DEPTH_LEVELS: usize = 5;
+
+/// Summary of the order book as seen by a trader. Best prices and depth only count other
+/// traders' orders and use the same fallbacks as `get_best_bid_and_ask`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MarketStats {
+    pub best_bid_price_ticks: u64,
+    pub best_ask_price_ticks: u64,
+    /// 0 if the book is crossed
+    pub spread_in_ticks: u64,
+    pub bid_depth_5_levels: u64,
+    pub ask_depth_5_levels: u64,
+    pub our_bid_price_ticks: Option<u64>,
+    pub our_ask_price_ticks: Option<u64>,
+}
+
+pub fn market_order_book_stats(
+    market: &dyn Market<Pubkey, FIFOOrderId, FIFORestingOrder, OrderPacket>,
+    trader_index: u64,
+) -> MarketStats {
+    let (best_bid, best_ask) = get_best_bid_and_ask(market, trader_index);
+    let depth = |side: Side| {
+        let mut levels = 0;
+        let mut last_price = None;
+        let mut depth = 0u64;
+        for (order_id, order) in market.get_book(side).iter() {
+            if order.trader_index == trader_index {
+                continue;
+            }
+            if last_price != Some(order_id.price_in_ticks) {
+                if levels == MARKET_STATS_DEPTH_LEVELS {
+                    break;
+                }
+                levels += 1;
+                last_price = Some(order_id.price_in_ticks);
+            }
+            depth = depth.saturating_add(order.num_base_lots.as_u64());
+        }
+        depth
+    };
+    let our_price = |side: Side| {
+        market
+            .get_book(side)
+            .iter()
+            .find(|(_, o)| o.trader_index == trader_index)
+            .map(|(o, _)| o.price_in_ticks.as_u64())
+    };
+    MarketStats {
+        best_bid_price_ticks: best_bid,
+        best_ask_price_ticks: best_ask,
+        spread_in_ticks: best_ask.saturating_sub(best_bid),
+        bid_depth_5_levels: depth(Side::Bid),
+        ask_depth_5_levels: depth(Side::Ask),
+        our_bid_price_ticks: our_price(Side::Bid),
+        our_ask_price_ticks: our_price(Side::Ask),
+    }
+}
+
+/// Determines how quote prices that fall between two ticks are rounded.
+#[derive(Debug, AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PricePrecisionMode {
+    /// Rounds the bid down and the ask up, away from the fair price
+    Conservative,
+    /// Rounds the bid up and the ask down, toward the fair price. Small edges may cross.
+    Aggressive,
+    /// Fails unless the price falls exactly on a tick
+    Exact,
+}
+
+impl PricePrecisionMode {
+    pub fn to_u8(&self) -> u8 {
+        match self {
+            PricePrecisionMode::Conservative => 0,
+            PricePrecisionMode::Aggressive => 1,
+            PricePrecisionMode::Exact => 2,
+        }
+    }
+
+    pub fn try_from_u8(byte: u8) -> Result<Self> {
+        match byte {
+            0 => Ok(PricePrecisionMode::Conservative),
+            1 => Ok(PricePrecisionMode::Aggressive),
+            2 => Ok(PricePrecisionMode::Exact),
+            _ => err!(StrategyError::InvalidPricePrecisionMode),
+        }
+    }
+}
+
+/// Returns the fair price moved by `edge_in_bps` away from the fair price on `side`, in ticks,
+/// rounded to a tick as `mode` requires.
+// `is_multiple_of` and `div_ceil` are newer than the rustc of the Solana BPF toolchain
+#[allow(clippy::manual_is_multiple_of, clippy::manual_div_ceil)]
+fn get_quote_price_in_ticks(
     fair_price_in_quote_atoms_per_raw_base_unit: u64,
     header: &MarketHeader,
     edge_in_bps: u64,
-) -> u64 {
-    let fair_price_in_ticks = fair_price_in_quote_atoms_per_raw_base_unit
-        * header.raw_base_units_per_base_unit as u64
-        / header.get_tick_size_in_quote_atoms_per_base_unit().as_u64();
-    let edge_in_ticks = edge_in_bps * fair_price_in_ticks / 10_000;
-    fair_price_in_ticks - edge_in_ticks
+    side: Side,
+    mode: PricePrecisionMode,
+) -> Result<u64> {
+    let multiplier_in_bps = match side {
+        Side::Bid => 10_000u64.saturating_sub(edge_in_bps),
+        Side::Ask => 10_000u64.saturating_add(edge_in_bps),
+    };
+    let numerator = (fair_price_in_quote_atoms_per_raw_base_unit as u128
+        * header.raw_base_units_per_base_unit as u128)
+        .saturating_mul(multiplier_in_bps as u128);
+    let denominator = header.get_tick_size_in_quote_atoms_per_base_unit().as_u64() as u128 * 10_000;
+    let round_up = match (mode, side) {
+        (PricePrecisionMode::Conservative, Side::Ask) => true,
+        (PricePrecisionMode::Aggressive, Side::Bid) => true,
+        (PricePrecisionMode::Exact, _) => {
+            if numerator % denominator != 0 {
+                msg!(
+                    "{:?} price of {}/{} ticks is not on a tick",
+                    side,
+                    numerator,
+                    denominator
+                );
+                return err!(OracleError::InexactPrice);
+            }
+            false
+        }
+        _ => false,
+    };
+    let price_in_ticks = if round_up {
+        // Rounds up without overflowing when the numerator saturated
+        numerator / denominator + u128::from(numerator % denominator != 0)
+    } else {
+        numerator / denominator
+    };
+    Ok(u64::try_from(price_in_ticks).unwrap_or(u64::MAX))
+}
+
+pub fn get_bid_price_in_ticks(
+    fair_price_in_quote_atoms_per_raw_base_unit: u64,
+    header: &MarketHeader,
+    edge_in_bps: u64,
+    mode: PricePrecisionMode,
+) -> Result<u64> {
+    get_quote_price_in_ticks(
+        fair_price_in_quote_atoms_per_raw_base_unit,
+        header,
+        edge_in_bps,
+        Side::Bid,
+        mode,
+    )
 }
 
-fn get_ask_price_in_ticks(
+pub fn get_ask_price_in_ticks(
     fair_price_in_quote_atoms_per_raw_base_unit: u64,
     header: &MarketHeader,
     edge_in_bps: u64,
+    mode: PricePrecisionMode,
+) -> Result<u64> {
+    get_quote_price_in_ticks(
+        fair_price_in_quote_atoms_per_raw_base_unit,
+        header,
+        edge_in_bps,
+        Side::Ask,
+        mode,
+    )
+}
+
+/// Returns the smallest edge that earns `target_profit_bps` on a round trip, where one side is
+/// filled as a maker and the position is closed as a taker.
+pub fn compute_minimum_profitable_edge_in_bps(
+    taker_fee_in_bps: u64,
+    maker_rebate_in_bps: u64,
+    target_profit_bps: u64,
+) -> u64 {
+    taker_fee_in_bps
+        .saturating_sub(maker_rebate_in_bps)
+        .saturating_add(target_profit_bps)
+}
+
+/// Converts the quote size into bid and ask sizes in base lots at the given prices, rounding down
+/// to whole lots. Returns None if either price is 0 or if either size rounds down to 0, since a
+/// dust order on one side would leave the quotes unbalanced.
+pub fn compute_order_sizes(
+    quote_size_in_quote_atoms: u64,
+    bid_price_in_ticks: u64,
+    ask_price_in_ticks: u64,
+    header: &MarketHeader,
+    market: &dyn Market<Pubkey, FIFOOrderId, FIFORestingOrder, OrderPacket>,
+) -> Option<(u64, u64)> {
+    let size_in_quote_lots =
+        quote_size_in_quote_atoms.checked_div(header.get_quote_lot_size().as_u64())? as u128;
+    let base_lots_per_base_unit = market.get_base_lots_per_base_unit().as_u64() as u128;
+    let tick_size_in_quote_lots = market.get_tick_size().as_u64() as u128;
+    let size_at = |price_in_ticks: u64| -> Option<u64> {
+        let size = (size_in_quote_lots * base_lots_per_base_unit)
+            .checked_div(price_in_ticks as u128 * tick_size_in_quote_lots)?;
+        u64::try_from(size).ok().filter(|size| *size > 0)
+    };
+    Some((size_at(bid_price_in_ticks)?, size_at(ask_price_in_ticks)?))
+}
+
+/// Returns the most quote atoms a limit order could trade through the opposite side of the book,
+/// as the number of crossed price levels (counting the best opposing price) times the tick size
+/// times the order size. Orders that do not cross return 0.
+pub fn get_max_taker_exposure_in_quote_atoms(
+    side: Side,
+    price_in_ticks: u64,
+    size_in_base_lots: u64,
+    best_bid: u64,
+    best_ask: u64,
+    tick_size_in_quote_atoms_per_base_unit: u64,
+    base_lots_per_base_unit: u64,
+) -> u64 {
+    let crossing_ticks = match side {
+        Side::Bid if best_ask != u64::MAX && price_in_ticks >= best_ask => {
+            price_in_ticks - best_ask + 1
+        }
+        Side::Ask if best_bid != 0 && price_in_ticks <= best_bid => best_bid - price_in_ticks + 1,
+        _ => return 0,
+    };
+    if base_lots_per_base_unit == 0 {
+        return 0;
+    }
+    let exposure = crossing_ticks as u128
+        * tick_size_in_quote_atoms_per_base_unit as u128
+        * size_in_base_lots as u128
+        / base_lots_per_base_unit as u128;
+    exposure.min(u64::MAX as u128) as u64
+}
+
+/// Returns the order size for a quote `level` away from fair, where level 0 is closest to fair.
+/// Each level is `decay_factor_in_bps / 10_000` times the size of the previous one, so sizes
+/// never increase with distance as long as the decay factor is at most 10_000.
+pub fn get_level_size(base_size: u64, level: u8, decay_factor_in_bps: u64) -> u64 {
+    (0..level).fold(base_size, |size, _| {
+        (size as u128 * decay_factor_in_bps as u128 / 10_000) as u64
+    })
+}
+
+/// Widens the edge by `decay_factor_in_bps` for every hour without a fill. The widening stops once
+/// the edge reaches `max_edge_in_bps`, but never narrows the configured edge. A `max_edge_in_bps`
+/// of 0 means there is no cap.
+pub fn get_effective_edge_in_bps(
+    quote_edge_in_bps: u64,
+    seconds_since_fill: i64,
+    decay_factor_in_bps: u64,
+    max_edge_in_bps: u64,
+) -> u64 {
+    let widening = (seconds_since_fill.max(0) as u128 * decay_factor_in_bps as u128 / 3600)
+        .min(u64::MAX as u128) as u64;
+    let max_widening = if max_edge_in_bps == 0 {
+        u64::MAX
+    } else {
+        max_edge_in_bps.saturating_sub(quote_edge_in_bps)
+    };
+    quote_edge_in_bps.saturating_add(widening.min(max_widening))
+}
+
+/// Number of fair price updates after which older prices start to carry less weight in the
+/// rolling mean and variance.
+pub const VOLATILITY_WINDOW: u64 = 64;
+
+/// Fractional bits of `PhoenixStrategyState::rolling_price_mean_x32`.
+const PRICE_MEAN_FRACTIONAL_BITS: u32 = 32;
+
+/// Returns `value / denominator` as a fixed-point number with `PRICE_MEAN_FRACTIONAL_BITS`
+/// fractional bits, saturating at the bounds of an i64.
+fn relative_to(value: i128, denominator: i128) -> i128 {
+    (value.saturating_mul(1 << PRICE_MEAN_FRACTIONAL_BITS) / denominator)
+        .clamp(i64::MIN as i128, i64::MAX as i128)
+}
+
+/// Adds `price` to a rolling mean and population variance with Welford's algorithm, where
+/// `count` is the number of prices already included. Once `count` reaches `VOLATILITY_WINDOW`,
+/// each new price is weighted as if the window were full, so old prices decay exponentially.
+///
+/// The mean is a Q32.32 fixed-point price and the variance is relative to the squared mean in
+/// Q0.64 fixed point, so that price moves of less than a quote atom per raw base unit still move
+/// both of them. The relative variance saturates just below 1, a standard deviation equal to the
+/// mean.
+pub fn update_rolling_variance(
+    mean_x32: u64,
+    variance_x64: u64,
+    count: u64,
+    price: u64,
+) -> (u64, u64) {
+    let price_x32 = (price as i128) << PRICE_MEAN_FRACTIONAL_BITS;
+    if count == 0 || mean_x32 == 0 {
+        return (price_x32.min(u64::MAX as i128) as u64, 0);
+    }
+    let n = (count.saturating_add(1)).min(VOLATILITY_WINDOW) as i128;
+    let mean = mean_x32 as i128;
+    let delta = price_x32 - mean;
+    let new_mean = (mean + delta / n).clamp(1, u64::MAX as i128);
+    // Rescale the previous variance from the old mean to the new one
+    let variance = (variance_x64 as u128 * mean as u128 / new_mean as u128)
+        .saturating_mul(mean as u128)
+        / new_mean as u128;
+    let variance = variance.min(i128::MAX as u128) as i128;
+    let new_variance = variance
+        + (relative_to(delta, new_mean) * relative_to(price_x32 - new_mean, new_mean) - variance)
+            / n;
+    (
+        new_mean as u64,
+        new_variance.clamp(0, u64::MAX as i128) as u64,
+    )
+}
+
+fn integer_sqrt(value: u64) -> u64 {
+    if value < 2 {
+        return value;
+    }
+    // Newton's method, starting above the root so that the iterates decrease monotonically
+    let mut x = value;
+    let mut y = x / 2 + 1;
+    while y < x {
+        x = y;
+        y = (x + value / x) / 2;
+    }
+    x
+}
+
+/// Returns the extra edge for the current volatility: `volatility_multiplier_in_bps` times the
+/// standard deviation of the fair price relative to the fair price. `mean_x32` and
+/// `variance_x64` are the rolling estimates from `update_rolling_variance`.
+pub fn get_volatility_edge_in_bps(
+    mean_x32: u64,
+    variance_x64: u64,
+    fair_price_in_quote_atoms_per_raw_base_unit: u64,
+    volatility_multiplier_in_bps: u64,
+) -> u64 {
+    if fair_price_in_quote_atoms_per_raw_base_unit == 0 {
+        return 0;
+    }
+    // The square root of the Q0.64 relative variance is the relative standard deviation in Q0.32
+    let std_dev_x32 =
+        (integer_sqrt(variance_x64) as u128 * mean_x32 as u128) >> PRICE_MEAN_FRACTIONAL_BITS;
+    (volatility_multiplier_in_bps as u128 * std_dev_x32
+        / ((fair_price_in_quote_atoms_per_raw_base_unit as u128) << PRICE_MEAN_FRACTIONAL_BITS))
+        .min(u64::MAX as u128) as u64
+}
+
+/// Seconds in a 365 day year, the period that annualized volatilities refer to.
+pub const SECONDS_PER_YEAR: u64 = 365 * 24 * 3600;
+
+/// Scales an annualized volatility down to the time between two quote updates,
+/// `annualized_vol_in_bps * sqrt(quote_refresh_interval_in_seconds / SECONDS_PER_YEAR)`, which is
+/// added to the edge.
+pub fn get_oracle_volatility_edge_in_bps(
+    annualized_vol_in_bps: u64,
+    quote_refresh_interval_in_seconds: u64,
+) -> u64 {
+    // The ratio is scaled by 10^16 so that its square root keeps 8 decimals for short intervals
+    let scaled_ratio = (quote_refresh_interval_in_seconds as u128 * 10u128.pow(16)
+        / SECONDS_PER_YEAR as u128)
+        .min(u64::MAX as u128) as u64;
+    (annualized_vol_in_bps as u128 * integer_sqrt(scaled_ratio) as u128 / 100_000_000)
+        .min(u64::MAX as u128) as u64
+}
+
+/// Returns the base lots filled on an order since fills were last recorded for it.
+/// An order that is no longer on the book is treated as fully filled.
+pub fn get_new_fill_in_base_lots(
+    initial_size_in_base_lots: u64,
+    remaining_size_in_base_lots: Option<u64>,
+    tracked_fill_in_base_lots: u64,
 ) -> u64 {
-    let fair_price_in_ticks = fair_price_in_quote_atoms_per_raw_base_unit
-        * header.raw_base_units_per_base_unit as u64
-        / header.get_tick_size_in_quote_atoms_per_base_unit().as_u64();
-    let edge_in_ticks = edge_in_bps * fair_price_in_ticks / 10_000;
-    fair_price_in_ticks + edge_in_ticks
+    initial_size_in_base_lots
+        .saturating_sub(remaining_size_in_base_lots.unwrap_or(0))
+        .saturating_sub(tracked_fill_in_base_lots)
+}
+
+/// Returns the share of `maker_volume` in the total volume in basis points, or None if nothing was
+/// filled.
+pub fn get_maker_share_in_bps(maker_volume: u64, taker_volume: u64) -> Option<u64> {
+    let total = maker_volume as u128 + taker_volume as u128;
+    (total > 0).then(|| (maker_volume as u128 * 10_000 / total) as u64)
+}
+
+/// Adds the base lots filled on the strategy's live orders since the last check to the cumulative
+/// fill counters and emits a `FillDetected` event if anything was filled.
+fn record_fills(
+    phoenix_strategy: &mut PhoenixStrategyState,
+    market: &dyn Market<Pubkey, FIFOOrderId, FIFORestingOrder, OrderPacket>,
+    unix_timestamp: i64,
+) -> (u64, u64) {
+    let new_fill = |side: Side, price_in_ticks: u64, sequence_number: u64, initial, tracked| {
+        if sequence_number == 0 {
+            return 0;
+        }
+        let order_id = FIFOOrderId::new_from_untyped(price_in_ticks, sequence_number);
+        let remaining = market
+            .get_book(side)
+            .get(&order_id)
+            .map(|order| order.num_base_lots.as_u64());
+        get_new_fill_in_base_lots(initial, remaining, tracked)
+    };
+    let bid_fill_in_base_lots = new_fill(
+        Side::Bid,
+        phoenix_strategy.bid_price_in_ticks,
+        phoenix_strategy.bid_order_sequence_number,
+        phoenix_strategy.initial_bid_size_in_base_lots,
+        phoenix_strategy.tracked_bid_fill_in_base_lots,
+    );
+    let ask_fill_in_base_lots = new_fill(
+        Side::Ask,
+        phoenix_strategy.ask_price_in_ticks,
+        phoenix_strategy.ask_order_sequence_number,
+        phoenix_strategy.initial_ask_size_in_base_lots,
+        phoenix_strategy.tracked_ask_fill_in_base_lots,
+    );
+    if bid_fill_in_base_lots == 0 && ask_fill_in_base_lots == 0 {
+        return (0, 0);
+    }
+
+    phoenix_strategy.tracked_bid_fill_in_base_lots += bid_fill_in_base_lots;
+    phoenix_strategy.tracked_ask_fill_in_base_lots += ask_fill_in_base_lots;
+    phoenix_strategy.cumulative_bid_fill_in_base_lots = phoenix_strategy
+        .cumulative_bid_fill_in_base_lots
+        .saturating_add(bid_fill_in_base_lots);
+    phoenix_strategy.cumulative_ask_fill_in_base_lots = phoenix_strategy
+        .cumulative_ask_fill_in_base_lots
+        .saturating_add(ask_fill_in_base_lots);
+    // Orders that rested since a previous update were filled passively
+    phoenix_strategy.cumulative_maker_bid_volume_in_base_lots = phoenix_strategy
+        .cumulative_maker_bid_volume_in_base_lots
+        .saturating_add(bid_fill_in_base_lots);
+    phoenix_strategy.cumulative_maker_ask_volume_in_base_lots = phoenix_strategy
+        .cumulative_maker_ask_volume_in_base_lots
+        .saturating_add(ask_fill_in_base_lots);
+    phoenix_strategy.last_fill_unix_timestamp = unix_timestamp;
+    emit!(FillDetected {
+        trader: phoenix_strategy.trader,
+        market: phoenix_strategy.market,
+        strategy_id: phoenix_strategy.strategy_id,
+        bid_fill_in_base_lots,
+        ask_fill_in_base_lots,
+        cumulative_bid_fill_in_base_lots: phoenix_strategy.cumulative_bid_fill_in_base_lots,
+        cumulative_ask_fill_in_base_lots: phoenix_strategy.cumulative_ask_fill_in_base_lots,
+        unix_timestamp,
+    });
+    (bid_fill_in_base_lots, ask_fill_in_base_lots)
+}
+
+/// Returns a warning for each side whose free deposited balance is below the strategy's minimum.
+/// A minimum of 0 never warns.
+pub fn get_low_balance_warnings(
+    phoenix_strategy: &PhoenixStrategyState,
+    free_base_lots: u64,
+    free_quote_atoms: u64,
+) -> Vec<LowBalanceWarning> {
+    [
+        (
+            "base",
+            free_base_lots,
+            phoenix_strategy.min_deposited_base_lots,
+        ),
+        (
+            "quote",
+            free_quote_atoms,
+            phoenix_strategy.min_deposited_quote_atoms,
+        ),
+    ]
+    .into_iter()
+    .filter(|(_, balance, threshold)| balance < threshold)
+    .map(|(side, balance, threshold)| LowBalanceWarning {
+        side: side.to_string(),
+        balance,
+        threshold,
+    })
+    .collect()
+}
+
+/// Widens the quotes symmetrically around their midpoint until the spread is at least
+/// `min_spread_in_bps`, measured as `(ask - bid) * 10_000 / mid`. Quotes that are already
+/// wide enough are returned unchanged.
+pub fn enforce_min_spread(
+    bid_price_in_ticks: u64,
+    ask_price_in_ticks: u64,
+    min_spread_in_bps: u64,
+) -> Result<(u64, u64)> {
+    let mid = (bid_price_in_ticks as u128 + ask_price_in_ticks as u128) / 2;
+    let spread = ask_price_in_ticks.saturating_sub(bid_price_in_ticks) as u128;
+    if min_spread_in_bps == 0 || spread * 10_000 >= min_spread_in_bps as u128 * mid {
+        return Ok((bid_price_in_ticks, ask_price_in_ticks));
+    }
+    // Round up so that the widened spread is never below the minimum
+    let half_spread = min_spread_in_bps as u128 * mid / 20_000 + 1;
+    // The bid cannot be widened below 1 tick
+    require!(half_spread < mid, RiskError::SpreadBelowMinimum);
+    Ok((
+        bid_price_in_ticks.min((mid - half_spread) as u64),
+        ask_price_in_ticks.max((mid + half_spread).min(u64::MAX as u128) as u64),
+    ))
 }
 
 #[derive(Debug, AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum PriceImprovementBehavior {
     Join,
     Dime,
@@ -131,16 +922,212 @@ impl PriceImprovementBehavior {
         }
     }
 
+    pub fn try_from_u8(byte: u8) -> Result<Self> {
+        match byte {
+            0 => Ok(PriceImprovementBehavior::Join),
+            1 => Ok(PriceImprovementBehavior::Dime),
+            2 => Ok(PriceImprovementBehavior::Ignore),
+            _ => err!(StrategyError::InvalidStrategyParams),
+        }
+    }
+
+    #[deprecated(note = "Use `try_from_u8` instead")]
     pub fn from_u8(byte: u8) -> Self {
+        Self::try_from_u8(byte).unwrap()
+    }
+}
+
+/// Determines how the `client_order_id` of placed orders is derived.
+#[derive(Debug, AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ClientOrderIdMode {
+    /// The first 16 bytes of the trader's pubkey, identical for every order
+    Pubkey,
+    /// A per-update counter in the upper 64 bits and the first 8 bytes of the trader's pubkey in
+    /// the lower 64 bits, unique for every update
+    Counter,
+}
+
+impl ClientOrderIdMode {
+    pub fn to_u8(&self) -> u8 {
+        match self {
+            ClientOrderIdMode::Pubkey => 0,
+            ClientOrderIdMode::Counter => 1,
+        }
+    }
+
+    pub fn try_from_u8(byte: u8) -> Result<Self> {
+        match byte {
+            0 => Ok(ClientOrderIdMode::Pubkey),
+            1 => Ok(ClientOrderIdMode::Counter),
+            _ => err!(StrategyError::InvalidStrategyParams),
+        }
+    }
+}
+
+/// Determines how `quote_size_in_quote_atoms` is interpreted.
+#[derive(Debug, AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SizingMode {
+    /// A fixed notional size in quote atoms
+    Fixed,
+    /// A share of the trader's free quote balance in the market, in basis points
+    PercentOfDepositedFunds,
+}
+
+impl SizingMode {
+    pub fn to_u8(&self) -> u8 {
+        match self {
+            SizingMode::Fixed => 0,
+            SizingMode::PercentOfDepositedFunds => 1,
+        }
+    }
+
+    pub fn try_from_u8(byte: u8) -> Result<Self> {
         match byte {
-            0 => PriceImprovementBehavior::Join,
-            1 => PriceImprovementBehavior::Dime,
-            2 => PriceImprovementBehavior::Ignore,
-            _ => panic!("Invalid PriceImprovementBehavior"),
+            0 => Ok(SizingMode::Fixed),
+            1 => Ok(SizingMode::PercentOfDepositedFunds),
+            _ => err!(StrategyError::InvalidSizingMode),
+        }
+    }
+}
+
+/// Returns the notional size of each quote in quote atoms. In `PercentOfDepositedFunds` mode
+/// `quote_size` is in basis points of `free_quote_atoms`.
+pub fn get_quote_size_in_quote_atoms(
+    sizing_mode: SizingMode,
+    quote_size: u64,
+    free_quote_atoms: u64,
+) -> u64 {
+    match sizing_mode {
+        SizingMode::Fixed => quote_size,
+        SizingMode::PercentOfDepositedFunds => {
+            (free_quote_atoms as u128 * quote_size as u128 / 10_000).min(u64::MAX as u128) as u64
+        }
+    }
+}
+
+/// Scales the size of a quote level by its distance from the fair price.
+#[derive(Debug, AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SizeAdjustmentCurve {
+    /// Every level has the base size
+    Flat,
+    /// Each tick of distance adds this many basis points of the base size
+    Linear(u64),
+    /// The base size times this factor, divided by the distance in ticks
+    Inverse(u64),
+}
+
+impl SizeAdjustmentCurve {
+    pub fn to_u8(&self) -> u8 {
+        match self {
+            SizeAdjustmentCurve::Flat => 0,
+            SizeAdjustmentCurve::Linear(_) => 1,
+            SizeAdjustmentCurve::Inverse(_) => 2,
+        }
+    }
+
+    /// Rebuilds the curve from the kind and parameters stored in the strategy state.
+    pub fn try_from_parts(kind: u8, slope_in_bps: u64, scaling_factor: u64) -> Result<Self> {
+        match kind {
+            0 => Ok(SizeAdjustmentCurve::Flat),
+            1 => Ok(SizeAdjustmentCurve::Linear(slope_in_bps)),
+            2 => Ok(SizeAdjustmentCurve::Inverse(scaling_factor)),
+            _ => err!(StrategyError::InvalidSizeAdjustmentCurve),
+        }
+    }
+}
+
+/// Returns the size of a level `distance_in_ticks` away from the fair price. `Inverse` treats a
+/// distance of 0 as 1 tick, so its size is capped at `base_size * scaling_factor`. Sizes saturate
+/// at u64::MAX.
+pub fn apply_size_curve(base_size: u64, distance_in_ticks: u64, curve: SizeAdjustmentCurve) -> u64 {
+    let size = match curve {
+        SizeAdjustmentCurve::Flat => base_size as u128,
+        SizeAdjustmentCurve::Linear(slope_in_bps) => {
+            let bps = 10_000 + distance_in_ticks as u128 * slope_in_bps as u128;
+            base_size as u128 * bps / 10_000
+        }
+        SizeAdjustmentCurve::Inverse(scaling_factor) => {
+            base_size as u128 * scaling_factor as u128 / distance_in_ticks.max(1) as u128
+        }
+    };
+    size.min(u64::MAX as u128) as u64
+}
+
+pub fn get_client_order_id(mode: ClientOrderIdMode, trader: &Pubkey, counter: u64) -> u128 {
+    let trader_bytes = trader.to_bytes();
+    match mode {
+        ClientOrderIdMode::Pubkey => u128::from_le_bytes(trader_bytes[..16].try_into().unwrap()),
+        ClientOrderIdMode::Counter => {
+            (counter as u128) << 64
+                | u64::from_le_bytes(trader_bytes[..8].try_into().unwrap()) as u128
+        }
+    }
+}
+
+/// Clamps the quote prices against the best external bid and ask according to the price improvement behavior.
+/// A side of the book with no external orders (best bid of 0 or best ask of u64::MAX) is never used for clamping.
+pub fn apply_price_improvement(
+    price_improvement_behavior: PriceImprovementBehavior,
+    bid_price_in_ticks: u64,
+    ask_price_in_ticks: u64,
+    best_bid: u64,
+    best_ask: u64,
+) -> (u64, u64) {
+    let has_external_bid = best_bid != 0;
+    let has_external_ask = best_ask != u64::MAX;
+    match price_improvement_behavior {
+        PriceImprovementBehavior::Join => {
+            // If price_improvement_behavior is set to Join, we will always join the best bid and ask
+            // if our quote prices are within the spread
+            (
+                if has_external_bid {
+                    bid_price_in_ticks.min(best_bid)
+                } else {
+                    bid_price_in_ticks
+                },
+                if has_external_ask {
+                    ask_price_in_ticks.max(best_ask)
+                } else {
+                    ask_price_in_ticks
+                },
+            )
+        }
+        PriceImprovementBehavior::Dime => {
+            // If price_improvement_behavior is set to Dime, we will never price improve by more than 1 tick
+            (
+                if has_external_bid {
+                    bid_price_in_ticks.min(best_bid.saturating_add(1))
+                } else {
+                    bid_price_in_ticks
+                },
+                if has_external_ask {
+                    ask_price_in_ticks.max(best_ask.saturating_sub(1))
+                } else {
+                    ask_price_in_ticks
+                },
+            )
+        }
+        PriceImprovementBehavior::Ignore => {
+            // If price_improvement_behavior is set to Ignore, we will not update our quotes based off the current
+            // market prices
+            (bid_price_in_ticks, ask_price_in_ticks)
         }
     }
 }
 
+/// Volatility published on-chain by an external program. The account data starts with the Anchor
+/// discriminator, the first 8 bytes of `sha256("account:VolatilityOracle")`, followed by the
+/// Borsh-encoded fields. Its owner is not checked, the trader chooses which account to trust.
+#[account]
+#[derive(Debug, Default)]
+pub struct VolatilityOracle {
+    pub annualized_vol_in_bps: u64,
+    pub updated_at_slot: u64,
+}
+
 #[account(zero_copy)]
 pub struct PhoenixStrategyState {
     pub trader: Pubkey,
@@ -159,50 +1146,756 @@ pub struct PhoenixStrategyState {
     pub quote_edge_in_bps: u64,
     /// Order notional size in quote atoms
     pub quote_size_in_quote_atoms: u64,
+    /// Fair price submitted in the most recent update, used to skip redundant updates
+    pub last_fair_price_in_quote_atoms_per_raw_base_unit: u64,
+    /// Size of each quote level relative to the previous one, in basis points (10_000 = no decay)
+    pub size_decay_factor_in_bps: u64,
+    /// Incremented on every update that places orders, used by `ClientOrderIdMode::Counter`
+    pub client_order_id_counter: u64,
+    /// Minimum spread between the bid and ask in basis points of the mid price
+    pub min_spread_in_bps: u64,
+    /// Basis points added to the edge for every hour without a fill
+    pub time_since_last_fill_decay_factor_in_bps: u64,
+    /// Upper bound on the edge after widening, 0 for no limit
+    pub max_edge_in_bps: u64,
+    /// Time at which one of the strategy's orders was last seen filled
+    pub last_fill_unix_timestamp: i64,
+    /// Total base lots bought through the strategy's bids
+    pub cumulative_bid_fill_in_base_lots: u64,
+    /// Total base lots sold through the strategy's asks
+    pub cumulative_ask_fill_in_base_lots: u64,
+    /// Base lots of the current bid that are already included in the cumulative fill
+    pub tracked_bid_fill_in_base_lots: u64,
+    /// Base lots of the current ask that are already included in the cumulative fill
+    pub tracked_ask_fill_in_base_lots: u64,
+    /// Rolling mean of the fair price, in quote atoms per raw base unit as Q32.32 fixed point
+    pub rolling_price_mean_x32: u64,
+    /// Rolling variance of the fair price relative to the squared rolling mean, as Q0.64 fixed
+    /// point
+    pub rolling_price_variance_x64: u64,
+    /// Number of fair prices included in the rolling mean and variance
+    pub price_update_count: u64,
+    /// Edge added per standard deviation of the fair price, relative to the fair price
+    pub volatility_multiplier_in_bps: u64,
+    /// Number of fair prices to observe before the volatility edge is applied
+    pub min_warmup_updates: u64,
+    /// Cap on the quote atoms a crossing limit order may take through, 0 for no limit
+    pub max_taker_exposure_in_quote_atoms: u64,
+    /// Maximum number of slots between the slot an update was submitted at and the slot it
+    /// lands in, 0 to accept any age
+    pub max_slot_age_for_price: u64,
+    /// Basis points of size added per tick of distance from the fair price by the linear size curve
+    pub size_curve_slope_in_bps: u64,
+    /// Multiplier of the base size at one tick from the fair price for the inverse size curve
+    pub size_curve_scaling_factor: u64,
+    /// Free quote atoms deposited in the market below which updates log a low balance warning
+    pub min_deposited_quote_atoms: u64,
+    /// Free base lots deposited in the market below which updates log a low balance warning
+    pub min_deposited_base_lots: u64,
+    /// Base lots bought through bids that were filled while resting on the book
+    pub cumulative_maker_bid_volume_in_base_lots: u64,
+    /// Base lots sold through asks that were filled while resting on the book
+    pub cumulative_maker_ask_volume_in_base_lots: u64,
+    /// Base lots bought through bids that crossed the book when they were placed
+    pub cumulative_taker_bid_volume_in_base_lots: u64,
+    /// Base lots sold through asks that crossed the book when they were placed
+    pub cumulative_taker_ask_volume_in_base_lots: u64,
+    /// Maximum number of slots since the volatility oracle was updated, 0 to accept any age
+    pub max_oracle_staleness_in_slots: u64,
+    /// Expected time between quote updates, the horizon that the oracle's volatility is scaled to
+    pub quote_refresh_interval_in_seconds: u64,
+    /// Market tick size when the strategy was last (re)initialized
+    pub cached_tick_size_in_quote_atoms: u64,
+    /// Market base lot size in base atoms when the strategy was last (re)initialized
+    pub cached_base_lot_size: u64,
+    /// Market quote lot size in quote atoms when the strategy was last (re)initialized
+    pub cached_quote_lot_size: u64,
+    /// Market raw base units per base unit when the strategy was last (re)initialized
+    pub cached_raw_base_units_per_base_unit: u32,
     /// If set to true, the orders will never cross the spread
     pub post_only: bool,
     /// Determines whether/how to improve BBO
     pub price_improvement_behavior: u8,
-    padding: [u8; 6],
+    /// Distinguishes multiple strategies for the same trader and market
+    pub strategy_id: u8,
+    /// If set to true, orders are only funded from the trader's deposited funds in the market
+    pub use_only_deposited_funds: bool,
+    /// Determines how the client order ID is derived
+    pub client_order_id_mode: u8,
+    /// Determines whether `quote_size_in_quote_atoms` is a fixed size or a share of the deposited
+    /// quote balance
+    pub sizing_mode: u8,
+    /// Determines how the size of a quote level scales with its distance from the fair price
+    pub size_adjustment_curve: u8,
+    /// User-defined label or other metadata. Not used by the program.
+    pub client_metadata: [u8; 32],
+    /// Layout version, see `STRATEGY_STATE_VERSION`. 0 for strategies created before it existed.
+    pub version: u8,
+    /// Determines how quote prices between two ticks are rounded
+    pub price_precision_mode: u8,
+    padding: [u8; 3],
+    /// Account holding a `VolatilityOracle` that widens the edge, the default pubkey if unset.
+    /// Placed after the other version 2 fields so that their offsets are unchanged.
+    pub volatility_oracle_pubkey: Pubkey,
+    /// Orders older than this are replaced even if they are unchanged, 0 to keep them indefinitely.
+    /// Fields from here on were added in version 3.
+    pub order_refresh_interval_in_seconds: u64,
+    /// Last time that every resting order was replaced, the age of the older resting order
+    pub last_order_refresh_unix_timestamp: i64,
+    /// Taker fee of the trader's fee tier, used to warn about unprofitable edges. Fields from here
+    /// on were added in version 4.
+    pub taker_fee_in_bps: u64,
+    /// Maker rebate of the trader's fee tier, used to warn about unprofitable edges
+    pub maker_rebate_in_bps: u64,
+    /// Minimum number of other traders' orders on a side of the book for that side to be quoted,
+    /// 0 to always quote. Fields from here on were added in version 5.
+    pub min_external_orders_per_side: u64,
 }
 
-#[derive(Debug, AnchorDeserialize, AnchorSerialize, Clone, Copy)]
-pub struct OrderParams {
-    pub fair_price_in_quote_atoms_per_raw_base_unit: u64,
-    pub strategy_params: StrategyParams,
-}
+impl PhoenixStrategyState {
+    /// Returns true if the strategy has a live order on either side of the book.
+    /// A sequence number of 0 means that no order has been placed on that side.
+    pub fn is_active(&self) -> bool {
+        self.has_live_bid() || self.has_live_ask()
+    }
 
-#[derive(Debug, AnchorDeserialize, AnchorSerialize, Clone, Copy)]
-pub struct StrategyParams {
-    pub quote_edge_in_bps: Option<u64>,
-    pub quote_size_in_quote_atoms: Option<u64>,
-    pub price_improvement_behavior: Option<PriceImprovementBehavior>,
-    pub post_only: Option<bool>,
-}
+    /// Checks that `initialize` may overwrite an existing strategy. Strategies with resting orders
+    /// are refused, since overwriting them would lose track of the orders.
+    pub fn check_can_initialize(&self) -> Result<()> {
+        if self.is_active() {
+            msg!("Strategy has resting orders, cancel them before initializing it again");
+            return err!(StrategyError::StrategyAlreadyActive);
+        }
+        Ok(())
+    }
 
-#[program]
-pub mod phoenix_onchain_mm {
-    use super::*;
+    /// Tracks the order just placed on `side`, which rests in the book with `resting_size_in_base_lots`
+    /// out of the `placed_size_in_base_lots` that were sent.
+    pub fn record_placed_order(
+        &mut self,
+        side: Side,
+        order_id: &FIFOOrderId,
+        placed_size_in_base_lots: u64,
+        resting_size_in_base_lots: u64,
+    ) {
+        // Whatever is missing from the new order was taken from the book
+        let taken_in_base_lots = placed_size_in_base_lots.saturating_sub(resting_size_in_base_lots);
+        match side {
+            Side::Bid => {
+                self.cumulative_taker_bid_volume_in_base_lots = self
+                    .cumulative_taker_bid_volume_in_base_lots
+                    .saturating_add(taken_in_base_lots);
+                self.bid_price_in_ticks = order_id.price_in_ticks.as_u64();
+                self.bid_order_sequence_number = order_id.order_sequence_number;
+                self.initial_bid_size_in_base_lots = resting_size_in_base_lots;
+                self.tracked_bid_fill_in_base_lots = 0;
+            }
+            Side::Ask => {
+                self.cumulative_taker_ask_volume_in_base_lots = self
+                    .cumulative_taker_ask_volume_in_base_lots
+                    .saturating_add(taken_in_base_lots);
+                self.ask_price_in_ticks = order_id.price_in_ticks.as_u64();
+                self.ask_order_sequence_number = order_id.order_sequence_number;
+                self.initial_ask_size_in_base_lots = resting_size_in_base_lots;
+                self.tracked_ask_fill_in_base_lots = 0;
+            }
+        }
+    }
 
-    pub fn initialize(ctx: Context<Initialize>, params: StrategyParams) -> Result<()> {
-        require!(
-            params.quote_edge_in_bps.is_some()
-                && params.quote_size_in_quote_atoms.is_some()
-                && params.price_improvement_behavior.is_some(),
-            StrategyError::InvalidStrategyParams
-        );
-        require!(
-            params.quote_edge_in_bps.unwrap() > 0,
-            StrategyError::EdgeMustBeNonZero
-        );
-        load_header(&ctx.accounts.market)?;
-        let clock = Clock::get()?;
-        msg!("Initializing Phoenix Strategy with params: {:?}", params);
-        let mut phoenix_strategy = ctx.accounts.phoenix_strategy.load_init()?;
-        *phoenix_strategy = PhoenixStrategyState {
-            trader: *ctx.accounts.user.key,
-            market: *ctx.accounts.market.key,
-            bid_order_sequence_number: 0,
+    /// Clears the order on `side` once it is no longer resting, e.g. after it was cancelled.
+    pub fn forget_order(&mut self, side: Side) {
+        match side {
+            Side::Bid => {
+                self.bid_order_sequence_number = 0;
+                self.bid_price_in_ticks = 0;
+                self.initial_bid_size_in_base_lots = 0;
+                self.tracked_bid_fill_in_base_lots = 0;
+            }
+            Side::Ask => {
+                self.ask_order_sequence_number = 0;
+                self.ask_price_in_ticks = 0;
+                self.initial_ask_size_in_base_lots = 0;
+                self.tracked_ask_fill_in_base_lots = 0;
+            }
+        }
+    }
+
+    /// Zeros the cumulative fill and volume counters to start a new tracking session, e.g. at the
+    /// start of a trading day. The params and resting orders are kept, and so is the part of the
+    /// resting orders' fills that was already counted, so that it is not counted again.
+    pub fn reset(&mut self, slot: u64, unix_timestamp: i64) {
+        self.cumulative_bid_fill_in_base_lots = 0;
+        self.cumulative_ask_fill_in_base_lots = 0;
+        self.cumulative_maker_bid_volume_in_base_lots = 0;
+        self.cumulative_maker_ask_volume_in_base_lots = 0;
+        self.cumulative_taker_bid_volume_in_base_lots = 0;
+        self.cumulative_taker_ask_volume_in_base_lots = 0;
+        self.last_update_slot = slot;
+        self.last_update_unix_timestamp = unix_timestamp;
+    }
+
+    pub fn has_live_bid(&self) -> bool {
+        self.bid_order_sequence_number != 0
+    }
+
+    pub fn has_live_ask(&self) -> bool {
+        self.ask_order_sequence_number != 0
+    }
+
+    /// Returns the number of seconds since the strategy was last updated.
+    pub fn time_since_last_update(&self, current_timestamp: i64) -> i64 {
+        current_timestamp - self.last_update_unix_timestamp
+    }
+
+    /// Logs a warning if the edge does not cover the fees. Quoting at a loss can be intentional, so
+    /// this is not an error.
+    pub fn warn_if_edge_unprofitable(&self) {
+        let minimum_edge_in_bps = compute_minimum_profitable_edge_in_bps(
+            self.taker_fee_in_bps,
+            self.maker_rebate_in_bps,
+            0,
+        );
+        if self.quote_edge_in_bps < minimum_edge_in_bps {
+            msg!(
+                "WARNING: Edge of {} bps is below the break-even edge of {} bps",
+                self.quote_edge_in_bps,
+                minimum_edge_in_bps
+            );
+        }
+    }
+
+    /// Returns true if both of the strategy's orders rest in the book without any fills, in which
+    /// case quoting the same fair price with the same params would place identical orders.
+    pub fn has_intact_orders(
+        &self,
+        market: &dyn Market<Pubkey, FIFOOrderId, FIFORestingOrder, OrderPacket>,
+    ) -> bool {
+        [
+            (
+                Side::Bid,
+                self.bid_price_in_ticks,
+                self.bid_order_sequence_number,
+                self.initial_bid_size_in_base_lots,
+            ),
+            (
+                Side::Ask,
+                self.ask_price_in_ticks,
+                self.ask_order_sequence_number,
+                self.initial_ask_size_in_base_lots,
+            ),
+        ]
+        .into_iter()
+        .all(|(side, price_in_ticks, sequence_number, initial_size)| {
+            sequence_number != 0
+                && market
+                    .get_book(side)
+                    .get(&FIFOOrderId::new_from_untyped(
+                        price_in_ticks,
+                        sequence_number,
+                    ))
+                    .map(|order| order.num_base_lots.as_u64())
+                    == Some(initial_size)
+        })
+    }
+
+    /// Restarts the refresh interval once every resting order is new: when a due refresh replaced
+    /// them, even if a side is not quoted again, or when orders were placed on both sides.
+    pub fn record_order_refresh(
+        &mut self,
+        refresh_due: bool,
+        placed_both_sides: bool,
+        unix_timestamp: i64,
+    ) {
+        if refresh_due || placed_both_sides {
+            self.last_order_refresh_unix_timestamp = unix_timestamp;
+        }
+    }
+
+    /// Returns true if the resting orders must be replaced even if they are unchanged.
+    pub fn is_order_refresh_due(&self, current_timestamp: i64) -> bool {
+        self.order_refresh_interval_in_seconds != 0
+            && current_timestamp.saturating_sub(self.last_order_refresh_unix_timestamp)
+                >= self.order_refresh_interval_in_seconds as i64
+    }
+
+    /// Returns the parameters that determine the quotes, used to skip updates that would place
+    /// identical orders.
+    fn quoting_params(&self) -> impl PartialEq {
+        (
+            (
+                self.quote_edge_in_bps,
+                self.quote_size_in_quote_atoms,
+                self.post_only,
+                self.use_only_deposited_funds,
+                self.price_improvement_behavior,
+                self.size_decay_factor_in_bps,
+                self.sizing_mode,
+                self.size_adjustment_curve,
+                self.size_curve_slope_in_bps,
+                self.size_curve_scaling_factor,
+                self.price_precision_mode,
+            ),
+            (
+                self.min_spread_in_bps,
+                self.time_since_last_fill_decay_factor_in_bps,
+                self.max_edge_in_bps,
+                self.volatility_multiplier_in_bps,
+                self.min_warmup_updates,
+                self.max_taker_exposure_in_quote_atoms,
+                self.volatility_oracle_pubkey,
+                self.max_oracle_staleness_in_slots,
+                self.quote_refresh_interval_in_seconds,
+                self.min_external_orders_per_side,
+            ),
+        )
+    }
+
+    /// Validates `params` and updates every parameter that is set.
+    pub fn apply_params(&mut self, params: &StrategyParams) -> Result<()> {
+        params.validate(false)?;
+        if let Some(edge) = params.quote_edge_in_bps {
+            self.quote_edge_in_bps = edge;
+        }
+        if let Some(size) = params.quote_size_in_quote_atoms {
+            self.quote_size_in_quote_atoms = size;
+        }
+        if let Some(post_only) = params.post_only {
+            self.post_only = post_only;
+        }
+        if let Some(use_only_deposited_funds) = params.use_only_deposited_funds {
+            self.use_only_deposited_funds = use_only_deposited_funds;
+        }
+        if let Some(price_improvement_behavior) = params.price_improvement_behavior {
+            self.price_improvement_behavior = price_improvement_behavior.to_u8();
+        }
+        if let Some(size_decay_factor_in_bps) = params.size_decay_factor_in_bps {
+            self.size_decay_factor_in_bps = size_decay_factor_in_bps;
+        }
+        if let Some(client_order_id_mode) = params.client_order_id_mode {
+            self.client_order_id_mode = client_order_id_mode.to_u8();
+        }
+        if let Some(min_spread_in_bps) = params.min_spread_in_bps {
+            self.min_spread_in_bps = min_spread_in_bps;
+        }
+        if let Some(decay_factor_in_bps) = params.time_since_last_fill_decay_factor_in_bps {
+            self.time_since_last_fill_decay_factor_in_bps = decay_factor_in_bps;
+        }
+        if let Some(max_edge_in_bps) = params.max_edge_in_bps {
+            self.max_edge_in_bps = max_edge_in_bps;
+        }
+        if let Some(volatility_multiplier_in_bps) = params.volatility_multiplier_in_bps {
+            self.volatility_multiplier_in_bps = volatility_multiplier_in_bps;
+        }
+        if let Some(min_warmup_updates) = params.min_warmup_updates {
+            self.min_warmup_updates = min_warmup_updates;
+        }
+        if let Some(max_taker_exposure) = params.max_taker_exposure_in_quote_atoms {
+            self.max_taker_exposure_in_quote_atoms = max_taker_exposure;
+        }
+        if let Some(max_slot_age_for_price) = params.max_slot_age_for_price {
+            self.max_slot_age_for_price = max_slot_age_for_price;
+        }
+        if let Some(client_metadata) = params.client_metadata {
+            self.client_metadata = client_metadata;
+        }
+        if let Some(sizing_mode) = params.sizing_mode {
+            self.sizing_mode = sizing_mode.to_u8();
+        }
+        if let Some(curve) = params.size_adjustment_curve {
+            self.set_size_adjustment_curve(curve);
+        }
+        if let Some(price_precision_mode) = params.price_precision_mode {
+            self.price_precision_mode = price_precision_mode.to_u8();
+        }
+        if let Some(order_refresh_interval_in_seconds) = params.order_refresh_interval_in_seconds {
+            self.order_refresh_interval_in_seconds = order_refresh_interval_in_seconds;
+        }
+        if let Some(taker_fee_in_bps) = params.taker_fee_in_bps {
+            self.taker_fee_in_bps = taker_fee_in_bps;
+        }
+        if let Some(maker_rebate_in_bps) = params.maker_rebate_in_bps {
+            self.maker_rebate_in_bps = maker_rebate_in_bps;
+        }
+        if params.quote_edge_in_bps.is_some()
+            || params.taker_fee_in_bps.is_some()
+            || params.maker_rebate_in_bps.is_some()
+        {
+            self.warn_if_edge_unprofitable();
+        }
+        if let Some(min_external_orders_per_side) = params.min_external_orders_per_side {
+            self.min_external_orders_per_side = min_external_orders_per_side;
+        }
+        if let Some(min_deposited_quote_atoms) = params.min_deposited_quote_atoms {
+            self.min_deposited_quote_atoms = min_deposited_quote_atoms;
+        }
+        if let Some(min_deposited_base_lots) = params.min_deposited_base_lots {
+            self.min_deposited_base_lots = min_deposited_base_lots;
+        }
+        if let Some(volatility_oracle_pubkey) = params.volatility_oracle_pubkey {
+            self.volatility_oracle_pubkey = volatility_oracle_pubkey;
+        }
+        if let Some(max_oracle_staleness_in_slots) = params.max_oracle_staleness_in_slots {
+            self.max_oracle_staleness_in_slots = max_oracle_staleness_in_slots;
+        }
+        if let Some(quote_refresh_interval_in_seconds) = params.quote_refresh_interval_in_seconds {
+            self.quote_refresh_interval_in_seconds = quote_refresh_interval_in_seconds;
+        }
+        Ok(())
+    }
+
+    pub fn size_adjustment_curve(&self) -> Result<SizeAdjustmentCurve> {
+        SizeAdjustmentCurve::try_from_parts(
+            self.size_adjustment_curve,
+            self.size_curve_slope_in_bps,
+            self.size_curve_scaling_factor,
+        )
+    }
+
+    /// Stores the curve's kind and parameter, clearing the parameter of the other curves.
+    fn set_size_adjustment_curve(&mut self, curve: SizeAdjustmentCurve) {
+        let (slope_in_bps, scaling_factor) = match curve {
+            SizeAdjustmentCurve::Flat => (0, 0),
+            SizeAdjustmentCurve::Linear(slope_in_bps) => (slope_in_bps, 0),
+            SizeAdjustmentCurve::Inverse(scaling_factor) => (0, scaling_factor),
+        };
+        self.size_adjustment_curve = curve.to_u8();
+        self.size_curve_slope_in_bps = slope_in_bps;
+        self.size_curve_scaling_factor = scaling_factor;
+    }
+
+    /// Fails if the fair price in an update submitted at `submitted_slot` is older than
+    /// `max_slot_age_for_price` at `current_slot`. A submitted slot of 0 skips the check.
+    pub fn check_slot_freshness(&self, submitted_slot: u64, current_slot: u64) -> Result<()> {
+        if submitted_slot == 0 || self.max_slot_age_for_price == 0 {
+            return Ok(());
+        }
+        let age = current_slot.saturating_sub(submitted_slot);
+        if age > self.max_slot_age_for_price {
+            msg!(
+                "Fair price submitted at slot {} is {} slots old, the limit is {}",
+                submitted_slot,
+                age,
+                self.max_slot_age_for_price
+            );
+            return err!(StrategyError::PriceTooStale);
+        }
+        Ok(())
+    }
+
+    /// Returns the edge added for the volatility reported by `volatility_oracle`, or 0 if the
+    /// strategy has no oracle. Fails if the oracle is configured but not passed, does not match
+    /// the configured account, or was last updated more than `max_oracle_staleness_in_slots` ago.
+    pub fn get_oracle_edge_in_bps(
+        &self,
+        volatility_oracle: Option<&AccountInfo>,
+        current_slot: u64,
+    ) -> Result<u64> {
+        if self.volatility_oracle_pubkey == Pubkey::default() {
+            return Ok(0);
+        }
+        let account = match volatility_oracle {
+            Some(account) if account.key() == self.volatility_oracle_pubkey => account,
+            _ => {
+                msg!(
+                    "Expected volatility oracle {}",
+                    self.volatility_oracle_pubkey
+                );
+                return err!(OracleError::InvalidVolatilityOracle);
+            }
+        };
+        let oracle =
+            VolatilityOracle::try_deserialize(&mut &account.data.borrow()[..]).map_err(|_| {
+                msg!("Failed to deserialize volatility oracle");
+                OracleError::InvalidVolatilityOracle
+            })?;
+        self.check_oracle_freshness(&oracle, current_slot)?;
+        Ok(get_oracle_volatility_edge_in_bps(
+            oracle.annualized_vol_in_bps,
+            self.quote_refresh_interval_in_seconds,
+        ))
+    }
+
+    /// Fails if `oracle` was last updated more than `max_oracle_staleness_in_slots` before
+    /// `current_slot`.
+    pub fn check_oracle_freshness(
+        &self,
+        oracle: &VolatilityOracle,
+        current_slot: u64,
+    ) -> Result<()> {
+        if self.max_oracle_staleness_in_slots == 0 {
+            return Ok(());
+        }
+        let age = current_slot.saturating_sub(oracle.updated_at_slot);
+        if age > self.max_oracle_staleness_in_slots {
+            msg!(
+                "Volatility oracle updated at slot {} is {} slots old, the limit is {}",
+                oracle.updated_at_slot,
+                age,
+                self.max_oracle_staleness_in_slots
+            );
+            return err!(StrategyError::StaleVolatilityOracle);
+        }
+        Ok(())
+    }
+
+    /// Records the market parameters that the quote edge and size were configured against.
+    pub fn cache_market_params(&mut self, header: &MarketHeader) {
+        self.cached_tick_size_in_quote_atoms =
+            header.get_tick_size_in_quote_atoms_per_base_unit().as_u64();
+        self.cached_base_lot_size = header.get_base_lot_size().as_u64();
+        self.cached_quote_lot_size = header.get_quote_lot_size().as_u64();
+        self.cached_raw_base_units_per_base_unit = header.raw_base_units_per_base_unit;
+    }
+
+    /// Fails if the market's tick size or raw base units per base unit differ from the values
+    /// cached when the strategy was last (re)initialized.
+    pub fn validate_market_params_unchanged(&self, header: &MarketHeader) -> Result<()> {
+        let tick_size = header.get_tick_size_in_quote_atoms_per_base_unit().as_u64();
+        let base_lot_size = header.get_base_lot_size().as_u64();
+        let quote_lot_size = header.get_quote_lot_size().as_u64();
+        if tick_size != self.cached_tick_size_in_quote_atoms
+            || base_lot_size != self.cached_base_lot_size
+            || quote_lot_size != self.cached_quote_lot_size
+            || header.raw_base_units_per_base_unit != self.cached_raw_base_units_per_base_unit
+        {
+            msg!(
+                "Market parameters changed: tick size {} -> {}, base lot size {} -> {}, quote lot size {} -> {}, raw base units per base unit {} -> {}",
+                self.cached_tick_size_in_quote_atoms,
+                tick_size,
+                self.cached_base_lot_size,
+                base_lot_size,
+                self.cached_quote_lot_size,
+                quote_lot_size,
+                self.cached_raw_base_units_per_base_unit,
+                header.raw_base_units_per_base_unit
+            );
+            return err!(StrategyError::MarketParamsChanged);
+        }
+        Ok(())
+    }
+
+    /// Checks invariants that every update preserves. A failure means the account was corrupted.
+    pub fn sanity_check(&self) -> Result<()> {
+        if self.bid_price_in_ticks != 0
+            && self.ask_price_in_ticks != 0
+            && self.bid_price_in_ticks >= self.ask_price_in_ticks
+        {
+            msg!(
+                "Stored bid {} is not below stored ask {}",
+                self.bid_price_in_ticks,
+                self.ask_price_in_ticks
+            );
+            return err!(StrategyError::CorruptStrategyState);
+        }
+        if self.quote_edge_in_bps > 10_000 {
+            msg!("Quote edge {} bps exceeds 100%", self.quote_edge_in_bps);
+            return err!(StrategyError::CorruptStrategyState);
+        }
+        if self.bid_order_sequence_number != 0 && self.initial_bid_size_in_base_lots == 0 {
+            msg!("Bid {} has no initial size", self.bid_order_sequence_number);
+            return err!(StrategyError::CorruptStrategyState);
+        }
+        if self.last_update_unix_timestamp < 0 {
+            msg!(
+                "Last update timestamp {} is negative",
+                self.last_update_unix_timestamp
+            );
+            return err!(StrategyError::CorruptStrategyState);
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, AnchorDeserialize, AnchorSerialize, Clone, Copy)]
+pub struct RebalanceParams {
+    /// Desired base lots held in the trader's seat. Negative targets sell down to zero.
+    pub target_base_lots: i64,
+    /// How far past the best opposing price the order may fill
+    pub max_slippage_in_ticks: u64,
+}
+
+#[derive(Debug, AnchorDeserialize, AnchorSerialize, Clone, Copy)]
+pub struct OrderParams {
+    pub fair_price_in_quote_atoms_per_raw_base_unit: u64,
+    pub strategy_params: StrategyParams,
+    /// Slot at which the fair price was observed, 0 to skip the freshness check
+    pub submitted_slot: u64,
+}
+
+impl OrderParams {
+    /// Checks the order parameters before they are sent. The strategy parameters are validated
+    /// separately with `StrategyParams::validate`.
+    pub fn validate(&self) -> Result<()> {
+        require!(
+            self.fair_price_in_quote_atoms_per_raw_base_unit > 0,
+            OracleError::InvalidFairPrice
+        );
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default, AnchorDeserialize, AnchorSerialize, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default, deny_unknown_fields))]
+pub struct StrategyParams {
+    pub quote_edge_in_bps: Option<u64>,
+    pub quote_size_in_quote_atoms: Option<u64>,
+    pub price_improvement_behavior: Option<PriceImprovementBehavior>,
+    pub post_only: Option<bool>,
+    pub use_only_deposited_funds: Option<bool>,
+    pub size_decay_factor_in_bps: Option<u64>,
+    pub client_order_id_mode: Option<ClientOrderIdMode>,
+    pub min_spread_in_bps: Option<u64>,
+    pub time_since_last_fill_decay_factor_in_bps: Option<u64>,
+    pub max_edge_in_bps: Option<u64>,
+    pub volatility_multiplier_in_bps: Option<u64>,
+    pub min_warmup_updates: Option<u64>,
+    pub max_taker_exposure_in_quote_atoms: Option<u64>,
+    pub max_slot_age_for_price: Option<u64>,
+    pub client_metadata: Option<[u8; 32]>,
+    pub sizing_mode: Option<SizingMode>,
+    pub size_adjustment_curve: Option<SizeAdjustmentCurve>,
+    pub price_precision_mode: Option<PricePrecisionMode>,
+    pub order_refresh_interval_in_seconds: Option<u64>,
+    pub taker_fee_in_bps: Option<u64>,
+    pub maker_rebate_in_bps: Option<u64>,
+    pub min_external_orders_per_side: Option<u64>,
+    pub min_deposited_quote_atoms: Option<u64>,
+    pub min_deposited_base_lots: Option<u64>,
+    pub volatility_oracle_pubkey: Option<Pubkey>,
+    pub max_oracle_staleness_in_slots: Option<u64>,
+    pub quote_refresh_interval_in_seconds: Option<u64>,
+}
+
+impl StrategyParams {
+    /// Checks every parameter that is set. `initialize` also requires the parameters that have no
+    /// default: the edge, the size and the price improvement behavior. Enum parameters such as the
+    /// price improvement behavior are already checked when they are deserialized.
+    pub fn validate(&self, is_initialize: bool) -> Result<()> {
+        if is_initialize {
+            require!(
+                self.quote_edge_in_bps.is_some()
+                    && self.quote_size_in_quote_atoms.is_some()
+                    && self.price_improvement_behavior.is_some(),
+                StrategyError::InvalidStrategyParams
+            );
+        }
+        if let Some(edge) = self.quote_edge_in_bps {
+            require!(edge > 0, StrategyError::EdgeMustBeNonZero);
+            require!(edge <= 10_000, StrategyError::InvalidStrategyParams);
+        }
+        if let Some(size) = self.quote_size_in_quote_atoms {
+            require!(size > 0, StrategyError::InvalidStrategyParams);
+        }
+        // A decay factor above 10_000 would make levels further from fair larger
+        if let Some(size_decay_factor_in_bps) = self.size_decay_factor_in_bps {
+            require!(
+                size_decay_factor_in_bps <= 10_000,
+                StrategyError::InvalidSizeDecayFactor
+            );
+        }
+        // An inverse curve with a scaling factor of 0 would size every level at 0
+        if let Some(SizeAdjustmentCurve::Inverse(scaling_factor)) = self.size_adjustment_curve {
+            require!(
+                scaling_factor > 0,
+                StrategyError::InvalidSizeAdjustmentCurve
+            );
+        }
+        Ok(())
+    }
+}
+
+/// A strategy recorded in a `StrategyRegistry`
+#[zero_copy]
+#[derive(Debug, PartialEq, Eq)]
+pub struct RegistryEntry {
+    pub market: Pubkey,
+    pub strategy_id: u8,
+}
+
+/// The strategies that a user has initialized, so that they can be found without knowing the
+/// markets. Only the first `count` entries are set.
+#[account(zero_copy)]
+pub struct StrategyRegistry {
+    pub entries: [RegistryEntry; MAX_REGISTRY_ENTRIES],
+    pub count: u8,
+}
+
+impl StrategyRegistry {
+    pub fn entries(&self) -> &[RegistryEntry] {
+        &self.entries[..(self.count as usize).min(MAX_REGISTRY_ENTRIES)]
+    }
+
+    /// Appends the strategy unless it is already registered, e.g. when it is initialized again.
+    pub fn register(&mut self, market: Pubkey, strategy_id: u8) -> Result<()> {
+        let entry = RegistryEntry {
+            market,
+            strategy_id,
+        };
+        if self.entries().contains(&entry) {
+            return Ok(());
+        }
+        let count = self.count as usize;
+        require!(count < MAX_REGISTRY_ENTRIES, StrategyError::RegistryFull);
+        self.entries[count] = entry;
+        self.count += 1;
+        Ok(())
+    }
+
+    /// Addresses of the registered strategies, given the user that owns the registry.
+    pub fn get_strategy_addresses(&self, user: &Pubkey) -> Vec<Pubkey> {
+        self.entries()
+            .iter()
+            .map(|entry| get_strategy_address_with_id(user, &entry.market, entry.strategy_id).0)
+            .collect()
+    }
+}
+
+/// Records the strategy in the registry, which is created if this is the user's first strategy.
+fn add_to_registry(
+    registry: &AccountLoader<StrategyRegistry>,
+    market: Pubkey,
+    strategy_id: u8,
+) -> Result<()> {
+    // As for the strategy, the discriminator is only written once the account is initialized
+    let is_new = registry.as_ref().try_borrow_data()?[..8] == [0; 8];
+    let mut registry = if is_new {
+        registry.load_init()?
+    } else {
+        registry.load_mut()?
+    };
+    registry.register(market, strategy_id)
+}
+
+#[program]
+pub mod phoenix_onchain_mm {
+    use super::*;
+
+    pub fn initialize(
+        ctx: Context<Initialize>,
+        params: StrategyParams,
+        strategy_id: u8,
+    ) -> Result<()> {
+        params.validate(true)?;
+        let header = load_header(&ctx.accounts.market)?;
+        let clock = Clock::get()?;
+        msg!("Initializing Phoenix Strategy with params: {:?}", params);
+        // The account is only created if it does not exist yet, in which case the discriminator
+        // has not been written
+        let is_new = ctx.accounts.phoenix_strategy.as_ref().try_borrow_data()?[..8] == [0; 8];
+        let mut phoenix_strategy = if is_new {
+            ctx.accounts.phoenix_strategy.load_init()?
+        } else {
+            let phoenix_strategy = ctx.accounts.phoenix_strategy.load_mut()?;
+            phoenix_strategy.check_can_initialize()?;
+            msg!("Strategy already exists, overwriting it");
+            phoenix_strategy
+        };
+        *phoenix_strategy = PhoenixStrategyState {
+            trader: *ctx.accounts.user.key,
+            market: *ctx.accounts.market.key,
+            volatility_oracle_pubkey: params.volatility_oracle_pubkey.unwrap_or_default(),
+            bid_order_sequence_number: 0,
             bid_price_in_ticks: 0,
             initial_bid_size_in_base_lots: 0,
             ask_order_sequence_number: 0,
@@ -212,14 +1905,103 @@ pub mod phoenix_onchain_mm {
             last_update_unix_timestamp: clock.unix_timestamp,
             quote_edge_in_bps: params.quote_edge_in_bps.unwrap(),
             quote_size_in_quote_atoms: params.quote_size_in_quote_atoms.unwrap(),
+            last_fair_price_in_quote_atoms_per_raw_base_unit: 0,
+            size_decay_factor_in_bps: params.size_decay_factor_in_bps.unwrap_or(10_000),
+            client_order_id_counter: 0,
+            min_spread_in_bps: params.min_spread_in_bps.unwrap_or(0),
+            time_since_last_fill_decay_factor_in_bps: params
+                .time_since_last_fill_decay_factor_in_bps
+                .unwrap_or(0),
+            max_edge_in_bps: params.max_edge_in_bps.unwrap_or(0),
+            last_fill_unix_timestamp: clock.unix_timestamp,
+            cumulative_bid_fill_in_base_lots: 0,
+            cumulative_ask_fill_in_base_lots: 0,
+            tracked_bid_fill_in_base_lots: 0,
+            tracked_ask_fill_in_base_lots: 0,
+            rolling_price_mean_x32: 0,
+            rolling_price_variance_x64: 0,
+            price_update_count: 0,
+            volatility_multiplier_in_bps: params.volatility_multiplier_in_bps.unwrap_or(0),
+            min_warmup_updates: params.min_warmup_updates.unwrap_or(0),
+            max_taker_exposure_in_quote_atoms: params
+                .max_taker_exposure_in_quote_atoms
+                .unwrap_or(0),
+            max_slot_age_for_price: params.max_slot_age_for_price.unwrap_or(0),
+            size_curve_slope_in_bps: 0,
+            size_curve_scaling_factor: 0,
+            min_deposited_quote_atoms: params.min_deposited_quote_atoms.unwrap_or(0),
+            min_deposited_base_lots: params.min_deposited_base_lots.unwrap_or(0),
+            cumulative_maker_bid_volume_in_base_lots: 0,
+            cumulative_maker_ask_volume_in_base_lots: 0,
+            cumulative_taker_bid_volume_in_base_lots: 0,
+            cumulative_taker_ask_volume_in_base_lots: 0,
+            max_oracle_staleness_in_slots: params.max_oracle_staleness_in_slots.unwrap_or(0),
+            quote_refresh_interval_in_seconds: params
+                .quote_refresh_interval_in_seconds
+                .unwrap_or(0),
+            cached_tick_size_in_quote_atoms: header
+                .get_tick_size_in_quote_atoms_per_base_unit()
+                .as_u64(),
+            cached_base_lot_size: header.get_base_lot_size().as_u64(),
+            cached_quote_lot_size: header.get_quote_lot_size().as_u64(),
+            cached_raw_base_units_per_base_unit: header.raw_base_units_per_base_unit,
             post_only: params.post_only.unwrap_or(false),
             price_improvement_behavior: params.price_improvement_behavior.unwrap().to_u8(),
-            padding: [0; 6],
+            strategy_id,
+            use_only_deposited_funds: params.use_only_deposited_funds.unwrap_or(false),
+            client_order_id_mode: params
+                .client_order_id_mode
+                .unwrap_or(ClientOrderIdMode::Pubkey)
+                .to_u8(),
+            sizing_mode: params.sizing_mode.unwrap_or(SizingMode::Fixed).to_u8(),
+            size_adjustment_curve: SizeAdjustmentCurve::Flat.to_u8(),
+            client_metadata: params.client_metadata.unwrap_or([0; 32]),
+            version: STRATEGY_STATE_VERSION,
+            price_precision_mode: params
+                .price_precision_mode
+                .unwrap_or(PricePrecisionMode::Conservative)
+                .to_u8(),
+            padding: [0; 3],
+            order_refresh_interval_in_seconds: params
+                .order_refresh_interval_in_seconds
+                .unwrap_or(0),
+            last_order_refresh_unix_timestamp: 0,
+            taker_fee_in_bps: params.taker_fee_in_bps.unwrap_or(0),
+            maker_rebate_in_bps: params.maker_rebate_in_bps.unwrap_or(0),
+            min_external_orders_per_side: params.min_external_orders_per_side.unwrap_or(0),
         };
-        Ok(())
+        phoenix_strategy.warn_if_edge_unprofitable();
+        if let Some(curve) = params.size_adjustment_curve {
+            phoenix_strategy.set_size_adjustment_curve(curve);
+        }
+        add_to_registry(
+            &ctx.accounts.strategy_registry,
+            *ctx.accounts.market.key,
+            strategy_id,
+        )
+    }
+
+    /// Records an existing strategy in the user's registry. `initialize` registers new strategies,
+    /// this is for strategies initialized before the registry existed.
+    pub fn register_strategy(ctx: Context<RegisterStrategy>, strategy_id: u8) -> Result<()> {
+        let phoenix_strategy = ctx.accounts.phoenix_strategy.load()?;
+        require!(
+            phoenix_strategy.strategy_id == strategy_id
+                && phoenix_strategy.trader == ctx.accounts.user.key(),
+            StrategyError::InvalidStrategyParams
+        );
+        add_to_registry(
+            &ctx.accounts.strategy_registry,
+            phoenix_strategy.market,
+            strategy_id,
+        )
     }
 
-    pub fn update_quotes(ctx: Context<UpdateQuotes>, params: OrderParams) -> Result<()> {
+    pub fn update_quotes(
+        ctx: Context<UpdateQuotes>,
+        params: OrderParams,
+        strategy_id: u8,
+    ) -> Result<()> {
         let UpdateQuotes {
             phoenix_strategy,
             user,
@@ -232,92 +2014,210 @@ pub mod phoenix_onchain_mm {
             quote_vault,
             base_vault,
             token_program,
+            volatility_oracle,
         } = ctx.accounts;
 
         let mut phoenix_strategy = phoenix_strategy.load_mut()?;
+        require!(
+            phoenix_strategy.strategy_id == strategy_id,
+            StrategyError::InvalidStrategyParams
+        );
+        phoenix_strategy.sanity_check()?;
+        params.validate()?;
 
         // Update timestamps
         let clock = Clock::get()?;
         phoenix_strategy.last_update_slot = clock.slot;
         phoenix_strategy.last_update_unix_timestamp = clock.unix_timestamp;
 
+        let previous_params = phoenix_strategy.quoting_params();
+
         // Update the strategy parameters
-        if let Some(edge) = params.strategy_params.quote_edge_in_bps {
-            if edge > 0 {
-                phoenix_strategy.quote_edge_in_bps = edge;
-            }
-        }
-        if let Some(size) = params.strategy_params.quote_size_in_quote_atoms {
-            phoenix_strategy.quote_size_in_quote_atoms = size;
-        }
-        if let Some(post_only) = params.strategy_params.post_only {
-            phoenix_strategy.post_only = post_only;
-        }
-        if let Some(price_improvement_behavior) = params.strategy_params.price_improvement_behavior
-        {
-            phoenix_strategy.price_improvement_behavior = price_improvement_behavior.to_u8();
-        }
+        phoenix_strategy.apply_params(&params.strategy_params)?;
+        phoenix_strategy.check_slot_freshness(params.submitted_slot, clock.slot)?;
+
+        // Every fair price counts towards the volatility estimate, even when nothing is requoted
+        (
+            phoenix_strategy.rolling_price_mean_x32,
+            phoenix_strategy.rolling_price_variance_x64,
+        ) = update_rolling_variance(
+            phoenix_strategy.rolling_price_mean_x32,
+            phoenix_strategy.rolling_price_variance_x64,
+            phoenix_strategy.price_update_count,
+            params.fair_price_in_quote_atoms_per_raw_base_unit,
+        );
+        phoenix_strategy.price_update_count = phoenix_strategy.price_update_count.saturating_add(1);
+
+        // Orders past the refresh interval are replaced even if they would be identical
+        let refresh_due = phoenix_strategy.is_order_refresh_due(clock.unix_timestamp);
+
+        // If neither the fair price nor the parameters changed, the quotes would be identical,
+        // so the update is skipped once the market shows that no order was filled.
+        // The edge widens over time when a fill decay factor is set and follows the volatility
+        // estimate or oracle when they are set, and the liquidity check depends on the book, so
+        // never skip in those cases.
+        let unchanged = !refresh_due
+            && phoenix_strategy.time_since_last_fill_decay_factor_in_bps == 0
+            && phoenix_strategy.min_external_orders_per_side == 0
+            && phoenix_strategy.volatility_multiplier_in_bps == 0
+            && phoenix_strategy.volatility_oracle_pubkey == Pubkey::default()
+            && params.fair_price_in_quote_atoms_per_raw_base_unit
+                == phoenix_strategy.last_fair_price_in_quote_atoms_per_raw_base_unit
+            && previous_params == phoenix_strategy.quoting_params();
+        phoenix_strategy.last_fair_price_in_quote_atoms_per_raw_base_unit =
+            params.fair_price_in_quote_atoms_per_raw_base_unit;
 
         // Load market
         let header = load_header(market_account)?;
+        check_market_status(&header)?;
+        if phoenix_strategy
+            .validate_market_params_unchanged(&header)
+            .is_err()
+        {
+            // Failing here would also revert the cancellation, so pull the quotes and stop
+            cancel_strategy_orders(
+                &mut phoenix_strategy,
+                &phoenix_program.to_account_info(),
+                log_authority,
+                user,
+                market_account,
+            )?;
+            msg!("Not quoting until the strategy is reinitialized");
+            return Ok(());
+        }
         let market_data = market_account.data.borrow();
         let (_, market_bytes) = market_data.split_at(std::mem::size_of::<MarketHeader>());
         let market = phoenix::program::load_with_dispatch(&header.market_size_params, market_bytes)
             .map_err(|_| {
                 msg!("Failed to deserialize market");
-                StrategyError::FailedToDeserializePhoenixMarket
+                MarketError::FailedToDeserializePhoenixMarket
             })?
             .inner;
 
+        if unchanged && phoenix_strategy.has_intact_orders(market) {
+            msg!("Fair price and parameters are unchanged and no order was filled");
+            return Ok(());
+        }
+
+        // Record any fills on the current orders before they are replaced
+        record_fills(&mut phoenix_strategy, market, clock.unix_timestamp);
+        let volatility_edge_in_bps =
+            if phoenix_strategy.price_update_count >= phoenix_strategy.min_warmup_updates {
+                get_volatility_edge_in_bps(
+                    phoenix_strategy.rolling_price_mean_x32,
+                    phoenix_strategy.rolling_price_variance_x64,
+                    params.fair_price_in_quote_atoms_per_raw_base_unit,
+                    phoenix_strategy.volatility_multiplier_in_bps,
+                )
+            } else {
+                0
+            };
+        let oracle_edge_in_bps = phoenix_strategy.get_oracle_edge_in_bps(
+            volatility_oracle.as_ref().map(|account| account.as_ref()),
+            clock.slot,
+        )?;
+        let edge_in_bps = get_effective_edge_in_bps(
+            phoenix_strategy
+                .quote_edge_in_bps
+                .saturating_add(volatility_edge_in_bps)
+                .saturating_add(oracle_edge_in_bps),
+            clock.unix_timestamp - phoenix_strategy.last_fill_unix_timestamp,
+            phoenix_strategy.time_since_last_fill_decay_factor_in_bps,
+            phoenix_strategy.max_edge_in_bps,
+        );
+
         // Compute quote prices
-        let mut bid_price_in_ticks = get_bid_price_in_ticks(
+        let price_precision_mode =
+            PricePrecisionMode::try_from_u8(phoenix_strategy.price_precision_mode)?;
+        let bid_price_in_ticks = get_bid_price_in_ticks(
             params.fair_price_in_quote_atoms_per_raw_base_unit,
             &header,
-            phoenix_strategy.quote_edge_in_bps,
-        );
+            edge_in_bps,
+            price_precision_mode,
+        )?;
 
-        let mut ask_price_in_ticks = get_ask_price_in_ticks(
+        let ask_price_in_ticks = get_ask_price_in_ticks(
             params.fair_price_in_quote_atoms_per_raw_base_unit,
             &header,
-            phoenix_strategy.quote_edge_in_bps,
-        );
+            edge_in_bps,
+            price_precision_mode,
+        )?;
 
         // Returns the best bid and ask prices that are not placed by the trader
         let trader_index = market.get_trader_index(&user.key()).unwrap_or(u32::MAX) as u64;
-        let (best_bid, best_ask) = get_best_bid_and_ask(market, trader_index);
+        let stats = market_order_book_stats(market, trader_index);
+        let (best_bid, best_ask) = (stats.best_bid_price_ticks, stats.best_ask_price_ticks);
 
-        msg!("Current market: {} @ {}", best_bid, best_ask);
+        msg!("Current market: {:?}", stats);
 
-        let price_improvement_behavior =
-            PriceImprovementBehavior::from_u8(phoenix_strategy.price_improvement_behavior);
-        match price_improvement_behavior {
-            PriceImprovementBehavior::Join => {
-                // If price_improvement_behavior is set to Join, we will always join the best bid and ask
-                // if our quote prices are within the spread
-                ask_price_in_ticks = ask_price_in_ticks.max(best_ask);
-                bid_price_in_ticks = bid_price_in_ticks.min(best_bid);
-            }
-            PriceImprovementBehavior::Dime => {
-                // If price_improvement_behavior is set to Dime, we will never price improve by more than 1 tick
-                ask_price_in_ticks = ask_price_in_ticks.max(best_ask - 1);
-                bid_price_in_ticks = bid_price_in_ticks.min(best_bid + 1);
-            }
-            PriceImprovementBehavior::Ignore => {
-                // If price_improvement_behavior is set to Ignore, we will not update our quotes based off the current
-                // market prices
-            }
+        // Price improvement against a crossed book produces nonsensical quotes. Failing here would
+        // also revert the cancellation, so the error is only logged.
+        if best_bid != 0 && best_ask != u64::MAX && best_bid >= best_ask {
+            error!(StrategyError::MarketIsCrossed).log();
+            msg!(
+                "WARNING: Market is crossed (best bid {} >= best ask {}), not quoting",
+                best_bid,
+                best_ask
+            );
+            cancel_strategy_orders(
+                &mut phoenix_strategy,
+                &phoenix_program.to_account_info(),
+                log_authority,
+                user,
+                market_account,
+            )?;
+            return Ok(());
         }
 
-        // Compute quote amounts in base lots
-        let size_in_quote_lots =
-            phoenix_strategy.quote_size_in_quote_atoms / header.get_quote_lot_size().as_u64();
+        let price_improvement_behavior =
+            PriceImprovementBehavior::try_from_u8(phoenix_strategy.price_improvement_behavior)?;
+        let (bid_price_in_ticks, ask_price_in_ticks) = apply_price_improvement(
+            price_improvement_behavior,
+            bid_price_in_ticks,
+            ask_price_in_ticks,
+            best_bid,
+            best_ask,
+        );
+        let (bid_price_in_ticks, ask_price_in_ticks) = enforce_min_spread(
+            bid_price_in_ticks,
+            ask_price_in_ticks,
+            phoenix_strategy.min_spread_in_bps,
+        )?;
+
+        let (free_base_lots, free_quote_lots) = get_inventory_balance(market, &user.key());
+        let free_quote_atoms = free_quote_lots.saturating_mul(header.get_quote_lot_size().as_u64());
+        for warning in get_low_balance_warnings(&phoenix_strategy, free_base_lots, free_quote_atoms)
+        {
+            msg!(
+                "WARNING: Low {} balance: {} {}",
+                warning.side,
+                warning.balance,
+                if warning.side == "quote" {
+                    "atoms"
+                } else {
+                    "lots"
+                }
+            );
+            emit!(warning);
+        }
+        let quote_size_in_quote_atoms = get_quote_size_in_quote_atoms(
+            SizingMode::try_from_u8(phoenix_strategy.sizing_mode)?,
+            phoenix_strategy.quote_size_in_quote_atoms,
+            free_quote_atoms,
+        );
 
-        let bid_size_in_base_lots = size_in_quote_lots
-            * market.get_base_lots_per_base_unit().as_u64()
-            / (bid_price_in_ticks * market.get_tick_size().as_u64());
-        let ask_size_in_base_lots = size_in_quote_lots
-            * market.get_base_lots_per_base_unit().as_u64()
-            / (ask_price_in_ticks * market.get_tick_size().as_u64());
+        // Compute quote amounts in base lots. Dust sizes place no new orders.
+        let (bid_size_in_base_lots, ask_size_in_base_lots) = compute_order_sizes(
+            quote_size_in_quote_atoms,
+            bid_price_in_ticks,
+            ask_price_in_ticks,
+            &header,
+            market,
+        )
+        .unwrap_or_else(|| {
+            msg!("Quote size is too small to place a whole base lot on both sides");
+            (0, 0)
+        });
 
         msg!(
             "Our market: {} {} @ {} {}",
@@ -338,6 +2238,7 @@ pub mod phoenix_onchain_mm {
                     phoenix_strategy.bid_order_sequence_number,
                 ),
                 phoenix_strategy.initial_bid_size_in_base_lots,
+                bid_size_in_base_lots,
             ),
             (
                 Side::Ask,
@@ -347,11 +2248,16 @@ pub mod phoenix_onchain_mm {
                     phoenix_strategy.ask_order_sequence_number,
                 ),
                 phoenix_strategy.initial_ask_size_in_base_lots,
+                ask_size_in_base_lots,
             ),
         ]
         .iter()
-        .filter_map(|(side, price, order_id, initial_size)| {
+        .filter_map(|(side, price, order_id, initial_size, new_size)| {
             if let Some(resting_order) = market.get_book(*side).get(order_id) {
+                if refresh_due {
+                    msg!("Refreshing resting order: {:?}", order_id);
+                    return Some(*order_id);
+                }
                 // The order is 100% identical, do not cancel it
                 if resting_order.num_base_lots == *initial_size
                     && order_id.price_in_ticks.as_u64() == *price
@@ -363,6 +2269,17 @@ pub mod phoenix_onchain_mm {
                     }
                     return None;
                 }
+                // The order was partially filled, but what remains is exactly what would be placed
+                if resting_order.num_base_lots == *new_size
+                    && order_id.price_in_ticks.as_u64() == *price
+                {
+                    msg!("Resting order matches the new quote: {:?}", order_id);
+                    match side {
+                        Side::Bid => update_bid = false,
+                        Side::Ask => update_ask = false,
+                    }
+                    return None;
+                }
                 msg!("Found partially filled resting order: {:?}", order_id);
                 // The order has been partially filled or reduced
                 return Some(*order_id);
@@ -373,44 +2290,125 @@ pub mod phoenix_onchain_mm {
         })
         .collect::<Vec<FIFOOrderId>>();
 
-        // Drop reference prior to invoking
-        drop(market_data);
+        // Don't update quotes if the price is invalid or if the sizes are 0
+        update_bid &= bid_price_in_ticks > 1 && bid_size_in_base_lots > 0;
+        update_ask &= ask_price_in_ticks < u64::MAX && ask_size_in_base_lots > 0;
 
-        // Cancel the old orders
-        if !orders_to_cancel.is_empty() {
-            invoke(
-                &phoenix::program::create_cancel_multiple_orders_by_id_with_free_funds_instruction(
-                    &market_account.key(),
-                    &user.key(),
-                    &CancelMultipleOrdersByIdParams {
-                        orders: orders_to_cancel
-                            .iter()
-                            .map(|o_id| CancelOrderParams {
-                                order_sequence_number: o_id.order_sequence_number,
-                                price_in_ticks: o_id.price_in_ticks.as_u64(),
-                                side: Side::from_order_sequence_number(o_id.order_sequence_number),
-                            })
-                            .collect::<Vec<_>>(),
-                    },
-                ),
-                &[
-                    phoenix_program.to_account_info(),
-                    log_authority.to_account_info(),
-                    user.to_account_info(),
-                    market_account.to_account_info(),
-                ],
-            )?;
+        // Quotes on a nearly empty side of the book are easy to manipulate
+        if phoenix_strategy.min_external_orders_per_side > 0 {
+            let (external_bids, external_asks) = count_external_orders(market, trader_index);
+            for (side, external_orders, update) in [
+                ("bid", external_bids, &mut update_bid),
+                ("ask", external_asks, &mut update_ask),
+            ] {
+                if external_orders < phoenix_strategy.min_external_orders_per_side {
+                    msg!(
+                        "WARNING: Only {} external {} orders, below the minimum of {}, not quoting the {}",
+                        external_orders,
+                        side,
+                        phoenix_strategy.min_external_orders_per_side,
+                        side
+                    );
+                    *update = false;
+                }
+            }
         }
 
-        // Don't update quotes if the price is invalid or if the sizes are 0
-        update_bid &= bid_price_in_ticks > 1 && bid_size_in_base_lots > 0;
-        update_ask &= ask_price_in_ticks < u64::MAX && ask_size_in_base_lots > 0;
+        if !check_balances(
+            market,
+            &user.key(),
+            &orders_to_cancel,
+            if update_bid { bid_size_in_base_lots } else { 0 },
+            bid_price_in_ticks,
+            if update_ask { ask_size_in_base_lots } else { 0 },
+        ) {
+            require!(
+                !phoenix_strategy.use_only_deposited_funds,
+                RiskError::InsufficientFunds
+            );
+            msg!("Warning: deposited funds do not cover the new orders, falling back to token accounts");
+        }
+
+        // Limit orders that could take more than the allowed exposure are posted instead
+        let mut bid_post_only = false;
+        let mut ask_post_only = false;
+        if !phoenix_strategy.post_only
+            && matches!(price_improvement_behavior, PriceImprovementBehavior::Join)
+            && phoenix_strategy.max_taker_exposure_in_quote_atoms != 0
+        {
+            let tick_size = header.get_tick_size_in_quote_atoms_per_base_unit().as_u64();
+            let base_lots_per_base_unit = market.get_base_lots_per_base_unit().as_u64();
+            for (side, price_in_ticks, size_in_base_lots, post_only) in [
+                (
+                    Side::Bid,
+                    bid_price_in_ticks,
+                    bid_size_in_base_lots,
+                    &mut bid_post_only,
+                ),
+                (
+                    Side::Ask,
+                    ask_price_in_ticks,
+                    ask_size_in_base_lots,
+                    &mut ask_post_only,
+                ),
+            ] {
+                let exposure = get_max_taker_exposure_in_quote_atoms(
+                    side,
+                    price_in_ticks,
+                    size_in_base_lots,
+                    best_bid,
+                    best_ask,
+                    tick_size,
+                    base_lots_per_base_unit,
+                );
+                if exposure > phoenix_strategy.max_taker_exposure_in_quote_atoms {
+                    msg!(
+                        "{:?} could take {} quote atoms, above the limit of {}, posting it instead",
+                        side,
+                        exposure,
+                        phoenix_strategy.max_taker_exposure_in_quote_atoms
+                    );
+                    *post_only = true;
+                }
+            }
+        }
+
+        // Drop reference prior to invoking
+        drop(market_data);
+
+        // Cancel the old orders
+        if !orders_to_cancel.is_empty() {
+            cancel_orders_by_id(
+                &phoenix_program.to_account_info(),
+                log_authority,
+                user,
+                market_account,
+                &orders_to_cancel,
+            )?;
+            // Forget the cancelled orders so that they are not mistaken for fills later
+            for order_id in orders_to_cancel.iter() {
+                phoenix_strategy.forget_order(Side::from_order_sequence_number(
+                    order_id.order_sequence_number,
+                ));
+            }
+        }
 
-        let client_order_id = u128::from_le_bytes(user.key().to_bytes()[..16].try_into().unwrap());
+        phoenix_strategy.record_order_refresh(
+            refresh_due,
+            update_bid && update_ask,
+            clock.unix_timestamp,
+        );
         if !update_ask && !update_bid && orders_to_cancel.is_empty() {
             msg!("No orders to update");
             return Ok(());
         }
+        phoenix_strategy.client_order_id_counter =
+            phoenix_strategy.client_order_id_counter.wrapping_add(1);
+        let client_order_id = get_client_order_id(
+            ClientOrderIdMode::try_from_u8(phoenix_strategy.client_order_id_mode)?,
+            &user.key(),
+            phoenix_strategy.client_order_id_counter,
+        );
         let mut order_ids = vec![];
         if phoenix_strategy.post_only
             || !matches!(price_improvement_behavior, PriceImprovementBehavior::Join)
@@ -437,15 +2435,23 @@ pub mod phoenix_onchain_mm {
                 false,
             );
             invoke(
-                &phoenix::program::create_new_multiple_order_instruction_with_custom_token_accounts(
-                    &market_account.key(),
-                    &user.key(),
-                    &base_account.key(),
-                    &quote_account.key(),
-                    &header.base_params.mint_key,
-                    &header.quote_params.mint_key,
-                    &multiple_order_packet,
-                ),
+                &if phoenix_strategy.use_only_deposited_funds {
+                    phoenix::program::create_new_multiple_order_with_free_funds_instruction(
+                        &market_account.key(),
+                        &user.key(),
+                        &multiple_order_packet,
+                    )
+                } else {
+                    phoenix::program::create_new_multiple_order_instruction_with_custom_token_accounts(
+                        &market_account.key(),
+                        &user.key(),
+                        &base_account.key(),
+                        &quote_account.key(),
+                        &header.base_params.mint_key,
+                        &header.quote_params.mint_key,
+                        &multiple_order_packet,
+                    )
+                },
                 &[
                     phoenix_program.to_account_info(),
                     log_authority.to_account_info(),
@@ -470,12 +2476,26 @@ pub mod phoenix_onchain_mm {
                         &quote_account.key(),
                         &header.base_params.mint_key,
                         &header.quote_params.mint_key,
-                        &OrderPacket::new_limit_order_default_with_client_order_id(
-                            Side::Bid,
-                            bid_price_in_ticks,
-                            bid_size_in_base_lots,
-                            client_order_id,
-                        ),
+                        &if bid_post_only {
+                            OrderPacket::new_post_only(
+                                Side::Bid,
+                                bid_price_in_ticks,
+                                bid_size_in_base_lots,
+                                client_order_id,
+                                false,
+                                phoenix_strategy.use_only_deposited_funds,
+                            )
+                        } else {
+                            OrderPacket::new_limit_order(
+                                Side::Bid,
+                                bid_price_in_ticks,
+                                bid_size_in_base_lots,
+                                SelfTradeBehavior::CancelProvide,
+                                None,
+                                client_order_id,
+                                phoenix_strategy.use_only_deposited_funds,
+                            )
+                        },
                     ),
                     &[
                         phoenix_program.to_account_info(),
@@ -501,12 +2521,26 @@ pub mod phoenix_onchain_mm {
                         &quote_account.key(),
                         &header.base_params.mint_key,
                         &header.quote_params.mint_key,
-                        &OrderPacket::new_limit_order_default_with_client_order_id(
-                            Side::Ask,
-                            ask_price_in_ticks,
-                            ask_size_in_base_lots,
-                            client_order_id,
-                        ),
+                        &if ask_post_only {
+                            OrderPacket::new_post_only(
+                                Side::Ask,
+                                ask_price_in_ticks,
+                                ask_size_in_base_lots,
+                                client_order_id,
+                                false,
+                                phoenix_strategy.use_only_deposited_funds,
+                            )
+                        } else {
+                            OrderPacket::new_limit_order(
+                                Side::Ask,
+                                ask_price_in_ticks,
+                                ask_size_in_base_lots,
+                                SelfTradeBehavior::CancelProvide,
+                                None,
+                                client_order_id,
+                                phoenix_strategy.use_only_deposited_funds,
+                            )
+                        },
                     ),
                     &[
                         phoenix_program.to_account_info(),
@@ -530,62 +2564,486 @@ pub mod phoenix_onchain_mm {
         let market = phoenix::program::load_with_dispatch(&header.market_size_params, market_bytes)
             .map_err(|_| {
                 msg!("Failed to deserialize market");
-                StrategyError::FailedToDeserializePhoenixMarket
+                MarketError::FailedToDeserializePhoenixMarket
             })?
             .inner;
 
-        for order_id in order_ids.iter() {
-            let side = Side::from_order_sequence_number(order_id.order_sequence_number);
-            match side {
-                Side::Ask => {
-                    market
-                        .get_book(Side::Ask)
-                        .get(&order_id)
-                        .map(|order| {
-                            msg!("Placed Ask Order: {:?}", order_id);
-                            phoenix_strategy.ask_price_in_ticks = order_id.price_in_ticks.as_u64();
-                            phoenix_strategy.ask_order_sequence_number =
-                                order_id.order_sequence_number;
-                            phoenix_strategy.initial_ask_size_in_base_lots =
-                                order.num_base_lots.as_u64();
-                        })
-                        .unwrap_or_else(|| {
-                            msg!("Ask order not found");
-                        });
+        for (side, placed, placed_size_in_base_lots) in [
+            (Side::Bid, update_bid, bid_size_in_base_lots),
+            (Side::Ask, update_ask, ask_size_in_base_lots),
+        ] {
+            if !placed {
+                continue;
+            }
+            match validate_order_was_placed(market, side, &order_ids) {
+                Ok((order_id, order)) => {
+                    msg!("Placed {:?} Order: {:?}", side, order_id);
+                    phoenix_strategy.record_placed_order(
+                        side,
+                        &order_id,
+                        placed_size_in_base_lots,
+                        order.num_base_lots.as_u64(),
+                    );
                 }
-                Side::Bid => {
-                    market
-                        .get_book(Side::Bid)
-                        .get(&order_id)
-                        .map(|order| {
-                            msg!("Placed Bid Order: {:?}", order_id);
-                            phoenix_strategy.bid_price_in_ticks = order_id.price_in_ticks.as_u64();
-                            phoenix_strategy.bid_order_sequence_number =
-                                order_id.order_sequence_number;
-                            phoenix_strategy.initial_bid_size_in_base_lots =
-                                order.num_base_lots.as_u64();
-                        })
-                        .unwrap_or_else(|| {
-                            msg!("Bid order not found");
-                        });
+                // Failing would also revert the cancellation of the replaced order, so the error
+                // is only logged. The side is forgotten so that a stale order is not tracked.
+                Err(e) => {
+                    e.log();
+                    msg!("{:?} order not found", side);
+                    phoenix_strategy.forget_order(side);
                 }
             }
         }
 
+        let maker_share = |maker, taker| {
+            get_maker_share_in_bps(maker, taker)
+                .map_or("n/a".to_string(), |share| format!("{} bps", share))
+        };
+        msg!(
+            "Maker share of fills: bids {}, asks {}",
+            maker_share(
+                phoenix_strategy.cumulative_maker_bid_volume_in_base_lots,
+                phoenix_strategy.cumulative_taker_bid_volume_in_base_lots
+            ),
+            maker_share(
+                phoenix_strategy.cumulative_maker_ask_volume_in_base_lots,
+                phoenix_strategy.cumulative_taker_ask_volume_in_base_lots
+            )
+        );
+
+        Ok(())
+    }
+
+    /// Updates the strategy parameters and records the market's current tick size and raw base
+    /// units per base unit, e.g. after a market migration stopped `update_quotes`.
+    pub fn reinitialize(
+        ctx: Context<Reinitialize>,
+        params: StrategyParams,
+        strategy_id: u8,
+    ) -> Result<()> {
+        let header = load_header(&ctx.accounts.market)?;
+        let mut phoenix_strategy = ctx.accounts.phoenix_strategy.load_mut()?;
+        require!(
+            phoenix_strategy.strategy_id == strategy_id,
+            StrategyError::InvalidStrategyParams
+        );
+        msg!("Reinitializing Phoenix Strategy with params: {:?}", params);
+        phoenix_strategy.apply_params(&params)?;
+        phoenix_strategy.cache_market_params(&header);
+        // Force the next update to requote
+        phoenix_strategy.last_fair_price_in_quote_atoms_per_raw_base_unit = 0;
+        Ok(())
+    }
+
+    /// Migrates the strategy account to layout version `state_version`, which must be the version
+    /// after its current one.
+    pub fn migrate_state(
+        ctx: Context<MigrateState>,
+        state_version: u8,
+        strategy_id: u8,
+    ) -> Result<()> {
+        let MigrateState {
+            phoenix_strategy,
+            user,
+            system_program,
+            ..
+        } = ctx.accounts;
+        let state = load_state(&phoenix_strategy.try_borrow_data()?)?;
+        require!(
+            state.strategy_id == strategy_id && state.trader == user.key(),
+            StrategyError::InvalidStrategyParams
+        );
+
+        // Newer layouts can be larger, the user pays the rent for the added bytes
+        let new_len = 8 + get_state_size(state_version);
+        if phoenix_strategy.data_len() < new_len {
+            let rent = Rent::get()?
+                .minimum_balance(new_len)
+                .saturating_sub(phoenix_strategy.lamports());
+            if rent > 0 {
+                anchor_lang::system_program::transfer(
+                    CpiContext::new(
+                        system_program.to_account_info(),
+                        anchor_lang::system_program::Transfer {
+                            from: user.to_account_info(),
+                            to: phoenix_strategy.to_account_info(),
+                        },
+                    ),
+                    rent,
+                )?;
+            }
+            phoenix_strategy.realloc(new_len, true)?;
+        }
+        migrate_state_data(&mut phoenix_strategy.try_borrow_mut_data()?, state_version)
+    }
+
+    pub fn cancel_all_orders(ctx: Context<CancelAllOrders>, strategy_id: u8) -> Result<()> {
+        let CancelAllOrders {
+            phoenix_strategy,
+            user,
+            phoenix_program,
+            log_authority,
+            market,
+        } = ctx.accounts;
+
+        let mut phoenix_strategy = phoenix_strategy.load_mut()?;
+        require!(
+            phoenix_strategy.strategy_id == strategy_id,
+            StrategyError::InvalidStrategyParams
+        );
+
+        cancel_strategy_orders(
+            &mut phoenix_strategy,
+            &phoenix_program.to_account_info(),
+            log_authority,
+            user,
+            market,
+        )
+    }
+
+    /// Cancels the strategy's order on one side of the book, 0 for the bid and 1 for the ask, and
+    /// keeps the other side resting. Does nothing if that side has no live order. The side is
+    /// quoted again by the next `update_quotes` that is not skipped as unchanged.
+    pub fn cancel_side(ctx: Context<CancelAllOrders>, strategy_id: u8, side: u8) -> Result<()> {
+        let CancelAllOrders {
+            phoenix_strategy,
+            user,
+            phoenix_program,
+            log_authority,
+            market,
+        } = ctx.accounts;
+
+        let mut phoenix_strategy = phoenix_strategy.load_mut()?;
+        require!(
+            phoenix_strategy.strategy_id == strategy_id,
+            StrategyError::InvalidStrategyParams
+        );
+        let side = match side {
+            0 => Side::Bid,
+            1 => Side::Ask,
+            _ => return err!(StrategyError::InvalidStrategyParams),
+        };
+        let (price_in_ticks, order_sequence_number) = match side {
+            Side::Bid => (
+                phoenix_strategy.bid_price_in_ticks,
+                phoenix_strategy.bid_order_sequence_number,
+            ),
+            Side::Ask => (
+                phoenix_strategy.ask_price_in_ticks,
+                phoenix_strategy.ask_order_sequence_number,
+            ),
+        };
+        if order_sequence_number == 0 {
+            msg!("No {:?} order to cancel", side);
+            return Ok(());
+        }
+
+        cancel_orders_by_id(
+            &phoenix_program.to_account_info(),
+            log_authority,
+            user,
+            market,
+            &[FIFOOrderId::new_from_untyped(
+                price_in_ticks,
+                order_sequence_number,
+            )],
+        )?;
+        phoenix_strategy.forget_order(side);
+        Ok(())
+    }
+
+    /// Zeros the strategy's fill tracking without touching its params or resting orders, see
+    /// `PhoenixStrategyState::reset`.
+    pub fn reset_session(ctx: Context<ResetSession>, strategy_id: u8) -> Result<()> {
+        let ResetSession {
+            phoenix_strategy: strategy_account,
+            user,
+            ..
+        } = ctx.accounts;
+
+        let mut phoenix_strategy = strategy_account.load_mut()?;
+        require!(
+            phoenix_strategy.strategy_id == strategy_id && phoenix_strategy.trader == user.key(),
+            StrategyError::InvalidStrategyParams
+        );
+        let clock = Clock::get()?;
+        phoenix_strategy.reset(clock.slot, clock.unix_timestamp);
+        emit!(SessionReset {
+            strategy: strategy_account.key(),
+            slot: clock.slot,
+            unix_timestamp: clock.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Records fills on the strategy's resting orders without changing quotes. This is cheap
+    /// enough to call more often than `update_quotes`.
+    pub fn track_fills(ctx: Context<TrackFills>, strategy_id: u8) -> Result<()> {
+        let TrackFills {
+            phoenix_strategy,
+            user,
+            market: market_account,
+        } = ctx.accounts;
+
+        let mut phoenix_strategy = phoenix_strategy.load_mut()?;
+        require!(
+            phoenix_strategy.strategy_id == strategy_id && phoenix_strategy.trader == user.key(),
+            StrategyError::InvalidStrategyParams
+        );
+
+        let header = load_header(market_account)?;
+        let market_data = market_account.data.borrow();
+        let (_, market_bytes) = market_data.split_at(std::mem::size_of::<MarketHeader>());
+        let market = phoenix::program::load_with_dispatch(&header.market_size_params, market_bytes)
+            .map_err(|_| {
+                msg!("Failed to deserialize market");
+                MarketError::FailedToDeserializePhoenixMarket
+            })?
+            .inner;
+
+        let (bid_fill_in_base_lots, ask_fill_in_base_lots) =
+            record_fills(&mut phoenix_strategy, market, Clock::get()?.unix_timestamp);
+        msg!(
+            "Filled {} bid lots and {} ask lots",
+            bid_fill_in_base_lots,
+            ask_fill_in_base_lots
+        );
+        Ok(())
+    }
+
+    /// Trades the base lots held in the trader's seat towards `target_base_lots` with an
+    /// immediate-or-cancel order against the book. This is the only instruction that takes
+    /// liquidity, so it pays the taker fee.
+    pub fn rebalance(
+        ctx: Context<Rebalance>,
+        params: RebalanceParams,
+        strategy_id: u8,
+    ) -> Result<()> {
+        let Rebalance {
+            phoenix_strategy,
+            user,
+            phoenix_program,
+            log_authority,
+            market: market_account,
+            seat,
+        } = ctx.accounts;
+        {
+            let phoenix_strategy = phoenix_strategy.load()?;
+            require!(
+                phoenix_strategy.strategy_id == strategy_id
+                    && phoenix_strategy.trader == user.key(),
+                StrategyError::InvalidStrategyParams
+            );
+        }
+
+        let header = load_header(market_account)?;
+        check_market_status(&header)?;
+        let market_data = market_account.data.borrow();
+        let (_, market_bytes) = market_data.split_at(std::mem::size_of::<MarketHeader>());
+        let market = phoenix::program::load_with_dispatch(&header.market_size_params, market_bytes)
+            .map_err(|_| {
+                msg!("Failed to deserialize market");
+                MarketError::FailedToDeserializePhoenixMarket
+            })?
+            .inner;
+
+        let (free_base_lots, locked_base_lots) = market
+            .get_trader_state(&user.key())
+            .map(|state| {
+                (
+                    state.base_lots_free.as_u64(),
+                    state.base_lots_locked.as_u64(),
+                )
+            })
+            .unwrap_or((0, 0));
+        let trader_index = market.get_trader_index(&user.key()).unwrap_or(u32::MAX) as u64;
+        let (best_bid, best_ask) = get_best_bid_and_ask(market, trader_index);
+        let order = get_rebalance_order(
+            free_base_lots + locked_base_lots,
+            params.target_base_lots,
+            best_bid,
+            best_ask,
+            params.max_slippage_in_ticks,
+        )?;
+        drop(market_data);
+
+        let (side, price_in_ticks, size_in_base_lots) = match order {
+            Some((Side::Ask, price_in_ticks, size_in_base_lots)) => {
+                // Base lots locked in resting asks cannot be sold until those orders are cancelled
+                (
+                    Side::Ask,
+                    price_in_ticks,
+                    size_in_base_lots.min(free_base_lots),
+                )
+            }
+            Some(order) => order,
+            None => {
+                msg!("Inventory is already on target");
+                return Ok(());
+            }
+        };
+        if size_in_base_lots == 0 {
+            msg!("No free base lots to sell");
+            return Ok(());
+        }
+        msg!(
+            "Rebalancing: {:?} {} base lots at up to {} ticks",
+            side,
+            size_in_base_lots,
+            price_in_ticks
+        );
+        invoke(
+            &phoenix::program::create_new_order_with_free_funds_instruction(
+                &market_account.key(),
+                &user.key(),
+                &OrderPacket::new_ioc_by_lots(
+                    side,
+                    price_in_ticks,
+                    size_in_base_lots,
+                    SelfTradeBehavior::CancelProvide,
+                    None,
+                    0,
+                    true,
+                ),
+            ),
+            &[
+                phoenix_program.to_account_info(),
+                log_authority.to_account_info(),
+                user.to_account_info(),
+                market_account.to_account_info(),
+                seat.to_account_info(),
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Moves all of the trader's free base and quote funds from the market back to their token
+    /// accounts. Open orders are not affected, the funds locked in them stay in the market.
+    pub fn withdraw_to_wallet(ctx: Context<Withdraw>, strategy_id: u8) -> Result<()> {
+        withdraw_free_funds(ctx.accounts, strategy_id, None, None)
+    }
+
+    /// Like `withdraw_to_wallet`, but only withdraws the given amounts of free funds. Fails if
+    /// they exceed the free funds, open orders are not cancelled to cover them.
+    pub fn partial_withdraw(
+        ctx: Context<Withdraw>,
+        strategy_id: u8,
+        base_lots_to_withdraw: u64,
+        quote_lots_to_withdraw: u64,
+    ) -> Result<()> {
+        withdraw_free_funds(
+            ctx.accounts,
+            strategy_id,
+            Some(base_lots_to_withdraw),
+            Some(quote_lots_to_withdraw),
+        )
+    }
+
+    /// Transfers any lamports held by the strategy account above the rent-exempt minimum back to the trader.
+    pub fn reclaim_excess_lamports(
+        ctx: Context<ReclaimExcessLamports>,
+        strategy_id: u8,
+    ) -> Result<()> {
+        let ReclaimExcessLamports {
+            phoenix_strategy,
+            user,
+            ..
+        } = ctx.accounts;
+        {
+            let phoenix_strategy = phoenix_strategy.load()?;
+            require!(
+                phoenix_strategy.strategy_id == strategy_id
+                    && phoenix_strategy.trader == user.key(),
+                StrategyError::InvalidStrategyParams
+            );
+        }
+
+        let strategy_info = phoenix_strategy.to_account_info();
+        let minimum_rent =
+            Rent::get()?.minimum_balance(8 + std::mem::size_of::<PhoenixStrategyState>());
+        let excess = strategy_info.lamports().saturating_sub(minimum_rent);
+        if excess == 0 {
+            msg!("No excess lamports to reclaim");
+            return Ok(());
+        }
+        **strategy_info.try_borrow_mut_lamports()? -= excess;
+        **user.to_account_info().try_borrow_mut_lamports()? += excess;
+        msg!("Reclaimed {} lamports", excess);
+        Ok(())
+    }
+
+    /// Emits the strategy's state as a `StrategySnapshot` event without modifying it.
+    pub fn snapshot(ctx: Context<Snapshot>) -> Result<()> {
+        let phoenix_strategy = ctx.accounts.phoenix_strategy.load()?;
+        emit!(StrategySnapshot::new(
+            ctx.accounts.phoenix_strategy.key(),
+            &phoenix_strategy
+        ));
+        Ok(())
+    }
+
+    pub fn health_check(ctx: Context<HealthCheck>) -> Result<()> {
+        let phoenix_strategy = ctx.accounts.phoenix_strategy.load()?;
+        let market_account = &ctx.accounts.market;
+        require_keys_eq!(
+            phoenix_strategy.market,
+            market_account.key(),
+            MarketError::MarketMismatch
+        );
+        phoenix_strategy.sanity_check()?;
+        if phoenix_strategy.has_live_bid() && phoenix_strategy.has_live_ask() {
+            require!(
+                phoenix_strategy.bid_price_in_ticks < phoenix_strategy.ask_price_in_ticks,
+                RiskError::CrossedQuotes
+            );
+        }
+
+        let header = load_header(market_account)?;
+        phoenix_strategy.validate_market_params_unchanged(&header)?;
+        let market_data = market_account.data.borrow();
+        let (_, market_bytes) = market_data.split_at(std::mem::size_of::<MarketHeader>());
+        let market = phoenix::program::load_with_dispatch(&header.market_size_params, market_bytes)
+            .map_err(|_| {
+                msg!("Failed to deserialize market");
+                MarketError::FailedToDeserializePhoenixMarket
+            })?
+            .inner;
+        require!(
+            market.get_trader_index(&phoenix_strategy.trader).is_some(),
+            MarketError::TraderNotRegistered
+        );
+
+        msg!(
+            "Strategy {} is healthy: bid {} @ {}, ask {} @ {}",
+            phoenix_strategy.strategy_id,
+            phoenix_strategy.bid_order_sequence_number,
+            phoenix_strategy.bid_price_in_ticks,
+            phoenix_strategy.ask_order_sequence_number,
+            phoenix_strategy.ask_price_in_ticks
+        );
         Ok(())
     }
 }
 
 #[derive(Accounts)]
+#[instruction(params: StrategyParams, strategy_id: u8)]
 pub struct Initialize<'info> {
     #[account(
-        init,
-        seeds=[b"phoenix".as_ref(), user.key.as_ref(), market.key.as_ref()],
+        init_if_needed,
+        seeds=[b"phoenix".as_ref(), user.key.as_ref(), market.key.as_ref(), get_strategy_id_seed(&strategy_id)],
         bump,
         payer = user,
         space = 8 + std::mem::size_of::<PhoenixStrategyState>(),
     )]
     pub phoenix_strategy: AccountLoader<'info, PhoenixStrategyState>,
+    #[account(
+        init_if_needed,
+        seeds=[b"registry".as_ref(), user.key.as_ref()],
+        bump,
+        payer = user,
+        space = 8 + std::mem::size_of::<StrategyRegistry>(),
+    )]
+    pub strategy_registry: AccountLoader<'info, StrategyRegistry>,
     #[account(mut)]
     pub user: Signer<'info>,
     /// CHECK: Checked in instruction
@@ -594,16 +3052,55 @@ pub struct Initialize<'info> {
 }
 
 #[derive(Accounts)]
+#[instruction(strategy_id: u8)]
+pub struct RegisterStrategy<'info> {
+    #[account(
+        seeds=[b"phoenix".as_ref(), user.key.as_ref(), market.key.as_ref(), get_strategy_id_seed(&strategy_id)],
+        bump,
+    )]
+    pub phoenix_strategy: AccountLoader<'info, PhoenixStrategyState>,
+    #[account(
+        init_if_needed,
+        seeds=[b"registry".as_ref(), user.key.as_ref()],
+        bump,
+        payer = user,
+        space = 8 + std::mem::size_of::<StrategyRegistry>(),
+    )]
+    pub strategy_registry: AccountLoader<'info, StrategyRegistry>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+    /// CHECK: Only used to derive the strategy address
+    pub market: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(params: StrategyParams, strategy_id: u8)]
+pub struct Reinitialize<'info> {
+    #[account(
+        mut,
+        seeds=[b"phoenix".as_ref(), user.key.as_ref(), market.key.as_ref(), get_strategy_id_seed(&strategy_id)],
+        bump,
+    )]
+    pub phoenix_strategy: AccountLoader<'info, PhoenixStrategyState>,
+    pub user: Signer<'info>,
+    /// CHECK: Checked in instruction
+    pub market: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(params: OrderParams, strategy_id: u8)]
 pub struct UpdateQuotes<'info> {
     #[account(
         mut,
-        seeds=[b"phoenix".as_ref(), user.key.as_ref(), market.key.as_ref()],
+        seeds=[b"phoenix".as_ref(), user.key.as_ref(), market.key.as_ref(), get_strategy_id_seed(&strategy_id)],
         bump,
     )]
     pub phoenix_strategy: AccountLoader<'info, PhoenixStrategyState>,
     pub user: Signer<'info>,
     pub phoenix_program: Program<'info, PhoenixV1>,
-    /// CHECK: Checked in CPI
+    /// CHECK: Address is fixed by the Phoenix program
+    #[account(address = phoenix::phoenix_log_authority::id())]
     pub log_authority: UncheckedAccount<'info>,
     /// CHECK: Checked in instruction and CPI
     #[account(mut)]
@@ -624,14 +3121,475 @@ pub struct UpdateQuotes<'info> {
     pub base_vault: UncheckedAccount<'info>,
     /// CHECK: Checked in CPI
     pub token_program: UncheckedAccount<'info>,
+    /// CHECK: Checked against the strategy's oracle and deserialized in instruction
+    pub volatility_oracle: Option<UncheckedAccount<'info>>,
 }
 
-// An enum for custom error codes
+#[derive(Accounts)]
+#[instruction(strategy_id: u8)]
+pub struct CancelAllOrders<'info> {
+    #[account(
+        mut,
+        seeds=[b"phoenix".as_ref(), user.key.as_ref(), market.key.as_ref(), get_strategy_id_seed(&strategy_id)],
+        bump,
+    )]
+    pub phoenix_strategy: AccountLoader<'info, PhoenixStrategyState>,
+    pub user: Signer<'info>,
+    pub phoenix_program: Program<'info, PhoenixV1>,
+    /// CHECK: Address is fixed by the Phoenix program
+    #[account(address = phoenix::phoenix_log_authority::id())]
+    pub log_authority: UncheckedAccount<'info>,
+    /// CHECK: Checked in CPI
+    #[account(mut)]
+    pub market: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(state_version: u8, strategy_id: u8)]
+pub struct MigrateState<'info> {
+    /// CHECK: Read as raw bytes, since older layouts cannot be loaded as the current state
+    #[account(
+        mut,
+        seeds=[b"phoenix".as_ref(), user.key.as_ref(), market.key.as_ref(), get_strategy_id_seed(&strategy_id)],
+        bump,
+        owner = crate::ID,
+    )]
+    pub phoenix_strategy: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+    /// CHECK: Only used to derive the strategy address
+    pub market: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Shared by `withdraw_to_wallet` and `partial_withdraw`, which both take the strategy ID first.
+#[derive(Accounts)]
+#[instruction(strategy_id: u8)]
+pub struct Withdraw<'info> {
+    #[account(
+        seeds=[b"phoenix".as_ref(), user.key.as_ref(), market.key.as_ref(), get_strategy_id_seed(&strategy_id)],
+        bump,
+    )]
+    pub phoenix_strategy: AccountLoader<'info, PhoenixStrategyState>,
+    pub user: Signer<'info>,
+    pub phoenix_program: Program<'info, PhoenixV1>,
+    /// CHECK: Address is fixed by the Phoenix program
+    #[account(address = phoenix::phoenix_log_authority::id())]
+    pub log_authority: UncheckedAccount<'info>,
+    /// CHECK: Checked in instruction and CPI
+    #[account(mut)]
+    pub market: UncheckedAccount<'info>,
+    /// CHECK: Checked in CPI
+    #[account(mut)]
+    pub base_account: UncheckedAccount<'info>,
+    /// CHECK: Checked in CPI
+    #[account(mut)]
+    pub quote_account: UncheckedAccount<'info>,
+    /// CHECK: Checked in CPI
+    #[account(mut)]
+    pub base_vault: UncheckedAccount<'info>,
+    /// CHECK: Checked in CPI
+    #[account(mut)]
+    pub quote_vault: UncheckedAccount<'info>,
+    /// CHECK: Checked in CPI
+    pub token_program: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(strategy_id: u8)]
+pub struct ReclaimExcessLamports<'info> {
+    #[account(
+        mut,
+        seeds=[b"phoenix".as_ref(), user.key.as_ref(), market.key.as_ref(), get_strategy_id_seed(&strategy_id)],
+        bump,
+    )]
+    pub phoenix_strategy: AccountLoader<'info, PhoenixStrategyState>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+    /// CHECK: Only used to derive the strategy address
+    pub market: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(params: RebalanceParams, strategy_id: u8)]
+pub struct Rebalance<'info> {
+    #[account(
+        seeds=[b"phoenix".as_ref(), user.key.as_ref(), market.key.as_ref(), get_strategy_id_seed(&strategy_id)],
+        bump,
+    )]
+    pub phoenix_strategy: AccountLoader<'info, PhoenixStrategyState>,
+    pub user: Signer<'info>,
+    pub phoenix_program: Program<'info, PhoenixV1>,
+    /// CHECK: Address is fixed by the Phoenix program
+    #[account(address = phoenix::phoenix_log_authority::id())]
+    pub log_authority: UncheckedAccount<'info>,
+    /// CHECK: Checked in instruction and CPI
+    #[account(mut)]
+    pub market: UncheckedAccount<'info>,
+    /// CHECK: Checked in CPI
+    pub seat: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(strategy_id: u8)]
+pub struct TrackFills<'info> {
+    #[account(
+        mut,
+        seeds=[b"phoenix".as_ref(), user.key.as_ref(), market.key.as_ref(), get_strategy_id_seed(&strategy_id)],
+        bump,
+    )]
+    pub phoenix_strategy: AccountLoader<'info, PhoenixStrategyState>,
+    pub user: Signer<'info>,
+    /// CHECK: Checked in instruction
+    pub market: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(strategy_id: u8)]
+pub struct ResetSession<'info> {
+    #[account(
+        mut,
+        seeds=[b"phoenix".as_ref(), user.key.as_ref(), market.key.as_ref(), get_strategy_id_seed(&strategy_id)],
+        bump,
+    )]
+    pub phoenix_strategy: AccountLoader<'info, PhoenixStrategyState>,
+    pub user: Signer<'info>,
+    /// CHECK: Only used to derive the strategy address
+    pub market: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct Snapshot<'info> {
+    pub phoenix_strategy: AccountLoader<'info, PhoenixStrategyState>,
+}
+
+#[derive(Accounts)]
+pub struct HealthCheck<'info> {
+    pub phoenix_strategy: AccountLoader<'info, PhoenixStrategyState>,
+    /// CHECK: Checked in instruction
+    pub market: UncheckedAccount<'info>,
+}
+
+#[event]
+pub struct FillDetected {
+    pub trader: Pubkey,
+    pub market: Pubkey,
+    pub strategy_id: u8,
+    pub bid_fill_in_base_lots: u64,
+    pub ask_fill_in_base_lots: u64,
+    pub cumulative_bid_fill_in_base_lots: u64,
+    pub cumulative_ask_fill_in_base_lots: u64,
+    pub unix_timestamp: i64,
+}
+
+/// Emitted by `reset_session` once the strategy's fill tracking was zeroed.
+#[event]
+pub struct SessionReset {
+    pub strategy: Pubkey,
+    pub slot: u64,
+    pub unix_timestamp: i64,
+}
+
+/// Emitted by `update_quotes` when a free deposited balance is below the strategy's minimum.
+/// `side` is "base" (balance in base lots) or "quote" (balance in quote atoms).
+#[event]
+#[derive(Debug)]
+pub struct LowBalanceWarning {
+    pub side: String,
+    pub balance: u64,
+    pub threshold: u64,
+}
+
+/// Every field of a strategy account, emitted by the `snapshot` instruction so that indexers can
+/// follow the state without decoding the account.
+#[event]
+#[derive(Debug)]
+pub struct StrategySnapshot {
+    pub strategy: Pubkey,
+    pub trader: Pubkey,
+    pub market: Pubkey,
+    pub volatility_oracle_pubkey: Pubkey,
+    pub bid_order_sequence_number: u64,
+    pub bid_price_in_ticks: u64,
+    pub initial_bid_size_in_base_lots: u64,
+    pub ask_order_sequence_number: u64,
+    pub ask_price_in_ticks: u64,
+    pub initial_ask_size_in_base_lots: u64,
+    pub last_update_slot: u64,
+    pub last_update_unix_timestamp: i64,
+    pub quote_edge_in_bps: u64,
+    pub quote_size_in_quote_atoms: u64,
+    pub last_fair_price_in_quote_atoms_per_raw_base_unit: u64,
+    pub size_decay_factor_in_bps: u64,
+    pub client_order_id_counter: u64,
+    pub min_spread_in_bps: u64,
+    pub time_since_last_fill_decay_factor_in_bps: u64,
+    pub max_edge_in_bps: u64,
+    pub last_fill_unix_timestamp: i64,
+    pub cumulative_bid_fill_in_base_lots: u64,
+    pub cumulative_ask_fill_in_base_lots: u64,
+    pub tracked_bid_fill_in_base_lots: u64,
+    pub tracked_ask_fill_in_base_lots: u64,
+    pub rolling_price_mean_x32: u64,
+    pub rolling_price_variance_x64: u64,
+    pub price_update_count: u64,
+    pub volatility_multiplier_in_bps: u64,
+    pub min_warmup_updates: u64,
+    pub max_taker_exposure_in_quote_atoms: u64,
+    pub max_slot_age_for_price: u64,
+    pub size_curve_slope_in_bps: u64,
+    pub size_curve_scaling_factor: u64,
+    pub min_deposited_quote_atoms: u64,
+    pub min_deposited_base_lots: u64,
+    pub cumulative_maker_bid_volume_in_base_lots: u64,
+    pub cumulative_maker_ask_volume_in_base_lots: u64,
+    pub cumulative_taker_bid_volume_in_base_lots: u64,
+    pub cumulative_taker_ask_volume_in_base_lots: u64,
+    pub max_oracle_staleness_in_slots: u64,
+    pub quote_refresh_interval_in_seconds: u64,
+    pub cached_tick_size_in_quote_atoms: u64,
+    pub cached_base_lot_size: u64,
+    pub cached_quote_lot_size: u64,
+    pub cached_raw_base_units_per_base_unit: u32,
+    pub post_only: bool,
+    pub price_improvement_behavior: u8,
+    pub strategy_id: u8,
+    pub use_only_deposited_funds: bool,
+    pub client_order_id_mode: u8,
+    pub sizing_mode: u8,
+    pub size_adjustment_curve: u8,
+    pub client_metadata: [u8; 32],
+    pub version: u8,
+    pub price_precision_mode: u8,
+    pub order_refresh_interval_in_seconds: u64,
+    pub last_order_refresh_unix_timestamp: i64,
+    pub taker_fee_in_bps: u64,
+    pub maker_rebate_in_bps: u64,
+    pub min_external_orders_per_side: u64,
+}
+
+impl StrategySnapshot {
+    pub fn new(strategy: Pubkey, state: &PhoenixStrategyState) -> Self {
+        Self {
+            strategy,
+            trader: state.trader,
+            market: state.market,
+            volatility_oracle_pubkey: state.volatility_oracle_pubkey,
+            bid_order_sequence_number: state.bid_order_sequence_number,
+            bid_price_in_ticks: state.bid_price_in_ticks,
+            initial_bid_size_in_base_lots: state.initial_bid_size_in_base_lots,
+            ask_order_sequence_number: state.ask_order_sequence_number,
+            ask_price_in_ticks: state.ask_price_in_ticks,
+            initial_ask_size_in_base_lots: state.initial_ask_size_in_base_lots,
+            last_update_slot: state.last_update_slot,
+            last_update_unix_timestamp: state.last_update_unix_timestamp,
+            quote_edge_in_bps: state.quote_edge_in_bps,
+            quote_size_in_quote_atoms: state.quote_size_in_quote_atoms,
+            last_fair_price_in_quote_atoms_per_raw_base_unit: state
+                .last_fair_price_in_quote_atoms_per_raw_base_unit,
+            size_decay_factor_in_bps: state.size_decay_factor_in_bps,
+            client_order_id_counter: state.client_order_id_counter,
+            min_spread_in_bps: state.min_spread_in_bps,
+            time_since_last_fill_decay_factor_in_bps: state
+                .time_since_last_fill_decay_factor_in_bps,
+            max_edge_in_bps: state.max_edge_in_bps,
+            last_fill_unix_timestamp: state.last_fill_unix_timestamp,
+            cumulative_bid_fill_in_base_lots: state.cumulative_bid_fill_in_base_lots,
+            cumulative_ask_fill_in_base_lots: state.cumulative_ask_fill_in_base_lots,
+            tracked_bid_fill_in_base_lots: state.tracked_bid_fill_in_base_lots,
+            tracked_ask_fill_in_base_lots: state.tracked_ask_fill_in_base_lots,
+            rolling_price_mean_x32: state.rolling_price_mean_x32,
+            rolling_price_variance_x64: state.rolling_price_variance_x64,
+            price_update_count: state.price_update_count,
+            volatility_multiplier_in_bps: state.volatility_multiplier_in_bps,
+            min_warmup_updates: state.min_warmup_updates,
+            max_taker_exposure_in_quote_atoms: state.max_taker_exposure_in_quote_atoms,
+            max_slot_age_for_price: state.max_slot_age_for_price,
+            size_curve_slope_in_bps: state.size_curve_slope_in_bps,
+            size_curve_scaling_factor: state.size_curve_scaling_factor,
+            min_deposited_quote_atoms: state.min_deposited_quote_atoms,
+            min_deposited_base_lots: state.min_deposited_base_lots,
+            cumulative_maker_bid_volume_in_base_lots: state
+                .cumulative_maker_bid_volume_in_base_lots,
+            cumulative_maker_ask_volume_in_base_lots: state
+                .cumulative_maker_ask_volume_in_base_lots,
+            cumulative_taker_bid_volume_in_base_lots: state
+                .cumulative_taker_bid_volume_in_base_lots,
+            cumulative_taker_ask_volume_in_base_lots: state
+                .cumulative_taker_ask_volume_in_base_lots,
+            max_oracle_staleness_in_slots: state.max_oracle_staleness_in_slots,
+            quote_refresh_interval_in_seconds: state.quote_refresh_interval_in_seconds,
+            cached_tick_size_in_quote_atoms: state.cached_tick_size_in_quote_atoms,
+            cached_base_lot_size: state.cached_base_lot_size,
+            cached_quote_lot_size: state.cached_quote_lot_size,
+            cached_raw_base_units_per_base_unit: state.cached_raw_base_units_per_base_unit,
+            post_only: state.post_only,
+            price_improvement_behavior: state.price_improvement_behavior,
+            strategy_id: state.strategy_id,
+            use_only_deposited_funds: state.use_only_deposited_funds,
+            client_order_id_mode: state.client_order_id_mode,
+            sizing_mode: state.sizing_mode,
+            size_adjustment_curve: state.size_adjustment_curve,
+            client_metadata: state.client_metadata,
+            version: state.version,
+            price_precision_mode: state.price_precision_mode,
+            order_refresh_interval_in_seconds: state.order_refresh_interval_in_seconds,
+            last_order_refresh_unix_timestamp: state.last_order_refresh_unix_timestamp,
+            taker_fee_in_bps: state.taker_fee_in_bps,
+            maker_rebate_in_bps: state.maker_rebate_in_bps,
+            min_external_orders_per_side: state.min_external_orders_per_side,
+        }
+    }
+}
+
+// Errors are grouped by category, each with its own range of error codes:
+// 6000 configuration, 6100 Phoenix market, 6200 risk limits and 6300 price input.
+//
+// An error's category is the condition it reports, not the enum it is declared in:
+// - Market: the Phoenix market or its book is not in the state the strategy expects
+// - Risk: the quotes or funds would break a risk limit
+// - Oracle: a price input is invalid or stale
+// - Config: the strategy's parameters, accounts or state are invalid
+// The 6100, 6200 and 6300 enums each hold a single category. `StrategyError` also holds the
+// variants that predate the categories and conditions that were added by name as
+// `StrategyError` variants, so those variants report their category through
+// `StrategyError::category`.
+
+/// Configuration and strategy state errors, along with the other conditions listed in
+/// `StrategyError::category`. Variants marked as legacy predate the categories and are no longer
+/// raised, they keep their codes so that existing clients can still decode them.
 #[error_code]
 pub enum StrategyError {
+    /// Legacy, never raised
     NoReturnData,
     InvalidStrategyParams,
     EdgeMustBeNonZero,
+    /// Legacy, raised as `MarketError::InvalidPhoenixProgram`
     InvalidPhoenixProgram,
+    /// Legacy, raised as `MarketError::FailedToDeserializePhoenixMarket`
     FailedToDeserializePhoenixMarket,
+    InvalidSizeDecayFactor,
+    CorruptStrategyState,
+    MarketParamsChanged,
+    InvalidAccountDiscriminator,
+    PriceTooStale,
+    InvalidSizingMode,
+    InvalidSizeAdjustmentCurve,
+    /// Logged by `update_quotes` without being returned, so that the cancellation is kept
+    MarketIsCrossed,
+    StaleVolatilityOracle,
+    InvalidStateVersion,
+    InvalidPricePrecisionMode,
+    /// `initialize` was called on a strategy that has resting orders
+    StrategyAlreadyActive,
+    /// Logged by `update_quotes` without being returned when a new order is missing from the book
+    OrderNotPlaced,
+    /// The user's `StrategyRegistry` already holds `MAX_REGISTRY_ENTRIES` strategies
+    RegistryFull,
+}
+
+/// Name for the 6000 range. The categories are separate `#[error_code]` enums because Anchor error
+/// codes are flat, so there is no `StrategyError::Config(ConfigError)` variant to match on.
+pub type ConfigError = StrategyError;
+
+#[error_code(offset = 6100)]
+pub enum MarketError {
+    InvalidPhoenixProgram,
+    FailedToDeserializePhoenixMarket,
+    MarketNotActive,
+    MarketMismatch,
+    TraderNotRegistered,
+    NoLiquidity,
+}
+
+#[error_code(offset = 6200)]
+pub enum RiskError {
+    InsufficientFunds,
+    SpreadBelowMinimum,
+    CrossedQuotes,
+}
+
+#[error_code(offset = 6300)]
+pub enum OracleError {
+    InvalidFairPrice,
+    InvalidVolatilityOracle,
+    InexactPrice,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCategory {
+    Config,
+    Market,
+    Risk,
+    Oracle,
+}
+
+impl StrategyError {
+    /// Every variant, in the order of their codes
+    pub const ALL: [StrategyError; 19] = [
+        StrategyError::NoReturnData,
+        StrategyError::InvalidStrategyParams,
+        StrategyError::EdgeMustBeNonZero,
+        StrategyError::InvalidPhoenixProgram,
+        StrategyError::FailedToDeserializePhoenixMarket,
+        StrategyError::InvalidSizeDecayFactor,
+        StrategyError::CorruptStrategyState,
+        StrategyError::MarketParamsChanged,
+        StrategyError::InvalidAccountDiscriminator,
+        StrategyError::PriceTooStale,
+        StrategyError::InvalidSizingMode,
+        StrategyError::InvalidSizeAdjustmentCurve,
+        StrategyError::MarketIsCrossed,
+        StrategyError::StaleVolatilityOracle,
+        StrategyError::InvalidStateVersion,
+        StrategyError::InvalidPricePrecisionMode,
+        StrategyError::StrategyAlreadyActive,
+        StrategyError::OrderNotPlaced,
+        StrategyError::RegistryFull,
+    ];
+
+    /// Returns the category of the condition that the variant reports, see `ErrorCategory`.
+    pub fn category(self) -> ErrorCategory {
+        match self {
+            StrategyError::InvalidPhoenixProgram
+            | StrategyError::FailedToDeserializePhoenixMarket
+            | StrategyError::MarketParamsChanged
+            | StrategyError::MarketIsCrossed
+            | StrategyError::OrderNotPlaced => ErrorCategory::Market,
+            StrategyError::PriceTooStale | StrategyError::StaleVolatilityOracle => {
+                ErrorCategory::Oracle
+            }
+            StrategyError::NoReturnData
+            | StrategyError::InvalidStrategyParams
+            | StrategyError::EdgeMustBeNonZero
+            | StrategyError::InvalidSizeDecayFactor
+            | StrategyError::CorruptStrategyState
+            | StrategyError::InvalidAccountDiscriminator
+            | StrategyError::InvalidSizingMode
+            | StrategyError::InvalidSizeAdjustmentCurve
+            | StrategyError::InvalidStateVersion
+            | StrategyError::InvalidPricePrecisionMode
+            | StrategyError::StrategyAlreadyActive
+            | StrategyError::RegistryFull => ErrorCategory::Config,
+        }
+    }
+}
+
+impl ErrorCategory {
+    /// Returns the category of a custom error code returned by this program, including the
+    /// `StrategyError` codes of other categories, see `StrategyError::category`.
+    pub fn from_error_code(code: u32) -> Option<Self> {
+        match code {
+            6000..=6099 => Some(
+                StrategyError::ALL
+                    .into_iter()
+                    .find(|error| u32::from(*error) == code)
+                    .map_or(ErrorCategory::Config, StrategyError::category),
+            ),
+            6100..=6199 => Some(ErrorCategory::Market),
+            6200..=6299 => Some(ErrorCategory::Risk),
+            6300..=6399 => Some(ErrorCategory::Oracle),
+            _ => None,
+        }
+    }
 }
@@ -0,0 +1,213 @@
+use crate::{PhoenixStrategyState, StrategyError, PHOENIX_STRATEGY_DISCRIMINATOR};
+use anchor_lang::{
+    __private::bytemuck::{self, Zeroable},
+    prelude::*,
+};
+
+/// Layout version of `PhoenixStrategyState` written by `initialize`.
+pub const STRATEGY_STATE_VERSION: u8 = 5;
+
+/// Size of the strategy state in version 4, before the liquidity check was appended.
+pub const STRATEGY_STATE_V4_SIZE: usize = std::mem::size_of::<PhoenixStrategyState>() - 8;
+
+/// Size of the strategy state in version 3, before the fee fields were appended.
+pub const STRATEGY_STATE_V3_SIZE: usize = STRATEGY_STATE_V4_SIZE - 16;
+
+/// Size of the strategy state in version 2, before the order refresh fields were appended.
+pub const STRATEGY_STATE_V2_SIZE: usize = STRATEGY_STATE_V3_SIZE - 16;
+
+/// Size of the strategy state in version 1, the layout of the original program.
+pub const STRATEGY_STATE_V1_SIZE: usize = std::mem::size_of::<PhoenixStrategyStateV1>();
+
+/// Layout of the strategy state in version 1, before the version field existed. Every strategy
+/// created by the original program has this layout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+pub struct PhoenixStrategyStateV1 {
+    pub trader: Pubkey,
+    pub market: Pubkey,
+    pub bid_order_sequence_number: u64,
+    pub bid_price_in_ticks: u64,
+    pub initial_bid_size_in_base_lots: u64,
+    pub ask_order_sequence_number: u64,
+    pub ask_price_in_ticks: u64,
+    pub initial_ask_size_in_base_lots: u64,
+    pub last_update_slot: u64,
+    pub last_update_unix_timestamp: i64,
+    pub quote_edge_in_bps: u64,
+    pub quote_size_in_quote_atoms: u64,
+    /// A bool, stored as a byte so that any account data is a valid value
+    pub post_only: u8,
+    pub price_improvement_behavior: u8,
+    pub padding: [u8; 6],
+}
+
+// SAFETY: every field is plain old data and `repr(C)` leaves no padding between them
+unsafe impl Zeroable for PhoenixStrategyStateV1 {}
+unsafe impl bytemuck::Pod for PhoenixStrategyStateV1 {}
+
+/// Returns the size of the strategy state in layout `state_version`, excluding the discriminator.
+pub fn get_state_size(state_version: u8) -> usize {
+    match state_version {
+        0 | 1 => STRATEGY_STATE_V1_SIZE,
+        2 => STRATEGY_STATE_V2_SIZE,
+        3 => STRATEGY_STATE_V3_SIZE,
+        4 => STRATEGY_STATE_V4_SIZE,
+        _ => std::mem::size_of::<PhoenixStrategyState>(),
+    }
+}
+
+/// Returns the layout version of a strategy account from its data, including the discriminator.
+/// Strategies created before the version field existed store 0 and are version 1.
+pub fn get_state_version(data: &[u8]) -> Result<u8> {
+    Ok(load_state(data)?.version.max(1))
+}
+
+/// Reads the strategy from account data of any layout version, including the discriminator.
+/// Version 1 accounts are converted as `MigrationV1ToV2` would, later versions only append fields,
+/// so the fields that an older account predates are zero.
+pub fn load_state(data: &[u8]) -> Result<PhoenixStrategyState> {
+    require!(
+        data.get(..8) == Some(&PHOENIX_STRATEGY_DISCRIMINATOR[..]),
+        StrategyError::InvalidAccountDiscriminator
+    );
+    let data = &data[8..];
+    if data.len() < STRATEGY_STATE_V1_SIZE {
+        msg!("Strategy account has an unsupported layout");
+        return err!(StrategyError::InvalidStateVersion);
+    }
+    let mut state = PhoenixStrategyState::zeroed();
+    let len = data.len().min(std::mem::size_of::<PhoenixStrategyState>());
+    bytemuck::bytes_of_mut(&mut state)[..len].copy_from_slice(&data[..len]);
+    // The version byte lies past the end of a version 1 account, or in the zeroed bytes that it
+    // was resized with
+    if state.version == 0 {
+        return Ok(MigrationV1ToV2::convert(bytemuck::pod_read_unaligned(
+            &data[..STRATEGY_STATE_V1_SIZE],
+        )));
+    }
+    Ok(state)
+}
+
+/// Writes the fields of `state` that fit in the account data, including the discriminator.
+fn store_state(data: &mut [u8], state: &PhoenixStrategyState) {
+    let data = &mut data[8..];
+    let len = data.len().min(std::mem::size_of::<PhoenixStrategyState>());
+    data[..len].copy_from_slice(&bytemuck::bytes_of(state)[..len]);
+}
+
+/// Migrates a strategy account's data, including the discriminator, from the previous layout
+/// version to `state_version`. Versions are migrated one at a time, so that every step only has
+/// to know about the layout before it. The data must already have the size of the new layout.
+pub fn migrate_state_data(data: &mut [u8], state_version: u8) -> Result<()> {
+    let current_version = get_state_version(data)?;
+    if state_version != current_version + 1 || state_version > STRATEGY_STATE_VERSION {
+        msg!(
+            "Cannot migrate strategy from version {} to {}, the latest version is {}",
+            current_version,
+            state_version,
+            STRATEGY_STATE_VERSION
+        );
+        return err!(StrategyError::InvalidStateVersion);
+    }
+    if data.len() < 8 + get_state_size(state_version) {
+        msg!(
+            "Strategy account must be resized to {} bytes before migrating to version {}",
+            8 + get_state_size(state_version),
+            state_version
+        );
+        return err!(StrategyError::InvalidStateVersion);
+    }
+    match state_version {
+        2 => MigrationV1ToV2::migrate(data)?,
+        3 => MigrationV2ToV3::migrate(data)?,
+        4 => MigrationV3ToV4::migrate(data)?,
+        5 => MigrationV4ToV5::migrate(data)?,
+        _ => return err!(StrategyError::InvalidStateVersion),
+    }
+    msg!(
+        "Migrated strategy from version {} to {}",
+        current_version,
+        state_version
+    );
+    Ok(())
+}
+
+/// Version 1 is the layout of the original program, see `PhoenixStrategyStateV1`. Version 2 keeps
+/// its fields up to `quote_size_in_quote_atoms` in place, inserts the fields added since before
+/// `post_only` and `price_improvement_behavior`, and adds the version. The added fields are zeroed,
+/// except for the size decay that is set to its default of no decay. The cached market params are
+/// zero as well, so updates fail with `MarketParamsChanged` until the strategy is reinitialized.
+pub struct MigrationV1ToV2;
+
+impl MigrationV1ToV2 {
+    pub fn migrate(data: &mut [u8]) -> Result<()> {
+        let mut state = load_state(data)?;
+        state.version = 2;
+        // The whole state is written, since every field after `quote_size_in_quote_atoms` moved
+        store_state(data, &state);
+        Ok(())
+    }
+
+    /// Moves the fields of a version 1 strategy into the current layout.
+    pub fn convert(state: PhoenixStrategyStateV1) -> PhoenixStrategyState {
+        PhoenixStrategyState {
+            trader: state.trader,
+            market: state.market,
+            bid_order_sequence_number: state.bid_order_sequence_number,
+            bid_price_in_ticks: state.bid_price_in_ticks,
+            initial_bid_size_in_base_lots: state.initial_bid_size_in_base_lots,
+            ask_order_sequence_number: state.ask_order_sequence_number,
+            ask_price_in_ticks: state.ask_price_in_ticks,
+            initial_ask_size_in_base_lots: state.initial_ask_size_in_base_lots,
+            last_update_slot: state.last_update_slot,
+            last_update_unix_timestamp: state.last_update_unix_timestamp,
+            quote_edge_in_bps: state.quote_edge_in_bps,
+            quote_size_in_quote_atoms: state.quote_size_in_quote_atoms,
+            size_decay_factor_in_bps: 10_000,
+            post_only: state.post_only != 0,
+            price_improvement_behavior: state.price_improvement_behavior,
+            version: 1,
+            ..PhoenixStrategyState::zeroed()
+        }
+    }
+}
+
+/// Version 3 appends `order_refresh_interval_in_seconds` and `last_order_refresh_unix_timestamp`.
+/// The resized account is zeroed, which disables the order refresh.
+pub struct MigrationV2ToV3;
+
+impl MigrationV2ToV3 {
+    pub fn migrate(data: &mut [u8]) -> Result<()> {
+        let mut state = load_state(data)?;
+        state.version = 3;
+        store_state(data, &state);
+        Ok(())
+    }
+}
+
+/// Version 4 appends `taker_fee_in_bps` and `maker_rebate_in_bps`. The resized account is zeroed,
+/// so the edge is checked against zero fees until they are set.
+pub struct MigrationV3ToV4;
+
+impl MigrationV3ToV4 {
+    pub fn migrate(data: &mut [u8]) -> Result<()> {
+        let mut state = load_state(data)?;
+        state.version = 4;
+        store_state(data, &state);
+        Ok(())
+    }
+}
+
+/// Version 5 appends `min_external_orders_per_side`. The resized account is zeroed, which quotes
+/// regardless of the depth of the book.
+pub struct MigrationV4ToV5;
+
+impl MigrationV4ToV5 {
+    pub fn migrate(data: &mut [u8]) -> Result<()> {
+        let mut state = load_state(data)?;
+        state.version = 5;
+        store_state(data, &state);
+        Ok(())
+    }
+}
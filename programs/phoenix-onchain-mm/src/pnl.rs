@@ -0,0 +1,32 @@
+/// Returns the theoretical PnL in quote atoms of buying `bid_fill_base_lots` at the bid and selling
+/// `ask_fill_base_lots` at the ask. Any net position left over is marked at the midpoint of the two
+/// quotes, so balanced fills earn exactly the spread.
+///
+/// Prices are converted to quote atoms per base lot with the market's tick size and number of base
+/// lots per base unit. The result saturates at the bounds of `i64`.
+pub fn calculate_theoretical_pnl(
+    bid_fill_base_lots: u64,
+    ask_fill_base_lots: u64,
+    bid_price_in_ticks: u64,
+    ask_price_in_ticks: u64,
+    tick_size_in_quote_atoms_per_base_unit: u64,
+    base_lots_per_base_unit: u64,
+) -> i64 {
+    if base_lots_per_base_unit == 0 {
+        return 0;
+    }
+    let (bid_fill, ask_fill) = (bid_fill_base_lots as i128, ask_fill_base_lots as i128);
+    let (bid_price, ask_price) = (bid_price_in_ticks as i128, ask_price_in_ticks as i128);
+
+    // Work in half ticks so that the midpoint is exact
+    let realized = ask_fill
+        .saturating_mul(ask_price)
+        .saturating_sub(bid_fill.saturating_mul(bid_price))
+        .saturating_mul(2);
+    let unrealized = (bid_fill - ask_fill).saturating_mul(bid_price + ask_price);
+    let pnl_in_quote_atoms = realized
+        .saturating_add(unrealized)
+        .saturating_mul(tick_size_in_quote_atoms_per_base_unit as i128)
+        / (2 * base_lots_per_base_unit as i128);
+    pnl_in_quote_atoms.clamp(i64::MIN as i128, i64::MAX as i128) as i64
+}
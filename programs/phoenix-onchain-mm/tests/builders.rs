@@ -0,0 +1,115 @@
+use anchor_lang::prelude::*;
+use phoenix_onchain_mm::{
+    OracleError, OrderParams, OrderParamsBuilder, PriceImprovementBehavior, StrategyError,
+    StrategyParams, StrategyParamsBuilder,
+};
+
+#[test]
+fn test_strategy_params_builder() {
+    let params = StrategyParamsBuilder::new()
+        .edge_in_bps(5)
+        .size_in_quote_atoms(100_000_000)
+        .price_improvement(PriceImprovementBehavior::Join)
+        .post_only(true)
+        .build()
+        .unwrap();
+    assert_eq!(params.quote_edge_in_bps, Some(5));
+    assert_eq!(params.quote_size_in_quote_atoms, Some(100_000_000));
+    assert_eq!(
+        params.price_improvement_behavior.map(|b| b.to_u8()),
+        Some(PriceImprovementBehavior::Join.to_u8())
+    );
+    assert_eq!(params.post_only, Some(true));
+    assert_eq!(params.use_only_deposited_funds, None);
+}
+
+#[test]
+fn test_strategy_params_builder_requires_fields() {
+    assert!(StrategyParamsBuilder::new().build().is_err());
+    assert!(StrategyParamsBuilder::new()
+        .edge_in_bps(5)
+        .size_in_quote_atoms(100_000_000)
+        .build()
+        .is_err());
+}
+
+#[test]
+fn test_order_params_builder() {
+    let strategy_params = StrategyParamsBuilder::new()
+        .edge_in_bps(5)
+        .size_in_quote_atoms(100_000_000)
+        .price_improvement(PriceImprovementBehavior::Ignore)
+        .build()
+        .unwrap();
+    let params = OrderParamsBuilder::new()
+        .fair_price_in_quote_atoms_per_raw_base_unit(20_000_000)
+        .strategy_params(strategy_params)
+        .build()
+        .unwrap();
+    assert_eq!(
+        params.fair_price_in_quote_atoms_per_raw_base_unit,
+        20_000_000
+    );
+    assert!(OrderParamsBuilder::new()
+        .strategy_params(strategy_params)
+        .build()
+        .is_err());
+}
+
+#[test]
+fn test_strategy_params_validation() {
+    let params = StrategyParams {
+        quote_edge_in_bps: Some(5),
+        ..StrategyParams::default()
+    };
+    // Updates may leave out the parameters that initialize requires
+    assert!(params.validate(false).is_ok());
+    assert_eq!(
+        params.validate(true).unwrap_err(),
+        error!(StrategyError::InvalidStrategyParams)
+    );
+    for (params, error) in [
+        (
+            StrategyParams {
+                quote_edge_in_bps: Some(0),
+                ..params
+            },
+            error!(StrategyError::EdgeMustBeNonZero),
+        ),
+        (
+            StrategyParams {
+                quote_size_in_quote_atoms: Some(0),
+                ..params
+            },
+            error!(StrategyError::InvalidStrategyParams),
+        ),
+        (
+            StrategyParams {
+                size_decay_factor_in_bps: Some(10_001),
+                ..params
+            },
+            error!(StrategyError::InvalidSizeDecayFactor),
+        ),
+    ] {
+        assert_eq!(params.validate(false).unwrap_err(), error);
+    }
+}
+
+#[test]
+fn test_order_params_validation() {
+    let params = OrderParams {
+        fair_price_in_quote_atoms_per_raw_base_unit: 0,
+        strategy_params: StrategyParams::default(),
+        submitted_slot: 0,
+    };
+    assert_eq!(
+        params.validate().unwrap_err(),
+        error!(OracleError::InvalidFairPrice)
+    );
+    assert!(OrderParams {
+        fair_price_in_quote_atoms_per_raw_base_unit: 1,
+        ..params
+    }
+    .validate()
+    .is_ok());
+}
@@ -0,0 +1,22 @@
+use anchor_lang::prelude::Pubkey;
+use phoenix_onchain_mm::{get_client_order_id, ClientOrderIdMode};
+
+#[test]
+fn test_pubkey_mode_ignores_counter() {
+    let trader = Pubkey::new_unique();
+    assert_eq!(
+        get_client_order_id(ClientOrderIdMode::Pubkey, &trader, 1),
+        get_client_order_id(ClientOrderIdMode::Pubkey, &trader, 2)
+    );
+}
+
+#[test]
+fn test_counter_mode_is_unique_per_update() {
+    let trader = Pubkey::new_unique();
+    let first = get_client_order_id(ClientOrderIdMode::Counter, &trader, 1);
+    let second = get_client_order_id(ClientOrderIdMode::Counter, &trader, 2);
+    assert_ne!(first, second);
+    // The lower 64 bits identify the trader and the upper 64 bits hold the counter
+    assert_eq!(first as u64, second as u64);
+    assert_eq!((second >> 64) as u64, 2);
+}
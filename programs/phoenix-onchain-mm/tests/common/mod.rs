@@ -0,0 +1,181 @@
+// Each integration test only uses part of the shared helpers
+#![allow(dead_code)]
+
+use anchor_lang::__private::bytemuck::{self, Zeroable};
+use anchor_lang::prelude::Pubkey;
+use phoenix::program::accounts::{MarketSizeParams, TokenParams};
+use phoenix::program::{load_with_dispatch, MarketHeader};
+use phoenix::quantities::QuoteLots;
+use phoenix::quantities::{
+    BaseAtomsPerBaseLot, BaseLots, BaseLotsPerBaseUnit, QuoteAtomsPerBaseUnitPerTick,
+    QuoteAtomsPerQuoteLot, QuoteLotsPerBaseUnitPerTick, WrapperU64,
+};
+use phoenix::state::markets::{FIFOMarket, FIFOOrderId, FIFORestingOrder, Market};
+use phoenix::state::{OrderPacket, TraderState};
+use sokoban::NodeAllocatorMap;
+
+const BOOK_SIZE: usize = 512;
+const NUM_SEATS: usize = 128;
+const BASE_DECIMALS: u32 = 9;
+
+type Dex = FIFOMarket<Pubkey, BOOK_SIZE, BOOK_SIZE, NUM_SEATS>;
+
+const MARKET_SIZE_PARAMS: MarketSizeParams = MarketSizeParams {
+    bids_size: BOOK_SIZE as u64,
+    asks_size: BOOK_SIZE as u64,
+    num_seats: NUM_SEATS as u64,
+};
+
+/// Builds a real Phoenix market in memory, so strategy logic can be tested without a validator.
+/// Resting orders are given as `(price_in_ticks, size_in_base_lots, trader_index)`.
+pub struct MockMarketBuilder {
+    tick_size_in_quote_atoms_per_base_unit: u64,
+    base_lot_size: u64,
+    quote_lot_size: u64,
+    raw_base_units_per_base_unit: u32,
+    bids: Vec<(u64, u64, u64)>,
+    asks: Vec<(u64, u64, u64)>,
+    traders: Vec<(Pubkey, u64, u64)>,
+}
+
+impl Default for MockMarketBuilder {
+    fn default() -> Self {
+        // SOL/USDC-like defaults: 0.001 USDC ticks, 0.001 SOL lots and 1 atom quote lots
+        Self {
+            tick_size_in_quote_atoms_per_base_unit: 1_000,
+            base_lot_size: 1_000_000,
+            quote_lot_size: 1,
+            raw_base_units_per_base_unit: 1,
+            bids: vec![],
+            asks: vec![],
+            traders: vec![],
+        }
+    }
+}
+
+impl MockMarketBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn tick_size(mut self, tick_size_in_quote_atoms_per_base_unit: u64) -> Self {
+        self.tick_size_in_quote_atoms_per_base_unit = tick_size_in_quote_atoms_per_base_unit;
+        self
+    }
+
+    pub fn base_lot_size(mut self, base_atoms_per_base_lot: u64) -> Self {
+        self.base_lot_size = base_atoms_per_base_lot;
+        self
+    }
+
+    pub fn quote_lot_size(mut self, quote_atoms_per_quote_lot: u64) -> Self {
+        self.quote_lot_size = quote_atoms_per_quote_lot;
+        self
+    }
+
+    pub fn raw_base_units_per_base_unit(mut self, raw_base_units_per_base_unit: u32) -> Self {
+        self.raw_base_units_per_base_unit = raw_base_units_per_base_unit;
+        self
+    }
+
+    pub fn add_bid(
+        mut self,
+        price_in_ticks: u64,
+        size_in_base_lots: u64,
+        trader_index: u64,
+    ) -> Self {
+        self.bids
+            .push((price_in_ticks, size_in_base_lots, trader_index));
+        self
+    }
+
+    pub fn add_ask(
+        mut self,
+        price_in_ticks: u64,
+        size_in_base_lots: u64,
+        trader_index: u64,
+    ) -> Self {
+        self.asks
+            .push((price_in_ticks, size_in_base_lots, trader_index));
+        self
+    }
+
+    /// Registers a trader with the given free balances
+    pub fn add_trader(mut self, trader: Pubkey, base_lots_free: u64, quote_lots_free: u64) -> Self {
+        self.traders.push((trader, base_lots_free, quote_lots_free));
+        self
+    }
+
+    pub fn build(self) -> MockMarket {
+        let mut base_params = TokenParams::zeroed();
+        base_params.decimals = BASE_DECIMALS;
+        let header = MarketHeader::new(
+            MARKET_SIZE_PARAMS,
+            base_params,
+            BaseAtomsPerBaseLot::new(self.base_lot_size),
+            TokenParams::zeroed(),
+            QuoteAtomsPerQuoteLot::new(self.quote_lot_size),
+            QuoteAtomsPerBaseUnitPerTick::new(self.tick_size_in_quote_atoms_per_base_unit),
+            Pubkey::default(),
+            Pubkey::default(),
+            Pubkey::default(),
+            self.raw_base_units_per_base_unit,
+        );
+
+        // Back the buffer with u128s so the market is correctly aligned
+        let mut data = vec![0u128; std::mem::size_of::<Dex>() / 16 + 1];
+        let bytes: &mut [u8] = bytemuck::cast_slice_mut(&mut data);
+        let market: &mut Dex = bytemuck::from_bytes_mut(&mut bytes[..std::mem::size_of::<Dex>()]);
+        market.bids.initialize();
+        market.asks.initialize();
+        market.traders.initialize();
+        market.base_lots_per_base_unit =
+            BaseLotsPerBaseUnit::new(10u64.pow(BASE_DECIMALS) / self.base_lot_size);
+        market.tick_size_in_quote_lots_per_base_unit = QuoteLotsPerBaseUnitPerTick::new(
+            self.tick_size_in_quote_atoms_per_base_unit / self.quote_lot_size,
+        );
+
+        for (trader, base_lots_free, quote_lots_free) in self.traders {
+            let mut state = TraderState::default();
+            state.base_lots_free = BaseLots::new(base_lots_free);
+            state.quote_lots_free = QuoteLots::new(quote_lots_free);
+            market.traders.insert(trader, state);
+        }
+
+        let mut sequence_number = 0;
+        for (price_in_ticks, size_in_base_lots, trader_index) in self.bids {
+            sequence_number += 1;
+            // Bid sequence numbers are inverted so that older orders sort first
+            market.bids.insert(
+                FIFOOrderId::new_from_untyped(price_in_ticks, !sequence_number),
+                FIFORestingOrder::new_default(trader_index, BaseLots::new(size_in_base_lots)),
+            );
+        }
+        for (price_in_ticks, size_in_base_lots, trader_index) in self.asks {
+            sequence_number += 1;
+            market.asks.insert(
+                FIFOOrderId::new_from_untyped(price_in_ticks, sequence_number),
+                FIFORestingOrder::new_default(trader_index, BaseLots::new(size_in_base_lots)),
+            );
+        }
+        MockMarket { header, data }
+    }
+}
+
+pub struct MockMarket {
+    pub header: MarketHeader,
+    data: Vec<u128>,
+}
+
+impl MockMarket {
+    pub fn market(&self) -> &dyn Market<Pubkey, FIFOOrderId, FIFORestingOrder, OrderPacket> {
+        let bytes = &bytemuck::cast_slice(&self.data)[..std::mem::size_of::<Dex>()];
+        load_with_dispatch(&MARKET_SIZE_PARAMS, bytes)
+            .unwrap()
+            .inner
+    }
+
+    pub fn tick_size_in_quote_lots(&self) -> u64 {
+        self.market().get_tick_size().as_u64()
+    }
+}
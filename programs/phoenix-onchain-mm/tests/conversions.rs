@@ -0,0 +1,81 @@
+mod common;
+
+use common::MockMarketBuilder;
+use phoenix_onchain_mm::{
+    base_lots_to_display, compute_break_even_spread_in_bps, get_spread_in_bps,
+    is_spread_profitable, price_usd_to_fair_price, price_usd_to_ticks, quote_atoms_to_usd,
+    tick_to_price_usd,
+};
+
+#[test]
+fn test_tick_price_round_trip() {
+    // 0.001 USDC ticks with 6 quote decimals
+    let mut mock = MockMarketBuilder::new().build();
+    mock.header.quote_params.decimals = 6;
+    assert_eq!(tick_to_price_usd(100_000, &mock.header), 100.0);
+    assert_eq!(price_usd_to_ticks(100.0, &mock.header), 100_000);
+    // Prices between ticks round to the nearest one
+    assert_eq!(price_usd_to_ticks(100.0004, &mock.header), 100_000);
+    assert_eq!(price_usd_to_ticks(100.0006, &mock.header), 100_001);
+    for ticks in [0, 1, 12_345, 987_654_321] {
+        assert_eq!(
+            price_usd_to_ticks(tick_to_price_usd(ticks, &mock.header), &mock.header),
+            ticks
+        );
+    }
+}
+
+#[test]
+fn test_tick_price_accounts_for_raw_base_units() {
+    // Each base unit is 1_000 raw base units, e.g. a market quoted per 1_000 BONK
+    let mut mock = MockMarketBuilder::new()
+        .raw_base_units_per_base_unit(1_000)
+        .build();
+    mock.header.quote_params.decimals = 6;
+    assert_eq!(tick_to_price_usd(100_000, &mock.header), 0.1);
+    assert_eq!(price_usd_to_ticks(0.1, &mock.header), 100_000);
+}
+
+#[test]
+fn test_price_usd_to_fair_price() {
+    let mut mock = MockMarketBuilder::new().build();
+    mock.header.quote_params.decimals = 6;
+    assert_eq!(price_usd_to_fair_price(20.0, &mock.header), 20_000_000);
+    // The fair price is per raw base unit, e.g. per BONK on a market quoted per 1_000 BONK
+    mock.header.raw_base_units_per_base_unit = 1_000;
+    assert_eq!(price_usd_to_fair_price(0.1, &mock.header), 100);
+}
+
+#[test]
+fn test_quote_atoms_to_usd() {
+    let mut mock = MockMarketBuilder::new().build();
+    mock.header.quote_params.decimals = 6;
+    assert_eq!(quote_atoms_to_usd(0, &mock.header), 0.0);
+    assert_eq!(quote_atoms_to_usd(1_500_000, &mock.header), 1.5);
+}
+
+#[test]
+fn test_base_lots_to_display_does_not_overflow() {
+    let mock = MockMarketBuilder::new().build();
+    assert_eq!(
+        base_lots_to_display(u64::MAX, &mock.header),
+        u64::MAX as f64 / 1_000.0
+    );
+}
+
+#[test]
+fn test_break_even_spread() {
+    assert_eq!(compute_break_even_spread_in_bps(5, 0), 10);
+    assert_eq!(compute_break_even_spread_in_bps(5, 2), 6);
+    // A rebate larger than the fee cannot make the break-even spread negative
+    assert_eq!(compute_break_even_spread_in_bps(2, 5), 0);
+}
+
+#[test]
+fn test_spread_profitability() {
+    // 20 ticks around a fair price of 10_000 ticks is 20 bps
+    assert_eq!(get_spread_in_bps(9_990, 10_010, 10_000), 20);
+    assert!(is_spread_profitable(9_990, 10_010, 10_000, 5));
+    assert!(!is_spread_profitable(9_990, 10_010, 10_000, 10));
+    assert!(!is_spread_profitable(9_990, 10_010, 0, 0));
+}
@@ -0,0 +1,87 @@
+use anchor_lang::prelude::*;
+use phoenix_onchain_mm::{ErrorCategory, MarketError, OracleError, RiskError, StrategyError};
+
+fn code(error: Error) -> u32 {
+    match error {
+        Error::AnchorError(error) => error.error_code_number,
+        Error::ProgramError(_) => panic!("not a custom error"),
+    }
+}
+
+#[test]
+fn test_legacy_codes_are_unchanged() {
+    assert_eq!(code(StrategyError::NoReturnData.into()), 6000);
+    assert_eq!(code(StrategyError::InvalidStrategyParams.into()), 6001);
+    assert_eq!(code(StrategyError::EdgeMustBeNonZero.into()), 6002);
+    assert_eq!(code(StrategyError::InvalidPhoenixProgram.into()), 6003);
+    assert_eq!(
+        code(StrategyError::FailedToDeserializePhoenixMarket.into()),
+        6004
+    );
+}
+
+#[test]
+fn test_categories_use_their_own_ranges() {
+    for (error, category) in [
+        (
+            StrategyError::InvalidStrategyParams.into(),
+            ErrorCategory::Config,
+        ),
+        (
+            StrategyError::CorruptStrategyState.into(),
+            ErrorCategory::Config,
+        ),
+        (
+            MarketError::InvalidPhoenixProgram.into(),
+            ErrorCategory::Market,
+        ),
+        (
+            MarketError::TraderNotRegistered.into(),
+            ErrorCategory::Market,
+        ),
+        (RiskError::InsufficientFunds.into(), ErrorCategory::Risk),
+        (RiskError::CrossedQuotes.into(), ErrorCategory::Risk),
+        (OracleError::InvalidFairPrice.into(), ErrorCategory::Oracle),
+        (OracleError::InexactPrice.into(), ErrorCategory::Oracle),
+        // StrategyError variants report the category of their condition
+        (
+            StrategyError::InvalidPhoenixProgram.into(),
+            ErrorCategory::Market,
+        ),
+        (
+            StrategyError::MarketParamsChanged.into(),
+            ErrorCategory::Market,
+        ),
+        (StrategyError::MarketIsCrossed.into(), ErrorCategory::Market),
+        (StrategyError::OrderNotPlaced.into(), ErrorCategory::Market),
+        (
+            StrategyError::StaleVolatilityOracle.into(),
+            ErrorCategory::Oracle,
+        ),
+        (StrategyError::PriceTooStale.into(), ErrorCategory::Oracle),
+    ] {
+        assert_eq!(ErrorCategory::from_error_code(code(error)), Some(category));
+    }
+    assert_eq!(code(MarketError::InvalidPhoenixProgram.into()), 6100);
+    assert_eq!(code(RiskError::InsufficientFunds.into()), 6200);
+    assert_eq!(code(OracleError::InvalidFairPrice.into()), 6300);
+    assert_eq!(ErrorCategory::from_error_code(0), None);
+}
+
+#[test]
+fn test_every_strategy_error_has_a_category() {
+    for (index, error) in StrategyError::ALL.into_iter().enumerate() {
+        let error_code = code(error.into());
+        // Listed in code order, without gaps
+        assert_eq!(error_code, 6000 + index as u32);
+        assert_eq!(
+            ErrorCategory::from_error_code(error_code),
+            Some(error.category())
+        );
+    }
+    // Codes that no variant uses yet fall back to the range
+    assert_eq!(
+        ErrorCategory::from_error_code(6000 + StrategyError::ALL.len() as u32),
+        Some(ErrorCategory::Config)
+    );
+}
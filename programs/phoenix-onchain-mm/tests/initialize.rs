@@ -0,0 +1,27 @@
+use anchor_lang::__private::bytemuck::Zeroable;
+use anchor_lang::prelude::*;
+use phoenix_onchain_mm::{PhoenixStrategyState, StrategyError};
+
+#[test]
+fn test_strategies_without_orders_can_be_initialized() {
+    let mut state = PhoenixStrategyState::zeroed();
+    assert!(state.check_can_initialize().is_ok());
+    // Parameters and fills alone do not block initialization
+    state.quote_edge_in_bps = 10;
+    state.cumulative_bid_fill_in_base_lots = 100;
+    assert!(state.check_can_initialize().is_ok());
+}
+
+#[test]
+fn test_strategies_with_resting_orders_are_not_reinitialized() {
+    let mut bid_only = PhoenixStrategyState::zeroed();
+    bid_only.bid_order_sequence_number = !1;
+    let mut ask_only = PhoenixStrategyState::zeroed();
+    ask_only.ask_order_sequence_number = 1;
+    for state in [bid_only, ask_only] {
+        assert_eq!(
+            state.check_can_initialize().unwrap_err(),
+            error!(StrategyError::StrategyAlreadyActive)
+        );
+    }
+}
@@ -0,0 +1,75 @@
+mod common;
+
+use anchor_lang::__private::bytemuck::Zeroable;
+use anchor_lang::prelude::Pubkey;
+use common::MockMarketBuilder;
+use phoenix_onchain_mm::{
+    base_lots_to_display, get_inventory_balance, get_low_balance_warnings, PhoenixStrategyState,
+};
+
+#[test]
+fn test_trader_without_seat_has_no_inventory() {
+    let mock = MockMarketBuilder::new()
+        .add_trader(Pubkey::new_unique(), 10, 20)
+        .build();
+    assert_eq!(
+        get_inventory_balance(mock.market(), &Pubkey::new_unique()),
+        (0, 0)
+    );
+}
+
+#[test]
+fn test_inventory_balance_reads_free_lots() {
+    let (flat, long, short) = (
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+    );
+    let mock = MockMarketBuilder::new()
+        .add_trader(flat, 0, 0)
+        .add_trader(long, 5_000, 0)
+        .add_trader(short, 0, 7_000_000)
+        .build();
+    assert_eq!(get_inventory_balance(mock.market(), &flat), (0, 0));
+    assert_eq!(get_inventory_balance(mock.market(), &long), (5_000, 0));
+    assert_eq!(get_inventory_balance(mock.market(), &short), (0, 7_000_000));
+}
+
+#[test]
+fn test_base_lots_to_display() {
+    // 0.001 base units per lot with the default builder settings
+    let mock = MockMarketBuilder::new().build();
+    assert_eq!(base_lots_to_display(0, &mock.header), 0.0);
+    assert_eq!(base_lots_to_display(1_000, &mock.header), 1.0);
+    assert_eq!(base_lots_to_display(1_500, &mock.header), 1.5);
+}
+
+#[test]
+fn test_low_balance_warnings() {
+    let mut state = PhoenixStrategyState::zeroed();
+    // Disabled thresholds never warn
+    assert!(get_low_balance_warnings(&state, 0, 0).is_empty());
+
+    state.min_deposited_quote_atoms = 1_000;
+    state.min_deposited_base_lots = 50;
+    assert!(get_low_balance_warnings(&state, 50, 1_000).is_empty());
+
+    let warnings = get_low_balance_warnings(&state, 10, 999);
+    assert_eq!(warnings.len(), 2);
+    assert_eq!(
+        (
+            warnings[0].side.as_str(),
+            warnings[0].balance,
+            warnings[0].threshold
+        ),
+        ("base", 10, 50)
+    );
+    assert_eq!(
+        (
+            warnings[1].side.as_str(),
+            warnings[1].balance,
+            warnings[1].threshold
+        ),
+        ("quote", 999, 1_000)
+    );
+}
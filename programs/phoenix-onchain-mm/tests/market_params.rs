@@ -0,0 +1,63 @@
+mod common;
+
+use anchor_lang::__private::bytemuck::Zeroable;
+use anchor_lang::prelude::*;
+use common::MockMarketBuilder;
+use phoenix_onchain_mm::{PhoenixStrategyState, StrategyError, StrategyParams};
+
+#[test]
+fn test_cached_market_params_validate() {
+    let mock = MockMarketBuilder::new().build();
+    let mut state = PhoenixStrategyState::zeroed();
+    state.cache_market_params(&mock.header);
+    assert_eq!(state.cached_tick_size_in_quote_atoms, 1_000);
+    assert_eq!(state.cached_raw_base_units_per_base_unit, 1);
+    assert_eq!(state.cached_base_lot_size, 1_000_000);
+    assert_eq!(state.cached_quote_lot_size, 1);
+    assert!(state.validate_market_params_unchanged(&mock.header).is_ok());
+}
+
+#[test]
+fn test_market_migrations_are_detected() {
+    let mut state = PhoenixStrategyState::zeroed();
+    state.cache_market_params(&MockMarketBuilder::new().build().header);
+    for migrated in [
+        MockMarketBuilder::new().tick_size(100).build(),
+        MockMarketBuilder::new()
+            .raw_base_units_per_base_unit(1_000)
+            .build(),
+        MockMarketBuilder::new().base_lot_size(1).build(),
+        MockMarketBuilder::new().quote_lot_size(10).build(),
+    ] {
+        assert_eq!(
+            state
+                .validate_market_params_unchanged(&migrated.header)
+                .unwrap_err(),
+            error!(StrategyError::MarketParamsChanged)
+        );
+    }
+}
+
+#[test]
+fn test_apply_params_only_sets_provided_values() {
+    let mut state = PhoenixStrategyState::zeroed();
+    state.quote_size_in_quote_atoms = 2_000;
+    state
+        .apply_params(&StrategyParams {
+            quote_edge_in_bps: Some(25),
+            ..StrategyParams::default()
+        })
+        .unwrap();
+    assert_eq!(state.quote_edge_in_bps, 25);
+    assert_eq!(state.quote_size_in_quote_atoms, 2_000);
+
+    assert_eq!(
+        state
+            .apply_params(&StrategyParams {
+                quote_edge_in_bps: Some(10_001),
+                ..StrategyParams::default()
+            })
+            .unwrap_err(),
+        error!(StrategyError::InvalidStrategyParams)
+    );
+}
@@ -0,0 +1,20 @@
+use anchor_lang::__private::bytemuck::Zeroable;
+use phoenix::program::status::MarketStatus;
+use phoenix::program::MarketHeader;
+use phoenix_onchain_mm::check_market_status;
+
+#[test]
+fn test_only_active_and_post_only_markets_accept_orders() {
+    let mut header = MarketHeader::zeroed();
+    for (status, accepts_orders) in [
+        (MarketStatus::Uninitialized, false),
+        (MarketStatus::Active, true),
+        (MarketStatus::PostOnly, true),
+        (MarketStatus::Paused, false),
+        (MarketStatus::Closed, false),
+        (MarketStatus::Tombstoned, false),
+    ] {
+        header.status = status as u64;
+        assert_eq!(check_market_status(&header).is_ok(), accepts_orders);
+    }
+}
@@ -0,0 +1,168 @@
+use anchor_lang::__private::bytemuck::{self, Zeroable};
+use anchor_lang::prelude::*;
+use phoenix_onchain_mm::{
+    get_state_size, get_state_version, load_state, migrate_state_data, PhoenixStrategyState,
+    PhoenixStrategyStateV1, StrategyError, PHOENIX_STRATEGY_DISCRIMINATOR, STRATEGY_STATE_V2_SIZE,
+    STRATEGY_STATE_VERSION,
+};
+
+fn account_data(state: &PhoenixStrategyState) -> Vec<u8> {
+    let mut data = PHOENIX_STRATEGY_DISCRIMINATOR.to_vec();
+    data.extend_from_slice(bytemuck::bytes_of(state));
+    data
+}
+
+#[test]
+fn test_original_strategies_migrate_to_v2() {
+    let v1 = PhoenixStrategyStateV1 {
+        trader: Pubkey::new_unique(),
+        market: Pubkey::new_unique(),
+        bid_order_sequence_number: !5,
+        bid_price_in_ticks: 99,
+        initial_bid_size_in_base_lots: 10,
+        ask_order_sequence_number: 6,
+        ask_price_in_ticks: 101,
+        initial_ask_size_in_base_lots: 12,
+        last_update_slot: 1_000,
+        last_update_unix_timestamp: 1_700_000_000,
+        quote_edge_in_bps: 25,
+        quote_size_in_quote_atoms: 500_000_000,
+        post_only: 1,
+        price_improvement_behavior: 2,
+        padding: [0; 6],
+    };
+    let mut data = PHOENIX_STRATEGY_DISCRIMINATOR.to_vec();
+    data.extend_from_slice(bytemuck::bytes_of(&v1));
+    assert_eq!(data.len(), 8 + 152);
+    assert_eq!(get_state_version(&data).unwrap(), 1);
+    assert_eq!(load_state(&data).unwrap().trader, v1.trader);
+
+    // The fields inserted in version 2 must fit before it is migrated
+    assert_eq!(
+        migrate_state_data(&mut data, 2).unwrap_err(),
+        error!(StrategyError::InvalidStateVersion)
+    );
+    data.resize(8 + get_state_size(2), 0);
+    migrate_state_data(&mut data, 2).unwrap();
+    assert_eq!(get_state_version(&data).unwrap(), 2);
+
+    let mut expected = PhoenixStrategyState::zeroed();
+    expected.trader = v1.trader;
+    expected.market = v1.market;
+    expected.bid_order_sequence_number = !5;
+    expected.bid_price_in_ticks = 99;
+    expected.initial_bid_size_in_base_lots = 10;
+    expected.ask_order_sequence_number = 6;
+    expected.ask_price_in_ticks = 101;
+    expected.initial_ask_size_in_base_lots = 12;
+    expected.last_update_slot = 1_000;
+    expected.last_update_unix_timestamp = 1_700_000_000;
+    expected.quote_edge_in_bps = 25;
+    expected.quote_size_in_quote_atoms = 500_000_000;
+    expected.post_only = true;
+    expected.price_improvement_behavior = 2;
+    expected.size_decay_factor_in_bps = 10_000;
+    expected.version = 2;
+    assert_eq!(data, account_data(&expected)[..8 + get_state_size(2)]);
+}
+
+#[test]
+fn test_original_strategies_migrate_to_the_latest_version() {
+    let v1 = PhoenixStrategyStateV1 {
+        quote_edge_in_bps: 25,
+        ..PhoenixStrategyStateV1::zeroed()
+    };
+    let mut data = PHOENIX_STRATEGY_DISCRIMINATOR.to_vec();
+    data.extend_from_slice(bytemuck::bytes_of(&v1));
+    for state_version in 2..=STRATEGY_STATE_VERSION {
+        data.resize(8 + get_state_size(state_version), 0);
+        migrate_state_data(&mut data, state_version).unwrap();
+    }
+    let state = load_state(&data).unwrap();
+    assert_eq!(state.version, STRATEGY_STATE_VERSION);
+    assert_eq!(state.quote_edge_in_bps, 25);
+    assert_eq!(data.len(), 8 + std::mem::size_of::<PhoenixStrategyState>());
+}
+
+#[test]
+fn test_migrations_are_applied_one_version_at_a_time() {
+    let mut data = account_data(&PhoenixStrategyState::zeroed());
+    for state_version in [0, 1, STRATEGY_STATE_VERSION + 1] {
+        assert_eq!(
+            migrate_state_data(&mut data, state_version).unwrap_err(),
+            error!(StrategyError::InvalidStateVersion)
+        );
+    }
+    migrate_state_data(&mut data, 2).unwrap();
+    // Already migrated
+    assert_eq!(
+        migrate_state_data(&mut data, 2).unwrap_err(),
+        error!(StrategyError::InvalidStateVersion)
+    );
+}
+
+#[test]
+fn test_other_accounts_are_rejected() {
+    let mut data = account_data(&PhoenixStrategyState::zeroed());
+    data[0] ^= 1;
+    assert_eq!(
+        get_state_version(&data).unwrap_err(),
+        error!(StrategyError::InvalidAccountDiscriminator)
+    );
+    let data = PHOENIX_STRATEGY_DISCRIMINATOR.to_vec();
+    assert_eq!(
+        get_state_version(&data).unwrap_err(),
+        error!(StrategyError::InvalidStateVersion)
+    );
+}
+
+#[test]
+fn test_v2_strategies_are_resized_and_migrate_to_v3() {
+    let mut state = PhoenixStrategyState::zeroed();
+    state.quote_edge_in_bps = 25;
+    state.version = 2;
+    let mut data = account_data(&state);
+    data.truncate(8 + STRATEGY_STATE_V2_SIZE);
+    assert_eq!(get_state_version(&data).unwrap(), 2);
+
+    // The appended fields must fit before the version is bumped
+    assert_eq!(
+        migrate_state_data(&mut data, 3).unwrap_err(),
+        error!(StrategyError::InvalidStateVersion)
+    );
+    data.resize(8 + get_state_size(3), 0);
+    migrate_state_data(&mut data, 3).unwrap();
+    state.version = 3;
+    assert_eq!(data, account_data(&state)[..8 + get_state_size(3)]);
+}
+
+#[test]
+fn test_v4_strategies_are_resized_and_migrate_to_v5() {
+    let mut state = PhoenixStrategyState::zeroed();
+    state.taker_fee_in_bps = 5;
+    state.version = 4;
+    let mut data = account_data(&state);
+    data.truncate(8 + get_state_size(4));
+    assert_eq!(get_state_version(&data).unwrap(), 4);
+
+    data.resize(8 + get_state_size(5), 0);
+    migrate_state_data(&mut data, 5).unwrap();
+    state.version = 5;
+    assert_eq!(data, account_data(&state)[..8 + get_state_size(5)]);
+    assert_eq!(load_state(&data).unwrap().min_external_orders_per_side, 0);
+}
+
+#[test]
+fn test_v2_strategies_migrate_to_the_latest_version() {
+    let mut state = PhoenixStrategyState::zeroed();
+    state.quote_edge_in_bps = 25;
+    state.version = 2;
+    let mut data = account_data(&state);
+    data.truncate(8 + STRATEGY_STATE_V2_SIZE);
+    for state_version in 3..=STRATEGY_STATE_VERSION {
+        data.resize(8 + get_state_size(state_version), 0);
+        migrate_state_data(&mut data, state_version).unwrap();
+    }
+    state.version = STRATEGY_STATE_VERSION;
+    assert_eq!(data, account_data(&state));
+}
@@ -0,0 +1,234 @@
+mod common;
+
+use anchor_lang::__private::bytemuck::Zeroable;
+use anchor_lang::prelude::*;
+use common::MockMarketBuilder;
+use phoenix::quantities::WrapperU64;
+use phoenix::state::markets::FIFOOrderId;
+use phoenix::state::Side;
+use phoenix_onchain_mm::{
+    apply_price_improvement, count_external_orders, get_ask_price_in_ticks, get_best_bid_and_ask,
+    get_bid_price_in_ticks, market_order_book_stats, validate_order_was_placed, MarketStats,
+    PhoenixStrategyState, PriceImprovementBehavior, PricePrecisionMode, StrategyError,
+};
+
+const OUR_TRADER_INDEX: u64 = 1;
+const OTHER_TRADER_INDEX: u64 = 2;
+
+#[test]
+fn test_empty_book_falls_back_to_sentinels() {
+    let mock = MockMarketBuilder::new().build();
+    assert_eq!(
+        get_best_bid_and_ask(mock.market(), OUR_TRADER_INDEX),
+        (0, u64::MAX)
+    );
+}
+
+#[test]
+fn test_best_bid_and_ask_skip_our_orders() {
+    let mock = MockMarketBuilder::new()
+        .add_bid(101, 10, OUR_TRADER_INDEX)
+        .add_bid(100, 10, OTHER_TRADER_INDEX)
+        .add_bid(99, 10, OTHER_TRADER_INDEX)
+        .add_ask(102, 10, OUR_TRADER_INDEX)
+        .add_ask(103, 10, OTHER_TRADER_INDEX)
+        .build();
+    assert_eq!(
+        get_best_bid_and_ask(mock.market(), OUR_TRADER_INDEX),
+        (100, 103)
+    );
+}
+
+#[test]
+fn test_book_with_only_our_orders_is_treated_as_empty() {
+    let mock = MockMarketBuilder::new()
+        .add_bid(100, 10, OUR_TRADER_INDEX)
+        .add_ask(103, 10, OUR_TRADER_INDEX)
+        .build();
+    let (best_bid, best_ask) = get_best_bid_and_ask(mock.market(), OUR_TRADER_INDEX);
+    assert_eq!((best_bid, best_ask), (0, u64::MAX));
+
+    // With no external orders, every behavior quotes the unmodified prices
+    for behavior in [
+        PriceImprovementBehavior::Join,
+        PriceImprovementBehavior::Dime,
+        PriceImprovementBehavior::Ignore,
+    ] {
+        assert_eq!(
+            apply_price_improvement(behavior, 98, 105, best_bid, best_ask),
+            (98, 105)
+        );
+    }
+}
+
+#[test]
+fn test_quotes_join_a_one_sided_book() {
+    let mock = MockMarketBuilder::new()
+        .tick_size(10_000)
+        .base_lot_size(1_000)
+        .quote_lot_size(10)
+        .raw_base_units_per_base_unit(1)
+        .add_bid(2_000, 10, OTHER_TRADER_INDEX)
+        .build();
+    assert_eq!(mock.tick_size_in_quote_lots(), 1_000);
+    assert_eq!(
+        mock.market().get_base_lots_per_base_unit().as_u64(),
+        1_000_000
+    );
+
+    // A fair price of 20.00 with 10 bps of edge is 1998 @ 2002 ticks
+    let fair_price = 20_000_000;
+    let mode = PricePrecisionMode::Conservative;
+    let bid = get_bid_price_in_ticks(fair_price, &mock.header, 10, mode).unwrap();
+    let ask = get_ask_price_in_ticks(fair_price, &mock.header, 10, mode).unwrap();
+    assert_eq!((bid, ask), (1_998, 2_002));
+
+    let (best_bid, best_ask) = get_best_bid_and_ask(mock.market(), OUR_TRADER_INDEX);
+    assert_eq!(
+        apply_price_improvement(PriceImprovementBehavior::Join, bid, ask, best_bid, best_ask),
+        (1_998, 2_002)
+    );
+}
+
+#[test]
+fn test_market_stats_summarize_the_book() {
+    let mock = MockMarketBuilder::new()
+        .add_bid(101, 10, OUR_TRADER_INDEX)
+        .add_bid(100, 10, OTHER_TRADER_INDEX)
+        .add_bid(100, 5, OTHER_TRADER_INDEX)
+        .add_bid(99, 1, OTHER_TRADER_INDEX)
+        .add_bid(98, 1, OTHER_TRADER_INDEX)
+        .add_bid(97, 1, OTHER_TRADER_INDEX)
+        .add_bid(96, 1, OTHER_TRADER_INDEX)
+        .add_bid(95, 100, OTHER_TRADER_INDEX)
+        .add_ask(104, 7, OTHER_TRADER_INDEX)
+        .build();
+    assert_eq!(
+        market_order_book_stats(mock.market(), OUR_TRADER_INDEX),
+        MarketStats {
+            best_bid_price_ticks: 100,
+            best_ask_price_ticks: 104,
+            spread_in_ticks: 4,
+            // Five external levels from 100 down to 96, skipping our order
+            bid_depth_5_levels: 19,
+            ask_depth_5_levels: 7,
+            our_bid_price_ticks: Some(101),
+            our_ask_price_ticks: None,
+        }
+    );
+}
+
+#[test]
+fn test_market_stats_on_crossed_and_empty_books() {
+    let crossed = MockMarketBuilder::new()
+        .add_bid(105, 10, OTHER_TRADER_INDEX)
+        .add_ask(103, 10, OTHER_TRADER_INDEX)
+        .build();
+    assert_eq!(
+        market_order_book_stats(crossed.market(), OUR_TRADER_INDEX).spread_in_ticks,
+        0
+    );
+
+    let empty = MockMarketBuilder::new().build();
+    let stats = market_order_book_stats(empty.market(), OUR_TRADER_INDEX);
+    assert_eq!(
+        (stats.best_bid_price_ticks, stats.best_ask_price_ticks),
+        (0, u64::MAX)
+    );
+    assert_eq!((stats.bid_depth_5_levels, stats.ask_depth_5_levels), (0, 0));
+}
+
+#[test]
+fn test_external_orders_are_counted_per_side() {
+    let mock = MockMarketBuilder::new()
+        .add_bid(101, 10, OUR_TRADER_INDEX)
+        .add_bid(100, 10, OTHER_TRADER_INDEX)
+        .add_bid(100, 5, OTHER_TRADER_INDEX)
+        .add_bid(99, 1, OTHER_TRADER_INDEX)
+        .add_ask(102, 10, OUR_TRADER_INDEX)
+        .build();
+    assert_eq!(
+        count_external_orders(mock.market(), OUR_TRADER_INDEX),
+        (3, 0)
+    );
+
+    let empty = MockMarketBuilder::new().build();
+    assert_eq!(
+        count_external_orders(empty.market(), OUR_TRADER_INDEX),
+        (0, 0)
+    );
+}
+
+#[test]
+fn test_post_only_rejection_is_detected_after_placement() {
+    // The new ask rests in the book, the post-only bid crossed the 101 ask and was rejected
+    let mock = MockMarketBuilder::new()
+        .add_ask(101, 10, OTHER_TRADER_INDEX)
+        .add_ask(105, 7, OUR_TRADER_INDEX)
+        .build();
+    let placed_ask = FIFOOrderId::new_from_untyped(105, 2);
+    let rejected_bid = FIFOOrderId::new_from_untyped(102, !3);
+
+    let (order_id, order) =
+        validate_order_was_placed(mock.market(), Side::Ask, &[rejected_bid, placed_ask]).unwrap();
+    assert_eq!(order_id, placed_ask);
+    assert_eq!(order.num_base_lots.as_u64(), 7);
+    // Rejected orders may be missing from the return data entirely
+    for placed_order_ids in [vec![rejected_bid, placed_ask], vec![placed_ask]] {
+        assert_eq!(
+            validate_order_was_placed(mock.market(), Side::Bid, &placed_order_ids).unwrap_err(),
+            error!(StrategyError::OrderNotPlaced)
+        );
+    }
+
+    // The previous bid was filled in full, so only forgetting it avoids tracking a stale order
+    let mut state = PhoenixStrategyState::zeroed();
+    state.bid_order_sequence_number = !1;
+    state.bid_price_in_ticks = 100;
+    state.record_placed_order(Side::Ask, &order_id, 10, order.num_base_lots.as_u64());
+    state.forget_order(Side::Bid);
+    assert!(!state.has_live_bid());
+    assert_eq!(state.bid_price_in_ticks, 0);
+    assert_eq!(
+        (
+            state.ask_order_sequence_number,
+            state.ask_price_in_ticks,
+            state.initial_ask_size_in_base_lots,
+            state.cumulative_taker_ask_volume_in_base_lots
+        ),
+        (2, 105, 7, 3)
+    );
+}
+
+#[test]
+fn test_orders_are_intact_until_filled() {
+    let mut state = PhoenixStrategyState::zeroed();
+    state.bid_order_sequence_number = !1;
+    state.bid_price_in_ticks = 99;
+    state.initial_bid_size_in_base_lots = 10;
+    state.ask_order_sequence_number = 2;
+    state.ask_price_in_ticks = 101;
+    state.initial_ask_size_in_base_lots = 10;
+
+    let intact = MockMarketBuilder::new()
+        .add_bid(99, 10, OUR_TRADER_INDEX)
+        .add_ask(101, 10, OUR_TRADER_INDEX)
+        .build();
+    assert!(state.has_intact_orders(intact.market()));
+
+    // A partial fill on either side needs a requote
+    let partially_filled = MockMarketBuilder::new()
+        .add_bid(99, 10, OUR_TRADER_INDEX)
+        .add_ask(101, 4, OUR_TRADER_INDEX)
+        .build();
+    assert!(!state.has_intact_orders(partially_filled.market()));
+
+    // So does a side that was filled in full or never placed
+    let bid_filled = MockMarketBuilder::new()
+        .add_ask(100, 10, OTHER_TRADER_INDEX)
+        .add_ask(101, 10, OUR_TRADER_INDEX)
+        .build();
+    assert!(!state.has_intact_orders(bid_filled.market()));
+    state.forget_order(Side::Bid);
+    assert!(!state.has_intact_orders(intact.market()));
+}
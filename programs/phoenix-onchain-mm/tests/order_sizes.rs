@@ -0,0 +1,226 @@
+mod common;
+
+use anchor_lang::__private::bytemuck::Zeroable;
+use anchor_lang::prelude::*;
+use common::MockMarketBuilder;
+use phoenix_onchain_mm::{
+    apply_size_curve, compute_order_sizes, get_quote_size_in_quote_atoms, PhoenixStrategyState,
+    SizeAdjustmentCurve, SizingMode, StrategyError, StrategyParams,
+};
+
+// With the default builder settings a price of 100_000 ticks is 100 quote units per base unit,
+// a base unit is 1_000 base lots and a quote lot is 1 quote atom.
+const PRICE_IN_TICKS: u64 = 100_000;
+
+#[test]
+fn test_exact_sizes() {
+    let mock = MockMarketBuilder::new().build();
+    // 100 quote units buys 1 base unit
+    assert_eq!(
+        compute_order_sizes(
+            100_000_000,
+            PRICE_IN_TICKS,
+            PRICE_IN_TICKS,
+            &mock.header,
+            mock.market()
+        ),
+        Some((1_000, 1_000))
+    );
+    // The cheaper bid buys more base lots than the ask sells
+    assert_eq!(
+        compute_order_sizes(
+            100_000_000,
+            PRICE_IN_TICKS / 2,
+            PRICE_IN_TICKS * 2,
+            &mock.header,
+            mock.market()
+        ),
+        Some((2_000, 500))
+    );
+}
+
+#[test]
+fn test_inexact_quote_size_rounds_down() {
+    let mock = MockMarketBuilder::new().build();
+    // Just short of another base lot
+    assert_eq!(
+        compute_order_sizes(
+            100_099_999,
+            PRICE_IN_TICKS,
+            PRICE_IN_TICKS,
+            &mock.header,
+            mock.market()
+        ),
+        Some((1_000, 1_000))
+    );
+    // Leftover quote atoms that do not fill a quote lot are ignored
+    let mock = MockMarketBuilder::new()
+        .tick_size(1_000)
+        .quote_lot_size(10)
+        .build();
+    assert_eq!(
+        compute_order_sizes(
+            100_000_009,
+            PRICE_IN_TICKS,
+            PRICE_IN_TICKS,
+            &mock.header,
+            mock.market()
+        ),
+        Some((1_000, 1_000))
+    );
+}
+
+#[test]
+fn test_dust_sizes_are_rejected() {
+    let mock = MockMarketBuilder::new().build();
+    // 0.099 quote units is less than one base lot of 0.1
+    assert_eq!(
+        compute_order_sizes(
+            99_999,
+            PRICE_IN_TICKS,
+            PRICE_IN_TICKS,
+            &mock.header,
+            mock.market()
+        ),
+        None
+    );
+    // Enough for one lot on the bid, but the integer division rounds the ask to 0
+    assert_eq!(
+        compute_order_sizes(
+            100_000,
+            PRICE_IN_TICKS,
+            PRICE_IN_TICKS + 1,
+            &mock.header,
+            mock.market()
+        ),
+        None
+    );
+    // Less than one quote lot
+    let mock = MockMarketBuilder::new()
+        .tick_size(1_000)
+        .quote_lot_size(10)
+        .build();
+    assert_eq!(
+        compute_order_sizes(9, 1, 1, &mock.header, mock.market()),
+        None
+    );
+}
+
+#[test]
+fn test_zero_price_is_rejected() {
+    let mock = MockMarketBuilder::new().build();
+    assert_eq!(
+        compute_order_sizes(100_000_000, 0, PRICE_IN_TICKS, &mock.header, mock.market()),
+        None
+    );
+    assert_eq!(
+        compute_order_sizes(100_000_000, PRICE_IN_TICKS, 0, &mock.header, mock.market()),
+        None
+    );
+}
+
+#[test]
+fn test_large_sizes_do_not_overflow() {
+    let mock = MockMarketBuilder::new().build();
+    // size_in_quote_lots * base_lots_per_base_unit overflows u64
+    assert_eq!(
+        compute_order_sizes(u64::MAX, 1_000, 1_000, &mock.header, mock.market()),
+        Some((u64::MAX / 1_000, u64::MAX / 1_000))
+    );
+}
+
+#[test]
+fn test_percent_of_deposited_funds_sizing() {
+    assert_eq!(
+        get_quote_size_in_quote_atoms(SizingMode::Fixed, 5_000_000, 1_000_000_000),
+        5_000_000
+    );
+    // 10% of the free quote balance
+    assert_eq!(
+        get_quote_size_in_quote_atoms(SizingMode::PercentOfDepositedFunds, 1_000, 1_000_000_000),
+        100_000_000
+    );
+    assert_eq!(
+        get_quote_size_in_quote_atoms(SizingMode::PercentOfDepositedFunds, 1_000, 0),
+        0
+    );
+    assert_eq!(
+        get_quote_size_in_quote_atoms(SizingMode::PercentOfDepositedFunds, 20_000, u64::MAX),
+        u64::MAX
+    );
+}
+
+#[test]
+fn test_unknown_sizing_mode_is_rejected() {
+    assert_eq!(
+        SizingMode::try_from_u8(SizingMode::PercentOfDepositedFunds.to_u8()).unwrap(),
+        SizingMode::PercentOfDepositedFunds
+    );
+    assert_eq!(
+        SizingMode::try_from_u8(2).unwrap_err(),
+        error!(StrategyError::InvalidSizingMode)
+    );
+}
+
+#[test]
+fn test_size_curves() {
+    assert_eq!(apply_size_curve(1_000, 7, SizeAdjustmentCurve::Flat), 1_000);
+    // 5% larger per tick of distance
+    assert_eq!(
+        apply_size_curve(1_000, 0, SizeAdjustmentCurve::Linear(500)),
+        1_000
+    );
+    assert_eq!(
+        apply_size_curve(1_000, 4, SizeAdjustmentCurve::Linear(500)),
+        1_200
+    );
+    assert_eq!(
+        apply_size_curve(1_000, 1, SizeAdjustmentCurve::Inverse(3)),
+        3_000
+    );
+    assert_eq!(
+        apply_size_curve(1_000, 4, SizeAdjustmentCurve::Inverse(3)),
+        750
+    );
+    // A level at the fair price gets the size of a level one tick away
+    assert_eq!(
+        apply_size_curve(1_000, 0, SizeAdjustmentCurve::Inverse(3)),
+        3_000
+    );
+    assert_eq!(
+        apply_size_curve(u64::MAX, 10, SizeAdjustmentCurve::Linear(10_000)),
+        u64::MAX
+    );
+}
+
+#[test]
+fn test_size_curve_is_stored_in_the_state() {
+    let mut state = PhoenixStrategyState::zeroed();
+    for curve in [
+        SizeAdjustmentCurve::Linear(250),
+        SizeAdjustmentCurve::Inverse(2),
+        SizeAdjustmentCurve::Flat,
+    ] {
+        state
+            .apply_params(&StrategyParams {
+                size_adjustment_curve: Some(curve),
+                ..StrategyParams::default()
+            })
+            .unwrap();
+        assert_eq!(state.size_adjustment_curve().unwrap(), curve);
+    }
+    assert_eq!(
+        state
+            .apply_params(&StrategyParams {
+                size_adjustment_curve: Some(SizeAdjustmentCurve::Inverse(0)),
+                ..StrategyParams::default()
+            })
+            .unwrap_err(),
+        error!(StrategyError::InvalidSizeAdjustmentCurve)
+    );
+    state.size_adjustment_curve = 3;
+    assert_eq!(
+        state.size_adjustment_curve().unwrap_err(),
+        error!(StrategyError::InvalidSizeAdjustmentCurve)
+    );
+}
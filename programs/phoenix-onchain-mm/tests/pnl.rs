@@ -0,0 +1,61 @@
+use phoenix_onchain_mm::calculate_theoretical_pnl;
+
+// 1 tick = 1000 quote atoms per base unit, 100 base lots per base unit
+const TICK_SIZE: u64 = 1_000;
+const BASE_LOTS_PER_BASE_UNIT: u64 = 100;
+
+fn pnl(bid_fill: u64, ask_fill: u64, bid_price: u64, ask_price: u64) -> i64 {
+    calculate_theoretical_pnl(
+        bid_fill,
+        ask_fill,
+        bid_price,
+        ask_price,
+        TICK_SIZE,
+        BASE_LOTS_PER_BASE_UNIT,
+    )
+}
+
+#[test]
+fn test_no_fills_has_no_pnl() {
+    assert_eq!(pnl(0, 0, 99, 101), 0);
+    assert_eq!(pnl(0, 0, 0, 0), 0);
+}
+
+#[test]
+fn test_balanced_fills_earn_the_spread() {
+    // 1 base unit bought at 99 ticks and sold at 101 ticks
+    assert_eq!(pnl(100, 100, 99, 101), 2 * TICK_SIZE as i64);
+    assert_eq!(pnl(300, 300, 99, 101), 6 * TICK_SIZE as i64);
+    // Quoting at the same price on both sides earns nothing
+    assert_eq!(pnl(100, 100, 100, 100), 0);
+}
+
+#[test]
+fn test_net_position_is_marked_at_the_mid() {
+    // Only bought: 1 base unit at 99, marked at 100
+    assert_eq!(pnl(100, 0, 99, 101), TICK_SIZE as i64);
+    // Only sold: 1 base unit at 101, marked at 100
+    assert_eq!(pnl(0, 100, 99, 101), TICK_SIZE as i64);
+    // Balanced unit earns the spread, the extra unit bought earns half of it
+    assert_eq!(pnl(200, 100, 99, 101), 3 * TICK_SIZE as i64);
+}
+
+#[test]
+fn test_heavily_skewed_fills() {
+    // Crossed quotes lose the spread on matched units and half of it on the net position
+    assert_eq!(pnl(1_000, 100, 101, 99), -11 * TICK_SIZE as i64);
+    assert_eq!(pnl(100, 1_000, 101, 99), -11 * TICK_SIZE as i64);
+    // Saturates instead of overflowing
+    assert_eq!(
+        calculate_theoretical_pnl(u64::MAX, u64::MAX, 0, u64::MAX, u64::MAX, 1),
+        i64::MAX
+    );
+}
+
+#[test]
+fn test_zero_base_lots_per_base_unit_is_ignored() {
+    assert_eq!(
+        calculate_theoretical_pnl(100, 100, 99, 101, TICK_SIZE, 0),
+        0
+    );
+}
@@ -0,0 +1,60 @@
+use phoenix_onchain_mm::{apply_price_improvement, PriceImprovementBehavior};
+
+const EMPTY_BID: u64 = 0;
+const EMPTY_ASK: u64 = u64::MAX;
+
+#[test]
+fn test_join_with_empty_book_does_not_clamp() {
+    let (bid, ask) = apply_price_improvement(
+        PriceImprovementBehavior::Join,
+        995,
+        1005,
+        EMPTY_BID,
+        EMPTY_ASK,
+    );
+    assert_eq!((bid, ask), (995, 1005));
+}
+
+#[test]
+fn test_dime_with_empty_book_does_not_overflow() {
+    let (bid, ask) = apply_price_improvement(
+        PriceImprovementBehavior::Dime,
+        995,
+        1005,
+        EMPTY_BID,
+        EMPTY_ASK,
+    );
+    assert_eq!((bid, ask), (995, 1005));
+}
+
+#[test]
+fn test_empty_bid_side_only() {
+    let (bid, ask) =
+        apply_price_improvement(PriceImprovementBehavior::Join, 995, 1005, EMPTY_BID, 1010);
+    assert_eq!((bid, ask), (995, 1010));
+    let (bid, ask) =
+        apply_price_improvement(PriceImprovementBehavior::Dime, 995, 1005, EMPTY_BID, 1010);
+    assert_eq!((bid, ask), (995, 1009));
+}
+
+#[test]
+fn test_empty_ask_side_only() {
+    let (bid, ask) =
+        apply_price_improvement(PriceImprovementBehavior::Join, 995, 1005, 990, EMPTY_ASK);
+    assert_eq!((bid, ask), (990, 1005));
+    let (bid, ask) =
+        apply_price_improvement(PriceImprovementBehavior::Dime, 995, 1005, 990, EMPTY_ASK);
+    assert_eq!((bid, ask), (991, 1005));
+}
+
+#[test]
+fn test_ignore_never_clamps() {
+    let (bid, ask) =
+        apply_price_improvement(PriceImprovementBehavior::Ignore, 995, 1005, 998, 1002);
+    assert_eq!((bid, ask), (995, 1005));
+}
+
+#[test]
+fn test_try_from_u8_rejects_unknown_behavior() {
+    assert!(PriceImprovementBehavior::try_from_u8(255).is_err());
+}
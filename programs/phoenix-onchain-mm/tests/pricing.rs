@@ -0,0 +1,305 @@
+use anchor_lang::__private::bytemuck::Zeroable;
+use anchor_lang::prelude::Pubkey;
+use phoenix::program::accounts::{MarketSizeParams, TokenParams};
+use phoenix::program::MarketHeader;
+use phoenix::quantities::{
+    BaseAtomsPerBaseLot, QuoteAtomsPerBaseUnitPerTick, QuoteAtomsPerQuoteLot, WrapperU64,
+};
+use phoenix::state::Side;
+use phoenix_onchain_mm::{
+    compute_minimum_profitable_edge_in_bps, enforce_min_spread, get_ask_price_in_ticks,
+    get_bid_price_in_ticks, get_effective_edge_in_bps, get_level_size, get_maker_share_in_bps,
+    get_max_taker_exposure_in_quote_atoms, get_new_fill_in_base_lots,
+    get_oracle_volatility_edge_in_bps, get_volatility_edge_in_bps, update_rolling_variance,
+    PriceImprovementBehavior, PricePrecisionMode,
+};
+use proptest::prelude::*;
+
+fn create_header(
+    tick_size_in_quote_atoms_per_base_unit: u64,
+    raw_base_units_per_base_unit: u32,
+) -> MarketHeader {
+    MarketHeader::new(
+        MarketSizeParams::default(),
+        TokenParams::zeroed(),
+        BaseAtomsPerBaseLot::new(1_000),
+        TokenParams::zeroed(),
+        QuoteAtomsPerQuoteLot::new(1),
+        QuoteAtomsPerBaseUnitPerTick::new(tick_size_in_quote_atoms_per_base_unit),
+        Pubkey::default(),
+        Pubkey::default(),
+        Pubkey::default(),
+        raw_base_units_per_base_unit,
+    )
+}
+
+proptest! {
+    #[test]
+    fn test_ask_is_never_below_bid(
+        fair_price in 1..10_000_000_000u64,
+        edge_in_bps in 0..=10_000u64,
+        raw_base_units_per_base_unit in 1..=1_000u32,
+        tick_size in (0..=1_000_000u64).prop_filter("tick size must be non-zero", |t| *t != 0),
+    ) {
+        let header = create_header(tick_size, raw_base_units_per_base_unit);
+        let mode = PricePrecisionMode::Conservative;
+        let bid = get_bid_price_in_ticks(fair_price, &header, edge_in_bps, mode).unwrap();
+        let ask = get_ask_price_in_ticks(fair_price, &header, edge_in_bps, mode).unwrap();
+        prop_assert!(ask >= bid);
+    }
+
+    #[test]
+    // Rounded up as the program does, which cannot use `div_ceil`
+    #[allow(clippy::manual_div_ceil)]
+    fn test_zero_edge_quotes_fair_price(
+        fair_price in 1..10_000_000_000u64,
+        raw_base_units_per_base_unit in 1..=1_000u32,
+        tick_size in 1..=1_000_000u64,
+    ) {
+        let header = create_header(tick_size, raw_base_units_per_base_unit);
+        let mode = PricePrecisionMode::Conservative;
+        let bid = get_bid_price_in_ticks(fair_price, &header, 0, mode).unwrap();
+        let ask = get_ask_price_in_ticks(fair_price, &header, 0, mode).unwrap();
+        // The ticks on either side of the fair price, or the fair price itself if it is on a tick
+        let fair_price_in_ticks = fair_price as u128 * raw_base_units_per_base_unit as u128;
+        prop_assert_eq!(bid as u128, fair_price_in_ticks / tick_size as u128);
+        prop_assert_eq!(
+            ask as u128,
+            (fair_price_in_ticks + tick_size as u128 - 1) / tick_size as u128
+        );
+    }
+
+    #[test]
+    fn test_price_improvement_behavior_round_trip(byte in any::<u8>()) {
+        if byte <= 2 {
+            prop_assert_eq!(PriceImprovementBehavior::try_from_u8(byte).unwrap().to_u8(), byte);
+        } else {
+            prop_assert!(PriceImprovementBehavior::try_from_u8(byte).is_err());
+        }
+    }
+
+    #[test]
+    fn test_level_sizes_never_increase(
+        base_size in any::<u64>(),
+        level in 0..=u8::MAX,
+        decay_factor_in_bps in 0..=10_000u64,
+    ) {
+        prop_assert_eq!(get_level_size(base_size, 0, decay_factor_in_bps), base_size);
+        prop_assert!(
+            get_level_size(base_size, level.saturating_add(1), decay_factor_in_bps)
+                <= get_level_size(base_size, level, decay_factor_in_bps)
+        );
+    }
+}
+
+proptest! {
+    #[test]
+    fn test_enforced_spread_is_at_least_minimum(
+        bid in 1..1_000_000u64,
+        width in 0..1_000u64,
+        min_spread_in_bps in 0..=1_000u64,
+    ) {
+        let ask = bid + width;
+        if let Ok((new_bid, new_ask)) = enforce_min_spread(bid, ask, min_spread_in_bps) {
+            prop_assert!(new_bid <= bid && new_ask >= ask);
+            let mid = (new_bid as u128 + new_ask as u128) / 2;
+            prop_assert!((new_ask - new_bid) as u128 * 10_000 >= min_spread_in_bps as u128 * mid);
+        }
+    }
+}
+
+#[test]
+fn test_level_size_halves_with_half_decay() {
+    assert_eq!(get_level_size(1_000, 1, 5_000), 500);
+    assert_eq!(get_level_size(1_000, 3, 5_000), 125);
+    assert_eq!(get_level_size(1_000, 3, 10_000), 1_000);
+}
+
+#[test]
+fn test_effective_edge_widens_per_hour_without_fill() {
+    assert_eq!(get_effective_edge_in_bps(10, 0, 5, 0), 10);
+    assert_eq!(get_effective_edge_in_bps(10, 3_600, 5, 0), 15);
+    assert_eq!(get_effective_edge_in_bps(10, 7_200, 5, 0), 20);
+    // Capped at the max edge, but never below the configured edge
+    assert_eq!(get_effective_edge_in_bps(10, 36_000, 5, 30), 30);
+    assert_eq!(get_effective_edge_in_bps(10, 36_000, 5, 5), 10);
+    // A clock that moved backwards does not narrow the edge
+    assert_eq!(get_effective_edge_in_bps(10, -3_600, 5, 0), 10);
+}
+
+#[test]
+fn test_new_fill_excludes_tracked_lots() {
+    // Untouched order
+    assert_eq!(get_new_fill_in_base_lots(100, Some(100), 0), 0);
+    // Partially filled, then filled further after the first check
+    assert_eq!(get_new_fill_in_base_lots(100, Some(60), 0), 40);
+    assert_eq!(get_new_fill_in_base_lots(100, Some(25), 40), 35);
+    // Order no longer on the book
+    assert_eq!(get_new_fill_in_base_lots(100, None, 75), 25);
+    assert_eq!(get_new_fill_in_base_lots(100, None, 100), 0);
+}
+
+#[test]
+fn test_oracle_volatility_is_scaled_to_the_refresh_interval() {
+    // 80% a year over one hour is 80% / sqrt(8760), about 85 bps
+    assert_eq!(get_oracle_volatility_edge_in_bps(8_000, 3_600), 85);
+    // A full year keeps the annualized volatility
+    assert_eq!(
+        get_oracle_volatility_edge_in_bps(8_000, 365 * 24 * 3600),
+        8_000
+    );
+    assert_eq!(get_oracle_volatility_edge_in_bps(8_000, 0), 0);
+    assert_eq!(get_oracle_volatility_edge_in_bps(0, 3_600), 0);
+}
+
+#[test]
+fn test_maker_share() {
+    assert_eq!(get_maker_share_in_bps(0, 0), None);
+    assert_eq!(get_maker_share_in_bps(30, 10), Some(7_500));
+    assert_eq!(get_maker_share_in_bps(0, 10), Some(0));
+    assert_eq!(get_maker_share_in_bps(u64::MAX, u64::MAX), Some(5_000));
+}
+
+#[test]
+fn test_rolling_variance_matches_population_variance() {
+    let (mut mean_x32, mut variance_x64) = (0, 0);
+    for (count, price) in [2_000u64, 4_000, 4_000, 4_000, 5_000, 5_000, 7_000, 9_000]
+        .into_iter()
+        .enumerate()
+    {
+        (mean_x32, variance_x64) =
+            update_rolling_variance(mean_x32, variance_x64, count as u64, price);
+    }
+    let mean = mean_x32 as f64 / 2f64.powi(32);
+    let variance = variance_x64 as f64 / 2f64.powi(64) * mean * mean;
+    assert!((mean - 5_000.0).abs() < 0.01, "mean {}", mean);
+    assert!(
+        (variance - 4_000_000.0).abs() < 1_000.0,
+        "variance {}",
+        variance
+    );
+}
+
+#[test]
+fn test_volatility_edge_scales_with_standard_deviation() {
+    // A mean of 10_000 and a standard deviation of 1/64 of it
+    let mean_x32 = 10_000 << 32;
+    let variance_x64 = 1 << 52;
+    assert_eq!(
+        get_volatility_edge_in_bps(mean_x32, variance_x64, 10_000, 10_000),
+        156
+    );
+    assert_eq!(
+        get_volatility_edge_in_bps(mean_x32, variance_x64, 10_000, 5_000),
+        78
+    );
+    // The standard deviation is relative to the fair price, not the mean
+    assert_eq!(
+        get_volatility_edge_in_bps(mean_x32, variance_x64, 20_000, 10_000),
+        78
+    );
+    assert_eq!(get_volatility_edge_in_bps(mean_x32, 0, 10_000, 10_000), 0);
+    assert_eq!(
+        get_volatility_edge_in_bps(mean_x32, variance_x64, 0, 10_000),
+        0
+    );
+}
+
+#[test]
+fn test_small_moves_around_a_low_price_widen_the_edge() {
+    // Moves of a single quote atom around a price of 100 have a standard deviation of 0.5%
+    let (mut mean_x32, mut variance_x64) = (0, 0);
+    for count in 0..1_000u64 {
+        let price = 100 + count % 2;
+        (mean_x32, variance_x64) = update_rolling_variance(mean_x32, variance_x64, count, price);
+    }
+    assert!(mean_x32.abs_diff(100 << 32 | 1 << 31) < 1 << 28);
+    let edge_in_bps = get_volatility_edge_in_bps(mean_x32, variance_x64, 100, 10_000);
+    assert!((45..=55).contains(&edge_in_bps), "edge {}", edge_in_bps);
+}
+
+#[test]
+fn test_max_taker_exposure() {
+    // 1_000 quote atoms per tick per base unit and 1_000 base lots per base unit
+    let exposure = |side, price_in_ticks, size_in_base_lots| {
+        get_max_taker_exposure_in_quote_atoms(
+            side,
+            price_in_ticks,
+            size_in_base_lots,
+            100,
+            105,
+            1_000,
+            1_000,
+        )
+    };
+    // Orders inside the spread do not take
+    assert_eq!(exposure(Side::Bid, 104, 10_000), 0);
+    assert_eq!(exposure(Side::Ask, 101, 10_000), 0);
+    // Joining the best opposing price crosses one level
+    assert_eq!(exposure(Side::Bid, 105, 10_000), 10_000);
+    assert_eq!(exposure(Side::Ask, 100, 10_000), 10_000);
+    // Every extra tick through the book adds another level
+    assert_eq!(exposure(Side::Bid, 107, 10_000), 30_000);
+    assert_eq!(exposure(Side::Ask, 98, 500), 1_500);
+
+    // An empty opposite side cannot be taken
+    assert_eq!(
+        get_max_taker_exposure_in_quote_atoms(Side::Bid, 200, 10, 0, u64::MAX, 1_000, 1_000),
+        0
+    );
+    assert_eq!(
+        get_max_taker_exposure_in_quote_atoms(Side::Ask, 1, 10, 0, u64::MAX, 1_000, 1_000),
+        0
+    );
+}
+
+#[test]
+fn test_price_precision_modes_round_inexact_prices() {
+    // A fair price of 100.05 ticks with 10 bps of edge is 99.94995 @ 100.15005 ticks
+    let header = create_header(100, 1);
+    let fair_price = 10_005;
+    let quote = |mode| {
+        (
+            get_bid_price_in_ticks(fair_price, &header, 10, mode),
+            get_ask_price_in_ticks(fair_price, &header, 10, mode),
+        )
+    };
+    let (bid, ask) = quote(PricePrecisionMode::Conservative);
+    assert_eq!((bid.unwrap(), ask.unwrap()), (99, 101));
+    let (bid, ask) = quote(PricePrecisionMode::Aggressive);
+    assert_eq!((bid.unwrap(), ask.unwrap()), (100, 100));
+    let (bid, ask) = quote(PricePrecisionMode::Exact);
+    assert!(bid.is_err() && ask.is_err());
+}
+
+#[test]
+fn test_price_precision_modes_agree_on_exact_prices() {
+    // A fair price of 100 ticks with 100 bps of edge is 99 @ 101 ticks
+    let header = create_header(100, 1);
+    for mode in [
+        PricePrecisionMode::Conservative,
+        PricePrecisionMode::Aggressive,
+        PricePrecisionMode::Exact,
+    ] {
+        assert_eq!(
+            get_bid_price_in_ticks(10_000, &header, 100, mode).unwrap(),
+            99
+        );
+        assert_eq!(
+            get_ask_price_in_ticks(10_000, &header, 100, mode).unwrap(),
+            101
+        );
+    }
+    for byte in 0..=2 {
+        assert_eq!(PricePrecisionMode::try_from_u8(byte).unwrap().to_u8(), byte);
+    }
+    assert!(PricePrecisionMode::try_from_u8(3).is_err());
+}
+
+#[test]
+fn test_minimum_profitable_edge_covers_fees() {
+    assert_eq!(compute_minimum_profitable_edge_in_bps(5, 0, 0), 5);
+    assert_eq!(compute_minimum_profitable_edge_in_bps(5, 2, 3), 6);
+    // A rebate above the taker fee does not make the edge negative
+    assert_eq!(compute_minimum_profitable_edge_in_bps(2, 5, 3), 3);
+}
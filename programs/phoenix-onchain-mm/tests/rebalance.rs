@@ -0,0 +1,52 @@
+use anchor_lang::prelude::*;
+use phoenix::state::Side;
+use phoenix_onchain_mm::{get_rebalance_order, MarketError};
+
+const BEST_BID: u64 = 99;
+const BEST_ASK: u64 = 101;
+
+#[test]
+fn test_no_order_when_on_target() {
+    assert_eq!(
+        get_rebalance_order(500, 500, BEST_BID, BEST_ASK, 5).unwrap(),
+        None
+    );
+}
+
+#[test]
+fn test_sells_excess_into_the_bid() {
+    assert_eq!(
+        get_rebalance_order(500, 200, BEST_BID, BEST_ASK, 5).unwrap(),
+        Some((Side::Ask, 94, 300))
+    );
+    // A negative target sells the whole position
+    assert_eq!(
+        get_rebalance_order(500, -100, BEST_BID, BEST_ASK, 0).unwrap(),
+        Some((Side::Ask, 99, 600))
+    );
+    // The limit price never drops to 0
+    assert_eq!(
+        get_rebalance_order(500, 0, 3, BEST_ASK, 10).unwrap(),
+        Some((Side::Ask, 1, 500))
+    );
+}
+
+#[test]
+fn test_buys_deficit_from_the_ask() {
+    assert_eq!(
+        get_rebalance_order(0, 250, BEST_BID, BEST_ASK, 5).unwrap(),
+        Some((Side::Bid, 106, 250))
+    );
+}
+
+#[test]
+fn test_requires_liquidity_on_the_opposing_side() {
+    assert_eq!(
+        get_rebalance_order(500, 0, 0, BEST_ASK, 5).unwrap_err(),
+        error!(MarketError::NoLiquidity)
+    );
+    assert_eq!(
+        get_rebalance_order(0, 500, BEST_BID, u64::MAX, 5).unwrap_err(),
+        error!(MarketError::NoLiquidity)
+    );
+}
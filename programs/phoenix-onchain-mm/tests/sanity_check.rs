@@ -0,0 +1,93 @@
+use anchor_lang::__private::bytemuck::Zeroable;
+use anchor_lang::prelude::*;
+use phoenix::state::Side;
+use phoenix_onchain_mm::{PhoenixStrategyState, StrategyError};
+
+fn valid_state() -> PhoenixStrategyState {
+    let mut state = PhoenixStrategyState::zeroed();
+    state.bid_order_sequence_number = !1;
+    state.bid_price_in_ticks = 99;
+    state.initial_bid_size_in_base_lots = 10;
+    state.ask_order_sequence_number = 1;
+    state.ask_price_in_ticks = 101;
+    state.initial_ask_size_in_base_lots = 10;
+    state.quote_edge_in_bps = 10;
+    state.last_update_unix_timestamp = 1_700_000_000;
+    state
+}
+
+#[test]
+fn test_valid_and_empty_states_pass() {
+    assert!(valid_state().sanity_check().is_ok());
+    assert!(PhoenixStrategyState::zeroed().sanity_check().is_ok());
+}
+
+#[test]
+fn test_each_corruption_is_rejected() {
+    let corruptions: [fn(&mut PhoenixStrategyState); 5] = [
+        |state| state.bid_price_in_ticks = state.ask_price_in_ticks,
+        |state| state.bid_price_in_ticks = state.ask_price_in_ticks + 1,
+        |state| state.quote_edge_in_bps = 10_001,
+        |state| state.initial_bid_size_in_base_lots = 0,
+        |state| state.last_update_unix_timestamp = -1,
+    ];
+    for corrupt in corruptions {
+        let mut state = valid_state();
+        corrupt(&mut state);
+        assert_eq!(
+            state.sanity_check().unwrap_err(),
+            error!(StrategyError::CorruptStrategyState)
+        );
+    }
+}
+
+#[test]
+fn test_forgetting_one_side_keeps_the_other() {
+    let mut state = valid_state();
+    state.tracked_bid_fill_in_base_lots = 4;
+    state.forget_order(Side::Bid);
+    assert!(!state.has_live_bid() && state.has_live_ask());
+    assert_eq!(
+        (
+            state.bid_price_in_ticks,
+            state.initial_bid_size_in_base_lots,
+            state.tracked_bid_fill_in_base_lots
+        ),
+        (0, 0, 0)
+    );
+    assert_eq!(state.ask_price_in_ticks, 101);
+    assert!(state.sanity_check().is_ok());
+
+    state.forget_order(Side::Ask);
+    assert!(!state.is_active());
+}
+
+#[test]
+fn test_reset_zeros_fill_tracking_only() {
+    let mut state = valid_state();
+    state.cumulative_bid_fill_in_base_lots = 30;
+    state.cumulative_ask_fill_in_base_lots = 20;
+    state.cumulative_maker_bid_volume_in_base_lots = 25;
+    state.cumulative_taker_ask_volume_in_base_lots = 5;
+    state.tracked_bid_fill_in_base_lots = 4;
+    state.reset(1_234, 1_700_086_400);
+
+    assert_eq!(
+        (
+            state.cumulative_bid_fill_in_base_lots,
+            state.cumulative_ask_fill_in_base_lots,
+            state.cumulative_maker_bid_volume_in_base_lots,
+            state.cumulative_taker_ask_volume_in_base_lots
+        ),
+        (0, 0, 0, 0)
+    );
+    assert_eq!(
+        (state.last_update_slot, state.last_update_unix_timestamp),
+        (1_234, 1_700_086_400)
+    );
+    // The resting orders and the fills already counted from them are kept
+    assert_eq!(state.tracked_bid_fill_in_base_lots, 4);
+    assert!(state.has_live_bid() && state.has_live_ask());
+    assert_eq!(state.quote_edge_in_bps, 10);
+    assert!(state.sanity_check().is_ok());
+}
@@ -0,0 +1,85 @@
+use anchor_lang::__private::bytemuck::Zeroable;
+use anchor_lang::prelude::*;
+use phoenix_onchain_mm::{OracleError, PhoenixStrategyState, StrategyError, VolatilityOracle};
+
+#[test]
+fn test_check_is_disabled_by_default() {
+    let state = PhoenixStrategyState::zeroed();
+    assert!(state.check_slot_freshness(100, 1_000).is_ok());
+}
+
+#[test]
+fn test_stale_prices_are_rejected() {
+    let mut state = PhoenixStrategyState::zeroed();
+    state.max_slot_age_for_price = 10;
+    assert!(state.check_slot_freshness(100, 100).is_ok());
+    assert!(state.check_slot_freshness(100, 110).is_ok());
+    assert_eq!(
+        state.check_slot_freshness(100, 111).unwrap_err(),
+        error!(StrategyError::PriceTooStale)
+    );
+    // A slot ahead of the cluster is not stale
+    assert!(state.check_slot_freshness(120, 110).is_ok());
+}
+
+#[test]
+fn test_unset_submitted_slot_skips_check() {
+    let mut state = PhoenixStrategyState::zeroed();
+    state.max_slot_age_for_price = 10;
+    assert!(state.check_slot_freshness(0, 1_000).is_ok());
+}
+
+#[test]
+fn test_stale_volatility_oracles_are_rejected() {
+    let mut state = PhoenixStrategyState::zeroed();
+    let oracle = VolatilityOracle {
+        annualized_vol_in_bps: 8_000,
+        updated_at_slot: 100,
+    };
+    assert!(state.check_oracle_freshness(&oracle, 1_000).is_ok());
+    state.max_oracle_staleness_in_slots = 10;
+    assert!(state.check_oracle_freshness(&oracle, 110).is_ok());
+    assert_eq!(
+        state.check_oracle_freshness(&oracle, 111).unwrap_err(),
+        error!(StrategyError::StaleVolatilityOracle)
+    );
+}
+
+#[test]
+fn test_volatility_oracle_is_required_once_configured() {
+    let mut state = PhoenixStrategyState::zeroed();
+    assert_eq!(state.get_oracle_edge_in_bps(None, 100).unwrap(), 0);
+    state.volatility_oracle_pubkey = Pubkey::new_unique();
+    assert_eq!(
+        state.get_oracle_edge_in_bps(None, 100).unwrap_err(),
+        error!(OracleError::InvalidVolatilityOracle)
+    );
+}
+
+#[test]
+fn test_orders_are_refreshed_after_the_interval() {
+    let mut state = PhoenixStrategyState::zeroed();
+    state.last_order_refresh_unix_timestamp = 1_000;
+    assert!(!state.is_order_refresh_due(1_000_000));
+    state.order_refresh_interval_in_seconds = 30;
+    assert!(!state.is_order_refresh_due(1_029));
+    assert!(state.is_order_refresh_due(1_030));
+}
+
+#[test]
+fn test_refresh_restarts_when_a_side_is_suppressed() {
+    let mut state = PhoenixStrategyState::zeroed();
+    state.order_refresh_interval_in_seconds = 30;
+    state.last_order_refresh_unix_timestamp = 1_000;
+    // A one-sided update outside of a refresh leaves the older order in place
+    state.record_order_refresh(false, false, 1_010);
+    assert_eq!(state.last_order_refresh_unix_timestamp, 1_000);
+
+    // The due refresh replaced every resting order, even though only one side was placed again
+    assert!(state.is_order_refresh_due(1_030));
+    state.record_order_refresh(true, false, 1_030);
+    assert!(!state.is_order_refresh_due(1_031));
+
+    state.record_order_refresh(false, true, 1_040);
+    assert_eq!(state.last_order_refresh_unix_timestamp, 1_040);
+}
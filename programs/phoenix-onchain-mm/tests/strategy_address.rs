@@ -0,0 +1,54 @@
+use anchor_lang::prelude::Pubkey;
+use phoenix_onchain_mm::{
+    get_strategy_address, get_strategy_address_with_id, PhoenixStrategyState, StrategyRegistry,
+    PHOENIX_STRATEGY_DISCRIMINATOR, STRATEGY_REGISTRY_DISCRIMINATOR,
+};
+use std::str::FromStr;
+
+#[test]
+fn test_strategy_address_matches_known_value() {
+    let user = Pubkey::from_str("GDqLPXfwDHXnqwfqtEJmqovA4KEy9XhoZxkg3MVyFK9N").unwrap();
+    let market = Pubkey::from_str("HhHRvLFvZid6FD7C96H93F2MkASjYfYAx8Y2P8KMAr6b").unwrap();
+    let (address, _) = get_strategy_address(&user, &market);
+    assert_eq!(
+        address,
+        Pubkey::from_str("6hn1DBwmhDYzwNEdrWu3gAaSR7Agd2Y28k6ybgMopwKT").unwrap()
+    );
+}
+
+#[test]
+fn test_strategy_id_zero_matches_default_address() {
+    let user = Pubkey::new_unique();
+    let market = Pubkey::new_unique();
+    assert_eq!(
+        get_strategy_address(&user, &market),
+        get_strategy_address_with_id(&user, &market, 0)
+    );
+    assert_eq!(
+        get_strategy_address(&user, &market),
+        Pubkey::find_program_address(
+            &[b"phoenix", user.as_ref(), market.as_ref()],
+            &phoenix_onchain_mm::id()
+        )
+    );
+    assert_ne!(
+        get_strategy_address(&user, &market).0,
+        get_strategy_address_with_id(&user, &market, 1).0
+    );
+}
+
+#[test]
+fn test_strategy_discriminator_matches_anchor() {
+    assert_eq!(
+        PHOENIX_STRATEGY_DISCRIMINATOR,
+        <PhoenixStrategyState as anchor_lang::Discriminator>::DISCRIMINATOR
+    );
+}
+
+#[test]
+fn test_registry_discriminator_matches_anchor() {
+    assert_eq!(
+        STRATEGY_REGISTRY_DISCRIMINATOR,
+        <StrategyRegistry as anchor_lang::Discriminator>::DISCRIMINATOR
+    );
+}
@@ -0,0 +1,67 @@
+use anchor_lang::__private::bytemuck::Zeroable;
+use anchor_lang::prelude::*;
+use phoenix_onchain_mm::{
+    get_strategy_address_with_id, get_strategy_registry_address, RegistryEntry, StrategyError,
+    StrategyRegistry, MAX_REGISTRY_ENTRIES,
+};
+
+#[test]
+fn test_strategies_are_registered_once() {
+    let mut registry = StrategyRegistry::zeroed();
+    let market = Pubkey::new_unique();
+    registry.register(market, 0).unwrap();
+    registry.register(market, 1).unwrap();
+    // Initializing an existing strategy again does not add a second entry
+    registry.register(market, 0).unwrap();
+    assert_eq!(
+        registry.entries(),
+        &[
+            RegistryEntry {
+                market,
+                strategy_id: 0
+            },
+            RegistryEntry {
+                market,
+                strategy_id: 1
+            },
+        ]
+    );
+
+    let user = Pubkey::new_unique();
+    assert_eq!(
+        registry.get_strategy_addresses(&user),
+        vec![
+            get_strategy_address_with_id(&user, &market, 0).0,
+            get_strategy_address_with_id(&user, &market, 1).0,
+        ]
+    );
+}
+
+#[test]
+fn test_full_registry_is_rejected() {
+    let mut registry = StrategyRegistry::zeroed();
+    for _ in 0..MAX_REGISTRY_ENTRIES {
+        registry.register(Pubkey::new_unique(), 0).unwrap();
+    }
+    assert_eq!(registry.count as usize, MAX_REGISTRY_ENTRIES);
+    assert_eq!(
+        registry.register(Pubkey::new_unique(), 0).unwrap_err(),
+        error!(StrategyError::RegistryFull)
+    );
+    // Strategies that are already registered can still be initialized again
+    let market = registry.entries()[0].market;
+    assert!(registry.register(market, 0).is_ok());
+}
+
+#[test]
+fn test_registry_address_is_per_user() {
+    let user = Pubkey::new_unique();
+    assert_eq!(
+        get_strategy_registry_address(&user),
+        Pubkey::find_program_address(&[b"registry", user.as_ref()], &phoenix_onchain_mm::id())
+    );
+    assert_ne!(
+        get_strategy_registry_address(&user).0,
+        get_strategy_registry_address(&Pubkey::new_unique()).0
+    );
+}
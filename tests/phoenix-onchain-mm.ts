@@ -151,6 +151,28 @@ const createTokenAccountAndMintTokens = async (
   return userTokenAccount;
 };
 
+const getStrategyAddress = (
+  programId: PublicKey,
+  user: PublicKey,
+  market: PublicKey,
+  strategyId: number = 0
+): PublicKey => {
+  const seeds = [Buffer.from("phoenix"), user.toBuffer(), market.toBuffer()];
+  if (strategyId !== 0) {
+    seeds.push(Buffer.from([strategyId]));
+  }
+  return PublicKey.findProgramAddressSync(seeds, programId)[0];
+};
+
+const getStrategyRegistryAddress = (
+  programId: PublicKey,
+  user: PublicKey
+): PublicKey =>
+  PublicKey.findProgramAddressSync(
+    [Buffer.from("registry"), user.toBuffer()],
+    programId
+  )[0];
+
 const createPhoenixClient = async (
   connection: Connection
 ): Promise<Phoenix.Client> => {
@@ -225,18 +247,53 @@ describe("phoenix-onchain-mm", () => {
     );
   });
   it("Is initialized!", async () => {
+    const phoenixStrategy = getStrategyAddress(
+      program.programId,
+      god.publicKey,
+      solMarketAddress
+    );
     const params = {
       quoteEdgeInBps: new BN(2),
       quoteSizeInQuoteAtoms: new BN(500 * 1e6),
       postOnly: false,
+      useOnlyDepositedFunds: false,
+      sizeDecayFactorInBps: new BN(10_000),
+      minSpreadInBps: new BN(0),
+      timeSinceLastFillDecayFactorInBps: new BN(0),
+      maxEdgeInBps: new BN(0),
+      volatilityMultiplierInBps: new BN(0),
+      minWarmupUpdates: new BN(0),
+      maxTakerExposureInQuoteAtoms: new BN(0),
+      maxSlotAgeForPrice: new BN(0),
+      minDepositedQuoteAtoms: new BN(0),
+      minDepositedBaseLots: new BN(0),
+      volatilityOraclePubkey: null,
+      maxOracleStalenessInSlots: new BN(0),
+      quoteRefreshIntervalInSeconds: new BN(0),
+      clientMetadata: null,
+      sizingMode: null,
+      sizeAdjustmentCurve: null,
+      pricePrecisionMode: null,
+      orderRefreshIntervalInSeconds: new BN(0),
+      takerFeeInBps: new BN(0),
+      makerRebateInBps: new BN(0),
+      minExternalOrdersPerSide: new BN(0),
+      clientOrderIdMode: {
+        pubkey: {},
+      },
       priceImprovementBehavior: {
         ignore: {},
       },
     };
 
     const tx = await program.methods
-      .initialize(params)
+      .initialize(params, 0)
       .accounts({
+        phoenixStrategy,
+        strategyRegistry: getStrategyRegistryAddress(
+          program.programId,
+          god.publicKey
+        ),
         user: god.publicKey,
         market: solMarketAddress,
         systemProgram: SystemProgram.programId,
@@ -258,11 +315,18 @@ describe("phoenix-onchain-mm", () => {
         })
         .catch((error) => console.error(error));
       const tx = await program.methods
-        .updateQuotes({
-          fairPriceInQuoteAtomsPerRawBaseUnit: new BN(Math.floor(price * 1e6)),
-          strategyParams: params,
-        })
+        .updateQuotes(
+          {
+            fairPriceInQuoteAtomsPerRawBaseUnit: new BN(
+              Math.floor(price * 1e6)
+            ),
+            strategyParams: params,
+            submittedSlot: new BN(0),
+          },
+          0
+        )
         .accounts({
+          phoenixStrategy,
           user: god.publicKey,
           market: solMarketAddress,
           phoenixProgram: Phoenix.PROGRAM_ID,
@@ -273,6 +337,7 @@ describe("phoenix-onchain-mm", () => {
           quoteVault: phoenixMarket.data.header.quoteParams.vaultKey,
           baseVault: phoenixMarket.data.header.baseParams.vaultKey,
           tokenProgram: TOKEN_PROGRAM_ID,
+          volatilityOracle: null,
         })
         .signers([god])
         .rpc({ skipPreflight: true });
@@ -280,4 +345,188 @@ describe("phoenix-onchain-mm", () => {
       await new Promise((r) => setTimeout(r, 1000));
     }
   });
+
+  it("Leaves post_only unchanged when it is not provided", async () => {
+    const phoenixStrategy = getStrategyAddress(
+      program.programId,
+      god.publicKey,
+      solMarketAddress
+    );
+    const phoenixMarket = phoenixClient.markets.get(
+      solMarketAddress.toBase58()
+    );
+    const updateQuotes = async (postOnly: boolean | null) =>
+      program.methods
+        .updateQuotes(
+          {
+            fairPriceInQuoteAtomsPerRawBaseUnit: new BN(20 * 1e6),
+            strategyParams: {
+              quoteEdgeInBps: null,
+              quoteSizeInQuoteAtoms: null,
+              postOnly,
+              useOnlyDepositedFunds: null,
+              sizeDecayFactorInBps: null,
+              minSpreadInBps: null,
+              timeSinceLastFillDecayFactorInBps: null,
+              maxEdgeInBps: null,
+              volatilityMultiplierInBps: null,
+              minWarmupUpdates: null,
+              maxTakerExposureInQuoteAtoms: null,
+              maxSlotAgeForPrice: null,
+              minDepositedQuoteAtoms: null,
+              minDepositedBaseLots: null,
+              volatilityOraclePubkey: null,
+              maxOracleStalenessInSlots: null,
+              quoteRefreshIntervalInSeconds: null,
+              clientMetadata: null,
+              sizingMode: null,
+              sizeAdjustmentCurve: null,
+              pricePrecisionMode: null,
+              orderRefreshIntervalInSeconds: null,
+              takerFeeInBps: null,
+              makerRebateInBps: null,
+              minExternalOrdersPerSide: null,
+              clientOrderIdMode: null,
+              priceImprovementBehavior: null,
+            },
+            submittedSlot: new BN(0),
+          },
+          0
+        )
+        .accounts({
+          phoenixStrategy,
+          user: god.publicKey,
+          market: solMarketAddress,
+          phoenixProgram: Phoenix.PROGRAM_ID,
+          logAuthority: Phoenix.getLogAuthority(),
+          seat: phoenixMarket.getSeatAddress(god.publicKey),
+          quoteAccount: makerUsdcTokenAccount,
+          baseAccount: makerWrappedSolTokenAccount,
+          quoteVault: phoenixMarket.data.header.quoteParams.vaultKey,
+          baseVault: phoenixMarket.data.header.baseParams.vaultKey,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          volatilityOracle: null,
+        })
+        .signers([god])
+        .rpc();
+
+    await updateQuotes(true);
+    await updateQuotes(null);
+    const strategy = await program.account.phoenixStrategyState.fetch(
+      phoenixStrategy
+    );
+    assert.isTrue(strategy.postOnly);
+  });
+
+  it("Cancels all orders without token accounts", async () => {
+    const phoenixStrategy = getStrategyAddress(
+      program.programId,
+      god.publicKey,
+      solMarketAddress
+    );
+    const tx = await program.methods
+      .cancelAllOrders(0)
+      .accounts({
+        phoenixStrategy,
+        user: god.publicKey,
+        phoenixProgram: Phoenix.PROGRAM_ID,
+        logAuthority: Phoenix.getLogAuthority(),
+        market: solMarketAddress,
+      })
+      .signers([god])
+      .rpc();
+    console.log("Cancel:", tx);
+
+    const strategy = await program.account.phoenixStrategyState.fetch(
+      phoenixStrategy
+    );
+    assert(strategy.bidOrderSequenceNumber.isZero());
+    assert(strategy.askOrderSequenceNumber.isZero());
+  });
+
+  it("Reclaims excess lamports while staying rent-exempt", async () => {
+    const phoenixStrategy = getStrategyAddress(
+      program.programId,
+      god.publicKey,
+      solMarketAddress
+    );
+    await sendAndConfirmTransaction(
+      connection,
+      new Transaction().add(
+        SystemProgram.transfer({
+          fromPubkey: god.publicKey,
+          toPubkey: phoenixStrategy,
+          lamports: 1_000_000,
+        })
+      ),
+      [god],
+      { commitment: "confirmed" }
+    );
+
+    const tx = await program.methods
+      .reclaimExcessLamports(0)
+      .accounts({
+        phoenixStrategy,
+        user: god.publicKey,
+        market: solMarketAddress,
+      })
+      .signers([god])
+      .rpc();
+    console.log("Reclaim:", tx);
+
+    const account = await connection.getAccountInfo(phoenixStrategy);
+    const minimumRent = await connection.getMinimumBalanceForRentExemption(
+      account.data.length
+    );
+    assert.equal(account.lamports, minimumRent);
+  });
+
+  it("Passes the health check", async () => {
+    const phoenixStrategy = getStrategyAddress(
+      program.programId,
+      god.publicKey,
+      solMarketAddress
+    );
+    const tx = await program.methods
+      .healthCheck()
+      .accounts({
+        phoenixStrategy,
+        market: solMarketAddress,
+      })
+      .rpc();
+    console.log("Health check:", tx);
+  });
+
+  it("Tracks fills without requoting", async () => {
+    const phoenixStrategy = getStrategyAddress(
+      program.programId,
+      god.publicKey,
+      solMarketAddress
+    );
+    const before = await program.account.phoenixStrategyState.fetch(
+      phoenixStrategy
+    );
+    const tx = await program.methods
+      .trackFills(0)
+      .accounts({
+        phoenixStrategy,
+        user: god.publicKey,
+        market: solMarketAddress,
+      })
+      .signers([god])
+      .rpc();
+    console.log("Track fills:", tx);
+
+    const after = await program.account.phoenixStrategyState.fetch(
+      phoenixStrategy
+    );
+    assert.ok(
+      after.cumulativeBidFillInBaseLots.gte(before.cumulativeBidFillInBaseLots)
+    );
+    assert.ok(
+      after.cumulativeAskFillInBaseLots.gte(before.cumulativeAskFillInBaseLots)
+    );
+    assert.ok(after.bidPriceInTicks.eq(before.bidPriceInTicks));
+    assert.ok(after.askPriceInTicks.eq(before.askPriceInTicks));
+  });
 });